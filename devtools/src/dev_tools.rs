@@ -1,14 +1,23 @@
+use indexmap::IndexMap;
 use leptos::*;
 use leptos_query::{
     cache_observer::{
-        CacheEvent, CacheObserver, CreatedQuery, ObserverAdded, QueryCacheKey, SerializedQuery,
+        CacheEvent, CacheObserver, CreatedQuery, ObserverAdded, ObserverKey, QueryActions,
+        QueryCacheKey, SerializedQuery, TimesChanged,
     },
     *,
 };
-use std::{collections::HashMap, time::Duration};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    time::Duration,
+};
 
 use crate::component::*;
-use crate::timeout::{time_until_stale, use_timeout};
+use crate::snapshot::{diff, CacheSnapshot, SnapshotChange};
 
 #[component]
 pub(crate) fn InnerDevtools() -> impl IntoView {
@@ -32,7 +41,15 @@ fn DevtoolsClient() -> impl IntoView {
     let client = leptos_query::use_query_client();
     let state = DevtoolsContext::new();
     client.register_cache_observer(state.clone());
-    provide_context(state);
+    client.register_fetch_middleware(Throttle(state.throttle));
+    provide_context(state.clone());
+
+    let now = state.now;
+    if let Ok(handle) =
+        set_interval_with_handle(move || now.set(Instant::now()), Duration::from_secs(1))
+    {
+        on_cleanup(move || handle.clear());
+    }
 
     // Ensure that selected query is closed if it is evicted.
     create_effect({
@@ -45,15 +62,16 @@ fn DevtoolsClient() -> impl IntoView {
             {
                 let cache = context.query_state.get();
 
-                if cache.get(&key).is_none() {
+                if !cache.contains_key(&key) {
                     context.selected_query.set(None);
                 }
             }
         }
     });
 
+    // With a shadow root, the styles are isolated from the app's styles in both directions.
     view! {
-        <Portal>
+        <Portal use_shadow=cfg!(feature = "shadow_dom")>
             <style>{include_str!("./styles.css")}</style>
             <div class="leptos-query-devtools lq-font-mono">
                 <Devtools/>
@@ -65,12 +83,44 @@ fn DevtoolsClient() -> impl IntoView {
 #[derive(Clone)]
 struct DevtoolsContext {
     owner: Owner,
-    query_state: RwSignal<HashMap<QueryCacheKey, QueryCacheEntry>>,
+    // In creation order, which breaks ties when sorting.
+    query_state: RwSignal<IndexMap<QueryCacheKey, QueryCacheEntry>>,
     open: RwSignal<bool>,
     filter: RwSignal<String>,
     sort: RwSignal<SortOption>,
     order_asc: RwSignal<bool>,
     selected_query: RwSignal<Option<QueryCacheEntry>>,
+    throttle: RwSignal<Option<Duration>>,
+    snapshots: RwSignal<Vec<CacheSnapshot>>,
+    // Index of the snapshot the cache is diffed against.
+    diff_against: RwSignal<Option<usize>>,
+    time_format: RwSignal<TimeFormat>,
+    // Ticks every second, so that relative times stay current.
+    now: RwSignal<Instant>,
+    // Cache events are applied once per frame, so that many simultaneous fetches cause a single re-render.
+    pending: Rc<RefCell<Vec<CacheEvent>>>,
+    flush_scheduled: Rc<Cell<bool>>,
+}
+
+// Delays every fetch, to simulate a slow network.
+struct Throttle(RwSignal<Option<Duration>>);
+
+impl FetchMiddleware for Throttle {
+    fn after_fetch(&self, _: &QueryCacheKey) -> Pin<Box<dyn Future<Output = ()>>> {
+        let delay = self.0.try_get_untracked().flatten();
+        Box::pin(async move {
+            if let Some(delay) = delay {
+                let (sender, receiver) = futures_channel::oneshot::channel();
+                set_timeout(
+                    move || {
+                        let _ = sender.send(());
+                    },
+                    delay,
+                );
+                let _ = receiver.await;
+            }
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -95,6 +145,14 @@ impl SortOption {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TimeFormat {
+    // Time of day, e.g. "2:05:09 PM".
+    Absolute,
+    // Time since, e.g. "12s ago".
+    Relative,
+}
+
 #[derive(Clone)]
 struct QueryCacheEntry {
     key: QueryCacheKey,
@@ -102,8 +160,25 @@ struct QueryCacheEntry {
     observer_count: RwSignal<usize>,
     gc_time: RwSignal<SettingTime>,
     stale_time: RwSignal<SettingTime>,
+    observers: RwSignal<Vec<ObserverEntry>>,
     is_stale: Signal<bool>,
-    mark_invalid: std::rc::Rc<dyn Fn() -> bool>,
+    // When the current data came from the persister rather than a fetch.
+    is_restored: Signal<bool>,
+    restored_at: RwSignal<Option<Instant>>,
+    // Shared by all entries with the same key and value types.
+    stats: Signal<QueryStats>,
+    actions: QueryActions,
+}
+
+// The options of a single observer, before they are merged.
+#[derive(Clone)]
+struct ObserverEntry {
+    id: ObserverKey,
+    location: String,
+    created_at: Instant,
+    stale_time: SettingTime,
+    gc_time: SettingTime,
+    refetch_interval: SettingTime,
 }
 
 fn use_devtools_context() -> DevtoolsContext {
@@ -111,15 +186,94 @@ fn use_devtools_context() -> DevtoolsContext {
 }
 
 impl DevtoolsContext {
+    // The data of every query, keyed by query key.
+    fn cache_entries(&self) -> HashMap<QueryCacheKey, Option<String>> {
+        self.query_state.with(|map| {
+            map.iter()
+                .map(|(key, entry)| (key.clone(), entry.state.with(|s| s.data().cloned())))
+                .collect()
+        })
+    }
+
     fn new() -> Self {
         DevtoolsContext {
             owner: Owner::current().expect("Owner to be present"),
-            query_state: create_rw_signal(HashMap::new()),
+            query_state: create_rw_signal(IndexMap::new()),
             open: create_rw_signal(false),
             filter: create_rw_signal("".to_string()),
             sort: create_rw_signal(SortOption::Time),
             order_asc: create_rw_signal(false),
             selected_query: create_rw_signal(None),
+            throttle: create_rw_signal(None),
+            snapshots: create_rw_signal(Vec::new()),
+            diff_against: create_rw_signal(None),
+            time_format: create_rw_signal(TimeFormat::Absolute),
+            now: create_rw_signal(Instant::now()),
+            pending: Rc::new(RefCell::new(Vec::new())),
+            flush_scheduled: Rc::new(Cell::new(false)),
+        }
+    }
+
+    fn schedule_flush(&self) {
+        if self.flush_scheduled.replace(true) {
+            return;
+        }
+
+        let context = self.clone();
+        let flush = move || {
+            context.flush_scheduled.set(false);
+            context.flush();
+        };
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "csr")] {
+                request_animation_frame(flush);
+            } else {
+                flush();
+            }
+        }
+    }
+
+    // Applies the pending events in a single batch.
+    fn flush(&self) {
+        let events = self.pending.take();
+
+        // Only the latest state of each query is shown, so earlier updates are skipped.
+        let latest_updates = events
+            .iter()
+            .enumerate()
+            .filter_map(|(index, event)| match event {
+                CacheEvent::Updated(query) => Some((query.key.clone(), index)),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+
+        batch(|| {
+            let mut updated = false;
+            for (index, event) in events.into_iter().enumerate() {
+                match event {
+                    CacheEvent::Updated(query) => {
+                        if latest_updates.get(&query.key) == Some(&index) {
+                            self.update_state(query);
+                            updated = true;
+                        }
+                    }
+                    event => self.apply_event(event),
+                }
+            }
+            // Sorting depends on the update times, so the list is refreshed once for all updates.
+            if updated {
+                self.query_state.update(|_| {});
+            }
+        });
+    }
+
+    fn update_state(&self, SerializedQuery { key, state, .. }: SerializedQuery) {
+        if let Some(entry_state) = self
+            .query_state
+            .with_untracked(|map| map.get(&key).map(|entry| entry.state))
+        {
+            entry_state.set(state);
         }
     }
 }
@@ -135,46 +289,12 @@ enum SettingTime {
 }
 
 impl SettingTime {
-    fn max(&self, other: Self) -> Self {
-        match (self, other) {
-            (SettingTime::None, other) => other,
-            (current, SettingTime::None) => *current,
-            (SettingTime::Some(current), SettingTime::Some(other)) if other > *current => {
-                SettingTime::Some(other)
-            }
-            (SettingTime::Some(current), SettingTime::Some(_)) => SettingTime::Some(*current),
-            (SettingTime::Infinity, _) => SettingTime::Infinity,
-            (_, SettingTime::Infinity) => SettingTime::Infinity,
-        }
-    }
-
-    fn min(&self, other: Self) -> Self {
-        match (self, other) {
-            (SettingTime::None, other) => other,
-            (current, SettingTime::None) => *current,
-            (SettingTime::Some(current), SettingTime::Some(other)) if other < *current => {
-                SettingTime::Some(other)
-            }
-            (SettingTime::Some(current), SettingTime::Some(_)) => SettingTime::Some(*current),
-            (SettingTime::Infinity, other) => other,
-            (current, SettingTime::Infinity) => *current,
-        }
-    }
-
     fn from_option(duration: Option<Duration>) -> Self {
         match duration {
             Some(duration) => SettingTime::Some(duration),
             None => SettingTime::Infinity,
         }
     }
-
-    fn to_expiration(self) -> Option<Duration> {
-        match self {
-            SettingTime::None => None,
-            SettingTime::Infinity => None,
-            SettingTime::Some(duration) => Some(duration),
-        }
-    }
 }
 
 impl std::fmt::Display for SettingTime {
@@ -189,46 +309,34 @@ impl std::fmt::Display for SettingTime {
 
 impl CacheObserver for DevtoolsContext {
     fn process_cache_event(&self, event: CacheEvent) {
+        self.pending.borrow_mut().push(event);
+        self.schedule_flush();
+    }
+}
+
+impl DevtoolsContext {
+    fn apply_event(&self, event: CacheEvent) {
         match event {
             CacheEvent::Created(CreatedQuery {
                 key,
                 state,
-                mark_invalid,
+                actions,
+                is_stale,
+                stats,
+                ..
             }) => {
                 // Need to create signals with root owner, or else they will be disposed of.
                 let entry = with_owner(self.owner, || {
                     let stale_time = create_rw_signal(SettingTime::None);
                     let state = create_rw_signal(state);
-
-                    let is_stale = {
-                        let (stale, set_stale) = create_signal(false);
-
-                        let updated_at = Signal::derive(move || state.with(|s| s.updated_at()));
-
-                        use_timeout(move || {
-                            match (updated_at.get(), stale_time.get().to_expiration()) {
-                                (Some(updated_at), Some(stale_time)) => {
-                                    let duration = time_until_stale(updated_at, stale_time);
-                                    if duration.is_zero() {
-                                        set_stale.set(true);
-                                        None
-                                    } else {
-                                        set_stale.set(false);
-                                        set_timeout_with_handle(
-                                            move || {
-                                                set_stale.set(true);
-                                            },
-                                            duration,
-                                        )
-                                        .ok()
-                                    }
-                                }
-                                _ => None,
-                            }
-                        });
-
-                        stale.into()
-                    };
+                    // The query's signal is disposed on removal, which may happen before the entry is removed.
+                    let is_stale = Signal::derive(move || is_stale.try_get().unwrap_or_default());
+                    let restored_at = create_rw_signal(None);
+                    // Fetched data has a newer timestamp, so the badge clears on its own.
+                    let is_restored = Signal::derive(move || {
+                        let restored_at = restored_at.get();
+                        restored_at.is_some() && state.with(|s| s.updated_at()) == restored_at
+                    });
 
                     QueryCacheEntry {
                         key: key.clone(),
@@ -236,8 +344,12 @@ impl CacheObserver for DevtoolsContext {
                         stale_time,
                         gc_time: create_rw_signal(SettingTime::None),
                         observer_count: create_rw_signal(0),
+                        observers: create_rw_signal(Vec::new()),
                         is_stale,
-                        mark_invalid,
+                        is_restored,
+                        restored_at,
+                        stats,
+                        actions,
                     }
                 });
 
@@ -245,51 +357,71 @@ impl CacheObserver for DevtoolsContext {
                     map.insert(key, entry);
                 })
             }
-            CacheEvent::Removed(key) => self.query_state.update(|map| {
-                map.remove(&key);
+            CacheEvent::Removed(key, _) => self.query_state.update(|map| {
+                map.shift_remove(&key);
             }),
-            // TODO: Fix this borrow error when using signal update.
-            CacheEvent::Updated(SerializedQuery { key, state }) => {
-                let map = self.query_state.get_untracked();
-                if let Some(entry) = map.get(&key) {
-                    entry.state.set(state);
-                }
-                self.query_state.set(map);
-            }
+            CacheEvent::Updated(query) => self.update_state(query),
             CacheEvent::ObserverAdded(observer) => {
-                let ObserverAdded { key, options } = observer;
+                let ObserverAdded {
+                    key,
+                    observer,
+                    options,
+                    location,
+                    ..
+                } = observer;
                 let QueryOptions {
                     stale_time,
                     gc_time,
+                    refetch_interval,
                     ..
                 } = options;
-                self.query_state.update(|map| {
-                    if let Some(entry) = map.get_mut(&key) {
+                // Only the entry changes, so the list isn't notified.
+                self.query_state.with_untracked(|map| {
+                    if let Some(entry) = map.get(&key) {
                         entry.observer_count.update(|c| *c += 1);
-                        {
-                            let current_gc = entry.gc_time.get_untracked();
-                            let setting_gc = SettingTime::from_option(gc_time);
-
-                            let new_gc = current_gc.max(setting_gc);
-                            entry.gc_time.set(new_gc);
-                        }
-                        {
-                            let current_stale = entry.stale_time.get_untracked();
-                            let setting_stale = SettingTime::from_option(stale_time);
-                            let new_stale = current_stale.min(setting_stale);
-                            entry.stale_time.set(new_stale);
-                        }
+                        entry.observers.update(|observers| {
+                            observers.push(ObserverEntry {
+                                id: observer,
+                                location: format!("{}:{}", location.file(), location.line()),
+                                created_at: Instant::now(),
+                                stale_time: SettingTime::from_option(stale_time),
+                                gc_time: SettingTime::from_option(gc_time),
+                                refetch_interval: SettingTime::from_option(refetch_interval),
+                            })
+                        });
                     }
                 });
             }
-            CacheEvent::ObserverRemoved(key) => {
-                self.query_state.update(|map| {
-                    if let Some(entry) = map.get_mut(&key) {
-                        entry.observer_count.update(|c| {
-                            if *c > 0 {
-                                *c -= 1
-                            }
+            CacheEvent::TimesChanged(TimesChanged {
+                key,
+                stale_time,
+                gc_time,
+                ..
+            }) => self.query_state.with_untracked(|map| {
+                if let Some(entry) = map.get(&key) {
+                    entry.stale_time.set(SettingTime::from_option(stale_time));
+                    entry.gc_time.set(SettingTime::from_option(gc_time));
+                }
+            }),
+            CacheEvent::RestoredFromPersister(SerializedQuery { key, state, .. }) => {
+                let map = self.query_state.get_untracked();
+                if let Some(entry) = map.get(&key) {
+                    entry.restored_at.set(state.updated_at());
+                }
+            }
+            CacheEvent::ObserverRemoved(removed) => {
+                self.query_state.with_untracked(|map| {
+                    if let Some(entry) = map.get(&removed.key) {
+                        // Only counted if its addition was, e.g. not before the query was created.
+                        let mut was_counted = false;
+                        entry.observers.update(|observers| {
+                            let count = observers.len();
+                            observers.retain(|o| o.id != removed.observer);
+                            was_counted = observers.len() < count;
                         });
+                        if was_counted {
+                            entry.observer_count.update(|c| *c -= 1);
+                        }
                     }
                 });
             }
@@ -442,13 +574,18 @@ fn Devtools() -> impl IntoView {
                                         <SearchInput/>
                                         <SetSort/>
                                         <SetSortOrder/>
+                                        <SetTimeFormat/>
+                                        <SetThrottle/>
+                                        <Snapshots/>
                                     </div>
-                                    <div class="lq-flex lq-items-center">
+                                    <div class="lq-flex lq-items-center lq-gap-2">
+                                        <PauseGc/>
                                         <ClearCache/>
                                     </div>
                                 </div>
                             </div>
 
+                            <SnapshotDiff/>
                             <ul class="lq-flex lq-flex-col lq-gap-1 lq-overflow-y-auto">
                                 <For each=move || query_state.get() key=|q| q.key.clone() let:entry>
                                     <QueryRow entry=entry/>
@@ -534,6 +671,18 @@ fn Header() -> impl IntoView {
 
     let total = Signal::derive(move || query_state.get().len());
 
+    // Breakdown of the total per key and value type, largest first.
+    let size_by_type = use_query_client().size_by_type();
+    let total_by_type = Signal::derive(move || {
+        size_by_type.with(|sizes| {
+            sizes
+                .iter()
+                .map(|size| format!("{} -> {}: {}", size.key_type, size.value_type, size.count))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    });
+
     let label_class = "lq-hidden lg:lq-inline-block";
     view! {
         <div class="lq-flex-none lq-flex lq-justify-between lq-w-full lq-overflow-y-hidden lq-items-center lq-border-b lq-border-lq-border lq-pb-2 lq-px-1">
@@ -557,10 +706,12 @@ fn Header() -> impl IntoView {
                     <span>{invalid}</span>
                 </DotBadge>
 
-                <DotBadge color=ColorOption::Gray>
-                    <span class=label_class>Total</span>
-                    <span>{total}</span>
-                </DotBadge>
+                <span title=total_by_type>
+                    <DotBadge color=ColorOption::Gray>
+                        <span class=label_class>Total</span>
+                        <span>{total}</span>
+                    </DotBadge>
+                </span>
             </div>
         </div>
     }
@@ -626,6 +777,187 @@ fn SetSort() -> impl IntoView {
     }
 }
 
+#[component]
+fn SetTimeFormat() -> impl IntoView {
+    let DevtoolsContext { time_format, .. } = use_devtools_context();
+
+    view! {
+        <button
+            class="lq-bg-lq-input lq-text-lq-input-foreground lq-rounded-md lq-px-2 lq-py-1 lq-text-xs lq-inline-flex lq-items-center lq-gap-1 lq-border lq-border-lq-border"
+            on:click=move |_| {
+                time_format
+                    .update(|format| {
+                        *format = match format {
+                            TimeFormat::Absolute => TimeFormat::Relative,
+                            TimeFormat::Relative => TimeFormat::Absolute,
+                        };
+                    });
+            }
+        >
+
+            {move || match time_format.get() {
+                TimeFormat::Absolute => "Absolute times",
+                TimeFormat::Relative => "Relative times",
+            }}
+        </button>
+    }
+}
+
+#[component]
+fn SetThrottle() -> impl IntoView {
+    let DevtoolsContext { throttle, .. } = use_devtools_context();
+
+    let options = [0, 500, 1000, 3000];
+
+    view! {
+        <select
+            class="lq-form-select lq-border-lq-border lq-border lq-text-xs lq-rounded-md lq-block lq-py-1 lq-px-2 lq-bg-lq-input lq-text-lq-input-foreground lq-line-clamp-1"
+            value=move || throttle.get().map(|d| d.as_millis()).unwrap_or_default().to_string()
+            on:change=move |ev| {
+                let millis = event_target_value(&ev).parse::<u64>().unwrap_or_default();
+                throttle.set((millis > 0).then(|| Duration::from_millis(millis)));
+            }
+        >
+
+            {options
+                .into_iter()
+                .map(|millis| {
+                    let label = if millis == 0 {
+                        "No throttling".to_string()
+                    } else {
+                        format!("Delay fetches {millis}ms")
+                    };
+                    view! { <option value=millis.to_string()>{label}</option> }
+                })
+                .collect_view()}
+        </select>
+    }
+}
+
+// Captures snapshots of the cache, and picks the one to diff against.
+#[component]
+fn Snapshots() -> impl IntoView {
+    let context = use_devtools_context();
+    let DevtoolsContext {
+        snapshots,
+        diff_against,
+        ..
+    } = context.clone();
+
+    let capture = move |_| {
+        let entries = untrack(|| context.cache_entries());
+        snapshots.update(|snapshots| {
+            let name = format!(
+                "Snapshot {} ({})",
+                snapshots.len() + 1,
+                format_time(Instant::now())
+            );
+            snapshots.push(CacheSnapshot { name, entries });
+        });
+    };
+
+    view! {
+        <button
+            class="lq-bg-lq-input lq-text-lq-input-foreground lq-rounded-md lq-px-2 lq-py-1 lq-text-xs lq-inline-flex lq-items-center lq-gap-1 lq-border lq-border-lq-border"
+            on:click=capture
+        >
+            Snapshot
+        </button>
+        <Show when=move || snapshots.with(|s| !s.is_empty())>
+            <select
+                class="lq-form-select lq-border-lq-border lq-border lq-text-xs lq-rounded-md lq-block lq-py-1 lq-px-2 lq-bg-lq-input lq-text-lq-input-foreground lq-line-clamp-1"
+                on:change=move |ev| {
+                    diff_against.set(event_target_value(&ev).parse::<usize>().ok());
+                }
+            >
+
+                <option value="" selected=move || diff_against.get().is_none()>
+                    No diff
+                </option>
+                {move || {
+                    snapshots
+                        .get()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, snapshot)| {
+                            view! {
+                                <option
+                                    value=index.to_string()
+                                    selected=move || diff_against.get() == Some(index)
+                                >
+                                    {format!("Diff against {}", snapshot.name)}
+                                </option>
+                            }
+                        })
+                        .collect_view()
+                }}
+
+            </select>
+        </Show>
+    }
+}
+
+// Added, removed and changed queries since the selected snapshot.
+#[component]
+fn SnapshotDiff() -> impl IntoView {
+    let context = use_devtools_context();
+    let DevtoolsContext {
+        snapshots,
+        diff_against,
+        ..
+    } = context.clone();
+
+    let changes = Signal::derive(move || {
+        let index = diff_against.get()?;
+        let before = snapshots.with(|s| s.get(index).map(|s| s.entries.clone()))?;
+        Some(diff(&before, &context.cache_entries()))
+    });
+
+    let value = |value: Option<String>| value.unwrap_or_else(|| "No data".to_string());
+
+    move || {
+        changes.get().map(|changes| {
+            let rows = if changes.is_empty() {
+                view! { <li class="lq-px-2 lq-py-1 lq-text-xs lq-text-zinc-400">No changes</li> }
+                    .into_view()
+            } else {
+                changes
+                    .into_iter()
+                    .map(|change| {
+                        let (symbol, class, key, detail) = match change {
+                            SnapshotChange::Added { key, after } => {
+                                ("+", "lq-text-green-700", key, value(after))
+                            }
+                            SnapshotChange::Removed { key, before } => {
+                                ("-", "lq-text-red-700", key, value(before))
+                            }
+                            SnapshotChange::Changed { key, before, after } => (
+                                "~",
+                                "lq-text-yellow-700",
+                                key,
+                                format!("{} -> {}", value(before), value(after)),
+                            ),
+                        };
+                        view! {
+                            <li class="lq-px-2 lq-py-1 lq-text-xs lq-flex lq-flex-col lq-gap-1">
+                                <span class=class>{format!("{symbol} {}", key.0)}</span>
+                                <pre class="lq-whitespace-pre-wrap lq-break-words lq-text-zinc-400">
+                                    {detail}
+                                </pre>
+                            </li>
+                        }
+                    })
+                    .collect_view()
+            };
+            view! {
+                <ul class="lq-flex lq-flex-col lq-border-lq-border lq-border-b lq-overflow-y-auto">
+                    {rows}
+                </ul>
+            }
+        })
+    }
+}
+
 #[component]
 fn SetSortOrder() -> impl IntoView {
     let DevtoolsContext { order_asc, .. } = use_devtools_context();
@@ -681,6 +1013,40 @@ fn SetSortOrder() -> impl IntoView {
     }
 }
 
+// Keeps entries around while inspecting them. Dropping the guard resumes collection.
+#[component]
+fn PauseGc() -> impl IntoView {
+    let cache = leptos_query::use_query_client();
+    let guard = create_rw_signal(None::<leptos_query::GcPauseGuard>);
+    let paused = cache.is_gc_paused();
+
+    view! {
+        <button
+            class="lq-bg-lq-input lq-text-lq-input-foreground lq-rounded-md lq-px-2 lq-py-1 lq-text-xs lq-inline-flex lq-items-center lq-gap-1 lq-border lq-border-lq-border"
+            title=move || {
+                if paused.get() {
+                    "Garbage collection is paused"
+                } else {
+                    "Garbage collection is running"
+                }
+            }
+
+            on:click=move |_| {
+                guard
+                    .update(|guard| {
+                        *guard = match guard.take() {
+                            Some(_) => None,
+                            None => Some(cache.pause_gc()),
+                        };
+                    });
+            }
+        >
+
+            {move || if guard.with(Option::is_some) { "Resume GC" } else { "Pause GC" }}
+        </button>
+    }
+}
+
 #[component]
 fn ClearCache() -> impl IntoView {
     let cache = leptos_query::use_query_client();
@@ -719,8 +1085,11 @@ fn QueryRow(entry: QueryCacheEntry) -> impl IntoView {
         state,
         observer_count,
         is_stale,
+        is_restored,
+        stats,
         ..
     } = entry.clone();
+    let hits = move || stats.with(|s| format!("{}/{}", s.cache_hits, s.mounts()));
     let observer = move || {
         let count = observer_count.get();
         if count == 0 {
@@ -743,7 +1112,7 @@ fn QueryRow(entry: QueryCacheEntry) -> impl IntoView {
             on:click={
                 let key = key.clone();
                 move |_| {
-                    if selected_query.get_untracked().map_or(false, |q| q.key == key) {
+                    if selected_query.get_untracked().is_some_and(|q| q.key == key) {
                         selected_query.set(None);
                     } else {
                         selected_query.set(Some(entry.clone()))
@@ -756,7 +1125,13 @@ fn QueryRow(entry: QueryCacheEntry) -> impl IntoView {
             <span class="lq-w-[4.5rem]">
                 <RowStateLabel state=state.into() is_stale/>
             </span>
+            <span class="lq-text-xs lq-text-zinc-200" title="Cache hits / mounts of this query type">
+                {hits}
+            </span>
             <span class="lq-text-sm">{key.0}</span>
+            <Show when=move || is_restored.get()>
+                <DotBadge color=ColorOption::Gray>Persisted</DotBadge>
+            </Show>
         </li>
     }
 }
@@ -772,6 +1147,7 @@ fn RowStateLabel(state: Signal<QueryState<String>>, is_stale: Signal<bool>) -> i
             QueryState::Loaded(_) if is_stale => "Stale",
             QueryState::Loaded(_) => "Loaded",
             QueryState::Invalid(_) => "Invalid",
+            QueryState::Rejected(_) => "Rejected",
         }
     });
 
@@ -783,7 +1159,7 @@ fn RowStateLabel(state: Signal<QueryState<String>>, is_stale: Signal<bool>) -> i
             }
             QueryState::Loaded(_) if is_stale => ColorOption::Yellow,
             QueryState::Loaded(_) => ColorOption::Green,
-            QueryState::Invalid(_) => ColorOption::Red,
+            QueryState::Invalid(_) | QueryState::Rejected(_) => ColorOption::Red,
         }
     });
 
@@ -802,27 +1178,17 @@ fn SelectedQuery(query: QueryCacheEntry) -> impl IntoView {
         key: query_key,
         state: query_state,
         is_stale,
+        is_restored,
+        stats,
         observer_count,
-        mark_invalid,
+        actions,
         stale_time,
         gc_time,
+        observers,
+        ..
     } = query;
 
-    #[cfg(feature = "csr")]
-    let last_update = Signal::derive(move || {
-        use wasm_bindgen::JsValue;
-        query_state.get().updated_at().map(|i| {
-            let time = JsValue::from_f64(i.0.as_millis() as f64);
-            let date = js_sys::Date::new(&time);
-            let hours = date.get_hours();
-            let minutes = date.get_minutes();
-            let seconds = date.get_seconds();
-            format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
-        })
-    });
-
-    #[cfg(not(feature = "csr"))]
-    let last_update = Signal::derive(move || query_state.get().updated_at().map(|i| i.to_string()));
+    let last_update = Signal::derive(move || query_state.get().updated_at());
 
     // Pretty print the JSON
     #[cfg(feature = "csr")]
@@ -830,7 +1196,7 @@ fn SelectedQuery(query: QueryCacheEntry) -> impl IntoView {
         use wasm_bindgen::JsValue;
         let value = query_state.get().data().cloned()?;
         let json = js_sys::JSON::parse(value.as_str()).ok()?;
-        let result = js_sys::JSON::stringify_with_replacer_and_space(
+        js_sys::JSON::stringify_with_replacer_and_space(
             &json,
             &JsValue::NULL,
             &JsValue::from_f64(2.0),
@@ -838,9 +1204,7 @@ fn SelectedQuery(query: QueryCacheEntry) -> impl IntoView {
         .ok()
         .map(|r| r.as_string())
         // If value is not json, just present value.
-        .unwrap_or(Some(value));
-
-        result
+        .unwrap_or(Some(value))
     });
 
     #[cfg(not(feature = "csr"))]
@@ -854,6 +1218,15 @@ fn SelectedQuery(query: QueryCacheEntry) -> impl IntoView {
 
     let gc_time = Signal::derive(move || gc_time.get().to_string());
 
+    let mounts = move || {
+        stats.with(|s| {
+            format!(
+                "{} hits, {} deduped, {} fetches",
+                s.cache_hits, s.deduped, s.fetches
+            )
+        })
+    };
+
     view! {
         <div class="lq-w-1/2 lq-overflow-y-scroll lq-max-h-full lq-border-black lq-border-l-4">
             <div class="lq-flex lq-flex-col lq-w-full lq-h-full lq-items-center">
@@ -874,12 +1247,24 @@ fn SelectedQuery(query: QueryCacheEntry) -> impl IntoView {
                         </div>
                         <div class=entry_class>
                             <dt class="lq-text-zinc-100">Last Update</dt>
-                            <dd class="lq-text-zinc-200">{last_update}</dd>
+                            <dd class="lq-text-zinc-200">
+                                <Timestamp instant=last_update/>
+                            </dd>
+                        </div>
+                        <div class=entry_class>
+                            <dt class="lq-text-zinc-100">Data Source</dt>
+                            <dd class="lq-text-zinc-200">
+                                {move || if is_restored.get() { "Persister" } else { "Fetch" }}
+                            </dd>
                         </div>
                         <div class=entry_class>
                             <dt class="lq-text-zinc-100">Active Observers</dt>
                             <dd class="lq-text-zinc-200">{observer_count}</dd>
                         </div>
+                        <div class=entry_class>
+                            <dt class="lq-text-zinc-100">Mounts</dt>
+                            <dd class="lq-text-zinc-200">{mounts}</dd>
+                        </div>
 
                         <div class=entry_class>
                             <dt class="lq-text-zinc-100">Stale Time</dt>
@@ -890,20 +1275,45 @@ fn SelectedQuery(query: QueryCacheEntry) -> impl IntoView {
                             <dd class="lq-text-zinc-200">{gc_time}</dd>
                         </div>
                     </dl>
+                    <ObserverList observers/>
                 </div>
                 <div class="lq-w-full">
                     <div class="lq-text-sm lq-text-lq-foreground lq-p-1 lq-bg-lq-accent">
                         Query Actions
                     </div>
                     <div class="lq-flex lq-items-center lq-gap-2 lq-p-1">
+                        <Button
+                            color=ColorOption::Blue
+                            on:click={
+                                let actions = actions.clone();
+                                move |_| {
+                                    actions.refetch();
+                                }
+                            }
+                        >
+
+                            Refetch
+                        </Button>
+                        <Button
+                            color=ColorOption::Yellow
+                            on:click={
+                                let actions = actions.clone();
+                                move |_| {
+                                    actions.invalidate();
+                                }
+                            }
+                        >
+
+                            Invalidate
+                        </Button>
                         <Button
                             color=ColorOption::Red
                             on:click=move |_| {
-                                mark_invalid();
+                                actions.evict();
                             }
                         >
 
-                            Invalidate
+                            Evict
                         </Button>
                     </div>
                 </div>
@@ -921,3 +1331,116 @@ fn SelectedQuery(query: QueryCacheEntry) -> impl IntoView {
         </div>
     }
 }
+
+// Each observer's own options, so that conflicting settings across components are visible.
+#[component]
+fn ObserverList(observers: RwSignal<Vec<ObserverEntry>>) -> impl IntoView {
+    let entry_class =
+        "lq-flex lq-items-center lq-justify-between lq-text-xs lq-font-medium lq-w-full";
+
+    view! {
+        <details class="lq-px-2 lq-py-1 lq-w-full lq-text-xs">
+            <summary class="lq-text-zinc-100">
+                {move || format!("Observers ({})", observers.with(Vec::len))}
+            </summary>
+            <For
+                each=move || observers.get()
+                key=|observer| observer.id
+                children=move |observer| {
+                    view! {
+                        <dl class="lq-flex lq-flex-col lq-gap-1 lq-py-1 lq-border-b lq-border-lq-border">
+                            <div class=entry_class>
+                                <dt class="lq-text-zinc-100">Source</dt>
+                                <dd class="lq-text-zinc-200 lq-break-words">{observer.location}</dd>
+                            </div>
+                            <div class=entry_class>
+                                <dt class="lq-text-zinc-100">Created</dt>
+                                <dd class="lq-text-zinc-200">
+                                    <Timestamp instant=Some(observer.created_at)/>
+                                </dd>
+                            </div>
+                            <div class=entry_class>
+                                <dt class="lq-text-zinc-100">Stale Time</dt>
+                                <dd class="lq-text-zinc-200">{observer.stale_time.to_string()}</dd>
+                            </div>
+                            <div class=entry_class>
+                                <dt class="lq-text-zinc-100">GC Time</dt>
+                                <dd class="lq-text-zinc-200">{observer.gc_time.to_string()}</dd>
+                            </div>
+                            <div class=entry_class>
+                                <dt class="lq-text-zinc-100">Refetch Interval</dt>
+                                <dd class="lq-text-zinc-200">
+                                    {observer.refetch_interval.to_string()}
+                                </dd>
+                            </div>
+                        </dl>
+                    }
+                }
+            />
+        </details>
+    }
+}
+
+// A timestamp in the selected format.
+#[component]
+fn Timestamp(#[prop(into)] instant: MaybeSignal<Option<Instant>>) -> impl IntoView {
+    let DevtoolsContext {
+        time_format, now, ..
+    } = use_devtools_context();
+
+    move || {
+        let instant = instant.get()?;
+        Some(match time_format.get() {
+            TimeFormat::Absolute => format_time(instant),
+            TimeFormat::Relative => format_relative(instant, now.get()),
+        })
+    }
+}
+
+// Local time of day in the user's locale and timezone, e.g. "2:05:09 PM" or "14:05:09".
+#[cfg(feature = "csr")]
+fn format_time(instant: Instant) -> String {
+    use wasm_bindgen::JsValue;
+
+    thread_local! {
+        static FORMAT: js_sys::Intl::DateTimeFormat = {
+            let options = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(&options, &"timeStyle".into(), &"medium".into());
+            js_sys::Intl::DateTimeFormat::new(&js_sys::Array::new(), &options)
+        };
+    }
+
+    let date = js_sys::Date::new(&JsValue::from_f64(instant.as_unix_millis() as f64));
+    FORMAT
+        .with(|format| format.format().call1(&JsValue::UNDEFINED, &date))
+        .ok()
+        .and_then(|formatted| formatted.as_string())
+        .unwrap_or_else(|| instant.to_string())
+}
+
+// Without browser apis, the timezone is unknown.
+#[cfg(not(feature = "csr"))]
+fn format_time(instant: Instant) -> String {
+    let seconds = instant.as_unix_millis() / 1000;
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        seconds / 3600 % 24,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// Time since the instant, e.g. "12s ago".
+fn format_relative(instant: Instant, now: Instant) -> String {
+    // The clock ticks every second, so recent instants may be slightly ahead of it.
+    let seconds = now
+        .as_unix_millis()
+        .saturating_sub(instant.as_unix_millis())
+        / 1000;
+    match seconds {
+        0 => "just now".to_string(),
+        1..=59 => format!("{seconds}s ago"),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        _ => format!("{}h ago", seconds / 3600),
+    }
+}
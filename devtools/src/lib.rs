@@ -14,6 +14,7 @@
 //! ## Features
 //! - `csr` Client side rendering: Needed to use browser apis, if this is not enabled your app (under a feature), you will not be able to use the devtools.
 //! - `force`: Always show the devtools, even in release mode.
+//! - `shadow_dom`: Render the devtools inside a shadow root, so that the app's styles (e.g. a global Tailwind setup) can't affect them, and theirs can't leak.
 //!
 //! Then in your app, render the devtools component. Make sure you also provide the query client.
 //!
@@ -84,7 +85,7 @@ pub fn LeptosQueryDevtools() -> impl IntoView {
 mod dev_tools;
 
 #[cfg(any(debug_assertions, feature = "force"))]
-mod timeout;
+mod component;
 
 #[cfg(any(debug_assertions, feature = "force"))]
-mod component;
+mod snapshot;
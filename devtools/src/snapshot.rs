@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use leptos_query::cache_observer::QueryCacheKey;
+
+/// The data of every query in the cache at one point in time.
+#[derive(Clone)]
+pub(crate) struct CacheSnapshot {
+    pub name: String,
+    pub entries: HashMap<QueryCacheKey, Option<String>>,
+}
+
+/// A difference between a snapshot and the current cache.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum SnapshotChange {
+    Added {
+        key: QueryCacheKey,
+        after: Option<String>,
+    },
+    Removed {
+        key: QueryCacheKey,
+        before: Option<String>,
+    },
+    Changed {
+        key: QueryCacheKey,
+        before: Option<String>,
+        after: Option<String>,
+    },
+}
+
+impl SnapshotChange {
+    pub fn key(&self) -> &QueryCacheKey {
+        match self {
+            SnapshotChange::Added { key, .. }
+            | SnapshotChange::Removed { key, .. }
+            | SnapshotChange::Changed { key, .. } => key,
+        }
+    }
+}
+
+/// Changes from `before` to `after`, sorted by key.
+pub(crate) fn diff(
+    before: &HashMap<QueryCacheKey, Option<String>>,
+    after: &HashMap<QueryCacheKey, Option<String>>,
+) -> Vec<SnapshotChange> {
+    let removed = before
+        .iter()
+        .filter(|(key, _)| !after.contains_key(key))
+        .map(|(key, before)| SnapshotChange::Removed {
+            key: key.clone(),
+            before: before.clone(),
+        });
+
+    let added_or_changed = after
+        .iter()
+        .filter_map(|(key, after)| match before.get(key) {
+            None => Some(SnapshotChange::Added {
+                key: key.clone(),
+                after: after.clone(),
+            }),
+            Some(before) if before != after => Some(SnapshotChange::Changed {
+                key: key.clone(),
+                before: before.clone(),
+                after: after.clone(),
+            }),
+            Some(_) => None,
+        });
+
+    let mut changes = removed.chain(added_or_changed).collect::<Vec<_>>();
+    changes.sort_by(|a, b| a.key().0.cmp(&b.key().0));
+    changes
+}
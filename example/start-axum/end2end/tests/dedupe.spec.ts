@@ -0,0 +1,31 @@
+import { test, expect } from "@playwright/test";
+import { POST_FETCH, countRequests, gotoHydrated, postBodies } from "./helpers";
+
+test("components using the same key share a single fetch", async ({ page }) => {
+  await gotoHydrated(page, "/");
+  const fetches = countRequests(page, POST_FETCH);
+
+  await page.getByRole("link", { name: "Double use of Post 2" }).click();
+
+  const bodies = postBodies(page);
+  await expect(bodies).toHaveCount(2);
+  await expect(bodies.first()).toContainText("Post 2: Timestamp");
+  await expect(bodies.last()).toContainText("Post 2: Timestamp");
+
+  const [first, second] = await bodies.allTextContents();
+  expect(first).toEqual(second);
+  expect(fetches()).toBe(1);
+});
+
+test("prefetched data is used without another fetch", async ({ page }) => {
+  await gotoHydrated(page, "/");
+  const prefetched = page.waitForResponse(POST_FETCH);
+  await page.getByRole("button", { name: "Prefetch Post Two" }).click();
+  await prefetched;
+
+  const fetches = countRequests(page, POST_FETCH);
+  await page.getByRole("link", { name: "Double use of Post 2" }).click();
+
+  await expect(postBodies(page).first()).toContainText("Post 2: Timestamp", { timeout: 1000 });
+  expect(fetches()).toBe(0);
+});
@@ -0,0 +1,29 @@
+import { expect, type Page } from "@playwright/test";
+
+/** Server function calls for posts. */
+export const POST_FETCH = /\/api\/get_?post/i;
+
+/**
+ * Loads a page and waits for hydration, so that links navigate on the client and queries run in the browser.
+ */
+export async function gotoHydrated(page: Page, path: string) {
+  await page.goto(path);
+  await page.waitForLoadState("networkidle");
+  await expect(page.locator("main")).toBeVisible();
+}
+
+/** Counts requests matching `url` from now on. */
+export function countRequests(page: Page, url: RegExp): () => number {
+  let count = 0;
+  page.on("request", (request) => {
+    if (url.test(request.url())) {
+      count += 1;
+    }
+  });
+  return () => count;
+}
+
+/** The rendered body of each post on the page. */
+export function postBodies(page: Page) {
+  return page.locator(".post-body h2");
+}
@@ -0,0 +1,27 @@
+import { test, expect } from "@playwright/test";
+import { gotoHydrated } from "./helpers";
+
+const DELETE_TODO = /\/api\/delete_?todo/i;
+
+// Todo server functions sleep for 1 second.
+const SERVER_DELAY = 1000;
+
+test("deleted todos are removed before the server responds", async ({ page }) => {
+  await gotoHydrated(page, "/todos");
+
+  const content = `Todo ${Date.now()}`;
+  await page.getByLabel("Add a Todo").fill(content);
+  await page.getByRole("button", { name: "Add" }).click();
+
+  const item = page.locator("li", { hasText: content });
+  await expect(item).toBeVisible();
+
+  const deleted = page.waitForResponse(DELETE_TODO);
+  await item.getByRole("button", { name: "X" }).click();
+  await expect(item).toHaveCount(0, { timeout: SERVER_DELAY / 2 });
+
+  // And stays removed once the list is refetched.
+  await deleted;
+  await page.waitForTimeout(SERVER_DELAY * 2);
+  await expect(item).toHaveCount(0);
+});
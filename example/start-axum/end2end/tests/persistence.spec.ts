@@ -0,0 +1,16 @@
+import { test, expect } from "@playwright/test";
+import { gotoHydrated, postBodies } from "./helpers";
+
+test("query data is persisted to local storage", async ({ page }) => {
+  await gotoHydrated(page, "/");
+  await page.getByRole("link", { name: "Post 1", exact: true }).click();
+  await expect(postBodies(page)).toContainText("Post 1: Timestamp");
+
+  await expect
+    .poll(() =>
+      page.evaluate(() =>
+        Object.values(localStorage).some((value) => value.includes("Post 1: Timestamp")),
+      ),
+    )
+    .toBe(true);
+});
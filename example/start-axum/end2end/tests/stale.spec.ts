@@ -0,0 +1,79 @@
+import { test, expect, type Page } from "@playwright/test";
+import { POST_FETCH, countRequests, gotoHydrated, postBodies } from "./helpers";
+
+// Posts have a stale time of 5 seconds.
+const STALE_TIME = 5000;
+
+async function loadPostOne(page: Page) {
+  await gotoHydrated(page, "/");
+  await page.getByRole("link", { name: "Post 1", exact: true }).click();
+  const body = postBodies(page);
+  await expect(body).toContainText("Post 1: Timestamp");
+  return (await body.textContent())!;
+}
+
+async function revisitPostOne(page: Page) {
+  await page.getByRole("link", { name: "Home" }).click();
+  await page.getByRole("link", { name: "Post 1", exact: true }).click();
+}
+
+test("fresh data is reused without refetching", async ({ page }) => {
+  const first = await loadPostOne(page);
+  const fetches = countRequests(page, POST_FETCH);
+
+  await revisitPostOne(page);
+
+  await expect(postBodies(page)).toHaveText(first, { timeout: 1000 });
+  await page.waitForTimeout(500);
+  expect(fetches()).toBe(0);
+});
+
+test("stale data is shown while it is refetched", async ({ page }) => {
+  const first = await loadPostOne(page);
+  await page.waitForTimeout(STALE_TIME + 500);
+
+  await revisitPostOne(page);
+
+  // The cached post is shown immediately, rather than a loading state.
+  const body = postBodies(page);
+  await expect(body).toHaveText(first, { timeout: 1000 });
+  await expect(body).not.toHaveText(first);
+  await expect(body).toContainText("Post 1: Timestamp");
+});
+
+test("invalidated data is refetched on next use", async ({ page }) => {
+  const first = await loadPostOne(page);
+  await page.getByRole("link", { name: "Home" }).click();
+  await page.getByRole("button", { name: "Invalidate Post One" }).click();
+
+  const fetches = countRequests(page, POST_FETCH);
+  await page.getByRole("link", { name: "Post 1", exact: true }).click();
+
+  const body = postBodies(page);
+  await expect(body).not.toHaveText(first);
+  await expect(body).toContainText("Post 1: Timestamp");
+  expect(fetches()).toBe(1);
+});
+
+test("switching keys mid-fetch doesn't get stuck loading", async ({ page }) => {
+  await gotoHydrated(page, "/");
+  await page.getByRole("link", { name: "Reactive" }).click();
+
+  // Switch to post 2 and straight back, while post 1 is still loading.
+  const switchPost = page.getByRole("button", { name: "Switch Post" });
+  await switchPost.click();
+  await switchPost.click();
+
+  const body = postBodies(page);
+  await expect(body).toContainText("Post 1: Timestamp");
+  await expect(body).not.toHaveText("Loading...");
+});
+
+test("leaving a page mid-fetch doesn't get stuck loading", async ({ page }) => {
+  await gotoHydrated(page, "/");
+  await page.getByRole("link", { name: "Post 1", exact: true }).click();
+  await page.getByRole("link", { name: "Home" }).click();
+  await page.getByRole("link", { name: "Post 1", exact: true }).click();
+
+  await expect(postBodies(page)).toContainText("Post 1: Timestamp");
+});
@@ -162,6 +162,16 @@ fn post_query() -> QueryScope<PostKey, Option<String>> {
         QueryOptions {
             default_value: None,
             refetch_interval: None,
+            align_refetch_to_completion: false,
+            refetch_hint: None,
+            validator: None,
+            migration: None,
+            min_fetch_interval: None,
+            revalidate_window: None,
+            priority: QueryPriority::Normal,
+            invalidate_mode: InvalidateMode::Immediate,
+            fetch_on_visible: None,
+            group: None,
             resource_option: Some(ResourceOption::NonBlocking),
             stale_time: Some(Duration::from_secs(5)),
             gc_time: Some(Duration::from_secs(60)),
@@ -207,7 +217,7 @@ fn Post(#[prop(into)] post_id: MaybeSignal<PostKey>) -> impl IntoView {
         state,
         refetch,
         ..
-    } = post_query().use_query(post_id);
+    } = post_query().use_query_signal(post_id);
 
     create_effect(move |_| logging::log!("State: {:#?}", state.get()));
 
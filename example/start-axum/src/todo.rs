@@ -251,12 +251,11 @@ fn AddTodoComponent() -> impl IntoView {
                 }
             });
 
-            // Optimistic update for individual TodoResponse.
+            // Optimistic update for individual TodoResponse, then invalidate it.
             let id = todo.id.clone();
-            todo_query.set_query_data(id.clone(), Ok(Some(todo)));
-
-            // Invalidate individual TodoResponse.
-            todo_query.invalidate_query(id);
+            todo_query.mutate_cache(|tx| {
+                tx.set(id.clone(), Ok(Some(todo))).invalidate(id);
+            });
 
             // Invalidate AllTodos.
             all_todos.invalidate_query(AllTodosTag);
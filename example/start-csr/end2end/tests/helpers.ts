@@ -0,0 +1,23 @@
+import { expect, type Page } from "@playwright/test";
+
+// Todo fetchers sleep for 1 second.
+export const FETCH_DELAY = 1000;
+
+/** Loads the todos page, and waits until the list has loaded. */
+export async function gotoTodos(page: Page) {
+  await page.goto("/todos");
+  await expect(page.locator(".animate-pulse")).toHaveCount(0);
+}
+
+/** Adds a todo with a unique title through the form, and returns the title. */
+export async function addTodo(page: Page) {
+  const title = `Todo ${Date.now()}`;
+  await page.locator("#title").fill(title);
+  await page.locator("#content").fill(`Content of ${title}`);
+  await page.getByRole("button", { name: "Create New" }).click();
+  return title;
+}
+
+export function todoItem(page: Page, title: string) {
+  return page.locator("li", { hasText: title });
+}
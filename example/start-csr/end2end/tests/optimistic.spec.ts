@@ -0,0 +1,30 @@
+import { test, expect } from "@playwright/test";
+import { FETCH_DELAY, addTodo, gotoTodos, todoItem } from "./helpers";
+
+test("added todos show before the mutation finishes", async ({ page }) => {
+  await gotoTodos(page);
+
+  const title = await addTodo(page);
+  const item = todoItem(page, title);
+  await expect(item).toBeVisible({ timeout: FETCH_DELAY / 2 });
+
+  // The temporary todo is replaced by the created one, rather than duplicated.
+  await page.waitForTimeout(FETCH_DELAY * 2);
+  await expect(item).toHaveCount(1);
+});
+
+test("deleted todos are removed before the mutation finishes", async ({ page }) => {
+  await gotoTodos(page);
+
+  const title = await addTodo(page);
+  const item = todoItem(page, title);
+  await expect(item).toBeVisible();
+  await page.waitForTimeout(FETCH_DELAY * 2);
+
+  await item.getByRole("button", { name: "Delete" }).click();
+  await expect(item).toHaveCount(0, { timeout: FETCH_DELAY / 2 });
+
+  // And stays removed once the list is refetched.
+  await page.waitForTimeout(FETCH_DELAY * 2);
+  await expect(item).toHaveCount(0);
+});
@@ -0,0 +1,21 @@
+import { test, expect } from "@playwright/test";
+import { FETCH_DELAY, addTodo, gotoTodos, todoItem } from "./helpers";
+
+for (const persister of ["LocalStorage", "IndexDB"]) {
+  test(`cached todos are restored from ${persister} after a reload`, async ({ page }) => {
+    await gotoTodos(page);
+    await page.locator("#query-persister").selectOption(persister);
+
+    const title = await addTodo(page);
+    const item = todoItem(page, title);
+    await expect(item).toBeVisible();
+    await page.waitForTimeout(FETCH_DELAY * 2);
+
+    // The todos live in memory, so only the persisted query has the todo after a reload.
+    await page.reload();
+    await expect(item).toBeVisible({ timeout: FETCH_DELAY / 2 });
+
+    // Until it is refetched.
+    await expect(item).toHaveCount(0);
+  });
+}
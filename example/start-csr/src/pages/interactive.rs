@@ -62,17 +62,9 @@ const CARD_CLASS: &str = "flex flex-col items-start justify-between w-full round
 #[component]
 fn TodoListItem(todo: Todo) -> impl IntoView {
     let delete = move |id: TodoId| async move {
-        let all_todos = all_todos_query();
-
-        all_todos.cancel_query(AllTodosKey);
-
-        all_todos.update_query_data_mut(AllTodosKey, move |todos| {
-            todos.retain(|t| t.id != id);
-        });
-
-        let _ = delete_todo(id).await;
-
-        all_todos.invalidate_query(AllTodosKey);
+        let _ = all_todos_query()
+            .optimistically_remove(AllTodosKey, move |t| t.id == id, delete_todo(id))
+            .await;
     };
 
     view! {
@@ -127,8 +119,6 @@ fn AddTodoEntry() -> impl IntoView {
     let add_todo = move || {
         let all_todos = all_todos_query();
         spawn_local(async move {
-            all_todos.cancel_query(AllTodosKey);
-
             let title = titleX.get_untracked();
             let content = contentX.get_untracked();
 
@@ -149,33 +139,22 @@ fn AddTodoEntry() -> impl IntoView {
                 TodoId(temp_id)
             };
 
-            // Optimistically add the todo to the list
-            all_todos.update_query_data_mut(AllTodosKey, {
-                let title = title.clone();
-                let content = content.clone();
-                |todos| {
-                    todos.push(Todo {
-                        id: temp_id,
-                        title,
-                        content,
-                        completed: false,
-                    })
-                }
-            });
+            // Optimistically add the todo to the list, and replace it with the real todo once added.
+            let temp_ids = all_todos.temp_ids(|todo: &Todo| todo.id);
+            let insert = temp_ids.insert(
+                AllTodosKey,
+                Todo {
+                    id: temp_id,
+                    title: title.clone(),
+                    content: content.clone(),
+                    completed: false,
+                },
+                add_todo(title, content),
+            );
 
             loading.set(true);
-            let todo = add_todo(title, content).await;
+            insert.await;
             loading.set(false);
-
-            // Replace the optimistic todo with the real todo
-            all_todos.update_query_data_mut(AllTodosKey, {
-                move |todos| {
-                    todos.retain(|t| t.id != temp_id);
-                    todos.push(todo);
-                }
-            });
-
-            all_todos.invalidate_query(AllTodosKey);
         })
     };
 
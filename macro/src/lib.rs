@@ -0,0 +1,238 @@
+#![forbid(unsafe_code)]
+
+//! Macros for [Leptos Query](https://docs.rs/leptos_query). Use them through the `leptos_query` crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{meta::ParseNestedMeta, parse_macro_input, FnArg, Ident, ItemFn, LitStr, ReturnType};
+
+/// Generates a `QueryScope` function for an async query fetcher.
+///
+/// See `leptos_query::query` for usage.
+#[proc_macro_attribute]
+pub fn query(args: TokenStream, input: TokenStream) -> TokenStream {
+    let mut attrs = QueryAttrs::default();
+    let parser = syn::meta::parser(|meta| attrs.parse(meta));
+    parse_macro_input!(args with parser);
+
+    let fetcher = parse_macro_input!(input as ItemFn);
+
+    match expand(attrs, fetcher) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct QueryAttrs {
+    name: Option<Ident>,
+    stale_time: Option<proc_macro2::TokenStream>,
+    gc_time: Option<proc_macro2::TokenStream>,
+    refetch_interval: Option<proc_macro2::TokenStream>,
+    min_fetch_interval: Option<proc_macro2::TokenStream>,
+    revalidate_window: Option<proc_macro2::TokenStream>,
+    priority: Option<proc_macro2::TokenStream>,
+    resource_option: Option<proc_macro2::TokenStream>,
+}
+
+impl QueryAttrs {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        let value = || -> syn::Result<LitStr> { meta.value()?.parse() };
+
+        if meta.path.is_ident("name") {
+            self.name = Some(value()?.parse()?);
+        } else if meta.path.is_ident("stale_time") {
+            self.stale_time = Some(time_setting(&value()?)?);
+        } else if meta.path.is_ident("gc_time") {
+            self.gc_time = Some(time_setting(&value()?)?);
+        } else if meta.path.is_ident("refetch_interval") {
+            self.refetch_interval = Some(time_setting(&value()?)?);
+        } else if meta.path.is_ident("min_fetch_interval") {
+            self.min_fetch_interval = Some(time_setting(&value()?)?);
+        } else if meta.path.is_ident("revalidate_window") {
+            self.revalidate_window = Some(time_setting(&value()?)?);
+        } else if meta.path.is_ident("priority") {
+            let lit = value()?;
+            let priority = match lit.value().as_str() {
+                "high" => quote!(High),
+                "normal" => quote!(Normal),
+                "low" => quote!(Low),
+                _ => {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        "expected one of \"high\", \"normal\" or \"low\"",
+                    ))
+                }
+            };
+            self.priority = Some(quote!(::leptos_query::QueryPriority::#priority));
+        } else if meta.path.is_ident("resource_option") {
+            let lit = value()?;
+            let option = match lit.value().as_str() {
+                "non_blocking" => quote!(NonBlocking),
+                "blocking" => quote!(Blocking),
+                "local" => quote!(Local),
+                _ => {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        "expected one of \"non_blocking\", \"blocking\" or \"local\"",
+                    ))
+                }
+            };
+            self.resource_option = Some(quote!(Some(::leptos_query::ResourceOption::#option)));
+        } else {
+            return Err(meta.error(
+                "unsupported query option, expected one of name, stale_time, gc_time, refetch_interval, min_fetch_interval, revalidate_window, priority or resource_option",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn time_setting(lit: &LitStr) -> syn::Result<proc_macro2::TokenStream> {
+    match parse_duration(&lit.value()) {
+        Ok(Some(millis)) => Ok(quote!(Some(::std::time::Duration::from_millis(#millis)))),
+        Ok(None) => Ok(quote!(None)),
+        Err(e) => Err(syn::Error::new(lit.span(), e)),
+    }
+}
+
+/// Parses "500ms", "30s", "5m", "1h", "1d", or "infinity" into milliseconds, matching `TimeSetting` parsing.
+/// Infinity is represented as None.
+fn parse_duration(value: &str) -> Result<Option<u64>, String> {
+    let value = value.trim();
+    if value == "infinity" {
+        return Ok(None);
+    }
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in duration \"{value}\""))?;
+    let (amount, unit) = value.split_at(split);
+    let amount = amount
+        .parse::<u64>()
+        .map_err(|_| format!("invalid duration \"{value}\""))?;
+
+    let multiplier = match unit {
+        "ms" => 1,
+        "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        "d" => 24 * 60 * 60 * 1000,
+        _ => {
+            return Err(format!(
+                "unknown unit \"{unit}\" in duration \"{value}\", expected one of ms, s, m, h, d"
+            ))
+        }
+    };
+
+    amount
+        .checked_mul(multiplier)
+        .map(Some)
+        .ok_or_else(|| format!("duration \"{value}\" is too large"))
+}
+
+fn expand(attrs: QueryAttrs, fetcher: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &fetcher.sig;
+
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new_spanned(
+            sig.fn_token,
+            "query fetchers must be async",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "query fetchers cannot be generic",
+        ));
+    }
+
+    let key = match (sig.inputs.len(), sig.inputs.first()) {
+        (1, Some(FnArg::Typed(arg))) => &arg.ty,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &sig.inputs,
+                "query fetchers must take exactly one argument, the query key",
+            ))
+        }
+    };
+    let value = match &sig.output {
+        ReturnType::Type(_, ty) => ty,
+        ReturnType::Default => {
+            return Err(syn::Error::new_spanned(
+                sig,
+                "query fetchers must return a value",
+            ))
+        }
+    };
+
+    let fetcher_name = &sig.ident;
+    let scope_name = attrs.name.unwrap_or_else(|| scope_name(fetcher_name));
+    let vis = &fetcher.vis;
+    let doc = format!("Query scope for [`{fetcher_name}`].");
+
+    let options = [
+        (quote!(stale_time), attrs.stale_time),
+        (quote!(gc_time), attrs.gc_time),
+        (quote!(refetch_interval), attrs.refetch_interval),
+        (quote!(min_fetch_interval), attrs.min_fetch_interval),
+        (quote!(revalidate_window), attrs.revalidate_window),
+        (quote!(priority), attrs.priority),
+        (quote!(resource_option), attrs.resource_option),
+    ]
+    .into_iter()
+    .filter_map(|(field, value)| value.map(|value| quote!(#field: #value,)));
+
+    Ok(quote! {
+        #fetcher
+
+        #[doc = #doc]
+        #vis fn #scope_name() -> ::leptos_query::QueryScope<#key, #value> {
+            ::leptos_query::create_query(
+                #fetcher_name,
+                ::leptos_query::QueryOptions {
+                    #(#options)*
+                    ..::leptos_query::QueryOptions::default()
+                },
+            )
+        }
+    })
+}
+
+// get_track -> track_query
+fn scope_name(fetcher: &Ident) -> Ident {
+    let name = fetcher.to_string();
+    let name = name
+        .strip_prefix("get_")
+        .or_else(|| name.strip_prefix("fetch_"))
+        .unwrap_or(&name);
+    format_ident!("{}_query", name, span = Span::call_site())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("500ms"), Ok(Some(500)));
+        assert_eq!(parse_duration("30s"), Ok(Some(30_000)));
+        assert_eq!(parse_duration("5m"), Ok(Some(300_000)));
+        assert_eq!(parse_duration("1h"), Ok(Some(3_600_000)));
+        assert_eq!(parse_duration("1d"), Ok(Some(86_400_000)));
+        assert_eq!(parse_duration("infinity"), Ok(None));
+
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("5 minutes").is_err());
+    }
+
+    #[test]
+    fn derives_scope_name() {
+        let name = |s: &str| scope_name(&format_ident!("{}", s)).to_string();
+        assert_eq!(name("get_track"), "track_query");
+        assert_eq!(name("fetch_user"), "user_query");
+        assert_eq!(name("todos"), "todos_query");
+    }
+}
@@ -0,0 +1,229 @@
+use std::rc::Rc;
+
+use crate::{
+    cache_observer::QueryCacheKey, query::Query, use_query_client, QueryKey, QueryScope,
+    QueryState, QueryValue,
+};
+
+/// A [`QueryScope`] with its key and value types erased, created with [`QueryScope::erase`].
+///
+/// Queries are addressed by their serialized key, as in [cache events](crate::cache_observer::CacheEvent).
+/// Useful for tooling that operates on many scopes at once, such as admin panels, without knowing their types.
+///
+/// # Example
+///
+/// ```
+/// use leptos_query::*;
+///
+/// fn registry() -> Vec<AnyQueryScope> {
+///     vec![user_query().erase(), track_query().erase()]
+/// }
+///
+/// // Invalidates every cached query, in every registered scope.
+/// fn invalidate_everything() {
+///     for scope in registry() {
+///         for key in scope.keys() {
+///             scope.invalidate(&key.0);
+///         }
+///     }
+/// }
+///
+/// fn user_query() -> QueryScope<u32, String> {
+///     create_query(|id| async move { format!("User {id}") }, QueryOptions::default())
+/// }
+///
+/// fn track_query() -> QueryScope<String, u64> {
+///     create_query(|_| async move { 0 }, QueryOptions::default())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct AnyQueryScope(Rc<dyn ErasedScope>);
+
+impl AnyQueryScope {
+    /// The name of the scope's key type.
+    pub fn key_type(&self) -> &'static str {
+        self.0.key_type()
+    }
+
+    /// The name of the scope's value type.
+    pub fn value_type(&self) -> &'static str {
+        self.0.value_type()
+    }
+
+    /// The serialized keys of the scope's queries in the cache.
+    pub fn keys(&self) -> Vec<QueryCacheKey> {
+        self.0.keys()
+    }
+
+    /// Invalidates a query. Returns whether it was invalidated. See [`QueryScope::invalidate_query`].
+    pub fn invalidate(&self, key: &str) -> bool {
+        self.0.invalidate(key)
+    }
+
+    /// Refetches a query with the scope's fetcher, even if the data is fresh. Returns whether the query is in the cache.
+    /// See [`QueryScope::refetch_query_force`].
+    pub fn refetch(&self, key: &str) -> bool {
+        self.0.refetch(key)
+    }
+
+    /// The state of a query, with its data serialized.
+    pub fn state(&self, key: &str) -> Option<QueryState<String>> {
+        self.0.state(key)
+    }
+
+    /// The state of a query as a JSON object, e.g. `{"state":"Loaded","updatedAt":1700000000000,"data":...}`.
+    ///
+    /// The data is included as serialized by its codec, which is JSON with the default serde codec.
+    /// `updatedAt` is in milliseconds since the Unix epoch, and is omitted along with `data` if the query has no data.
+    pub fn state_json(&self, key: &str) -> Option<String> {
+        let state = self.state(key)?;
+        let kind = state.kind();
+        Some(match state.query_data() {
+            Some(data) => format!(
+                r#"{{"state":"{kind:?}","updatedAt":{},"data":{}}}"#,
+                data.updated_at.as_unix_millis(),
+                data.data
+            ),
+            None => format!(r#"{{"state":"{kind:?}"}}"#),
+        })
+    }
+}
+
+impl std::fmt::Debug for AnyQueryScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnyQueryScope")
+            .field("key_type", &self.key_type())
+            .field("value_type", &self.value_type())
+            .finish()
+    }
+}
+
+impl<K, V> From<QueryScope<K, V>> for AnyQueryScope
+where
+    K: QueryKey + 'static,
+    V: QueryValue + 'static,
+{
+    fn from(scope: QueryScope<K, V>) -> Self {
+        AnyQueryScope(Rc::new(scope))
+    }
+}
+
+trait ErasedScope {
+    fn key_type(&self) -> &'static str;
+    fn value_type(&self) -> &'static str;
+    fn keys(&self) -> Vec<QueryCacheKey>;
+    fn invalidate(&self, key: &str) -> bool;
+    fn refetch(&self, key: &str) -> bool;
+    fn state(&self, key: &str) -> Option<QueryState<String>>;
+}
+
+impl<K, V> ErasedScope for QueryScope<K, V>
+where
+    K: QueryKey + 'static,
+    V: QueryValue + 'static,
+{
+    fn key_type(&self) -> &'static str {
+        std::any::type_name::<K>()
+    }
+
+    fn value_type(&self) -> &'static str {
+        std::any::type_name::<V>()
+    }
+
+    fn keys(&self) -> Vec<QueryCacheKey> {
+        queries(self)
+            .iter()
+            .map(|query| query.get_key().into())
+            .collect()
+    }
+
+    fn invalidate(&self, key: &str) -> bool {
+        find(self, key).is_some_and(|query| query.mark_invalid())
+    }
+
+    fn refetch(&self, key: &str) -> bool {
+        let Some(query) = find(self, key) else {
+            return false;
+        };
+        #[cfg(any(feature = "hydrate", feature = "csr"))]
+        {
+            let scope = self.clone();
+            let key = query.get_key().clone();
+            leptos::spawn_local(async move {
+                scope.refetch_query_force(key).await;
+            });
+        }
+        #[cfg(not(any(feature = "hydrate", feature = "csr")))]
+        let _ = query;
+        true
+    }
+
+    fn state(&self, key: &str) -> Option<QueryState<String>> {
+        let query = find(self, key)?;
+        Some(query.with_state(|state| {
+            state.map_data(|data| leptos::Serializable::ser(data).expect("Serialize Query State"))
+        }))
+    }
+}
+
+fn queries<K, V>(_: &QueryScope<K, V>) -> Vec<Query<K, V>>
+where
+    K: QueryKey + 'static,
+    V: QueryValue + 'static,
+{
+    use_query_client().cache.get_queries::<K, V>()
+}
+
+// Keys are matched serialized, as the key type can't be parsed back.
+fn find<K, V>(scope: &QueryScope<K, V>, key: &str) -> Option<Query<K, V>>
+where
+    K: QueryKey + 'static,
+    V: QueryValue + 'static,
+{
+    queries(scope)
+        .into_iter()
+        .find(|query| crate::cache_observer::make_cache_key(query.get_key()) == key)
+}
+
+#[cfg(all(test, not(any(feature = "csr", feature = "hydrate"))))]
+mod tests {
+    use leptos::*;
+
+    use crate::{create_query, provide_query_client, QueryOptions};
+
+    #[test]
+    fn erased_scope() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let scope = create_query(
+            |id: u32| async move { id.to_string() },
+            QueryOptions::default(),
+        );
+        let erased = scope.erase();
+        assert_eq!("u32", erased.key_type());
+        assert!(erased.keys().is_empty());
+
+        scope.set_query_data(1, "one".to_string());
+        // Other types with the same key are ignored.
+        create_query(|id: u32| async move { id }, QueryOptions::default()).set_query_data(2, 2);
+        assert_eq!(
+            vec!["1".to_string()],
+            erased.keys().into_iter().map(|k| k.0).collect::<Vec<_>>()
+        );
+
+        let json = erased.state_json("1").unwrap();
+        assert!(json.starts_with(r#"{"state":"Loaded","updatedAt":"#));
+        assert!(json.ends_with(r#","data":"one"}"#));
+        assert!(erased.state_json("2").is_none());
+
+        assert!(erased.invalidate("1"));
+        assert!(matches!(
+            erased.state("1"),
+            Some(crate::QueryState::Invalid(_))
+        ));
+        assert!(!erased.invalidate("2"));
+        assert!(erased.refetch("1"));
+        assert!(!erased.refetch("2"));
+    }
+}
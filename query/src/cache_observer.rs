@@ -1,6 +1,10 @@
-use std::{fmt::Debug, rc::Rc};
+use std::{
+    cell::RefCell, collections::HashMap, fmt::Debug, panic::Location, rc::Rc, time::Duration,
+};
 
-use crate::{query::Query, QueryState};
+use crate::{query::Query, HoldGuard, Instant, QueryOptions, QueryState};
+
+pub use crate::query_observer::ObserverKey;
 
 /// Subscribing to cache events
 pub trait CacheObserver {
@@ -15,12 +19,18 @@ pub enum CacheEvent {
     Created(CreatedQuery),
     /// A query that has been updated in the cache.
     Updated(SerializedQuery),
+    /// A query's data has been restored from the persister, rather than fetched. Sent after the corresponding `Updated`.
+    RestoredFromPersister(SerializedQuery),
     /// A query that has been removed from the cache.
-    Removed(QueryCacheKey),
+    Removed(QueryCacheKey, QueryTypes),
     /// A new observer has been added to the query.
     ObserverAdded(ObserverAdded),
     /// A observer has been removed from the query.
-    ObserverRemoved(QueryCacheKey),
+    /// Always paired with an earlier `ObserverAdded` with the same key and observer id.
+    ObserverRemoved(ObserverRemoved),
+    /// The stale or gc time of the query, merged across its observers, has changed.
+    /// Sent after the `ObserverAdded` or `ObserverRemoved` that changed it, so observers with the same options don't send one.
+    TimesChanged(TimesChanged),
 }
 
 impl CacheEvent {
@@ -42,14 +52,20 @@ impl CacheEvent {
         CacheEvent::Updated(payload)
     }
 
-    pub(crate) fn removed<K>(key: &K) -> Self
+    pub(crate) fn restored<K, V>(query: Query<K, V>) -> Self
     where
         K: crate::QueryKey + 'static,
+        V: crate::QueryValue + 'static,
     {
-        CacheEvent::Removed(key.into())
+        CacheEvent::RestoredFromPersister(query.into())
     }
 
-    pub(crate) fn observer_added<K, V>(key: &K, options: crate::QueryOptions<V>) -> Self
+    pub(crate) fn observer_added<K, V>(
+        key: &K,
+        observer: ObserverKey,
+        options: crate::QueryOptions<V>,
+        location: &'static Location<'static>,
+    ) -> Self
     where
         K: crate::QueryKey + 'static,
         V: crate::QueryValue + 'static,
@@ -58,15 +74,40 @@ impl CacheEvent {
             options.map_value(|v| leptos::Serializable::ser(&v).expect("Serialize Query Options"));
         CacheEvent::ObserverAdded(ObserverAdded {
             key: key.into(),
+            types: QueryTypes::of::<K, V>(),
+            observer,
             options,
+            location,
+        })
+    }
+
+    pub(crate) fn observer_removed<K, V>(key: &K, observer: ObserverKey) -> Self
+    where
+        K: crate::QueryKey + 'static,
+        V: 'static,
+    {
+        CacheEvent::ObserverRemoved(ObserverRemoved {
+            key: key.into(),
+            types: QueryTypes::of::<K, V>(),
+            observer,
         })
     }
 
-    pub(crate) fn observer_removed<K>(key: &K) -> Self
+    pub(crate) fn times_changed<K, V>(
+        key: &K,
+        stale_time: Option<Duration>,
+        gc_time: Option<Duration>,
+    ) -> Self
     where
         K: crate::QueryKey + 'static,
+        V: 'static,
     {
-        CacheEvent::ObserverRemoved(key.into())
+        CacheEvent::TimesChanged(TimesChanged {
+            key: key.into(),
+            types: QueryTypes::of::<K, V>(),
+            stale_time,
+            gc_time,
+        })
     }
 }
 
@@ -75,10 +116,16 @@ impl CacheEvent {
 pub struct CreatedQuery {
     /// Serialized query key.
     pub key: QueryCacheKey,
+    /// The key and value types of the query.
+    pub types: QueryTypes,
     /// Serialized query state.
     pub state: QueryState<String>,
-    /// Mark invalid
-    pub mark_invalid: Rc<dyn Fn() -> bool>,
+    /// Actions on the query, such as invalidating or evicting it.
+    pub actions: QueryActions,
+    /// If the query is stale. Shared with the query, so it is only valid until the query is removed.
+    pub is_stale: leptos::Signal<bool>,
+    /// Mount statistics for all queries with the same key and value types.
+    pub stats: leptos::Signal<crate::QueryStats>,
 }
 
 impl Debug for CreatedQuery {
@@ -86,30 +133,194 @@ impl Debug for CreatedQuery {
         f.debug_struct("CreatedQuery")
             .field("key", &self.key)
             .field("state", &self.state)
+            .field(
+                "is_stale",
+                &leptos::SignalGetUntracked::try_get_untracked(&self.is_stale),
+            )
             .finish()
     }
 }
 
+/// Actions on a query in the cache, for tools that only know it by its serialized key, such as the devtools.
+///
+/// Received with [`CacheEvent::Created`]. Once the query is removed from the cache, actions do nothing.
+/// Actions apply to the [`QueryClient`](crate::QueryClient) in context.
+#[derive(Clone)]
+pub struct QueryActions(Rc<dyn AnyQueryActions>);
+
+impl QueryActions {
+    /// Marks the query as invalid, which refetches it if it is observed. Returns false if the query isn't loaded.
+    pub fn invalidate(&self) -> bool {
+        self.0.invalidate()
+    }
+
+    /// Cancels any in-flight fetch and refetches the query with the fetcher of its observers.
+    /// Returns false if the query has no observer with a fetcher.
+    pub fn refetch(&self) -> bool {
+        self.0.refetch()
+    }
+
+    /// Removes the query from the cache. Returns false if it was already removed.
+    /// If the cache is being written to, the eviction is deferred and true is returned.
+    pub fn evict(&self) -> bool {
+        self.0.evict()
+    }
+
+    /// Replaces the data of the query with serialized data, as produced by [`Serializable::ser`](leptos::Serializable::ser).
+    /// Returns `Ok(false)` if the query was removed.
+    pub fn set_serialized_data(&self, data: &str) -> Result<bool, leptos::SerializationError> {
+        self.0.set_serialized_data(data)
+    }
+
+    /// Keeps the query in the cache until the returned guard is dropped. Returns `None` if the query was removed.
+    pub fn pin(&self) -> Option<HoldGuard> {
+        self.0.pin()
+    }
+}
+
+impl Debug for QueryActions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryActions").finish_non_exhaustive()
+    }
+}
+
+trait AnyQueryActions {
+    fn invalidate(&self) -> bool;
+    fn refetch(&self) -> bool;
+    fn evict(&self) -> bool;
+    fn set_serialized_data(&self, data: &str) -> Result<bool, leptos::SerializationError>;
+    fn pin(&self) -> Option<HoldGuard>;
+}
+
+// Actions hold on to the query, so they check that it is still the one in the cache.
+impl<K, V> AnyQueryActions for Query<K, V>
+where
+    K: crate::QueryKey + 'static,
+    V: crate::QueryValue + 'static,
+{
+    fn invalidate(&self) -> bool {
+        is_cached(self) && self.mark_invalid()
+    }
+
+    fn refetch(&self) -> bool {
+        let has_fetcher = self
+            .get_observers()
+            .iter()
+            .any(|observer| observer.get_fetcher().is_some());
+        if !is_cached(self) || !has_fetcher {
+            return false;
+        }
+        self.cancel();
+        self.execute();
+        true
+    }
+
+    fn evict(&self) -> bool {
+        crate::use_query_client().cache.evict_query(self.get_id())
+    }
+
+    fn set_serialized_data(&self, data: &str) -> Result<bool, leptos::SerializationError> {
+        let data = <V as leptos::Serializable>::de(data)?;
+        if !is_cached(self) {
+            return Ok(false);
+        }
+        self.set_state(QueryState::Loaded(crate::QueryData::now(data)));
+        Ok(true)
+    }
+
+    fn pin(&self) -> Option<HoldGuard> {
+        if !is_cached(self) {
+            return None;
+        }
+        self.hold();
+        let query = self.clone();
+        Some(HoldGuard::new(move || query.release()))
+    }
+}
+
+fn is_cached<K, V>(query: &Query<K, V>) -> bool
+where
+    K: crate::QueryKey + 'static,
+    V: crate::QueryValue + 'static,
+{
+    crate::use_query_client()
+        .cache
+        .get_query_by_id::<K, V>(query.get_id())
+        .is_some()
+}
+
 /// A query that has been updated in the cache.
 #[derive(Clone, Debug)]
 pub struct SerializedQuery {
     /// The key of the query.
     pub key: QueryCacheKey,
+    /// The key and value types of the query.
+    pub types: QueryTypes,
     /// The serialized state of the query.
     pub state: QueryState<String>,
 }
 
 /// A serialized key for a query in the cache.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QueryCacheKey(pub String);
 
+/// The key and value type names of a query.
+/// Queries of different types may have the same [`QueryCacheKey`], e.g. a `u32` id for both posts and users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryTypes {
+    /// The name of the key type.
+    pub key: &'static str,
+    /// The name of the value type.
+    pub value: &'static str,
+}
+
+impl QueryTypes {
+    pub(crate) fn of<K: 'static, V: 'static>() -> Self {
+        QueryTypes {
+            key: std::any::type_name::<K>(),
+            value: std::any::type_name::<V>(),
+        }
+    }
+}
+
 /// A new observer has been added to the query.
 #[derive(Clone, Debug)]
 pub struct ObserverAdded {
     /// The key of the query.
     pub key: QueryCacheKey,
+    /// The key and value types of the query.
+    pub types: QueryTypes,
+    /// The observer, which is also sent when it is removed.
+    pub observer: ObserverKey,
     /// The observers options.
     pub options: crate::QueryOptions<String>,
+    /// Where the observer was created, e.g. the `use_query` call in a component.
+    pub location: &'static Location<'static>,
+}
+
+/// An observer has been removed from the query.
+#[derive(Clone, Debug)]
+pub struct ObserverRemoved {
+    /// The key of the query.
+    pub key: QueryCacheKey,
+    /// The key and value types of the query.
+    pub types: QueryTypes,
+    /// The observer that was removed.
+    pub observer: ObserverKey,
+}
+
+/// The stale and gc times in effect for a query, merged across its observers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimesChanged {
+    /// The key of the query.
+    pub key: QueryCacheKey,
+    /// The key and value types of the query.
+    pub types: QueryTypes,
+    /// The shortest stale time of the observers, or `None` if the query never goes stale.
+    pub stale_time: Option<Duration>,
+    /// The longest gc time of any observer so far, or `None` if the query is never garbage collected.
+    pub gc_time: Option<Duration>,
 }
 
 impl<K, V> From<Query<K, V>> for CreatedQuery
@@ -123,12 +334,17 @@ where
             state.map_data(|data| leptos::Serializable::ser(data).expect("Serialize Query State"))
         });
 
-        let mark_invalid = Rc::new(move || query.mark_invalid());
+        let is_stale = query.stale_signal().into();
+        let stats = query.stats_signal();
+        let actions = QueryActions(Rc::new(query));
 
         CreatedQuery {
             key,
+            types: QueryTypes::of::<K, V>(),
             state,
-            mark_invalid,
+            actions,
+            is_stale,
+            stats,
         }
     }
 }
@@ -144,7 +360,11 @@ where
             state.map_data(|data| leptos::Serializable::ser(data).expect("Serialize Query State"))
         });
 
-        SerializedQuery { key, state }
+        SerializedQuery {
+            key,
+            types: QueryTypes::of::<K, V>(),
+            state,
+        }
     }
 }
 
@@ -163,3 +383,525 @@ where
 {
     format!("{key:?}")
 }
+
+/// Logs a warning when observers of the same key use conflicting options, in debug builds.
+///
+/// Options of all observers of a key are merged, so a `use_query` with a short `stale_time`
+/// also affects every other `use_query` of that key. The warning points to both call sites.
+/// Queries of different key or value types are separate, even if their keys serialize the same.
+///
+/// ```
+/// use leptos_query::{cache_observer::OptionConflictLogger, *};
+///
+/// fn App() {
+///     provide_query_client();
+///     use_query_client().register_cache_observer(OptionConflictLogger::default());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct OptionConflictLogger {
+    observers: RefCell<HashMap<(QueryCacheKey, QueryTypes), Vec<ObserverAdded>>>,
+}
+
+impl OptionConflictLogger {
+    // Conflicts between the new observer and each active observer of the same key.
+    fn conflicts(&self, added: &ObserverAdded) -> Vec<String> {
+        let observers = self.observers.borrow();
+        let Some(existing) = observers.get(&(added.key.clone(), added.types)) else {
+            return Vec::new();
+        };
+        existing
+            .iter()
+            .filter_map(|other| {
+                let fields = conflicting_fields(&other.options, &added.options);
+                (!fields.is_empty()).then(|| {
+                    format!(
+                        "Conflicting options for query {:?} between {} and {}: {}",
+                        added.key.0,
+                        other.location,
+                        added.location,
+                        fields.join(", ")
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+impl CacheObserver for OptionConflictLogger {
+    fn process_cache_event(&self, event: CacheEvent) {
+        match event {
+            CacheEvent::ObserverAdded(added) => {
+                for conflict in self.conflicts(&added) {
+                    leptos::logging::debug_warn!("{conflict}");
+                }
+                self.observers
+                    .borrow_mut()
+                    .entry((added.key.clone(), added.types))
+                    .or_default()
+                    .push(added);
+            }
+            CacheEvent::ObserverRemoved(removed) => {
+                let mut observers = self.observers.borrow_mut();
+                let query = (removed.key, removed.types);
+                if let Some(existing) = observers.get_mut(&query) {
+                    existing.retain(|o| o.observer != removed.observer);
+                    if existing.is_empty() {
+                        observers.remove(&query);
+                    }
+                }
+            }
+            CacheEvent::Removed(key, types) => {
+                self.observers.borrow_mut().remove(&(key, types));
+            }
+            CacheEvent::Created(_)
+            | CacheEvent::Updated(_)
+            | CacheEvent::RestoredFromPersister(_)
+            | CacheEvent::TimesChanged(_) => {}
+        }
+    }
+}
+
+/// Logs a warning for queries that look leaked, in debug builds. Catches timer and cleanup bugs, in app code and in the cache itself.
+///
+/// - Queries that have had no observers for longer than `gc_factor` times their `gc_time`, i.e. garbage collection didn't happen.
+/// - Queries whose observer count keeps growing, e.g. from observers that are never cleaned up.
+///   Reported once the count reaches `observer_threshold`, and again each time it doubles.
+///
+/// Leaks are checked whenever a cache event is received, so it is meant for development.
+/// Queries of different key or value types are tracked separately, even if their keys serialize the same.
+///
+/// Queries kept on purpose, with [`QueryClient::hold_query`](crate::QueryClient::hold_query), [`QueryClient::pause_gc`](crate::QueryClient::pause_gc)
+/// or a postponing [`QueryClient::on_before_gc`](crate::QueryClient::on_before_gc) hook, aren't visible in cache events, so they are reported too.
+///
+/// ```
+/// use leptos_query::{cache_observer::LeakDetector, *};
+///
+/// fn App() {
+///     provide_query_client();
+///     use_query_client().register_cache_observer(LeakDetector::default().gc_factor(3));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LeakDetector {
+    gc_factor: u32,
+    observer_threshold: usize,
+    queries: RefCell<HashMap<(QueryCacheKey, QueryTypes), Liveness>>,
+}
+
+#[derive(Debug, Default)]
+struct Liveness {
+    observers: usize,
+    // The longest gc time of any observer. Queries that never expire aren't reported.
+    gc_time: Option<Duration>,
+    never_expires: bool,
+    unobserved_since: Option<Instant>,
+    reported_unobserved: bool,
+    next_observer_report: usize,
+}
+
+impl Default for LeakDetector {
+    fn default() -> Self {
+        Self {
+            gc_factor: 2,
+            observer_threshold: 100,
+            queries: RefCell::default(),
+        }
+    }
+}
+
+impl LeakDetector {
+    /// Sets how many times its gc time a query may go without observers before it is reported. Default is 2.
+    pub fn gc_factor(self, gc_factor: u32) -> Self {
+        Self { gc_factor, ..self }
+    }
+
+    /// Sets the observer count at which a query is first reported. Default is 100.
+    pub fn observer_threshold(self, observer_threshold: usize) -> Self {
+        Self {
+            observer_threshold,
+            ..self
+        }
+    }
+
+    // Updates the tracked queries, and returns the observer count report, if any.
+    fn track(&self, event: &CacheEvent, now: Instant) -> Option<String> {
+        let mut queries = self.queries.borrow_mut();
+        match event {
+            CacheEvent::Created(created) => {
+                queries
+                    .entry((created.key.clone(), created.types))
+                    .or_insert_with(|| Liveness {
+                        unobserved_since: Some(now),
+                        ..Liveness::default()
+                    });
+            }
+            CacheEvent::ObserverAdded(added) => {
+                let query = queries.entry((added.key.clone(), added.types)).or_default();
+                query.observers += 1;
+                query.unobserved_since = None;
+                query.reported_unobserved = false;
+                match added.options.gc_time {
+                    Some(gc_time) => query.gc_time = query.gc_time.max(Some(gc_time)),
+                    None => query.never_expires = true,
+                }
+
+                let next_report = query.next_observer_report.max(self.observer_threshold);
+                if query.observers >= next_report {
+                    query.next_observer_report = next_report.saturating_mul(2);
+                    return Some(format!(
+                        "Query {:?} has {} observers, which may never be cleaned up. Latest added at {}",
+                        added.key.0, query.observers, added.location
+                    ));
+                }
+            }
+            CacheEvent::ObserverRemoved(removed) => {
+                if let Some(query) = queries.get_mut(&(removed.key.clone(), removed.types)) {
+                    query.observers = query.observers.saturating_sub(1);
+                    if query.observers == 0 {
+                        query.unobserved_since = Some(now);
+                    }
+                }
+            }
+            CacheEvent::Removed(key, types) => {
+                queries.remove(&(key.clone(), *types));
+            }
+            CacheEvent::Updated(_)
+            | CacheEvent::RestoredFromPersister(_)
+            | CacheEvent::TimesChanged(_) => {}
+        }
+        None
+    }
+
+    // Queries that should have been garbage collected by `now`. Each is only reported once per period without observers.
+    fn check(&self, now: Instant) -> Vec<String> {
+        let mut queries = self.queries.borrow_mut();
+        queries
+            .iter_mut()
+            .filter_map(|((key, _), query)| {
+                let gc_time = query.gc_time.filter(|_| !query.never_expires)?;
+                let unobserved = now - query.unobserved_since?;
+                // Overflows are too long to ever be reached.
+                let limit = gc_time.checked_mul(self.gc_factor)?;
+                if query.reported_unobserved || unobserved <= limit {
+                    return None;
+                }
+                query.reported_unobserved = true;
+                Some(format!(
+                    "Query {:?} has had no observers for {unobserved:?}, more than {}x its gc_time of {gc_time:?}. It should have been garbage collected",
+                    key.0, self.gc_factor
+                ))
+            })
+            .collect()
+    }
+}
+
+impl CacheObserver for LeakDetector {
+    fn process_cache_event(&self, event: CacheEvent) {
+        let now = Instant::now();
+        let reports = self.track(&event, now).into_iter().chain(self.check(now));
+        for report in reports {
+            leptos::logging::debug_warn!("{report}");
+        }
+    }
+}
+
+// Describes each merged option that differs, along with the rule that resolves it.
+fn conflicting_fields(a: &QueryOptions<String>, b: &QueryOptions<String>) -> Vec<String> {
+    fn time(time: Option<Duration>) -> String {
+        time.map_or_else(|| "infinity".to_string(), |t| format!("{t:?}"))
+    }
+
+    let mut fields = Vec::new();
+    let times = [
+        ("stale_time", a.stale_time, b.stale_time, "MINIMUM"),
+        ("gc_time", a.gc_time, b.gc_time, "MAXIMUM"),
+        (
+            "refetch_interval",
+            a.refetch_interval,
+            b.refetch_interval,
+            "MINIMUM",
+        ),
+        (
+            "min_fetch_interval",
+            a.min_fetch_interval,
+            b.min_fetch_interval,
+            "MAXIMUM",
+        ),
+        (
+            "revalidate_window",
+            a.revalidate_window,
+            b.revalidate_window,
+            "MAXIMUM",
+        ),
+    ];
+    for (name, a, b, rule) in times {
+        if a != b {
+            fields.push(format!(
+                "{name} {} vs {} (the {rule} is used)",
+                time(a),
+                time(b)
+            ));
+        }
+    }
+    if a.priority != b.priority {
+        fields.push(format!(
+            "priority {:?} vs {:?} (the HIGHEST is used)",
+            a.priority, b.priority
+        ));
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{query_observer::next_id, QueryPriority};
+
+    fn added(key: &str, observer: ObserverKey, options: QueryOptions<String>) -> CacheEvent {
+        CacheEvent::ObserverAdded(ObserverAdded {
+            key: QueryCacheKey(key.to_string()),
+            types: QueryTypes::of::<String, String>(),
+            observer,
+            options,
+            location: Location::caller(),
+        })
+    }
+
+    fn conflicts(logger: &OptionConflictLogger, event: &CacheEvent) -> Vec<String> {
+        match event {
+            CacheEvent::ObserverAdded(added) => logger.conflicts(added),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn detects_leaked_queries() {
+        let detector = LeakDetector::default().observer_threshold(2);
+        let options = QueryOptions::<String>::default().set_gc_time("10s");
+        let start = Instant::now();
+        let later = |secs| Instant(start.0 + Duration::from_secs(secs));
+        let (first, second) = (next_id(), next_id());
+
+        // Reported once the threshold is reached, then each time the count doubles.
+        assert!(detector
+            .track(&added("a", first, options.clone()), start)
+            .is_none());
+        assert!(detector
+            .track(&added("a", second, options.clone()), start)
+            .is_some());
+        assert!(detector
+            .track(&added("a", next_id(), options.clone()), start)
+            .is_none());
+        assert!(detector
+            .track(&added("a", next_id(), options.clone()), start)
+            .is_some());
+
+        // Queries that never expire aren't reported.
+        detector.track(
+            &added("b", next_id(), options.clone().set_gc_time(None)),
+            start,
+        );
+
+        let keys = detector
+            .queries
+            .borrow()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        for (key, types) in keys {
+            let observers = detector.queries.borrow()[&(key.clone(), types)].observers;
+            for _ in 0..observers {
+                detector.track(
+                    &CacheEvent::ObserverRemoved(ObserverRemoved {
+                        key: key.clone(),
+                        types,
+                        observer: first,
+                    }),
+                    start,
+                );
+            }
+        }
+
+        assert!(detector.check(later(20)).is_empty());
+        let leaks = detector.check(later(21));
+        assert_eq!(leaks.len(), 1);
+        assert!(leaks[0].contains("\"a\""));
+        // Only reported once.
+        assert!(detector.check(later(60)).is_empty());
+
+        detector.track(
+            &CacheEvent::Removed(
+                QueryCacheKey("a".to_string()),
+                QueryTypes::of::<String, String>(),
+            ),
+            later(60),
+        );
+        assert!(detector
+            .queries
+            .borrow()
+            .get(&(
+                QueryCacheKey("a".to_string()),
+                QueryTypes::of::<String, String>()
+            ))
+            .is_none());
+    }
+
+    #[test]
+    fn leaks_are_tracked_per_type() {
+        let detector = LeakDetector::default()
+            .observer_threshold(2)
+            .gc_factor(u32::MAX);
+        let start = Instant::now();
+        let of_type = |types, gc_time: Duration| {
+            CacheEvent::ObserverAdded(ObserverAdded {
+                key: QueryCacheKey("1".to_string()),
+                types,
+                observer: next_id(),
+                options: QueryOptions::<String>::default().set_gc_time(gc_time),
+                location: Location::caller(),
+            })
+        };
+        let (posts, users) = (
+            QueryTypes::of::<u32, String>(),
+            QueryTypes::of::<u32, Vec<String>>(),
+        );
+
+        // Observers of other types with the same key aren't counted together.
+        assert!(detector
+            .track(&of_type(posts, Duration::from_secs(10)), start)
+            .is_none());
+        assert!(detector
+            .track(&of_type(users, Duration::MAX), start)
+            .is_none());
+
+        // Removing one type keeps tracking the other.
+        detector.track(
+            &CacheEvent::Removed(QueryCacheKey("1".to_string()), users),
+            start,
+        );
+        assert_eq!(1, detector.queries.borrow().len());
+
+        // A gc time that overflows once multiplied is never reported.
+        detector.track(&of_type(users, Duration::MAX), start);
+        for types in [posts, users] {
+            detector.track(
+                &CacheEvent::ObserverRemoved(ObserverRemoved {
+                    key: QueryCacheKey("1".to_string()),
+                    types,
+                    observer: next_id(),
+                }),
+                start,
+            );
+        }
+        assert!(detector
+            .check(Instant(start.0 + Duration::from_secs(3600 * 24 * 365)))
+            .is_empty());
+    }
+
+    #[test]
+    fn detects_conflicting_options() {
+        let logger = OptionConflictLogger::default();
+        let options = QueryOptions::<String>::default().set_stale_time("10s");
+        let first = next_id();
+
+        logger.process_cache_event(added("a", first, options.clone()));
+
+        // Same options don't conflict, and neither do other keys.
+        let same = added("a", next_id(), options.clone());
+        assert!(conflicts(&logger, &same).is_empty());
+        let other_key = added("b", next_id(), options.clone().set_stale_time("1s"));
+        assert!(conflicts(&logger, &other_key).is_empty());
+
+        let conflicting = added(
+            "a",
+            next_id(),
+            options
+                .clone()
+                .set_stale_time("1s")
+                .set_priority(QueryPriority::High),
+        );
+        let found = conflicts(&logger, &conflicting);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].contains("stale_time 10s vs 1s (the MINIMUM is used)"));
+        assert!(found[0].contains("priority Normal vs High (the HIGHEST is used)"));
+        assert!(found[0].contains(file!()));
+
+        // Removed observers no longer conflict.
+        logger.process_cache_event(CacheEvent::ObserverRemoved(ObserverRemoved {
+            key: QueryCacheKey("a".to_string()),
+            types: QueryTypes::of::<String, String>(),
+            observer: first,
+        }));
+        assert!(conflicts(&logger, &conflicting).is_empty());
+    }
+
+    #[test]
+    fn options_of_other_types_dont_conflict() {
+        let logger = OptionConflictLogger::default();
+        let of_type = |types, stale_time| {
+            CacheEvent::ObserverAdded(ObserverAdded {
+                key: QueryCacheKey("1".to_string()),
+                types,
+                observer: next_id(),
+                options: QueryOptions::<String>::default().set_stale_time(stale_time),
+                location: Location::caller(),
+            })
+        };
+        let (posts, users) = (
+            QueryTypes::of::<u32, String>(),
+            QueryTypes::of::<u32, Vec<String>>(),
+        );
+
+        logger.process_cache_event(of_type(posts, "10s"));
+        assert!(conflicts(&logger, &of_type(users, "1s")).is_empty());
+        logger.process_cache_event(of_type(users, "1s"));
+        assert_eq!(1, conflicts(&logger, &of_type(posts, "1s")).len());
+
+        // Removing one type keeps the observers of the other.
+        logger.process_cache_event(CacheEvent::Removed(QueryCacheKey("1".to_string()), users));
+        assert_eq!(1, conflicts(&logger, &of_type(posts, "1s")).len());
+    }
+
+    #[cfg(not(any(feature = "csr", feature = "hydrate")))]
+    #[test]
+    fn query_actions() {
+        use crate::{provide_query_client, use_query_client};
+
+        let _ = leptos::create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+        client.set_query_data::<u32, String>(1, "a".to_string());
+        let query = client.cache.get_query::<u32, String>(&1).unwrap();
+        let CreatedQuery { actions, .. } = query.into();
+
+        assert!(actions.invalidate());
+        assert!(matches!(
+            client.peek_query_state::<u32, String>(&1),
+            Some(QueryState::Invalid(_))
+        ));
+        // Not observed, so there is no fetcher.
+        assert!(!actions.refetch());
+
+        assert!(actions.set_serialized_data(r#""b""#).unwrap());
+        assert!(actions.set_serialized_data("not json").is_err());
+        assert_eq!(
+            Some("b".to_string()),
+            client
+                .peek_query_state::<u32, String>(&1)
+                .and_then(|state| state.data().cloned())
+        );
+
+        let pin = actions.pin();
+        assert!(pin.is_some());
+        assert!(actions.evict());
+
+        // Removed queries are left alone.
+        assert!(!actions.evict());
+        assert!(!actions.invalidate());
+        assert!(actions.pin().is_none());
+        assert!(!actions.set_serialized_data(r#""c""#).unwrap());
+        assert!(client.peek_query_state::<u32, String>(&1).is_none());
+    }
+}
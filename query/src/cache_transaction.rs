@@ -0,0 +1,76 @@
+use std::borrow::Borrow;
+
+use crate::{QueryClient, QueryKey, QueryScope, QueryValue};
+
+/// Cache updates for queries of one scope, applied together once the transaction closure returns.
+/// Created by [`QueryScope::mutate_cache`].
+///
+/// Nothing is applied until the closure returns, so a panic inside it leaves the cache untouched.
+/// The updates are applied in a single [`batch`](leptos::batch), so effects and observers only run once, after all of them.
+pub struct CacheTransaction<'a, K, V> {
+    scope: &'a QueryScope<K, V>,
+    #[allow(clippy::type_complexity)]
+    ops: Vec<Box<dyn FnOnce(&QueryClient)>>,
+}
+
+impl<'a, K, V> CacheTransaction<'a, K, V>
+where
+    K: QueryKey + 'static,
+    V: QueryValue + 'static,
+{
+    pub(crate) fn new(scope: &'a QueryScope<K, V>) -> Self {
+        CacheTransaction {
+            scope,
+            ops: Vec::new(),
+        }
+    }
+
+    /// Sets the data of a query. See [`QueryScope::set_query_data`].
+    pub fn set(&mut self, key: K, data: V) -> &mut Self {
+        let key = self.scope.normalize_key(key);
+        self.push(move |client| client.set_query_data(key, data))
+    }
+
+    /// Updates the data of a query. If the updater returns `None`, the data is not updated. See [`QueryScope::update_query_data`].
+    pub fn update(
+        &mut self,
+        key: K,
+        updater: impl FnOnce(Option<&V>) -> Option<V> + 'static,
+    ) -> &mut Self {
+        let key = self.scope.normalize_key(key);
+        self.push(move |client| client.update_query_data(key, updater))
+    }
+
+    /// Mutates the data of an existing query. See [`QueryScope::update_query_data_mut`].
+    pub fn update_mut(
+        &mut self,
+        key: impl Borrow<K>,
+        updater: impl FnOnce(&mut V) + 'static,
+    ) -> &mut Self {
+        let key = self.scope.normalize_key(key.borrow().clone());
+        self.push(move |client| {
+            client.update_query_data_mut::<K, V>(key, updater);
+        })
+    }
+
+    /// Invalidates a query. See [`QueryScope::invalidate_query`].
+    pub fn invalidate(&mut self, key: impl Borrow<K>) -> &mut Self {
+        let key = self.scope.normalize_key(key.borrow().clone());
+        self.push(move |client| {
+            client.invalidate_query::<K, V>(key);
+        })
+    }
+
+    fn push(&mut self, op: impl FnOnce(&QueryClient) + 'static) -> &mut Self {
+        self.ops.push(Box::new(op));
+        self
+    }
+
+    pub(crate) fn commit(self, client: &QueryClient) {
+        leptos::batch(|| {
+            for op in self.ops {
+                op(client)
+            }
+        })
+    }
+}
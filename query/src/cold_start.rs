@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use crate::{Instant, QueryClient, QueryPlugin};
+
+/// Plugin that invalidates persisted data if the app was closed for longer than a window, no matter when the data was fetched.
+///
+/// While the app runs, the time it was last active is written to the [persister](crate::query_persister) on a heartbeat.
+/// On startup, if that was more than `window` ago, every restored query is invalidated once restored.
+/// Their data is still shown, but observed queries refetch right away, and others when next used, rather than trusting data that may be days old.
+///
+/// Must be installed after the persister. Only runs on the client (`csr` or `hydrate`).
+///
+/// Example
+/// ```
+/// use leptos_query::{query_persister::QueryPersister, *};
+/// use std::time::Duration;
+///
+/// fn provide_client(persister: impl QueryPersister + Clone + 'static) {
+///     QueryClientBuilder::new()
+///         .persister(persister)
+///         .plugin(ColdStart::new(Duration::from_secs(12 * 60 * 60)))
+///         .provide();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ColdStart {
+    window: Duration,
+    #[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+    heartbeat: Duration,
+}
+
+// Stored alongside the queries, so it is cleared with them.
+#[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+const LAST_ACTIVE_KEY: &str = "__leptos_query_last_active";
+
+impl ColdStart {
+    /// Invalidates restored data if the app was closed for longer than `window`. Records activity every minute.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            heartbeat: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets how often the time the app was last active is recorded.
+    /// The app may have been closed for up to this long more than measured.
+    pub fn heartbeat(self, heartbeat: Duration) -> Self {
+        Self { heartbeat, ..self }
+    }
+
+    // Whether the app was closed for longer than the window. Without a record, there is no persisted data to distrust.
+    #[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+    fn is_cold(&self, last_active: Option<Instant>, now: Instant) -> bool {
+        last_active.is_some_and(|last_active| now - last_active > self.window)
+    }
+}
+
+impl QueryPlugin for ColdStart {
+    fn install(&self, client: &QueryClient) {
+        cfg_if::cfg_if! {
+            if #[cfg(any(feature = "csr", feature = "hydrate"))] {
+                use futures::FutureExt;
+                use leptos::*;
+                use crate::query_persister::PersistQueryData;
+
+                let Some(persister) = client.cache.persister() else {
+                    logging::debug_warn!("ColdStart requires a persister. Install it after adding one.");
+                    return;
+                };
+
+                let policy = *self;
+                let cold = {
+                    let persister = persister.clone();
+                    async move {
+                        let last_active = persister
+                            .retrieve(LAST_ACTIVE_KEY)
+                            .await
+                            .map(|record| Instant::from_unix_millis(record.updated_at));
+                        policy.is_cold(last_active, Instant::now())
+                    }
+                }
+                .boxed_local()
+                .shared();
+                client.cache.set_cold_start(cold.clone());
+
+                let beat = move || {
+                    let persister = persister.clone();
+                    spawn_local(async move {
+                        let record = PersistQueryData {
+                            value: String::new(),
+                            updated_at: Instant::now().as_unix_millis(),
+                        };
+                        persister.persist(LAST_ACTIVE_KEY, record).await;
+                    })
+                };
+
+                // The previous record is read before it is overwritten.
+                let owner = client.cache.owner();
+                spawn_local(async move {
+                    cold.await;
+                    beat();
+                    match set_interval_with_handle(beat, policy.heartbeat) {
+                        Ok(handle) => with_owner(owner, || on_cleanup(move || handle.clear())),
+                        Err(e) => logging::debug_warn!("Failed to start heartbeat: {e:?}"),
+                    }
+                });
+            } else {
+                let _ = client;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cold_after_window() {
+        let policy = ColdStart::new(Duration::from_secs(60 * 60));
+        let at = |secs| Instant(Duration::from_secs(secs));
+
+        assert!(!policy.is_cold(None, at(10_000)));
+        assert!(!policy.is_cold(Some(at(0)), at(3600)));
+        assert!(policy.is_cold(Some(at(0)), at(3601)));
+        // A clock set back counts as active.
+        assert!(!policy.is_cold(Some(at(5000)), at(0)));
+    }
+}
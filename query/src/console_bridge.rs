@@ -0,0 +1,147 @@
+use crate::{QueryClient, QueryPlugin};
+
+/// Exposes the query client to the browser console as `window.__leptosQuery`, in debug builds.
+///
+/// Handy for poking the cache while debugging:
+/// - `__leptosQuery.keys()` lists the serialized keys of all queries in the cache.
+/// - `__leptosQuery.invalidate(key)` invalidates the queries with a serialized key, e.g. `"TodoId(1)"`, and returns how many were invalidated.
+/// - `__leptosQuery.invalidateType(name)` invalidates the queries with a key or value type name. See [`QueryClient::invalidate_queries_by_type_name`].
+/// - `__leptosQuery.clear()` removes all queries.
+///
+/// Installing does nothing in release builds, or outside the browser.
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// #[component]
+/// fn App() -> impl IntoView {
+///     QueryClientBuilder::new().plugin(ConsoleBridge).provide();
+///
+///     // Rest of App...
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleBridge;
+
+impl QueryPlugin for ConsoleBridge {
+    fn install(&self, client: &QueryClient) {
+        #[cfg(all(debug_assertions, any(feature = "hydrate", feature = "csr")))]
+        attach(client.clone());
+        #[cfg(not(all(debug_assertions, any(feature = "hydrate", feature = "csr"))))]
+        let _ = client;
+    }
+}
+
+#[cfg(all(debug_assertions, any(feature = "hydrate", feature = "csr")))]
+fn attach(client: QueryClient) {
+    use js_sys::{
+        wasm_bindgen::{closure::Closure, JsValue},
+        Array, Object, Reflect,
+    };
+
+    let bridge_object = Object::new();
+    let set = |name: &str, function: JsValue| {
+        if let Err(e) = Reflect::set(&bridge_object, &name.into(), &function) {
+            leptos::logging::debug_warn!("Failed to expose {name} to the console: {e:?}");
+        }
+    };
+    let bridge = Bridge(client);
+
+    set("keys", {
+        let bridge = bridge.clone();
+        Closure::<dyn Fn() -> Array>::new(move || {
+            bridge
+                .keys()
+                .into_iter()
+                .map(|key| JsValue::from_str(&key))
+                .collect()
+        })
+        .into_js_value()
+    });
+    set("invalidate", {
+        let bridge = bridge.clone();
+        Closure::<dyn Fn(String) -> u32>::new(move |key: String| bridge.invalidate(key) as u32)
+            .into_js_value()
+    });
+    set("invalidateType", {
+        let bridge = bridge.clone();
+        Closure::<dyn Fn(String) -> u32>::new(move |name: String| {
+            bridge.invalidate_type(&name) as u32
+        })
+        .into_js_value()
+    });
+    set(
+        "clear",
+        Closure::<dyn Fn()>::new(move || bridge.clear()).into_js_value(),
+    );
+
+    if let Err(e) = Reflect::set(&leptos::window(), &"__leptosQuery".into(), &bridge_object) {
+        leptos::logging::debug_warn!("Failed to expose the query client to the console: {e:?}");
+    }
+}
+
+// The console calls in from outside the reactive tree, so each call runs under the client's owner.
+#[cfg(any(test, all(debug_assertions, any(feature = "hydrate", feature = "csr"))))]
+#[derive(Clone)]
+struct Bridge(QueryClient);
+
+#[cfg(any(test, all(debug_assertions, any(feature = "hydrate", feature = "csr"))))]
+impl Bridge {
+    fn keys(&self) -> Vec<String> {
+        leptos::with_owner(self.0.cache.owner(), || {
+            self.0.cache.keys().into_iter().map(|key| key.0).collect()
+        })
+    }
+
+    fn invalidate(&self, key: String) -> usize {
+        use crate::{cache_observer::QueryCacheKey, InvalidationManifest};
+
+        let manifest = InvalidationManifest::new().cache_key(QueryCacheKey(key));
+        leptos::with_owner(self.0.cache.owner(), || {
+            self.0.apply_invalidations(&manifest)
+        })
+    }
+
+    fn invalidate_type(&self, name: &str) -> usize {
+        leptos::with_owner(self.0.cache.owner(), || {
+            self.0.invalidate_queries_by_type_name(name)
+        })
+    }
+
+    fn clear(&self) {
+        leptos::with_owner(self.0.cache.owner(), || self.0.clear())
+    }
+}
+
+#[cfg(all(test, not(any(feature = "csr", feature = "hydrate"))))]
+mod tests {
+    use super::*;
+    use crate::QueryClientBuilder;
+    use leptos::*;
+
+    #[test]
+    fn bridge_calls_run_under_the_client_owner() {
+        let runtime = create_runtime();
+
+        // Like the console, the bridge is called from outside the owner that provides the client.
+        let bridge = Bridge(run_as_child(|| {
+            let client = QueryClientBuilder::new().provide();
+            client.set_query_data::<u32, u32>(0, 0);
+            client.set_query_data::<u32, u32>(1, 1);
+            client
+        }));
+        assert!(use_context::<QueryClient>().is_none());
+
+        assert_eq!(bridge.keys().len(), 2);
+        assert_eq!(bridge.invalidate("0".to_string()), 1);
+        // The first query is already invalid.
+        assert_eq!(bridge.invalidate_type("u32"), 1);
+        bridge.clear();
+        assert!(bridge.keys().is_empty());
+
+        // The child owner must be disposed with the runtime.
+        runtime.dispose();
+    }
+}
@@ -2,13 +2,54 @@ use std::pin::Pin;
 use std::rc::Rc;
 use std::{borrow::Borrow, future::Future};
 
-use leptos::Signal;
+use leptos::{MaybeSignal, Signal, SignalGet};
 
 use crate::{
-    use_query, use_query_client, QueryKey, QueryOptions, QueryResult, QueryState, QueryValue,
-    RefetchFn,
+    use_optional_query, use_query, use_query_client, use_query_signal, AnyQueryScope,
+    CacheTransaction, DataMut, GcDecision, HoldGuard, PrefetchHandle, QueryClientHandle, QueryKey,
+    QueryOptions, QueryPriority, QueryResult, QueryState, QueryStats, QueryValue, RefetchFn,
+    ResourceOption, SplitQueryResult, TempIds, TimeSetting,
 };
 
+/// Generates a [`QueryScope`] function from an async query fetcher, removing the boilerplate of [`create_query`].
+///
+/// The fetcher must take exactly one argument, which is the query key.
+/// The scope function is named after the fetcher, with any `get_` or `fetch_` prefix removed and `_query` appended.
+///
+/// # Options
+///
+/// * `name`: Override the name of the generated function.
+/// * `stale_time`, `gc_time`, `refetch_interval`, `min_fetch_interval`, `revalidate_window`: Durations such as `"500ms"`, `"30s"`, `"5m"`, `"1h"`, `"1d"`, or `"infinity"`.
+/// * `resource_option`: One of `"non_blocking"`, `"blocking"` or `"local"`.
+/// * `priority`: One of `"high"`, `"normal"` or `"low"`.
+///
+/// Options that are not set use [`QueryOptions::default()`].
+///
+/// # Example
+///
+/// ```
+/// use leptos_query::*;
+///
+/// // Generates `fn track_query() -> QueryScope<TrackId, TrackData>`.
+/// #[query(stale_time = "5s", gc_time = "1m")]
+/// async fn get_track(id: TrackId) -> TrackData {
+///     todo!()
+/// }
+///
+/// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// struct TrackId(i32);
+///
+/// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// struct TrackData {
+///    name: String,
+/// }
+///
+/// fn use_track(id: TrackId) -> QueryResult<TrackData, impl RefetchFn> {
+///     track_query().use_query(move || id.clone())
+/// }
+/// ```
+pub use leptos_query_macro::query;
+
 /// Creates a new [`QueryScope`] for managing queries with specific key and value types. This reduces the need to use the [`QueryClient`](crate::QueryClient) directly.
 ///
 /// Useful for having typed invalidation, setting, and updating of queries.
@@ -19,6 +60,7 @@ use crate::{
 /// * `options`: Query options used to configure all queries within this scope.
 ///
 /// Returns a new [`QueryScope`].
+/// Keys that should share a cache entry despite differing, e.g. by a request timestamp, can be normalized with [`QueryScope::with_key_normalizer`].
 ///
 /// # Example
 ///
@@ -84,7 +126,111 @@ where
     Fu: Future<Output = V> + 'static,
 {
     let fetcher = Rc::new(move |s| Box::pin(fetcher(s)) as Pin<Box<dyn Future<Output = V>>>);
-    QueryScope { fetcher, options }
+    QueryScope {
+        fetcher,
+        options,
+        normalize_key: None,
+    }
+}
+
+/// Like [`create_query`], but the fetcher also receives a [`QueryClientHandle`], e.g. to load the queries it depends on.
+///
+/// The handle is taken from the current [`QueryClient`](crate::QueryClient) when the scope is created.
+///
+/// # Example
+///
+/// ```
+/// use leptos_query::*;
+///
+/// fn track_query() -> QueryScope<TrackId, TrackData> {
+///     create_query_with_client(
+///         |client, id: TrackId| async move {
+///             // Reuses cached album data, and only fetches it if missing or stale.
+///             let album = client.ensure_query_data(&album_query(), id.album).await;
+///             get_track(id, album).await
+///         },
+///         QueryOptions::default(),
+///     )
+/// }
+///
+/// fn album_query() -> QueryScope<u32, String> {
+///     create_query(|id| async move { todo!() }, QueryOptions::default())
+/// }
+///
+/// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// struct TrackId {
+///     album: u32,
+///     track: u32,
+/// }
+///
+/// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// struct TrackData {
+///    name: String,
+///    album: Option<String>,
+/// }
+///
+/// async fn get_track(id: TrackId, album: Option<String>) -> TrackData {
+///     todo!()
+/// }
+/// ```
+pub fn create_query_with_client<K, V, Fu>(
+    fetcher: impl Fn(QueryClientHandle, K) -> Fu + 'static,
+    options: QueryOptions<V>,
+) -> QueryScope<K, V>
+where
+    K: QueryKey + 'static,
+    V: QueryValue + 'static,
+    Fu: Future<Output = V> + 'static,
+{
+    let client = QueryClientHandle::new(use_query_client());
+    create_query(move |key| fetcher(client.clone(), key), options)
+}
+
+/// Like [`create_query`], but the scope is shared by everything that uses the same `id` under the current [`QueryClient`](crate::QueryClient).
+///
+/// Useful when the scope is created inside of components: the first call registers the fetcher and options, and later calls reuse them.
+/// This keeps options consistent across components, and avoids allocating a new fetcher per call.
+///
+/// # Example
+///
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// fn track_query() -> QueryScope<TrackId, TrackData> {
+///     create_shared_query("track", get_track, QueryOptions::default())
+/// }
+///
+/// #[component]
+/// fn Track(id: TrackId) -> impl IntoView {
+///     let QueryResult { data, .. } = track_query().use_query(move || id.clone());
+///
+///     // Rest of component...
+/// }
+///
+/// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// struct TrackId(i32);
+///
+/// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// struct TrackData {
+///    name: String,
+/// }
+///
+/// async fn get_track(id: TrackId) -> TrackData {
+///     todo!()
+/// }
+/// ```
+pub fn create_shared_query<K, V, Fu>(
+    id: &'static str,
+    fetcher: impl Fn(K) -> Fu + 'static,
+    options: QueryOptions<V>,
+) -> QueryScope<K, V>
+where
+    K: QueryKey + 'static,
+    V: QueryValue + 'static,
+    Fu: Future<Output = V> + 'static,
+{
+    use_query_client().shared_scope(id, || create_query(fetcher, options))
 }
 
 /// A scope for managing queries with specific key and value types within a type-safe environment.
@@ -95,6 +241,8 @@ pub struct QueryScope<K, V> {
     #[allow(clippy::type_complexity)]
     fetcher: Rc<dyn Fn(K) -> Pin<Box<dyn Future<Output = V>>>>,
     options: QueryOptions<V>,
+    #[allow(clippy::type_complexity)]
+    normalize_key: Option<Rc<dyn Fn(K) -> K>>,
 }
 
 impl<K, V> QueryScope<K, V>
@@ -130,30 +278,87 @@ where
     ///    name: String,
     /// }
     /// ```
+    #[track_caller]
     pub fn use_query(&self, key: impl Fn() -> K + 'static) -> QueryResult<V, impl RefetchFn> {
-        use_query(key, self.make_fetcher(), self.options.clone())
+        use_query(
+            self.make_key(key),
+            self.make_fetcher(),
+            self.options.clone(),
+        )
+    }
+
+    /// Executes a query whose key may not be known yet. The query is idle while the key is `None`.
+    /// See [`use_optional_query`](crate::use_optional_query()).
+    #[track_caller]
+    pub fn use_optional_query(
+        &self,
+        key: impl Fn() -> Option<K> + 'static,
+    ) -> QueryResult<V, impl RefetchFn> {
+        let scope = self.clone();
+        use_optional_query(
+            move || key().map(|key| scope.normalize_key(key)),
+            self.make_fetcher(),
+            self.options.clone(),
+        )
+    }
+
+    /// Executes a query from a key that is already a signal, e.g. a [`Memo`](leptos::Memo) or a component prop.
+    /// See [`use_query_signal`](crate::use_query_signal()).
+    #[track_caller]
+    pub fn use_query_signal(
+        &self,
+        key: impl Into<MaybeSignal<K>>,
+    ) -> QueryResult<V, impl RefetchFn> {
+        let key = match key.into() {
+            // Normalized once, rather than on every read.
+            MaybeSignal::Static(key) => MaybeSignal::Static(self.normalize_key(key)),
+            key if self.normalize_key.is_none() => key,
+            key => MaybeSignal::Dynamic(Signal::derive(self.make_key(move || key.get()))),
+        };
+        use_query_signal(key, self.make_fetcher(), self.options.clone())
     }
 
     /// Executes a query with additional options that override the default options provided at the scope's creation.
     ///
     /// Returns a [`QueryResult`] similar to [`QueryScope::use_query`], but with the provided override options applied.
+    #[track_caller]
     pub fn use_query_with_options(
         &self,
         key: impl Fn() -> K + 'static,
         options: QueryOptions<V>,
     ) -> QueryResult<V, impl RefetchFn> {
-        use_query(key, self.make_fetcher(), options)
+        use_query(self.make_key(key), self.make_fetcher(), options)
     }
 
     /// Executes a query with additional options derived from the default options.
     ///
     /// Returns a [`QueryResult`] similar to [`QueryScope::use_query`], but with the provided override options applied.
+    #[track_caller]
     pub fn use_query_map_options(
         &self,
         key: impl Fn() -> K + 'static,
         options: impl FnOnce(QueryOptions<V>) -> QueryOptions<V>,
     ) -> QueryResult<V, impl RefetchFn> {
-        use_query(key, self.make_fetcher(), options(self.options.clone()))
+        use_query(
+            self.make_key(key),
+            self.make_fetcher(),
+            options(self.options.clone()),
+        )
+    }
+
+    /// Mount statistics for this scope's key and value types. See [`QueryClient::query_stats`](crate::QueryClient::query_stats).
+    pub fn stats(&self) -> Signal<QueryStats> {
+        use_query_client().query_stats::<K, V>()
+    }
+
+    /// Whether any query of this scope's key and value types is fetching. See [`QueryClient::is_any_fetching`](crate::QueryClient::is_any_fetching).
+    pub fn is_any_fetching(&self) -> Signal<bool> {
+        use_query_client().is_any_fetching::<K, V>()
+    }
+
+    /// Number of queries of this scope's key and value types that have data. See [`QueryClient::count_loaded`](crate::QueryClient::count_loaded).
+    pub fn count_loaded(&self) -> Signal<usize> {
+        use_query_client().count_loaded::<K, V>()
     }
 
     /// Retrieves the default options for this scope.
@@ -161,13 +366,205 @@ where
         &self.options
     }
 
+    /// Derives a new scope with different options, sharing the same fetcher.
+    ///
+    /// Useful for serving both aggressive and lazy consumers from one scope definition.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use leptos_query::*;
+    /// use std::time::Duration;
+    ///
+    /// fn track_query() -> QueryScope<TrackId, TrackData> {
+    ///     create_query(get_track, QueryOptions::default())
+    /// }
+    ///
+    /// // Always refetch on mount.
+    /// fn live_track_query() -> QueryScope<TrackId, TrackData> {
+    ///     track_query().with_stale_time(Duration::ZERO)
+    /// }
+    ///
+    /// async fn get_track(id: TrackId) -> TrackData {
+    ///     todo!()
+    /// }
+    ///
+    /// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+    /// struct TrackId(i32);
+    ///
+    /// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    /// struct TrackData {
+    ///    name: String,
+    /// }
+    /// ```
+    pub fn with_options(&self, options: QueryOptions<V>) -> Self {
+        QueryScope {
+            fetcher: self.fetcher.clone(),
+            options,
+            normalize_key: self.normalize_key.clone(),
+        }
+    }
+
+    /// Derives a new scope with options derived from this scope's options, sharing the same fetcher.
+    pub fn map_options(&self, options: impl FnOnce(QueryOptions<V>) -> QueryOptions<V>) -> Self {
+        self.with_options(options(self.options.clone()))
+    }
+
+    /// Derives a new scope with a different stale time, sharing the same fetcher.
+    pub fn with_stale_time(&self, stale_time: impl Into<TimeSetting>) -> Self {
+        self.map_options(|options| options.set_stale_time(stale_time))
+    }
+
+    /// Derives a new scope with a different gc time, sharing the same fetcher.
+    pub fn with_gc_time(&self, gc_time: impl Into<TimeSetting>) -> Self {
+        self.map_options(|options| options.set_gc_time(gc_time))
+    }
+
+    /// Derives a new scope with a different refetch interval, sharing the same fetcher.
+    pub fn with_refetch_interval(&self, refetch_interval: impl Into<TimeSetting>) -> Self {
+        self.map_options(|options| options.set_refetch_interval(refetch_interval))
+    }
+
+    /// Derives a new scope with a different priority, sharing the same fetcher.
+    pub fn with_priority(&self, priority: QueryPriority) -> Self {
+        self.map_options(|options| options.set_priority(priority))
+    }
+
+    /// Derives a new scope with a different resource option, sharing the same fetcher.
+    /// Decides whether its queries are streamed in after the shell, or awaited before it, during SSR. See [`ResourceOption`].
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// // Needed for the page title, so the shell waits for it.
+    /// fn article_query() -> QueryScope<u32, String> {
+    ///     create_query(get_article, QueryOptions::default()).with_resource_option(ResourceOption::Blocking)
+    /// }
+    ///
+    /// async fn get_article(id: u32) -> String {
+    ///     todo!()
+    /// }
+    /// ```
+    pub fn with_resource_option(&self, resource_option: ResourceOption) -> Self {
+        self.map_options(|options| options.set_resource_option(Some(resource_option)))
+    }
+
+    /// Derives a new scope with a different min fetch interval, sharing the same fetcher.
+    pub fn with_min_fetch_interval(&self, min_fetch_interval: impl Into<TimeSetting>) -> Self {
+        self.map_options(|options| options.set_min_fetch_interval(min_fetch_interval))
+    }
+
+    /// Derives a new scope with a different revalidate window, sharing the same fetcher.
+    pub fn with_revalidate_window(&self, revalidate_window: impl Into<TimeSetting>) -> Self {
+        self.map_options(|options| options.set_revalidate_window(revalidate_window))
+    }
+
+    /// Derives a new scope that migrates persisted or server-rendered data from older app versions, sharing the same fetcher.
+    /// See [`QueryOptions::set_migration`].
+    #[cfg(feature = "migrate")]
+    pub fn with_migration(
+        &self,
+        migration: impl Fn(serde_json::Value) -> Option<V> + 'static,
+    ) -> Self {
+        self.map_options(|options| options.set_migration(migration))
+    }
+
+    /// Derives a new scope that normalizes keys before they are used, so that logically identical keys share a cache entry.
+    ///
+    /// Useful for keys with fields that shouldn't affect identity, e.g. a request timestamp or tracing id.
+    /// Every key passed to the scope is normalized, and the fetcher receives the normalized key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// fn search_query() -> QueryScope<Search, Vec<String>> {
+    ///     create_query(search, QueryOptions::default())
+    ///         .with_key_normalizer(|key| Search { trace_id: 0, ..key })
+    /// }
+    ///
+    /// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+    /// struct Search {
+    ///     text: String,
+    ///     trace_id: u64,
+    /// }
+    ///
+    /// async fn search(key: Search) -> Vec<String> {
+    ///     todo!()
+    /// }
+    /// ```
+    pub fn with_key_normalizer(&self, normalize: impl Fn(K) -> K + 'static) -> Self {
+        QueryScope {
+            fetcher: self.fetcher.clone(),
+            options: self.options.clone(),
+            normalize_key: Some(Rc::new(normalize)),
+        }
+    }
+
+    /// Derives a new scope whose fetcher is wrapped by `middleware`, without affecting other scopes.
+    ///
+    /// The middleware receives the key and the wrapped fetcher, so it can adjust the key, validate the response, or skip fetching altogether.
+    /// Calling this repeatedly stacks middleware, with the last one outermost.
+    /// For middleware that applies to every fetch made by the client, see [`FetchMiddleware`](crate::FetchMiddleware).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    ///
+    /// fn user_query() -> QueryScope<u32, Option<User>> {
+    ///     create_query(get_user, QueryOptions::default()).with_middleware(|id, next| async move {
+    ///         // Discard users that don't match the requested id.
+    ///         next.fetch(id).await.filter(|user| user.id == id)
+    ///     })
+    /// }
+    ///
+    /// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    /// struct User {
+    ///     id: u32,
+    ///     name: String,
+    /// }
+    ///
+    /// async fn get_user(id: u32) -> Option<User> {
+    ///     todo!()
+    /// }
+    /// ```
+    pub fn with_middleware<Fu>(
+        &self,
+        middleware: impl Fn(K, NextFetcher<K, V>) -> Fu + 'static,
+    ) -> Self
+    where
+        Fu: Future<Output = V> + 'static,
+    {
+        let next = NextFetcher {
+            fetcher: self.fetcher.clone(),
+        };
+        QueryScope {
+            fetcher: Rc::new(move |key| Box::pin(middleware(key, next.clone()))),
+            options: self.options.clone(),
+            normalize_key: self.normalize_key.clone(),
+        }
+    }
+
     /// Prefetches a query and stores it in the cache. Useful for preloading data before it is needed.
+    /// Runs at the scope's priority, use [`with_priority`](Self::with_priority) to prefetch without delaying more important queries.
     /// If you don't need the result opt for [`fetch_query()`](Self::fetch_query)
     /// This should usually be called in a [`create_effect`](leptos::create_effect) or on an event (e.g. on:click).
-    pub async fn prefetch_query(&self, key: K) {
-        use_query_client()
-            .prefetch_query(key, self.make_fetcher())
-            .await
+    /// See [`QueryClient::prefetch_query`](crate::QueryClient::prefetch_query) for awaiting or cancelling the returned handle.
+    pub fn prefetch_query(&self, key: K) -> PrefetchHandle {
+        use_query_client().prefetch_query_with_priority(
+            self.normalize_key(key),
+            self.make_fetcher(),
+            self.options.priority,
+        )
+    }
+
+    /// Prefetches a query once the browser is idle, at low priority. Useful for warming up secondary routes.
+    /// See [`QueryClient::prefetch_on_idle`](crate::QueryClient::prefetch_on_idle).
+    pub fn prefetch_on_idle(&self, key: K) {
+        use_query_client().prefetch_on_idle(self.normalize_key(key), self.make_fetcher())
     }
 
     /// Fetch a query and store it in cache.
@@ -177,7 +574,20 @@ where
     /// This should usually be called in a [`create_effect`](leptos::create_effect) or on an event (e.g. on:click).
     pub async fn fetch_query(&self, key: K) -> QueryState<V> {
         use_query_client()
-            .fetch_query(key, self.make_fetcher())
+            .fetch_query_with_priority(
+                self.normalize_key(key),
+                self.make_fetcher(),
+                self.options.priority,
+            )
+            .await
+    }
+
+    /// Fetches a query and stores it in the cache, even if the data is fresh. Any in-flight fetch for the query is cancelled first.
+    ///
+    /// Useful for explicit refreshes (e.g. pull to refresh), where stale time shouldn't apply.
+    pub async fn refetch_query_force(&self, key: K) -> QueryState<V> {
+        use_query_client()
+            .refetch_query_force(self.normalize_key(key), self.make_fetcher())
             .await
     }
 
@@ -185,7 +595,7 @@ where
     ///
     /// Returns A [`Signal`] containing the current [`QueryState`] of the query. If the query does not exist, the signal's value will be [`None`].
     pub fn get_query_state(&self, key: impl Fn() -> K + 'static) -> Signal<Option<QueryState<V>>> {
-        use_query_client().get_query_state(key)
+        use_query_client().get_query_state(self.make_key(key))
     }
 
     /// Retrieve the current state for an existing query.
@@ -193,14 +603,19 @@ where
     ///
     /// If the query does not exist, [`None`](Option::None) will be returned.
     pub fn peek_query_state(&self, key: &K) -> Option<QueryState<V>> {
-        use_query_client().peek_query_state(key)
+        use_query_client().peek_query_state(&self.normalize_key(key.clone()))
+    }
+
+    /// Computes over the data of an existing query without cloning it. See [`QueryClient::with_query_data`](crate::QueryClient::with_query_data).
+    pub fn with_query_data<R>(&self, key: &K, f: impl FnOnce(&V) -> R) -> Option<R> {
+        use_query_client().with_query_data(&self.normalize_key(key.clone()), f)
     }
 
     /// Invalidates a query in the cache, identified by a specific key, marking it as needing a refetch.
     ///
     /// Returns a boolean indicating whether the query was successfully invalidated.
     pub fn invalidate_query(&self, key: impl Borrow<K>) -> bool {
-        use_query_client().invalidate_query::<K, V>(key)
+        use_query_client().invalidate_query::<K, V>(self.normalize_key(key.borrow().clone()))
     }
 
     /// Invalidates multiple queries in the cache, identified by a collection of keys.
@@ -212,7 +627,12 @@ where
         V: QueryValue + 'static,
         Q: Borrow<K> + 'static,
     {
-        use_query_client().invalidate_queries::<K, V, Q>(keys)
+        let client = use_query_client();
+        let invalidated = keys
+            .into_iter()
+            .filter(|key| client.invalidate_query::<K, V>(self.normalize_key(key.borrow().clone())))
+            .collect();
+        Some(invalidated)
     }
 
     /// Invalidates all queries in the cache of a specific type, triggering a refetch for active queries.
@@ -233,12 +653,12 @@ where
         key: K,
         updater: impl FnOnce(Option<&V>) -> Option<V> + 'static,
     ) {
-        use_query_client().update_query_data(key, updater);
+        use_query_client().update_query_data(self.normalize_key(key), updater);
     }
 
     /// Sets the data of an existing query in the cache, identified by a specific key.
     pub fn set_query_data(&self, key: K, data: V) {
-        use_query_client().set_query_data(key, data);
+        use_query_client().set_query_data(self.normalize_key(key), data);
     }
 
     /// Mutates the data of an existing query in the cache, identified by a specific key.
@@ -252,18 +672,491 @@ where
     ///
     /// # Returns a boolean indicating whether the query data was successfully mutated.
     pub fn update_query_data_mut(&self, key: impl Borrow<K>, updater: impl FnOnce(&mut V)) -> bool {
-        use_query_client().update_query_data_mut(key, updater)
+        use_query_client().update_query_data_mut(self.normalize_key(key.borrow().clone()), updater)
+    }
+
+    /// Mutates the data of an existing query in the cache, only notifying listeners if the data was mutably accessed.
+    /// See [`QueryClient::update_query_data_tracked`](crate::QueryClient::update_query_data_tracked).
+    ///
+    /// Returns a boolean indicating whether the query data was changed.
+    pub fn update_query_data_tracked(
+        &self,
+        key: impl Borrow<K>,
+        updater: impl FnOnce(&mut DataMut<V>),
+        bump_updated_at: bool,
+    ) -> bool {
+        use_query_client().update_query_data_tracked(
+            self.normalize_key(key.borrow().clone()),
+            updater,
+            bump_updated_at,
+        )
+    }
+
+    /// Applies several cache updates for this scope at once, e.g. an optimistic update of a list and its items.
+    ///
+    /// The updates are recorded in the closure, and applied together once it returns, with a single notification batch. See [`CacheTransaction`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// fn rename(id: u32, name: String) {
+    ///     user_query().mutate_cache(|tx| {
+    ///         tx.update_mut(id, move |user| user.name = name);
+    ///         tx.invalidate(id);
+    ///     });
+    /// }
+    ///
+    /// fn user_query() -> QueryScope<u32, User> {
+    ///     create_query(get_user, QueryOptions::default())
+    /// }
+    ///
+    /// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    /// struct User {
+    ///     name: String,
+    /// }
+    ///
+    /// async fn get_user(id: u32) -> User {
+    ///     todo!()
+    /// }
+    /// ```
+    pub fn mutate_cache<R>(&self, f: impl FnOnce(&mut CacheTransaction<K, V>) -> R) -> R {
+        let client = use_query_client();
+        let mut tx = CacheTransaction::new(self);
+        let result = f(&mut tx);
+        tx.commit(&client);
+        result
+    }
+
+    /// Keeps a query in the cache until the returned guard is dropped.
+    /// See [`QueryClient::hold_query`](crate::QueryClient::hold_query).
+    pub fn hold_query(&self, key: impl Borrow<K>) -> HoldGuard {
+        use_query_client().hold_query::<K, V>(self.normalize_key(key.borrow().clone()))
+    }
+
+    /// Sets a hook that decides whether the scope's queries are collected once their gc time has elapsed.
+    /// See [`QueryClient::on_before_gc`](crate::QueryClient::on_before_gc).
+    pub fn on_before_gc(&self, hook: impl Fn(&K, &QueryState<V>) -> GcDecision + 'static) {
+        use_query_client().on_before_gc(hook)
+    }
+
+    /// Ephemeral UI state for a query, stored alongside the cache entry.
+    /// See [`QueryClient::query_ui_state`](crate::QueryClient::query_ui_state).
+    #[cfg(feature = "ui_state")]
+    pub fn query_ui_state(&self, key: impl Borrow<K>) -> leptos::RwSignal<serde_json::Value> {
+        use_query_client().query_ui_state::<K, V>(self.normalize_key(key.borrow().clone()))
     }
 
     /// Cancels an ongoing fetch operation for a query, identified by a specific key.
     ///
     /// Returns a boolean indicating whether the fetch operation was active and successfully cancelled.
     pub fn cancel_query(&self, key: K) -> bool {
-        use_query_client().cancel_query::<K, V>(key)
+        use_query_client().cancel_query::<K, V>(self.normalize_key(key))
+    }
+
+    /// Erases the key and value types, so that scopes of different types can be handled together. See [`AnyQueryScope`].
+    pub fn erase(&self) -> AnyQueryScope {
+        self.clone().into()
+    }
+
+    pub(crate) fn normalize_key(&self, key: K) -> K {
+        match &self.normalize_key {
+            Some(normalize) => normalize(key),
+            None => key,
+        }
     }
 
-    fn make_fetcher(&self) -> impl Fn(K) -> Pin<Box<dyn Future<Output = V>>> {
+    fn make_key(&self, key: impl Fn() -> K + 'static) -> impl Fn() -> K + 'static {
+        let scope = self.clone();
+        move || scope.normalize_key(key())
+    }
+
+    pub(crate) fn make_fetcher(&self) -> impl Fn(K) -> Pin<Box<dyn Future<Output = V>>> {
         let fetcher = self.fetcher.clone();
         move |key| fetcher(key)
     }
 }
+
+/// The fetcher wrapped by a [`QueryScope::with_middleware`] middleware.
+pub struct NextFetcher<K, V> {
+    #[allow(clippy::type_complexity)]
+    fetcher: Rc<dyn Fn(K) -> Pin<Box<dyn Future<Output = V>>>>,
+}
+
+impl<K, V> NextFetcher<K, V> {
+    /// Fetches the value for `key` with the wrapped fetcher.
+    pub fn fetch(&self, key: K) -> impl Future<Output = V> {
+        (self.fetcher)(key)
+    }
+}
+
+impl<K, V> Clone for NextFetcher<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            fetcher: self.fetcher.clone(),
+        }
+    }
+}
+
+impl<K, V> std::fmt::Debug for NextFetcher<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NextFetcher").finish_non_exhaustive()
+    }
+}
+
+impl<K, T, E> QueryScope<K, Result<T, E>>
+where
+    K: QueryKey + 'static,
+    T: Clone + 'static,
+    E: Clone + 'static,
+    Result<T, E>: QueryValue,
+{
+    /// Executes a query whose value is a [`Result`], with the data and error split into separate signals.
+    /// The last `Ok` data is kept while a refetch fails, so it can be shown alongside the error.
+    ///
+    /// Returns a [`SplitQueryResult`], see [`QueryResult::split`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// fn test() {
+    ///     provide_query_client();
+    ///     let query_scope = create_query(fetch_user_name, QueryOptions::default());
+    ///     let SplitQueryResult { data, error, .. } = query_scope.use_query_result(|| 1);
+    /// }
+    ///
+    /// async fn fetch_user_name(id: u32) -> Result<String, String> {
+    ///    todo!()
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn use_query_result(
+        &self,
+        key: impl Fn() -> K + 'static,
+    ) -> SplitQueryResult<T, E, impl RefetchFn> {
+        self.use_query(key).split()
+    }
+}
+
+impl<K, T> QueryScope<K, Vec<T>>
+where
+    K: QueryKey + 'static,
+    T: Clone + 'static,
+    Vec<T>: QueryValue,
+{
+    /// Optimistically appends an item to a cached list while a mutation runs.
+    ///
+    /// Any in-flight fetch for the list is cancelled first, so it can't overwrite the update.
+    /// The list is invalidated once the mutation completes, to replace the optimistic update with the server's data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    ///
+    /// fn add(todo: Todo) {
+    ///     let mutation = all_todos_query().optimistically_insert(AllTodos, todo.clone(), add_todo(todo));
+    ///     spawn_local(async move {
+    ///         let _ = mutation.await;
+    ///     });
+    /// }
+    ///
+    /// fn all_todos_query() -> QueryScope<AllTodos, Vec<Todo>> {
+    ///     create_query(get_todos, QueryOptions::default())
+    /// }
+    ///
+    /// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+    /// struct AllTodos;
+    ///
+    /// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    /// struct Todo {
+    ///     id: u32,
+    ///     content: String,
+    /// }
+    ///
+    /// async fn get_todos(_: AllTodos) -> Vec<Todo> {
+    ///     todo!()
+    /// }
+    ///
+    /// async fn add_todo(todo: Todo) -> Result<(), ServerFnError> {
+    ///     todo!()
+    /// }
+    /// ```
+    pub fn optimistically_insert<R: 'static>(
+        &self,
+        key: K,
+        item: T,
+        mutation: impl Future<Output = R> + 'static,
+    ) -> Pin<Box<dyn Future<Output = R>>> {
+        self.optimistically(key, |list| list.push(item), mutation)
+    }
+
+    /// Optimistically removes the matching items from a cached list while a mutation runs.
+    /// See [`optimistically_insert`](Self::optimistically_insert).
+    pub fn optimistically_remove<R: 'static>(
+        &self,
+        key: K,
+        predicate: impl Fn(&T) -> bool,
+        mutation: impl Future<Output = R> + 'static,
+    ) -> Pin<Box<dyn Future<Output = R>>> {
+        self.optimistically(key, |list| list.retain(|item| !predicate(item)), mutation)
+    }
+
+    /// Optimistically replaces the matching items in a cached list while a mutation runs, e.g. an item with the same id.
+    /// See [`optimistically_insert`](Self::optimistically_insert).
+    pub fn replace_matching<R: 'static>(
+        &self,
+        key: K,
+        predicate: impl Fn(&T) -> bool,
+        item: T,
+        mutation: impl Future<Output = R> + 'static,
+    ) -> Pin<Box<dyn Future<Output = R>>> {
+        self.optimistically(
+            key,
+            |list| {
+                for existing in list.iter_mut().filter(|existing| predicate(existing)) {
+                    *existing = item.clone();
+                }
+            },
+            mutation,
+        )
+    }
+
+    /// Tracks temporary ids of optimistically inserted items, so they can be reconciled once the server assigns the real ids.
+    /// See [`TempIds`].
+    pub fn temp_ids<Id>(&self, id_of: impl Fn(&T) -> Id + 'static) -> TempIds<K, T, Id>
+    where
+        Id: QueryKey + 'static,
+    {
+        TempIds::new(self.clone(), id_of)
+    }
+
+    // The update is applied immediately, rather than when the returned future is first polled.
+    fn optimistically<R: 'static>(
+        &self,
+        key: K,
+        update: impl FnOnce(&mut Vec<T>),
+        mutation: impl Future<Output = R> + 'static,
+    ) -> Pin<Box<dyn Future<Output = R>>> {
+        let key = self.normalize_key(key);
+        let client = use_query_client();
+        client.cancel_query::<K, Vec<T>>(key.clone());
+        client.update_query_data_mut::<K, Vec<T>>(&key, update);
+        Box::pin(async move {
+            let result = mutation.await;
+            client.invalidate_query::<K, Vec<T>>(key);
+            result
+        })
+    }
+}
+
+#[cfg(all(test, not(any(feature = "csr", feature = "hydrate"))))]
+mod tests {
+    use leptos::*;
+
+    use super::*;
+    use crate::provide_query_client;
+
+    async fn list(_: ()) -> Vec<u32> {
+        vec![]
+    }
+
+    #[test]
+    fn cache_transaction() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let scope = create_query(|n: u32| async move { n }, QueryOptions::default());
+        scope.set_query_data(1, 1);
+        scope.set_query_data(2, 2);
+
+        let runs = Rc::new(std::cell::Cell::new(0));
+        let (first, second) = (scope.get_query_state(|| 1), scope.get_query_state(|| 2));
+        create_isomorphic_effect({
+            let runs = runs.clone();
+            move |_| {
+                first.track();
+                second.track();
+                runs.set(runs.get() + 1);
+            }
+        });
+        assert_eq!(1, runs.get());
+
+        let data = |key| scope.peek_query_state(&key).and_then(|s| s.data().cloned());
+
+        // Nothing is applied if the closure panics.
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            scope.mutate_cache(|tx| {
+                tx.set(1, 10);
+                panic!("aborted")
+            })
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(Some(1), data(1));
+
+        let result = scope.mutate_cache(|tx| {
+            tx.set(1, 10).update(2, |n| n.map(|n| n * 10));
+            tx.update_mut(3, |n| *n = 30);
+            tx.invalidate(2);
+            "done"
+        });
+        assert_eq!("done", result);
+        assert_eq!(Some(10), data(1));
+        assert_eq!(Some(20), data(2));
+        assert!(matches!(
+            scope.peek_query_state(&2),
+            Some(QueryState::Invalid(_))
+        ));
+        assert!(scope.peek_query_state(&3).is_none());
+        // A single batch of notifications.
+        assert_eq!(2, runs.get());
+    }
+
+    #[test]
+    fn optimistic_list_updates() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let scope = create_query(list, QueryOptions::default());
+        scope.set_query_data((), vec![1, 2, 3]);
+
+        let data = || scope.peek_query_state(&()).and_then(|s| s.data().cloned());
+        let is_invalid = || matches!(scope.peek_query_state(&()), Some(QueryState::Invalid(_)));
+
+        let insert = scope.optimistically_insert((), 4, async { "inserted" });
+        // Applied before the mutation completes.
+        assert_eq!(Some(vec![1, 2, 3, 4]), data());
+        assert!(!is_invalid());
+        assert_eq!("inserted", futures::executor::block_on(insert));
+        assert!(is_invalid());
+
+        futures::executor::block_on(scope.optimistically_remove((), |n| n % 2 == 0, async {}));
+        assert_eq!(Some(vec![1, 3]), data());
+
+        futures::executor::block_on(scope.replace_matching((), |n| *n == 3, 30, async {}));
+        assert_eq!(Some(vec![1, 30]), data());
+    }
+
+    async fn detail(id: i32) -> String {
+        id.to_string()
+    }
+
+    #[test]
+    fn reconcile_temp_ids() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let list = create_query(
+            |_: ()| async { Vec::<(i32, u32)>::new() },
+            QueryOptions::default(),
+        );
+        let details = create_query(detail, QueryOptions::default());
+        list.set_query_data((), vec![(1, 10)]);
+
+        let temp_ids = list.temp_ids(|(id, _)| *id).with_detail(&details);
+        let insert = temp_ids.insert((), (-1, 20), async { (2, 20) });
+        details.set_query_data(-1, "draft".to_string());
+        assert_eq!(
+            Some(vec![(1, 10), (-1, 20)]),
+            list.peek_query_state(&()).and_then(|s| s.data().cloned())
+        );
+
+        assert_eq!((2, 20), futures::executor::block_on(insert));
+        assert_eq!(
+            Some(vec![(1, 10), (2, 20)]),
+            list.peek_query_state(&()).and_then(|s| s.data().cloned())
+        );
+        assert_eq!(
+            Some("draft".to_string()),
+            details.peek_query_state(&2).and_then(|s| s.data().cloned())
+        );
+        assert_eq!(2, temp_ids.resolve(&-1));
+        assert_eq!(1, temp_ids.resolve(&1));
+    }
+
+    #[test]
+    fn middleware_wraps_fetcher() {
+        let _ = create_runtime();
+
+        let scope = create_query(detail, QueryOptions::default());
+        let wrapped = scope
+            .with_middleware(|id, next| async move { format!("<{}>", next.fetch(id * 10).await) })
+            .with_middleware(|id, next| async move { format!("[{}]", next.fetch(id).await) });
+
+        assert_eq!(
+            "[<10>]",
+            futures::executor::block_on(wrapped.make_fetcher()(1))
+        );
+        // The original scope is unaffected.
+        assert_eq!("1", futures::executor::block_on(scope.make_fetcher()(1)));
+    }
+
+    #[test]
+    fn scope_aggregates() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let scope = create_query(detail, QueryOptions::default());
+        let fetching = scope.is_any_fetching();
+        let loaded = scope.count_loaded();
+        assert!(!fetching.get());
+        assert_eq!(0, loaded.get());
+
+        scope.set_query_data(1, "1".to_string());
+        scope.set_query_data(2, "2".to_string());
+        assert_eq!(2, loaded.get());
+
+        let query = use_query_client()
+            .cache
+            .get_or_create_query::<i32, String>(3);
+        query.set_state(QueryState::Loading);
+        assert!(fetching.get());
+        assert_eq!(2, loaded.get());
+
+        query.set_state(QueryState::Loaded(crate::QueryData::now("3".to_string())));
+        assert!(!fetching.get());
+        assert_eq!(3, loaded.get());
+    }
+
+    #[test]
+    fn normalized_keys_share_entry() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        // The second field is ignored.
+        let scope = create_query(
+            |(id, _): (u32, u32)| async move { id },
+            QueryOptions::default(),
+        )
+        .with_key_normalizer(|(id, _)| (id, 0));
+
+        scope.set_query_data((1, 10), 100);
+        assert_eq!(
+            Some(100),
+            scope
+                .peek_query_state(&(1, 20))
+                .and_then(|s| s.data().cloned())
+        );
+        assert!(scope.invalidate_query((1, 30)));
+        scope.set_query_data((1, 0), 100);
+        assert_eq!(
+            Some(vec![(1, 40)]),
+            scope.invalidate_queries([(1, 40), (2, 0)])
+        );
+
+        // Derived scopes keep the normalizer.
+        let derived = scope.with_stale_time(std::time::Duration::ZERO);
+        assert!(derived.peek_query_state(&(1, 50)).is_some());
+        assert_eq!(1, use_query_client().size().get_untracked());
+
+        let blocking = scope.with_resource_option(ResourceOption::Blocking);
+        assert_eq!(
+            Some(ResourceOption::Blocking),
+            blocking.get_options().resource_option
+        );
+    }
+}
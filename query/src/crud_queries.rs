@@ -0,0 +1,230 @@
+use std::{future::Future, pin::Pin, rc::Rc};
+
+use crate::{create_query, use_query_client, QueryKey, QueryOptions, QueryScope, QueryValue};
+
+/// Creates the [`QueryScope`]s for a REST-like resource: one for lists of items, and one for single items.
+///
+/// Mutations are wired up with [`CrudQueries::create`], [`CrudQueries::update`] and [`CrudQueries::delete`], which wrap a fetcher so that the affected queries are invalidated once it completes.
+///
+/// # Parameters
+///
+/// * `list`: Fetcher for a list of items. The key can be used for filters or pagination, or be a unit struct.
+/// * `get`: Fetcher for a single item.
+///
+/// Both scopes use the default options, which can be overridden with [`QueryScope::with_options`].
+///
+/// # Example
+///
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// fn todo_queries() -> CrudQueries<AllTodos, Vec<Todo>, TodoId, Option<Todo>> {
+///     create_crud_queries(get_todos, get_todo)
+/// }
+///
+/// #[component]
+/// fn Todos() -> impl IntoView {
+///     let queries = todo_queries();
+///     let QueryResult { data, .. } = queries.list.use_query(|| AllTodos);
+///
+///     // Invalidates all todo lists once the todo is added.
+///     let add_todo = create_action({
+///         let add = queries.create(add_todo);
+///         move |content: &String| add(content.clone())
+///     });
+///
+///     // Invalidates the todo, and all todo lists, once the todo is deleted.
+///     let delete_todo = create_action({
+///         let delete = queries.delete(delete_todo);
+///         move |id: &TodoId| delete(*id)
+///     });
+///
+///     // Rest of component...
+/// }
+///
+/// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// struct AllTodos;
+///
+/// #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+/// struct TodoId(u32);
+///
+/// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// struct Todo {
+///     id: TodoId,
+///     content: String,
+/// }
+///
+/// async fn get_todos(_: AllTodos) -> Vec<Todo> {
+///     todo!()
+/// }
+///
+/// async fn get_todo(id: TodoId) -> Option<Todo> {
+///     todo!()
+/// }
+///
+/// async fn add_todo(content: String) -> Result<(), ServerFnError> {
+///     todo!()
+/// }
+///
+/// async fn delete_todo(id: TodoId) -> Result<(), ServerFnError> {
+///     todo!()
+/// }
+/// ```
+pub fn create_crud_queries<ListKey, List, Id, Item, ListFu, ItemFu>(
+    list: impl Fn(ListKey) -> ListFu + 'static,
+    get: impl Fn(Id) -> ItemFu + 'static,
+) -> CrudQueries<ListKey, List, Id, Item>
+where
+    ListKey: QueryKey + 'static,
+    List: QueryValue + 'static,
+    Id: QueryKey + 'static,
+    Item: QueryValue + 'static,
+    ListFu: Future<Output = List> + 'static,
+    ItemFu: Future<Output = Item> + 'static,
+{
+    CrudQueries {
+        list: create_query(list, QueryOptions::default()),
+        item: create_query(get, QueryOptions::default()),
+    }
+}
+
+/// Query scopes for a REST-like resource. Created with [`create_crud_queries`].
+#[derive(Clone)]
+pub struct CrudQueries<ListKey, List, Id, Item> {
+    /// Scope for lists of items.
+    pub list: QueryScope<ListKey, List>,
+    /// Scope for single items.
+    pub item: QueryScope<Id, Item>,
+}
+
+type Mutation<R> = Pin<Box<dyn Future<Output = R>>>;
+
+impl<ListKey, List, Id, Item> CrudQueries<ListKey, List, Id, Item>
+where
+    ListKey: QueryKey + 'static,
+    List: QueryValue + 'static,
+    Id: QueryKey + 'static,
+    Item: QueryValue + 'static,
+{
+    /// Wraps a create fetcher, so that all lists are invalidated once it completes.
+    pub fn create<In, R, Fu>(
+        &self,
+        create: impl Fn(In) -> Fu + 'static,
+    ) -> impl Fn(In) -> Mutation<R> + Clone
+    where
+        Fu: Future<Output = R> + 'static,
+    {
+        let create = Rc::new(create);
+        move |input| {
+            // The client is retrieved before awaiting, while the reactive owner is still available.
+            let client = use_query_client();
+            let mutation = create(input);
+            Box::pin(async move {
+                let result = mutation.await;
+                client.invalidate_query_type::<ListKey, List>();
+                result
+            })
+        }
+    }
+
+    /// Wraps an update fetcher, so that the item and all lists are invalidated once it completes.
+    pub fn update<In, R, Fu>(
+        &self,
+        update: impl Fn(Id, In) -> Fu + 'static,
+    ) -> impl Fn(Id, In) -> Mutation<R> + Clone
+    where
+        Fu: Future<Output = R> + 'static,
+    {
+        let update = Rc::new(update);
+        move |id: Id, input| {
+            let client = use_query_client();
+            let mutation = update(id.clone(), input);
+            Box::pin(async move {
+                let result = mutation.await;
+                client.invalidate_query::<Id, Item>(id);
+                client.invalidate_query_type::<ListKey, List>();
+                result
+            })
+        }
+    }
+
+    /// Wraps a delete fetcher, so that the item and all lists are invalidated once it completes.
+    pub fn delete<R, Fu>(
+        &self,
+        delete: impl Fn(Id) -> Fu + 'static,
+    ) -> impl Fn(Id) -> Mutation<R> + Clone
+    where
+        Fu: Future<Output = R> + 'static,
+    {
+        let update = self.update(move |id, ()| delete(id));
+        move |id| update(id, ())
+    }
+}
+
+#[cfg(all(test, not(any(feature = "csr", feature = "hydrate"))))]
+mod tests {
+    use leptos::*;
+
+    use super::*;
+    use crate::{provide_query_client, QueryState};
+
+    #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+    struct AllItems;
+
+    async fn list(_: AllItems) -> Vec<u32> {
+        vec![]
+    }
+
+    async fn get(id: u32) -> u32 {
+        id
+    }
+
+    #[test]
+    fn mutations_invalidate_items_and_lists() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let queries = create_crud_queries(list, get);
+
+        let reset = || {
+            queries.list.set_query_data(AllItems, vec![0, 1]);
+            queries.item.set_query_data(0, 0);
+            queries.item.set_query_data(1, 1);
+        };
+        let list_invalid = || {
+            matches!(
+                client.peek_query_state::<AllItems, Vec<u32>>(&AllItems),
+                Some(QueryState::Invalid(_))
+            )
+        };
+        let item_invalid = |id: u32| {
+            matches!(
+                client.peek_query_state::<u32, u32>(&id),
+                Some(QueryState::Invalid(_))
+            )
+        };
+
+        reset();
+        let create = queries.create(|item: u32| async move { item });
+        assert_eq!(2, futures::executor::block_on(create(2)));
+        assert!(list_invalid());
+        assert!(!item_invalid(0));
+
+        reset();
+        let update = queries.update(|id: u32, _: u32| async move { id });
+        futures::executor::block_on(update(0, 10));
+        assert!(list_invalid());
+        assert!(item_invalid(0));
+        assert!(!item_invalid(1));
+
+        reset();
+        let delete = queries.delete(|_: u32| async {});
+        futures::executor::block_on(delete(1));
+        assert!(list_invalid());
+        assert!(item_invalid(1));
+        assert!(!item_invalid(0));
+    }
+}
@@ -0,0 +1,57 @@
+use std::ops::{Deref, DerefMut};
+
+/// Mutable access to query data that records whether it was mutated.
+/// Used by [`QueryClient::update_query_data_tracked`](crate::QueryClient::update_query_data_tracked), so that updates which only read the data don't notify listeners.
+///
+/// Reading through [`Deref`] is not a change. Any access through [`DerefMut`] is, even if the value ends up the same.
+pub struct DataMut<'a, V> {
+    data: &'a mut V,
+    changed: bool,
+}
+
+impl<'a, V> DataMut<'a, V> {
+    pub(crate) fn new(data: &'a mut V) -> Self {
+        Self {
+            data,
+            changed: false,
+        }
+    }
+
+    /// Whether the data has been mutably accessed.
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+}
+
+impl<V> Deref for DataMut<'_, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.data
+    }
+}
+
+impl<V> DerefMut for DataMut<'_, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        self.changed = true;
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_mutation() {
+        let mut items = vec![1, 2];
+        let mut data = DataMut::new(&mut items);
+
+        assert_eq!(2, data.len());
+        assert!(!data.is_changed());
+
+        data.push(3);
+        assert!(data.is_changed());
+        assert_eq!(vec![1, 2, 3], items);
+    }
+}
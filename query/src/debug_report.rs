@@ -0,0 +1,165 @@
+use serde::Serialize;
+
+use crate::{
+    cache_observer::{CacheEvent, QueryCacheKey},
+    query::Query,
+    Instant, QueryKey, QueryStateKind, QueryValue,
+};
+
+/// Returns the value to include in a report, given the serialized key and value of a query.
+pub type Redact<'a> = &'a dyn Fn(&QueryCacheKey, &str) -> Option<String>;
+
+// The most recent cache events kept for reports.
+pub(crate) const MAX_EVENTS: usize = 100;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugReport {
+    pub generated_at: u64,
+    pub queries: Vec<QueryReport>,
+    pub events: Vec<EventReport>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryReport {
+    key: String,
+    key_type: &'static str,
+    value_type: &'static str,
+    state: QueryStateKind,
+    updated_at: Option<u64>,
+    stale: bool,
+    observers: Vec<ObserverReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ObserverReport {
+    location: String,
+    stale_time_ms: Option<u64>,
+    gc_time_ms: Option<u64>,
+    refetch_interval_ms: Option<u64>,
+    priority: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventReport {
+    at: u64,
+    event: &'static str,
+    key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<QueryStateKind>,
+}
+
+impl EventReport {
+    pub fn new(event: &CacheEvent) -> Self {
+        let (event, key, state) = match event {
+            CacheEvent::Created(query) => ("created", &query.key, Some(query.state.kind())),
+            CacheEvent::Updated(query) => ("updated", &query.key, Some(query.state.kind())),
+            CacheEvent::RestoredFromPersister(query) => {
+                ("restored", &query.key, Some(query.state.kind()))
+            }
+            CacheEvent::Removed(key, _) => ("removed", key, None),
+            CacheEvent::ObserverAdded(observer) => ("observerAdded", &observer.key, None),
+            CacheEvent::ObserverRemoved(observer) => ("observerRemoved", &observer.key, None),
+            CacheEvent::TimesChanged(times) => ("timesChanged", &times.key, None),
+        };
+        EventReport {
+            at: Instant::now().as_unix_millis(),
+            event,
+            key: key.0.clone(),
+            state,
+        }
+    }
+}
+
+pub(crate) fn query_report<K, V>(query: &Query<K, V>, redact: Redact) -> QueryReport
+where
+    K: QueryKey + 'static,
+    V: QueryValue + 'static,
+{
+    let key = QueryCacheKey::from(query.get_key());
+    let (state, updated_at, serialized) = query.with_state(|state| {
+        (
+            state.kind(),
+            state.updated_at().map(|at| at.as_unix_millis()),
+            state
+                .data()
+                .and_then(|data| leptos::Serializable::ser(data).ok()),
+        )
+    });
+    // Redacted outside of the state borrow, as it is app code.
+    let value = serialized.and_then(|serialized| redact(&key, &serialized));
+    let millis = |duration: Option<std::time::Duration>| duration.map(|d| d.as_millis() as u64);
+    let observers = query
+        .get_observers()
+        .iter()
+        .map(|observer| {
+            let options = observer.get_options();
+            ObserverReport {
+                location: observer.get_location().to_string(),
+                stale_time_ms: millis(options.stale_time),
+                gc_time_ms: millis(options.gc_time),
+                refetch_interval_ms: millis(options.refetch_interval),
+                priority: format!("{:?}", options.priority),
+            }
+        })
+        .collect();
+
+    QueryReport {
+        key: key.0,
+        key_type: std::any::type_name::<K>(),
+        value_type: std::any::type_name::<V>(),
+        state,
+        updated_at,
+        stale: query.is_stale(),
+        observers,
+        value,
+    }
+}
+
+#[cfg(all(test, not(any(feature = "csr", feature = "hydrate"))))]
+mod tests {
+    use leptos::*;
+
+    use crate::{
+        provide_query_client, query_observer::QueryObserver, use_query_client, QueryOptions,
+    };
+
+    #[test]
+    fn reports_queries_and_events() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+        client.set_query_data::<u32, String>(1, "secret".to_string());
+        let _observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default().set_stale_time(std::time::Duration::from_secs(5)),
+            client.cache.get_query::<u32, String>(&1),
+        );
+
+        let report: serde_json::Value = serde_json::from_str(&client.debug_report()).unwrap();
+        let query = &report["queries"][0];
+        assert_eq!("1", query["key"]);
+        assert_eq!("Loaded", query["state"]);
+        assert_eq!(5000, query["observers"][0]["staleTimeMs"]);
+        assert!(query["observers"][0]["location"]
+            .as_str()
+            .unwrap()
+            .contains("debug_report.rs"));
+        // Values are left out by default.
+        assert!(query.get("value").is_none());
+        let events = report["events"].as_array().unwrap();
+        assert_eq!("created", events[0]["event"]);
+        assert_eq!("observerAdded", events[events.len() - 2]["event"]);
+        assert_eq!("timesChanged", events.last().unwrap()["event"]);
+
+        let redacted = client.debug_report_with(|_, value| Some(value.replace("secret", "***")));
+        let report: serde_json::Value = serde_json::from_str(&redacted).unwrap();
+        assert_eq!(r#""***""#, report["queries"][0]["value"]);
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+};
+
+use crate::cache_observer::QueryCacheKey;
+
+/// Hooks into every fetch made by the client. Registered with [`QueryClient::register_fetch_middleware`](crate::QueryClient::register_fetch_middleware).
+///
+/// Useful for simulating a slow network, e.g. to exercise loading states against a fast local backend.
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::{cache_observer::QueryCacheKey, *};
+/// use std::{future::Future, pin::Pin};
+///
+/// struct LogFetches;
+///
+/// impl FetchMiddleware for LogFetches {
+///     fn after_fetch(&self, key: &QueryCacheKey) -> Pin<Box<dyn Future<Output = ()>>> {
+///         logging::log!("Fetched {}", key.0);
+///         Box::pin(async {})
+///     }
+/// }
+///
+/// #[component]
+/// fn App() -> impl IntoView {
+///     provide_query_client();
+///     use_query_client().register_fetch_middleware(LogFetches);
+///
+///     // Rest of App...
+/// }
+/// ```
+pub trait FetchMiddleware {
+    /// Called once a fetcher has completed, before its value is stored.
+    /// The value is held back until the returned future resolves. Cancelling the query also cancels the future.
+    fn after_fetch(&self, key: &QueryCacheKey) -> Pin<Box<dyn Future<Output = ()>>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FetchMiddlewareKey(u32);
+
+#[derive(Clone, Default)]
+pub struct FetchMiddlewares {
+    #[allow(clippy::type_complexity)]
+    middlewares: Rc<RefCell<Vec<(FetchMiddlewareKey, Rc<dyn FetchMiddleware>)>>>,
+    next_key: Rc<Cell<u32>>,
+}
+
+impl FetchMiddlewares {
+    pub fn register(&self, middleware: impl FetchMiddleware + 'static) -> FetchMiddlewareKey {
+        let key = FetchMiddlewareKey(self.next_key.get());
+        self.next_key.set(key.0 + 1);
+        self.middlewares
+            .borrow_mut()
+            .push((key, Rc::new(middleware)));
+        key
+    }
+
+    pub fn unregister(&self, key: FetchMiddlewareKey) {
+        self.middlewares.borrow_mut().retain(|(k, _)| *k != key);
+    }
+
+    // Middlewares are copied out, so that they can register or unregister while running.
+    pub async fn after_fetch<K>(&self, key: &K)
+    where
+        K: crate::QueryKey + 'static,
+    {
+        let middlewares = self
+            .middlewares
+            .borrow()
+            .iter()
+            .map(|(_, m)| m.clone())
+            .collect::<Vec<_>>();
+        if middlewares.is_empty() {
+            return;
+        }
+
+        let key = QueryCacheKey::from(key);
+        for middleware in middlewares {
+            middleware.after_fetch(&key).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Record(Rc<RefCell<Vec<String>>>);
+
+    impl FetchMiddleware for Record {
+        fn after_fetch(&self, key: &QueryCacheKey) -> Pin<Box<dyn Future<Output = ()>>> {
+            self.0.borrow_mut().push(key.0.clone());
+            Box::pin(async {})
+        }
+    }
+
+    #[test]
+    fn runs_registered_middlewares() {
+        let middlewares = FetchMiddlewares::default();
+        let fetched = Rc::new(RefCell::new(Vec::new()));
+
+        let key = middlewares.register(Record(fetched.clone()));
+        futures::executor::block_on(middlewares.after_fetch(&1_u32));
+        assert_eq!(vec!["1".to_string()], *fetched.borrow());
+
+        middlewares.unregister(key);
+        futures::executor::block_on(middlewares.after_fetch(&2_u32));
+        assert_eq!(1, fetched.borrow().len());
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    rc::Rc,
+};
+
+use futures_channel::oneshot;
+
+use crate::QueryPriority;
+
+/// Orders fetches by priority.
+///
+/// High and normal priority fetches start immediately.
+/// Low priority fetches are held back while any high priority fetch is active, so that above-the-fold data wins over prefetching.
+#[derive(Clone, Default)]
+pub struct FetchScheduler {
+    state: Rc<SchedulerState>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    active_high: Cell<usize>,
+    waiting_low: RefCell<Vec<oneshot::Sender<()>>>,
+}
+
+/// Held for the duration of a fetch.
+pub struct FetchPermit {
+    state: Rc<SchedulerState>,
+    priority: QueryPriority,
+}
+
+impl FetchScheduler {
+    /// Resolves once a fetch with the given priority may start.
+    /// Acquiring is synchronous for everything but low priority, so that high priority fetches are counted before any await.
+    pub fn acquire(&self, priority: QueryPriority) -> impl Future<Output = FetchPermit> {
+        // Created up front, so that dropping the future releases a high priority slot.
+        let permit = FetchPermit {
+            state: self.state.clone(),
+            priority,
+        };
+        if priority == QueryPriority::High {
+            let active = &permit.state.active_high;
+            active.set(active.get() + 1);
+        }
+        async move {
+            if priority == QueryPriority::Low {
+                while permit.state.active_high.get() > 0 {
+                    let (sender, receiver) = oneshot::channel();
+                    permit.state.waiting_low.borrow_mut().push(sender);
+                    let _ = receiver.await;
+                }
+            }
+            permit
+        }
+    }
+}
+
+impl Drop for FetchPermit {
+    fn drop(&mut self) {
+        if self.priority == QueryPriority::High {
+            let active = self.state.active_high.get().saturating_sub(1);
+            self.state.active_high.set(active);
+            if active == 0 {
+                let waiting = self.state.waiting_low.take();
+                for sender in waiting {
+                    let _ = sender.send(());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[test]
+    fn low_priority_waits_for_high_priority() {
+        let scheduler = FetchScheduler::default();
+
+        let high = scheduler.acquire(QueryPriority::High);
+        let mut low = Box::pin(scheduler.acquire(QueryPriority::Low));
+        assert!((&mut low).now_or_never().is_none());
+
+        // Normal priority is never held back.
+        assert!(scheduler
+            .acquire(QueryPriority::Normal)
+            .now_or_never()
+            .is_some());
+
+        drop(high.now_or_never().unwrap());
+        assert!(low.now_or_never().is_some());
+    }
+}
@@ -1,30 +1,31 @@
-use std::{cell::Cell, rc::Rc, time::Duration};
+use std::{
+    cell::{Cell, RefCell},
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    rc::Rc,
+    time::Duration,
+};
 
 use leptos::{leptos_dom::helpers::TimeoutHandle, *};
 
-use crate::query::Query;
+use crate::{
+    query_cache::QueryId,
+    util::{run_or_defer, try_borrow, try_borrow_mut},
+    Instant,
+};
 
-#[derive(Clone)]
-pub struct GarbageCollector<K, V> {
-    query: Rc<Query<K, V>>,
+#[derive(Clone, Debug)]
+pub struct GarbageCollector {
+    // Shared with the query, whose id is assigned when it is inserted into the cache.
+    query: Rc<Cell<QueryId>>,
     // Outer options is if option has been set, inner option is the actual value.
     // If inner option is none, then the query should not be garbage collected.
     gc_time: Rc<Cell<GcTime>>,
-    handle: Rc<Cell<Option<TimeoutHandle>>>,
-}
-
-impl<K, V> std::fmt::Debug for GarbageCollector<K, V>
-where
-    K: crate::QueryKey,
-    V: crate::QueryValue,
-{
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("GarbageCollector")
-            .field("query", &self.query)
-            .field("gc_time", &self.gc_time)
-            .field("handle", &self.handle)
-            .finish()
-    }
+    // Collection is postponed until at least this instant, e.g. for recently visited routes.
+    retain_until: Rc<Cell<Option<Instant>>>,
+    // Number of outstanding holds. The query is never collected while held.
+    holds: Rc<Cell<usize>>,
+    scheduler: GcScheduler,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -46,16 +47,14 @@ impl GcTime {
     }
 }
 
-impl<K, V> GarbageCollector<K, V>
-where
-    K: crate::QueryKey + 'static,
-    V: crate::QueryValue + 'static,
-{
-    pub fn new(query: Query<K, V>) -> Self {
+impl GarbageCollector {
+    pub fn new(query: Rc<Cell<QueryId>>, scheduler: GcScheduler) -> Self {
         Self {
-            query: Rc::new(query),
+            query,
             gc_time: Rc::new(Cell::new(GcTime::None)),
-            handle: Rc::new(Cell::new(None)),
+            retain_until: Rc::new(Cell::new(None)),
+            holds: Rc::new(Cell::new(0)),
+            scheduler,
         }
     }
 
@@ -78,35 +77,279 @@ where
         }
     }
 
-    pub fn enable_gc(&self) {
-        if self.handle.get().is_some() {
+    /// The gc time in effect, where `None` is never collected.
+    pub fn gc_time(&self) -> Option<Duration> {
+        match self.gc_time.get() {
+            GcTime::Some(gc_time) => Some(gc_time),
+            GcTime::None | GcTime::Never => None,
+        }
+    }
+
+    pub fn enable_gc(&self, updated_at: Option<Instant>) {
+        if self.holds.get() > 0 || self.scheduler.is_scheduled(self.query.get()) {
             return;
         }
 
         let gc_time = self.gc_time.get();
-        let updated_at = self.query.get_updated_at();
 
         if let (GcTime::Some(gc_time), Some(updated_at)) = (gc_time, updated_at) {
-            let time_until_gc = crate::util::time_until_stale(updated_at, gc_time);
-            let query = self.query.clone();
-            let new_handle = set_timeout_with_handle(
-                move || {
-                    let client = crate::use_query_client();
-                    let key = query.get_key();
-                    client.cache.evict_query::<K, V>(key);
-                },
-                time_until_gc,
-            )
-            .ok();
+            // A deadline past the representable time is never reached, so nothing is scheduled.
+            let Some(deadline) = updated_at.0.checked_add(gc_time).map(Instant) else {
+                return;
+            };
+            let deadline = self
+                .retain_until
+                .get()
+                .map_or(deadline, |r| r.max(deadline));
+            self.schedule(deadline);
+        }
+    }
 
-            self.handle.set(new_handle);
+    /// Postpones collection until at least `until`. Keeps the latest instant.
+    pub fn retain_until(&self, until: Instant) {
+        let until = self.retain_until.get().map_or(until, |r| r.max(until));
+        self.retain_until.set(Some(until));
+
+        // Reschedule if collection is already pending.
+        if let Some(deadline) = self.scheduler.deadline(self.query.get()) {
+            if deadline < until {
+                self.schedule(until);
+            }
         }
     }
 
+    /// Reschedules a due collection, once it was postponed by a [`GcDecision`].
+    pub fn postpone(&self, until: Instant) {
+        self.retain_until(until);
+        self.schedule(until);
+    }
+
     pub fn disable_gc(&self) {
-        if let Some(handle) = self.handle.take() {
+        self.scheduler.cancel(self.query.get());
+    }
+
+    // Retention saturates at the latest instant, which is never reached.
+    fn schedule(&self, deadline: Instant) {
+        if deadline.0 == Duration::MAX {
+            self.disable_gc();
+        } else {
+            self.scheduler.schedule(self.query.get(), deadline);
+        }
+    }
+
+    /// Pins the query until a matching [`release`](Self::release).
+    pub fn hold(&self) {
+        self.holds.set(self.holds.get() + 1);
+        self.disable_gc();
+    }
+
+    /// Returns true if this was the last hold.
+    pub fn release(&self) -> bool {
+        let holds = self.holds.get().saturating_sub(1);
+        self.holds.set(holds);
+        holds == 0
+    }
+}
+
+/// Whether a query may be garbage collected, decided by the hook set with [`QueryClient::on_before_gc`](crate::QueryClient::on_before_gc).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcDecision {
+    /// Evict the query.
+    Collect,
+    /// Keep the query for at least this long, then ask again. `Duration::MAX` keeps it indefinitely.
+    Postpone(Duration),
+}
+
+/// Evicts queries from the cache once their gc time has elapsed.
+/// A single timer is shared by all queries, which wakes at the earliest deadline and sweeps every due query.
+#[derive(Clone)]
+pub struct GcScheduler {
+    owner: Owner,
+    state: Rc<RefCell<SchedulerState>>,
+    // Number of outstanding pauses. Nothing is collected while paused, due queries are collected on resume.
+    pauses: RwSignal<usize>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    // Min-heap of deadlines. Entries are removed lazily, an entry is only valid if it matches `deadlines`.
+    queue: BinaryHeap<Reverse<(Instant, QueryId)>>,
+    deadlines: HashMap<QueryId, Instant>,
+    // The pending wake up, if any.
+    timer: Option<(Instant, TimeoutHandle)>,
+}
+
+impl std::fmt::Debug for GcScheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GcScheduler")
+            .field(
+                "scheduled",
+                &self.state.try_borrow().map(|s| s.deadlines.len()).ok(),
+            )
+            .finish()
+    }
+}
+
+impl GcScheduler {
+    pub fn new(owner: Owner) -> Self {
+        Self {
+            owner,
+            state: Rc::new(RefCell::new(SchedulerState::default())),
+            pauses: with_owner(owner, || create_rw_signal(0)),
+        }
+    }
+
+    /// Defers all collection until a matching [`resume`](Self::resume).
+    pub fn pause(&self) {
+        self.pauses.try_update(|pauses| *pauses += 1);
+    }
+
+    /// Collects queries that became due while paused, once the last pause is resumed.
+    pub fn resume(&self) {
+        let resumed = self.pauses.try_update(|pauses| {
+            *pauses = pauses.saturating_sub(1);
+            *pauses == 0
+        });
+        if resumed == Some(true) {
+            self.arm();
+        }
+    }
+
+    pub fn paused_signal(&self) -> Signal<bool> {
+        let pauses = self.pauses;
+        Signal::derive(move || pauses.try_get().unwrap_or_default() > 0)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.pauses.try_get_untracked().unwrap_or_default() > 0
+    }
+
+    pub fn schedule(&self, query: QueryId, deadline: Instant) {
+        let scheduler = self.clone();
+        run_or_defer(move || {
+            let mut state = try_borrow_mut(&scheduler.state, "gc schedule")?;
+            state.deadlines.insert(query, deadline);
+            state.queue.push(Reverse((deadline, query)));
+            drop(state);
+            scheduler.arm();
+            Ok(())
+        })
+    }
+
+    pub fn cancel(&self, query: QueryId) {
+        // The queue entry is left in place, and skipped once it is due.
+        let state = self.state.clone();
+        run_or_defer(move || {
+            try_borrow_mut(&state, "gc cancel")?
+                .deadlines
+                .remove(&query);
+            Ok(())
+        })
+    }
+
+    pub fn is_scheduled(&self, query: QueryId) -> bool {
+        try_borrow(&self.state, "gc is_scheduled").is_ok_and(|s| s.deadlines.contains_key(&query))
+    }
+
+    fn deadline(&self, query: QueryId) -> Option<Instant> {
+        try_borrow(&self.state, "gc deadline")
+            .ok()
+            .and_then(|s| s.deadlines.get(&query).copied())
+    }
+
+    /// Removes and returns all queries that are due at `now`, earliest first.
+    fn take_due(&self, now: Instant) -> Vec<QueryId> {
+        let Ok(mut state) = try_borrow_mut(&self.state, "gc sweep") else {
+            return Vec::new();
+        };
+        let SchedulerState {
+            queue, deadlines, ..
+        } = &mut *state;
+
+        let mut due = Vec::new();
+        while let Some(Reverse((deadline, query))) = queue.peek().copied() {
+            if deadline > now {
+                break;
+            }
+            queue.pop();
+            if deadlines.get(&query) == Some(&deadline) {
+                deadlines.remove(&query);
+                due.push(query);
+            }
+        }
+        due
+    }
+
+    // Ensures the timer wakes at the earliest valid deadline.
+    fn arm(&self) {
+        if self.is_paused() {
+            return;
+        }
+        let Ok(mut state) = try_borrow_mut(&self.state, "gc arm") else {
+            return;
+        };
+        let SchedulerState {
+            queue,
+            deadlines,
+            timer,
+        } = &mut *state;
+
+        // Drop cancelled entries, so the timer doesn't wake needlessly.
+        while let Some(Reverse((deadline, query))) = queue.peek().copied() {
+            if deadlines.get(&query) == Some(&deadline) {
+                break;
+            }
+            queue.pop();
+        }
+
+        let Some(Reverse((next, _))) = queue.peek().copied() else {
+            return;
+        };
+
+        if let Some((wake_at, handle)) = timer.take() {
+            if wake_at <= next {
+                *timer = Some((wake_at, handle));
+                return;
+            }
             handle.clear();
         }
+
+        cfg_if::cfg_if! {
+            if #[cfg(any(feature = "csr", feature = "hydrate"))] {
+                let scheduler = self.clone();
+                let handle = set_timeout_with_handle(
+                    move || scheduler.sweep(),
+                    crate::util::time_until(next),
+                )
+                .ok();
+                *timer = handle.map(|handle| (next, handle));
+            } else {
+                // Timers are only available in the browser.
+                let _ = timer;
+            }
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+    fn sweep(&self) {
+        if let Ok(mut state) = try_borrow_mut(&self.state, "gc sweep") {
+            state.timer = None;
+        }
+        if self.is_paused() {
+            return;
+        }
+
+        let due = self.take_due(Instant::now());
+        if !due.is_empty() {
+            with_owner(self.owner, || {
+                let cache = crate::use_query_client().cache;
+                for query in due {
+                    cache.collect_query(query);
+                }
+            });
+        }
+
+        self.arm();
     }
 }
 
@@ -114,15 +357,11 @@ where
 mod test {
     use super::*;
 
-    fn create_query() -> GarbageCollector<String, String> {
-        let query = Query::<String, String>::new("key".into());
-        let gc = query.get_gc().expect("gc should be present");
-        gc
-    }
-
     #[test]
     fn test_gc() {
-        let gc = create_query();
+        let _ = create_runtime();
+        let scheduler = GcScheduler::new(Owner::current().unwrap());
+        let gc = GarbageCollector::new(Rc::default(), scheduler);
         assert_eq!(gc.gc_time.get(), GcTime::None);
 
         gc.update_gc_time(Some(Duration::from_secs(10)));
@@ -137,4 +376,126 @@ mod test {
 
         assert_eq!(gc.gc_time.get(), GcTime::Never);
     }
+
+    #[test]
+    fn scheduler_sweeps_due_queries_in_order() {
+        let _ = create_runtime();
+        let scheduler = GcScheduler::new(Owner::current().unwrap());
+
+        let mut ids = slotmap::SlotMap::<QueryId, ()>::with_key();
+        let (a, b, c) = (ids.insert(()), ids.insert(()), ids.insert(()));
+        let at = |secs| Instant(Duration::from_secs(secs));
+
+        scheduler.schedule(a, at(30));
+        scheduler.schedule(b, at(10));
+        scheduler.schedule(c, at(20));
+        scheduler.cancel(c);
+
+        // Rescheduling replaces the previous deadline.
+        scheduler.schedule(a, at(5));
+
+        assert!(scheduler.take_due(at(1)).is_empty());
+        assert_eq!(scheduler.take_due(at(25)), vec![a, b]);
+        assert!(!scheduler.is_scheduled(c));
+        assert!(scheduler.take_due(at(60)).is_empty());
+    }
+
+    #[test]
+    fn pause_defers_collection() {
+        let _ = create_runtime();
+        let scheduler = GcScheduler::new(Owner::current().unwrap());
+        let gc = GarbageCollector::new(Rc::default(), scheduler.clone());
+        gc.update_gc_time(Some(Duration::from_secs(10)));
+        let paused = scheduler.paused_signal();
+
+        scheduler.pause();
+        scheduler.pause();
+        assert!(paused.get());
+
+        // Queries are still scheduled, and kept until resumed.
+        gc.enable_gc(Some(Instant(Duration::ZERO)));
+        assert!(scheduler.is_scheduled(gc.query.get()));
+        scheduler.sweep();
+        assert!(scheduler.is_scheduled(gc.query.get()));
+
+        scheduler.resume();
+        assert!(paused.get());
+        scheduler.resume();
+        assert!(!paused.get());
+    }
+
+    #[test]
+    fn retain_postpones_collection() {
+        let _ = create_runtime();
+        let scheduler = GcScheduler::new(Owner::current().unwrap());
+        let gc = GarbageCollector::new(Rc::default(), scheduler.clone());
+        let at = |secs| Instant(Duration::from_secs(secs));
+
+        gc.update_gc_time(Some(Duration::from_secs(10)));
+        gc.enable_gc(Some(at(0)));
+        assert_eq!(scheduler.deadline(gc.query.get()), Some(at(10)));
+
+        // Pending collection is rescheduled.
+        gc.retain_until(at(30));
+        assert_eq!(scheduler.deadline(gc.query.get()), Some(at(30)));
+
+        // An earlier instant doesn't shorten retention, and applies once gc is enabled again.
+        gc.disable_gc();
+        gc.retain_until(at(20));
+        gc.enable_gc(Some(at(0)));
+        assert_eq!(scheduler.deadline(gc.query.get()), Some(at(30)));
+    }
+
+    #[test]
+    fn hold_prevents_collection() {
+        let _ = create_runtime();
+        let scheduler = GcScheduler::new(Owner::current().unwrap());
+        let gc = GarbageCollector::new(Rc::default(), scheduler.clone());
+        gc.update_gc_time(Some(Duration::from_secs(10)));
+
+        gc.enable_gc(Some(Instant(Duration::ZERO)));
+        assert!(scheduler.is_scheduled(gc.query.get()));
+
+        // Holding cancels pending collection.
+        gc.hold();
+        gc.hold();
+        assert!(!scheduler.is_scheduled(gc.query.get()));
+        gc.enable_gc(Some(Instant(Duration::ZERO)));
+        assert!(!scheduler.is_scheduled(gc.query.get()));
+
+        assert!(!gc.release());
+        gc.enable_gc(Some(Instant(Duration::ZERO)));
+        assert!(!scheduler.is_scheduled(gc.query.get()));
+
+        assert!(gc.release());
+        gc.enable_gc(Some(Instant(Duration::ZERO)));
+        assert!(scheduler.is_scheduled(gc.query.get()));
+    }
+
+    #[test]
+    fn overflowing_gc_time_is_never_scheduled() {
+        let _ = create_runtime();
+        let scheduler = GcScheduler::new(Owner::current().unwrap());
+        let gc = GarbageCollector::new(Rc::default(), scheduler.clone());
+        gc.update_gc_time(Some(Duration::MAX));
+
+        gc.enable_gc(Some(Instant::now()));
+        assert!(!scheduler.is_scheduled(gc.query.get()));
+    }
+
+    #[test]
+    fn saturated_retention_is_never_scheduled() {
+        let _ = create_runtime();
+        let scheduler = GcScheduler::new(Owner::current().unwrap());
+        let gc = GarbageCollector::new(Rc::default(), scheduler.clone());
+        gc.update_gc_time(Some(Duration::from_secs(10)));
+
+        gc.enable_gc(Some(Instant(Duration::ZERO)));
+        assert!(scheduler.is_scheduled(gc.query.get()));
+
+        gc.retain_until(Instant(Duration::MAX));
+        assert!(!scheduler.is_scheduled(gc.query.get()));
+        gc.enable_gc(Some(Instant(Duration::ZERO)));
+        assert!(!scheduler.is_scheduled(gc.query.get()));
+    }
 }
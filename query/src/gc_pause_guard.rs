@@ -0,0 +1,29 @@
+/// Defers all garbage collection while alive. Created with [`QueryClient::pause_gc`](crate::QueryClient::pause_gc).
+///
+/// Queries that become due while paused are collected once the last guard is dropped.
+#[must_use = "garbage collection is only paused until the guard is dropped"]
+pub struct GcPauseGuard {
+    resume: Option<Box<dyn FnOnce()>>,
+}
+
+impl GcPauseGuard {
+    pub(crate) fn new(resume: impl FnOnce() + 'static) -> Self {
+        Self {
+            resume: Some(Box::new(resume)),
+        }
+    }
+}
+
+impl Drop for GcPauseGuard {
+    fn drop(&mut self) {
+        if let Some(resume) = self.resume.take() {
+            resume();
+        }
+    }
+}
+
+impl std::fmt::Debug for GcPauseGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GcPauseGuard").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,147 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use indexmap::{IndexMap, IndexSet};
+use leptos::*;
+use leptos_router::use_location;
+
+use crate::{
+    cache_observer::{CacheEvent, CacheObserver, QueryCacheKey},
+    use_query_client,
+};
+
+/// Options for [`use_history_cache`].
+#[derive(Debug, Clone, Copy)]
+pub struct HistoryCacheOptions {
+    /// How many recently visited routes to remember. Default is 10.
+    pub depth: usize,
+    /// How long to keep the queries of a route that was navigated away from, in addition to their gc time.
+    /// Default is none, which leaves gc times unchanged.
+    pub retain_for: Option<Duration>,
+}
+
+impl Default for HistoryCacheOptions {
+    fn default() -> Self {
+        Self {
+            depth: 10,
+            retain_for: None,
+        }
+    }
+}
+
+/// Keeps back/forward navigation instant, by prioritizing the queries of recently visited routes.
+///
+/// Records the queries used on each route. When navigating away from a route, its queries are marked as most recently used in the cache,
+/// and optionally kept from being garbage collected for [`HistoryCacheOptions::retain_for`].
+/// Retention is bounded, so queries of routes that are never revisited are still collected.
+///
+/// Must be called inside of a [`Router`](leptos_router::Router). Requires the `router` feature.
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+/// use leptos_router::*;
+/// use std::time::Duration;
+///
+/// #[component]
+/// fn App() -> impl IntoView {
+///     provide_query_client();
+///
+///     view! {
+///         <Router>
+///             <HistoryCache/>
+///             <Routes>
+///                 <Route path="/" view=|| view! { "Home" }/>
+///             </Routes>
+///         </Router>
+///     }
+/// }
+///
+/// #[component]
+/// fn HistoryCache() -> impl IntoView {
+///     use_history_cache(HistoryCacheOptions {
+///         retain_for: Some(Duration::from_secs(5 * 60)),
+///         ..HistoryCacheOptions::default()
+///     });
+/// }
+/// ```
+pub fn use_history_cache(options: HistoryCacheOptions) {
+    let location = use_location();
+    let cache = use_query_client().cache;
+    let history = Rc::new(RefCell::new(RouteHistory::new(options.depth)));
+
+    let observer = cache.register_observer(RouteRecorder {
+        path: location.pathname,
+        history: history.clone(),
+    });
+    on_cleanup({
+        let cache = cache.clone();
+        move || cache.unregister_observer(observer)
+    });
+
+    create_effect(move |previous: Option<String>| {
+        let path = location.pathname.get();
+        history.borrow_mut().visit(&path);
+
+        if let Some(previous) = previous.filter(|previous| *previous != path) {
+            let keys = history.borrow().keys(&previous);
+            cache.touch_queries(&keys, options.retain_for);
+        }
+        path
+    });
+}
+
+// The queries observed on each route, least to most recently visited.
+struct RouteHistory {
+    depth: usize,
+    routes: IndexMap<String, IndexSet<QueryCacheKey>>,
+}
+
+impl RouteHistory {
+    fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            routes: IndexMap::new(),
+        }
+    }
+
+    fn visit(&mut self, path: &str) {
+        let keys = self.routes.shift_remove(path).unwrap_or_default();
+        self.routes.insert(path.to_string(), keys);
+        while self.routes.len() > self.depth {
+            self.routes.shift_remove_index(0);
+        }
+    }
+
+    fn record(&mut self, path: &str, key: QueryCacheKey) {
+        if let Some(keys) = self.routes.get_mut(path) {
+            keys.insert(key);
+        }
+    }
+
+    fn keys(&self, path: &str) -> Vec<QueryCacheKey> {
+        self.routes
+            .get(path)
+            .map(|keys| keys.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+struct RouteRecorder {
+    path: Memo<String>,
+    history: Rc<RefCell<RouteHistory>>,
+}
+
+impl CacheObserver for RouteRecorder {
+    fn process_cache_event(&self, event: CacheEvent) {
+        // Read at event time, as the new route may render before the history effect runs.
+        if let CacheEvent::ObserverAdded(added) = event {
+            if let (Some(path), Ok(mut history)) =
+                (self.path.try_get_untracked(), self.history.try_borrow_mut())
+            {
+                history.visit(&path);
+                history.record(&path, added.key);
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+/// Keeps a query in the cache while alive. Created with [`QueryClient::hold_query`](crate::QueryClient::hold_query).
+///
+/// The query isn't garbage collected until the guard is dropped, even if it has no observers.
+/// Once the last guard of a query is dropped, collection resumes as if its last observer was just removed.
+#[must_use = "the query is only held until the guard is dropped"]
+pub struct HoldGuard {
+    release: Option<Box<dyn FnOnce()>>,
+}
+
+impl HoldGuard {
+    pub(crate) fn new(release: impl FnOnce() + 'static) -> Self {
+        Self {
+            release: Some(Box::new(release)),
+        }
+    }
+}
+
+impl Drop for HoldGuard {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+impl std::fmt::Debug for HoldGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HoldGuard").finish_non_exhaustive()
+    }
+}
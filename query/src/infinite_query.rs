@@ -0,0 +1,534 @@
+use std::{future::Future, pin::Pin, rc::Rc};
+
+use leptos::*;
+
+use crate::{
+    create_query, use_query_client, QueryKey, QueryOptions, QueryResult, QueryScope, QueryValue,
+    RefetchFn,
+};
+
+/// Derives the parameter for the page that follows this one.
+///
+/// Infinite queries use it to fetch subsequent pages, without the caller tracking cursors.
+///
+/// # Example
+///
+/// ```
+/// use leptos_query::PageCursor;
+///
+/// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// struct TodoPage {
+///     todos: Vec<String>,
+///     next_offset: Option<u32>,
+/// }
+///
+/// impl PageCursor<u32> for TodoPage {
+///     fn next_cursor(&self) -> Option<u32> {
+///         self.next_offset
+///     }
+/// }
+/// ```
+pub trait PageCursor<C> {
+    /// The parameter for the next page. None if this is the last page.
+    fn next_cursor(&self) -> Option<C>;
+}
+
+/// The fetched pages of an infinite query, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InfinitePages<C, P> {
+    /// The fetched pages.
+    pub pages: Vec<P>,
+    /// The cursor each page was fetched with. None for the first page.
+    pub cursors: Vec<Option<C>>,
+    /// The cursors of pages dropped from the start to stay within the max pages, oldest first.
+    /// Used to fetch them again when scrolling back.
+    pub dropped_cursors: Vec<Option<C>>,
+}
+
+impl<C, P> InfinitePages<C, P> {
+    /// Creates the pages from the first page.
+    pub fn new(first_page: P) -> Self {
+        Self {
+            pages: vec![first_page],
+            cursors: vec![None],
+            dropped_cursors: Vec::new(),
+        }
+    }
+
+    /// The cursor for the page after the last fetched page.
+    pub fn next_cursor(&self) -> Option<C>
+    where
+        P: PageCursor<C>,
+    {
+        self.pages.last()?.next_cursor()
+    }
+
+    /// If there are more pages to fetch.
+    pub fn has_next_page(&self) -> bool
+    where
+        P: PageCursor<C>,
+    {
+        self.next_cursor().is_some()
+    }
+
+    /// If pages were dropped from the start, and can be fetched again.
+    pub fn has_previous_page(&self) -> bool {
+        !self.dropped_cursors.is_empty()
+    }
+
+    // Drops pages from the start, remembering their cursors.
+    fn drop_first_pages(&mut self, max_pages: usize) {
+        let excess = self.pages.len().saturating_sub(max_pages);
+        self.pages.drain(..excess);
+        self.dropped_cursors.extend(self.cursors.drain(..excess));
+    }
+
+    // Drops pages from the end. They are fetched again through the next cursor.
+    fn drop_last_pages(&mut self, max_pages: usize) {
+        self.pages.truncate(max_pages);
+        self.cursors.truncate(max_pages);
+    }
+
+    /// Collects the items of every page, in page order.
+    pub fn items<T, I>(&self, items: impl Fn(&P) -> I) -> Vec<T>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.pages.iter().flat_map(items).collect()
+    }
+}
+
+// Each page and cursor is serialized on its own, so any codec works for them.
+impl<C, P> Serializable for InfinitePages<C, P>
+where
+    C: Serializable,
+    P: Serializable,
+{
+    fn ser(&self) -> Result<String, SerializationError> {
+        let pages = self
+            .pages
+            .iter()
+            .map(Serializable::ser)
+            .collect::<Result<Vec<_>, _>>()?;
+        let cursors = self
+            .cursors
+            .iter()
+            .map(|cursor| cursor.as_ref().map(Serializable::ser).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+        let dropped_cursors = self
+            .dropped_cursors
+            .iter()
+            .map(|cursor| cursor.as_ref().map(Serializable::ser).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+        (pages, cursors, dropped_cursors).ser()
+    }
+
+    fn de(bytes: &str) -> Result<Self, SerializationError> {
+        let (pages, cursors, dropped_cursors) =
+            <(Vec<String>, Vec<Option<String>>, Vec<Option<String>>)>::de(bytes)?;
+        Ok(Self {
+            pages: pages
+                .iter()
+                .map(|page| P::de(page))
+                .collect::<Result<_, _>>()?,
+            cursors: cursors
+                .iter()
+                .map(|cursor| cursor.as_deref().map(C::de).transpose())
+                .collect::<Result<_, _>>()?,
+            dropped_cursors: dropped_cursors
+                .iter()
+                .map(|cursor| cursor.as_deref().map(C::de).transpose())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Creates an [`InfiniteQueryScope`], for data that is fetched one page at a time (e.g. infinite scrolling feeds).
+///
+/// # Parameters
+///
+/// * `fetcher`: Fetches a single page. Receives the query key, and the cursor for the page (None for the first page).
+/// * `options`: Options for the query. Refetching the query (e.g. when stale or invalidated) resets it to the first page.
+///
+/// # Example
+///
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// fn todos_query() -> InfiniteQueryScope<TodoFilter, u32, TodoPage> {
+///     create_infinite_query(get_todos, QueryOptions::default())
+/// }
+///
+/// #[component]
+/// fn Todos() -> impl IntoView {
+///     let query = todos_query();
+///     let QueryResult { data, .. } = query.use_query(|| TodoFilter::All);
+///     let todos = flatten_pages(data, |page: &TodoPage| page.todos.clone());
+///
+///     let load_more = create_action(move |_: &()| {
+///         let query = query.clone();
+///         async move { query.fetch_next_page(TodoFilter::All).await }
+///     });
+///
+///     // Rest of component...
+/// }
+///
+/// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// enum TodoFilter {
+///     All,
+///     Completed,
+/// }
+///
+/// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// struct TodoPage {
+///     todos: Vec<String>,
+///     next_offset: Option<u32>,
+/// }
+///
+/// impl PageCursor<u32> for TodoPage {
+///     fn next_cursor(&self) -> Option<u32> {
+///         self.next_offset
+///     }
+/// }
+///
+/// async fn get_todos(filter: TodoFilter, offset: Option<u32>) -> TodoPage {
+///     todo!()
+/// }
+/// ```
+pub fn create_infinite_query<K, C, P, Fu>(
+    fetcher: impl Fn(K, Option<C>) -> Fu + 'static,
+    options: QueryOptions<InfinitePages<C, P>>,
+) -> InfiniteQueryScope<K, C, P>
+where
+    K: QueryKey + 'static,
+    C: QueryValue + PartialEq + 'static,
+    P: QueryValue + PageCursor<C> + 'static,
+    Fu: Future<Output = P> + 'static,
+{
+    let fetcher: PageFetcher<K, C, P> = Rc::new(move |key, cursor| Box::pin(fetcher(key, cursor)));
+    let scope = create_query(
+        {
+            let fetcher = fetcher.clone();
+            move |key| {
+                let page = fetcher(key, None);
+                async move { InfinitePages::new(page.await) }
+            }
+        },
+        options,
+    );
+    InfiniteQueryScope {
+        scope,
+        fetcher,
+        max_pages: None,
+    }
+}
+
+type PageFetcher<K, C, P> = Rc<dyn Fn(K, Option<C>) -> Pin<Box<dyn Future<Output = P>>>>;
+
+/// A scope for queries that are fetched one page at a time. Created with [`create_infinite_query`].
+#[derive(Clone)]
+pub struct InfiniteQueryScope<K, C, P> {
+    scope: QueryScope<K, InfinitePages<C, P>>,
+    fetcher: PageFetcher<K, C, P>,
+    max_pages: Option<usize>,
+}
+
+impl<K, C, P> InfiniteQueryScope<K, C, P>
+where
+    K: QueryKey + 'static,
+    C: QueryValue + PartialEq + 'static,
+    P: QueryValue + PageCursor<C> + 'static,
+{
+    /// Executes the query, fetching the first page if needed. See [`QueryScope::use_query`].
+    #[track_caller]
+    pub fn use_query(
+        &self,
+        key: impl Fn() -> K + 'static,
+    ) -> QueryResult<InfinitePages<C, P>, impl RefetchFn> {
+        self.scope.use_query(key)
+    }
+
+    /// The underlying scope, for invalidating, updating, and otherwise managing the pages.
+    pub fn scope(&self) -> &QueryScope<K, InfinitePages<C, P>> {
+        &self.scope
+    }
+
+    /// Derives a new scope that keeps at most `max_pages` pages in memory, sharing the same fetcher.
+    ///
+    /// Fetching the next page drops the oldest pages, which can be fetched again with [`fetch_previous_page`](Self::fetch_previous_page).
+    /// Fetching a previous page drops the furthest pages, which can be fetched again with [`fetch_next_page`](Self::fetch_next_page).
+    /// At least one page is always kept.
+    pub fn with_max_pages(&self, max_pages: usize) -> Self {
+        Self {
+            scope: self.scope.clone(),
+            fetcher: self.fetcher.clone(),
+            max_pages: Some(max_pages.max(1)),
+        }
+    }
+
+    /// Fetches the page after the last fetched page, and appends it to the query.
+    ///
+    /// Returns false if there is no next page, if the first page hasn't been fetched yet, or if the pages changed while fetching.
+    /// This should usually be called on an event (e.g. on:click), or when the end of the list becomes visible.
+    pub async fn fetch_next_page(&self, key: K) -> bool {
+        // The client is retrieved before awaiting, while the reactive owner is still available.
+        let client = use_query_client();
+        let Some(cursor) = client
+            .peek_query_state::<K, InfinitePages<C, P>>(&key)
+            .and_then(|state| state.data()?.next_cursor())
+        else {
+            return false;
+        };
+
+        let page = (self.fetcher)(key.clone(), Some(cursor.clone())).await;
+
+        let mut appended = false;
+        client.update_query_data_mut::<K, InfinitePages<C, P>>(key, |pages| {
+            // The pages may have been refetched or extended while this page was loading.
+            if pages.next_cursor().as_ref() == Some(&cursor) {
+                pages.pages.push(page);
+                pages.cursors.push(Some(cursor));
+                if let Some(max_pages) = self.max_pages {
+                    pages.drop_first_pages(max_pages);
+                }
+                appended = true;
+            }
+        });
+        appended
+    }
+
+    /// Fetches the last page that was dropped to stay within the max pages, and prepends it to the query.
+    ///
+    /// Returns false if no pages were dropped, or if the pages changed while fetching.
+    /// This should usually be called when the start of the list becomes visible.
+    pub async fn fetch_previous_page(&self, key: K) -> bool {
+        let client = use_query_client();
+        let Some(cursor) = client
+            .peek_query_state::<K, InfinitePages<C, P>>(&key)
+            .and_then(|state| state.data()?.dropped_cursors.last().cloned())
+        else {
+            return false;
+        };
+
+        let page = (self.fetcher)(key.clone(), cursor.clone()).await;
+
+        let mut prepended = false;
+        client.update_query_data_mut::<K, InfinitePages<C, P>>(key, |pages| {
+            if pages.dropped_cursors.last() == Some(&cursor) {
+                pages.dropped_cursors.pop();
+                pages.pages.insert(0, page);
+                pages.cursors.insert(0, cursor);
+                if let Some(max_pages) = self.max_pages {
+                    pages.drop_last_pages(max_pages);
+                }
+                prepended = true;
+            }
+        });
+        prepended
+    }
+
+    /// Refetches a single page by its index in [`InfinitePages::pages`], without refetching the other pages.
+    ///
+    /// Returns false if the page doesn't exist, or if the pages changed while fetching.
+    pub async fn refetch_page(&self, key: K, index: usize) -> bool {
+        self.refetch_pages(key, |i, _| i == index).await > 0
+    }
+
+    /// Refetches the pages matching the predicate, which receives each page's index and current data.
+    /// The other pages are kept as is, so a refetched page should not change where the next page starts.
+    ///
+    /// The pages are fetched concurrently. Returns how many pages were replaced; a page is skipped if the pages changed while it was fetching.
+    pub async fn refetch_pages(&self, key: K, predicate: impl Fn(usize, &P) -> bool) -> usize {
+        let client = use_query_client();
+        let Some(pages) = client
+            .peek_query_state::<K, InfinitePages<C, P>>(&key)
+            .and_then(|state| state.data().cloned())
+        else {
+            return 0;
+        };
+
+        let refetches = pages
+            .pages
+            .iter()
+            .zip(pages.cursors)
+            .enumerate()
+            .filter(|(index, (page, _))| predicate(*index, page))
+            .map(|(index, (_, cursor))| {
+                let page = (self.fetcher)(key.clone(), cursor.clone());
+                async move { (index, cursor, page.await) }
+            });
+        let refetched = futures::future::join_all(refetches).await;
+
+        let mut replaced = 0;
+        client.update_query_data_mut::<K, InfinitePages<C, P>>(key, |pages| {
+            for (index, cursor, page) in refetched {
+                if pages.cursors.get(index) == Some(&cursor) {
+                    pages.pages[index] = page;
+                    replaced += 1;
+                }
+            }
+        });
+        replaced
+    }
+}
+
+/// Flattens the pages of an infinite query into a single list, for rendering.
+///
+/// Empty until the first page has been fetched.
+pub fn flatten_pages<C, P, T, I>(
+    data: Signal<Option<InfinitePages<C, P>>>,
+    items: impl Fn(&P) -> I + 'static,
+) -> Signal<Vec<T>>
+where
+    I: IntoIterator<Item = T>,
+{
+    Signal::derive(move || {
+        data.with(|data| {
+            data.as_ref()
+                .map(|pages| pages.items(&items))
+                .unwrap_or_default()
+        })
+    })
+}
+
+#[cfg(all(test, not(any(feature = "csr", feature = "hydrate"))))]
+mod tests {
+    use super::*;
+    use crate::provide_query_client;
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Page {
+        items: Vec<u32>,
+        next: Option<u32>,
+    }
+
+    impl PageCursor<u32> for Page {
+        fn next_cursor(&self) -> Option<u32> {
+            self.next
+        }
+    }
+
+    // Pages of two items, ending at 6.
+    async fn get_page(_: (), cursor: Option<u32>) -> Page {
+        let start = cursor.unwrap_or_default();
+        Page {
+            items: vec![start, start + 1],
+            next: Some(start + 2).filter(|next| *next < 6),
+        }
+    }
+
+    #[test]
+    fn fetches_pages_with_cursor() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let query = create_infinite_query(get_page, QueryOptions::default());
+
+        // Nothing to continue from.
+        assert!(!futures::executor::block_on(query.fetch_next_page(())));
+
+        let first = futures::executor::block_on(get_page((), None));
+        query.scope().set_query_data((), InfinitePages::new(first));
+
+        let scope = query.scope().clone();
+        let data =
+            Signal::derive(move || scope.peek_query_state(&()).and_then(|s| s.data().cloned()));
+        let items = flatten_pages(data, |page: &Page| page.items.clone());
+
+        assert!(futures::executor::block_on(query.fetch_next_page(())));
+        assert!(futures::executor::block_on(query.fetch_next_page(())));
+        assert!(!futures::executor::block_on(query.fetch_next_page(())));
+
+        assert_eq!(items.get_untracked(), vec![0, 1, 2, 3, 4, 5]);
+
+        let pages = query
+            .scope()
+            .peek_query_state(&())
+            .unwrap()
+            .data()
+            .cloned()
+            .unwrap();
+        assert_eq!(pages.cursors, vec![None, Some(2), Some(4)]);
+        assert!(!pages.has_next_page());
+        assert_eq!(InfinitePages::de(&pages.ser().unwrap()).unwrap(), pages);
+    }
+
+    #[test]
+    fn drops_pages_beyond_max() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let query = create_infinite_query(get_page, QueryOptions::default()).with_max_pages(2);
+
+        let first = futures::executor::block_on(get_page((), None));
+        query.scope().set_query_data((), InfinitePages::new(first));
+
+        let pages = || {
+            query
+                .scope()
+                .peek_query_state(&())
+                .unwrap()
+                .data()
+                .cloned()
+                .unwrap()
+        };
+
+        // No pages dropped yet.
+        assert!(!futures::executor::block_on(query.fetch_previous_page(())));
+
+        assert!(futures::executor::block_on(query.fetch_next_page(())));
+        assert!(futures::executor::block_on(query.fetch_next_page(())));
+        assert_eq!(pages().cursors, vec![Some(2), Some(4)]);
+        assert_eq!(pages().dropped_cursors, vec![None]);
+        assert!(pages().has_previous_page());
+
+        // Scrolling back drops the furthest page.
+        assert!(futures::executor::block_on(query.fetch_previous_page(())));
+        assert_eq!(pages().cursors, vec![None, Some(2)]);
+        assert!(!pages().has_previous_page());
+        assert!(pages().has_next_page());
+        assert_eq!(pages().items(|page| page.items.clone()), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn refetches_matching_pages() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let query = create_infinite_query(get_page, QueryOptions::default());
+
+        let outdated = |cursor: Option<u32>| Page {
+            items: vec![],
+            next: Some(cursor.unwrap_or_default() + 2),
+        };
+        let cursors = vec![None, Some(2), Some(4)];
+        query.scope().set_query_data(
+            (),
+            InfinitePages {
+                pages: cursors.iter().copied().map(outdated).collect(),
+                cursors,
+                dropped_cursors: vec![],
+            },
+        );
+
+        let items = || {
+            query
+                .scope()
+                .peek_query_state(&())
+                .unwrap()
+                .data()
+                .unwrap()
+                .items(|page| page.items.clone())
+        };
+
+        assert!(futures::executor::block_on(query.refetch_page((), 1)));
+        assert_eq!(items(), vec![2, 3]);
+
+        assert!(!futures::executor::block_on(query.refetch_page((), 3)));
+
+        let refetched = query.refetch_pages((), |_, page| page.items.is_empty());
+        assert_eq!(futures::executor::block_on(refetched), 2);
+        assert_eq!(items(), vec![0, 1, 2, 3, 4, 5]);
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
+    cell::Cell,
     ops::{Add, Sub},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 /// Instant that can be used in both wasm and non-wasm environments.
@@ -8,30 +9,115 @@ use std::{
 #[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Instant(pub std::time::Duration);
 
+thread_local! {
+    // The latest time returned by `Instant::now`, and how far the wall clock has been set back since.
+    static CLOCK: Cell<(Duration, Duration)> = const { Cell::new((Duration::ZERO, Duration::ZERO)) };
+}
+
 impl Instant {
     /// Get the current time as a Unix Timestamp.
+    ///
+    /// Never goes backwards, even if the wall clock is set back, e.g. by NTP. Time then continues from the latest instant, at the rate of the wall clock.
+    /// Forward jumps are kept, as they can't be told apart from the device sleeping, during which data does get older.
     pub fn now() -> Self {
-        cfg_if::cfg_if! {
-            if #[cfg(any(feature = "hydrate", feature = "csr"))] {
-                let millis = js_sys::Date::now();
-                let duration = std::time::Duration::from_millis(millis as u64);
-                Instant(duration)
-            } else {
-                let duration = std::time::SystemTime::now()
-                    .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                    .expect("System clock was before 1970.");
-                Instant(duration)
-            }
+        let wall = wall_clock();
+        CLOCK.with(|clock| {
+            let (latest, set_back) = clock.get();
+            let (now, set_back) = advance(wall, latest, set_back);
+            clock.set((now, set_back));
+            Instant(now)
+        })
+    }
+
+    /// Creates an Instant from milliseconds since the Unix Epoch, e.g. from `Date.now()` or a persisted timestamp.
+    pub const fn from_unix_millis(millis: u64) -> Self {
+        Instant(Duration::from_millis(millis))
+    }
+
+    /// Milliseconds since the Unix Epoch.
+    pub fn as_unix_millis(&self) -> u64 {
+        self.0.as_millis() as u64
+    }
+}
+
+fn wall_clock() -> Duration {
+    cfg_if::cfg_if! {
+        if #[cfg(any(feature = "hydrate", feature = "csr"))] {
+            Duration::from_millis(js_sys::Date::now() as u64)
+        } else {
+            std::time::SystemTime::now()
+                .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                .expect("System clock was before 1970.")
         }
     }
 }
 
+// Returns the current time, and the updated amount the wall clock has been set back.
+fn advance(wall: Duration, latest: Duration, set_back: Duration) -> (Duration, Duration) {
+    let now = wall + set_back;
+    if now < latest {
+        (latest, set_back + (latest - now))
+    } else {
+        (now, set_back)
+    }
+}
+
+/// Times before the Unix Epoch are clamped to the epoch.
+impl From<SystemTime> for Instant {
+    fn from(time: SystemTime) -> Self {
+        Instant(
+            time.duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default(),
+        )
+    }
+}
+
+impl From<Instant> for SystemTime {
+    fn from(instant: Instant) -> Self {
+        SystemTime::UNIX_EPOCH + instant.0
+    }
+}
+
+/// Serialized as milliseconds since the Unix Epoch.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Instant {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.as_unix_millis())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Instant {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u64::deserialize(deserializer).map(Instant::from_unix_millis)
+    }
+}
+
+/// Times before the Unix Epoch are clamped to the epoch.
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Instant {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        Instant::from_unix_millis(time.timestamp_millis().max(0) as u64)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Instant> for chrono::DateTime<chrono::Utc> {
+    fn from(instant: Instant) -> Self {
+        i64::try_from(instant.as_unix_millis())
+            .ok()
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC)
+    }
+}
+
+/// Saturates to zero if `rhs` is later, e.g. for a timestamp from a clock that is ahead.
 impl Sub<Instant> for Instant {
     type Output = Duration;
 
     #[inline]
     fn sub(self, rhs: Instant) -> Self::Output {
-        self.0 - rhs.0
+        self.0.saturating_sub(rhs.0)
     }
 }
 
@@ -54,3 +140,52 @@ impl std::fmt::Debug for Instant {
         f.debug_tuple("Instant").field(&self.0.as_millis()).finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wall_clock_conversions() {
+        let instant = Instant::from_unix_millis(1_700_000_000_123);
+        assert_eq!(1_700_000_000_123, instant.as_unix_millis());
+
+        let system_time = SystemTime::from(instant);
+        assert_eq!(instant, Instant::from(system_time));
+
+        let before_epoch = SystemTime::UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(Instant::from_unix_millis(0), Instant::from(before_epoch));
+    }
+
+    #[test]
+    fn clock_never_goes_backwards() {
+        let secs = Duration::from_secs;
+
+        let (now, set_back) = advance(secs(100), secs(90), Duration::ZERO);
+        assert_eq!((secs(100), Duration::ZERO), (now, set_back));
+
+        // Set back by 60 seconds.
+        let (now, set_back) = advance(secs(40), now, set_back);
+        assert_eq!((secs(100), secs(60)), (now, set_back));
+
+        // Continues at the rate of the wall clock.
+        let (now, set_back) = advance(secs(45), now, set_back);
+        assert_eq!((secs(105), secs(60)), (now, set_back));
+
+        // Forward jumps are kept.
+        let (now, _) = advance(secs(3600), now, set_back);
+        assert_eq!(secs(3660), now);
+
+        assert!(Instant::now() <= Instant::now());
+        assert_eq!(Duration::ZERO, Instant(secs(1)) - Instant(secs(2)));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_conversions() {
+        let instant = Instant::from_unix_millis(1_700_000_000_123);
+        let date_time = chrono::DateTime::<chrono::Utc>::from(instant);
+        assert_eq!(1_700_000_000_123, date_time.timestamp_millis());
+        assert_eq!(instant, Instant::from(date_time));
+    }
+}
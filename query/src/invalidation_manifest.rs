@@ -0,0 +1,69 @@
+use crate::cache_observer::QueryCacheKey;
+
+/// Queries to invalidate after a mutation.
+///
+/// Built alongside the mutation, e.g. returned from a server function with its result, and applied on the client with [`QueryClient::apply_invalidations`](crate::QueryClient::apply_invalidations).
+/// This keeps the knowledge of which queries a mutation affects next to the mutation itself.
+///
+/// Keys are matched by their serialized form, so every query with a matching key is invalidated, regardless of its value type.
+/// Enable the `serde` feature to return a manifest from a server function.
+///
+/// Example
+/// ```
+/// use leptos_query::*;
+///
+/// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// struct TodoListKey;
+///
+/// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// struct TodoKey(u32);
+///
+/// // On the server, next to the mutation.
+/// fn update_todo(id: u32) -> InvalidationManifest {
+///     // Update the todo...
+///     InvalidationManifest::new().key(&TodoListKey).key(&TodoKey(id))
+/// }
+///
+/// // On the client, once the mutation has completed.
+/// fn on_updated(manifest: InvalidationManifest) {
+///     use_query_client().apply_invalidations(&manifest);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InvalidationManifest {
+    keys: Vec<QueryCacheKey>,
+}
+
+impl InvalidationManifest {
+    /// Creates an empty manifest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a query key to invalidate.
+    pub fn key<K>(self, key: &K) -> Self
+    where
+        K: crate::QueryKey + 'static,
+    {
+        self.cache_key(QueryCacheKey::from(key))
+    }
+
+    /// Adds an already serialized key to invalidate.
+    pub fn cache_key(mut self, key: QueryCacheKey) -> Self {
+        if !self.keys.contains(&key) {
+            self.keys.push(key);
+        }
+        self
+    }
+
+    /// The serialized keys to invalidate.
+    pub fn keys(&self) -> &[QueryCacheKey] {
+        &self.keys
+    }
+
+    /// If there is nothing to invalidate.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
@@ -0,0 +1,237 @@
+use leptos::*;
+
+use crate::{QueryResult, RefetchFn, SplitQueryResult};
+
+/// Combines several query results into one, with the data of all queries as a tuple.
+///
+/// Accepts [`QueryResult`]s and [`SplitQueryResult`]s, up to 8 at once.
+/// The data is only present once every query has data, and the flags are true if they are true for any query.
+///
+/// # Example
+///
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// #[component]
+/// fn UserPosts(id: u32) -> impl IntoView {
+///     let JoinedQueries { data, is_fetching, .. } = join_queries!(
+///         user_query().use_query(move || id),
+///         posts_query().use_query(move || id),
+///     );
+///
+///     view! {
+///         <Transition fallback=|| "Loading...">
+///             {move || data.get().map(|(user, posts)| format!("{} wrote {} posts", user, posts.len()))}
+///         </Transition>
+///     }
+/// }
+///
+/// fn user_query() -> QueryScope<u32, String> {
+///     create_query(|id| async move { todo!() }, QueryOptions::default())
+/// }
+///
+/// fn posts_query() -> QueryScope<u32, Vec<String>> {
+///     create_query(|id| async move { todo!() }, QueryOptions::default())
+/// }
+/// ```
+#[macro_export]
+macro_rules! join_queries {
+    ($($query:expr),+ $(,)?) => {
+        $crate::JoinParts::join(($($crate::JoinableQuery::join_part(&$query),)+))
+    };
+}
+
+/// Combined query results. Created with [`join_queries!`](crate::join_queries).
+#[derive(Clone, Copy)]
+pub struct JoinedQueries<T: 'static> {
+    /// The data of every query, once all of them have data.
+    /// Should be called inside of a [`Transition`](leptos::Transition) or [`Suspense`](leptos::Suspense) component.
+    pub data: Signal<Option<T>>,
+    /// If any query is fetching for the first time.
+    pub is_loading: Signal<bool>,
+    /// If any query is actively fetching.
+    pub is_fetching: Signal<bool>,
+    /// If any query data has been marked as invalid.
+    pub is_invalid: Signal<bool>,
+    /// If any query data is older than the stale time.
+    pub is_stale: Signal<bool>,
+    /// If the latest fetch of any [`SplitQueryResult`] failed, or the latest fetched value of any query was rejected by its [`validator`](crate::QueryOptions::validator).
+    pub is_error: Signal<bool>,
+}
+
+/// A query result that can be combined with [`join_queries!`](crate::join_queries).
+pub trait JoinableQuery {
+    /// The data contributed to the combined tuple.
+    type Data: 'static;
+
+    /// The signals of this query, to be combined with others.
+    fn join_part(&self) -> JoinPart<Self::Data>;
+}
+
+/// The signals of a single query in [`join_queries!`](crate::join_queries).
+#[derive(Clone, Copy)]
+pub struct JoinPart<T: 'static> {
+    data: Signal<Option<T>>,
+    flags: Flags,
+}
+
+// Aggregated across queries.
+#[derive(Clone, Copy)]
+struct Flags {
+    is_loading: Signal<bool>,
+    is_fetching: Signal<bool>,
+    is_invalid: Signal<bool>,
+    is_stale: Signal<bool>,
+    is_error: Signal<bool>,
+}
+
+impl<V, R> JoinableQuery for QueryResult<V, R>
+where
+    V: 'static,
+    R: RefetchFn,
+{
+    type Data = V;
+
+    fn join_part(&self) -> JoinPart<V> {
+        let validation_error = self.validation_error;
+        JoinPart {
+            data: self.data,
+            flags: Flags {
+                is_loading: self.is_loading,
+                is_fetching: self.is_fetching,
+                is_invalid: self.is_invalid,
+                is_stale: self.is_stale,
+                is_error: Signal::derive(move || validation_error.with(Option::is_some)),
+            },
+        }
+    }
+}
+
+impl<T, E, R> JoinableQuery for SplitQueryResult<T, E, R>
+where
+    T: 'static,
+    E: 'static,
+    R: RefetchFn,
+{
+    type Data = T;
+
+    fn join_part(&self) -> JoinPart<T> {
+        let (error, validation_error) = (self.error, self.validation_error);
+        JoinPart {
+            data: self.data,
+            flags: Flags {
+                is_loading: self.is_loading,
+                is_fetching: self.is_fetching,
+                is_invalid: self.is_invalid,
+                is_stale: self.is_stale,
+                is_error: Signal::derive(move || {
+                    error.with(Option::is_some) || validation_error.with(Option::is_some)
+                }),
+            },
+        }
+    }
+}
+
+/// A tuple of [`JoinPart`]s, combined by [`join_queries!`](crate::join_queries).
+pub trait JoinParts {
+    /// The tuple of the data of every query.
+    type Data: 'static;
+
+    /// Combines the parts.
+    fn join(self) -> JoinedQueries<Self::Data>;
+}
+
+fn join_flags<T, const N: usize>(data: Signal<Option<T>>, flags: [Flags; N]) -> JoinedQueries<T> {
+    let any = move |flag: fn(&Flags) -> Signal<bool>| {
+        Signal::derive(move || flags.iter().any(|flags| flag(flags).get()))
+    };
+    JoinedQueries {
+        data,
+        is_loading: any(|f| f.is_loading),
+        is_fetching: any(|f| f.is_fetching),
+        is_invalid: any(|f| f.is_invalid),
+        is_stale: any(|f| f.is_stale),
+        is_error: any(|f| f.is_error),
+    }
+}
+
+macro_rules! impl_join_parts {
+    ($($T:ident $i:tt),+) => {
+        impl<$($T: Clone + 'static),+> JoinParts for ($(JoinPart<$T>,)+) {
+            type Data = ($($T,)+);
+
+            fn join(self) -> JoinedQueries<Self::Data> {
+                let parts = self;
+                // Every query is read, so that all of them suspend together.
+                let data = Signal::derive(move || {
+                    let data = ($(parts.$i.data.get(),)+);
+                    Some(($(data.$i?,)+))
+                });
+                join_flags(data, [$(parts.$i.flags),+])
+            }
+        }
+    };
+}
+
+impl_join_parts!(A 0);
+impl_join_parts!(A 0, B 1);
+impl_join_parts!(A 0, B 1, C 2);
+impl_join_parts!(A 0, B 1, C 2, D 3);
+impl_join_parts!(A 0, B 1, C 2, D 3, E 4);
+impl_join_parts!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_join_parts!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_join_parts!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use leptos::*;
+
+    fn result<V: Clone>(
+        data: RwSignal<Option<V>>,
+        is_loading: RwSignal<bool>,
+    ) -> QueryResult<V, impl RefetchFn> {
+        QueryResult {
+            data: data.into(),
+            state: Signal::derive(|| QueryState::Created),
+            is_loading: is_loading.into(),
+            is_fetching: is_loading.into(),
+            is_invalid: Signal::derive(|| false),
+            is_stale: Signal::derive(|| false),
+            validation_error: Signal::derive(|| None),
+            refetch: || {},
+        }
+    }
+
+    #[test]
+    fn joins_data_and_flags() {
+        let _ = create_runtime();
+
+        let (a, a_loading) = (RwSignal::new(None::<u32>), RwSignal::new(true));
+        let (b, b_loading) = (
+            RwSignal::new(None::<Result<String, String>>),
+            RwSignal::new(true),
+        );
+
+        let joined = join_queries!(result(a, a_loading), result(b, b_loading).split());
+        assert_eq!(None, joined.data.get());
+        assert!(joined.is_loading.get());
+
+        a.set(Some(1));
+        a_loading.set(false);
+        assert_eq!(None, joined.data.get());
+        assert!(joined.is_loading.get());
+
+        b.set(Some(Ok("b".to_string())));
+        b_loading.set(false);
+        assert_eq!(Some((1, "b".to_string())), joined.data.get());
+        assert!(!joined.is_loading.get());
+        assert!(!joined.is_error.get());
+
+        // A failed refetch keeps the last data.
+        b.set(Some(Err("failed".to_string())));
+        assert_eq!(Some((1, "b".to_string())), joined.data.get());
+        assert!(joined.is_error.get());
+    }
+}
@@ -40,6 +40,7 @@
 //!
 //! ## The main entry points to using Queries are:
 //! - [`create_query`](create_query::create_query) - **Recommended**: Creates a [`QueryScope`] which encapsulates `use_query` and other methods for managing queries.
+//! - [`#[query]`](macro@query) - Generates the [`QueryScope`] function for an async fetcher, instead of calling `create_query` by hand.
 //! - [`use_query`][use_query::use_query] - A query primitive for reading, caching, and refetching data.
 //!
 //! ## Feature Flags
@@ -48,6 +49,8 @@
 //! - `hydrate` Hydration: Ensure that queries are hydrated on the client, when using server-side rendering.
 //! - `local_storage` - Enables local storage persistance for queries.
 //! - `index_db` - Enables index db persistance for queries.
+//! - `chrono` - Conversions between [`Instant`] and `chrono::DateTime<Utc>`.
+//! - `serde` - `Serialize` and `Deserialize` for [`QueryState`], [`QueryData`], [`Instant`], and [`InvalidationManifest`].
 //!
 //! ## Version compatibility for Leptos and Leptos Query
 //!
@@ -240,37 +243,134 @@
 //! ```
 //!
 
+mod any_query_scope;
 /// Subcriptions to cache-wide query events.
 pub mod cache_observer;
+mod cache_transaction;
+mod cold_start;
+mod console_bridge;
 mod create_query;
+mod crud_queries;
+mod data_mut;
+#[cfg(feature = "debug_report")]
+mod debug_report;
+mod fetch_middleware;
+mod fetch_scheduler;
 mod garbage_collector;
+mod gc_pause_guard;
+#[cfg(feature = "router")]
+mod history_cache;
+mod hold_guard;
+mod infinite_query;
 mod instant;
+mod invalidation_manifest;
+mod join_queries;
+/// Simulated network for prototyping and tests.
+#[cfg(feature = "mock")]
+pub mod mock;
+mod prefetch_handle;
+mod prefetch_query;
 mod query;
 mod query_cache;
 mod query_client;
+mod query_client_builder;
+mod query_client_handle;
 mod query_executor;
+mod query_export;
+mod query_group;
 mod query_observer;
 mod query_options;
 /// Utitities for client side query persistance.
 pub mod query_persister;
+mod query_plugin;
 mod query_result;
 mod query_state;
+mod query_stats;
+mod query_value_rc;
+mod refetch_on_wake;
+mod server_fn_retry;
+mod tab_lease;
+mod temp_ids;
 mod use_query;
 mod util;
+mod visibility;
 
+pub use any_query_scope::AnyQueryScope;
+pub use cache_transaction::CacheTransaction;
+pub use cold_start::ColdStart;
+pub use console_bridge::ConsoleBridge;
 pub use create_query::*;
+pub use crud_queries::*;
+pub use data_mut::DataMut;
+pub use fetch_middleware::FetchMiddleware;
+pub use garbage_collector::GcDecision;
+pub use gc_pause_guard::GcPauseGuard;
+#[cfg(feature = "router")]
+pub use history_cache::*;
+pub use hold_guard::HoldGuard;
+pub use infinite_query::*;
 pub use instant::*;
+pub use invalidation_manifest::*;
+pub use join_queries::*;
+pub use prefetch_handle::PrefetchHandle;
+pub use prefetch_query::*;
 pub use query_client::*;
+pub use query_client_builder::*;
+pub use query_client_handle::*;
 pub use query_executor::*;
+pub use query_export::*;
+pub use query_group::{QueryGroup, QueryGroupStatus};
 pub use query_options::*;
+pub use query_plugin::*;
 pub use query_result::*;
 pub use query_state::*;
+pub use query_stats::{QueryStats, TypeSize};
+pub use query_value_rc::QueryValueRc;
+pub use refetch_on_wake::*;
+pub use server_fn_retry::{ServerFnErrorKind, ServerFnRetry};
+pub use tab_lease::CrossTabLease;
+pub use temp_ids::*;
 pub use use_query::*;
+pub use visibility::*;
 
 /// Convenience trait for query key requirements.
+///
+/// Implemented for every type that is `Debug + Clone + Hash + Eq`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as a query key",
+    label = "not a query key",
+    note = "query keys must implement `Debug`, `Clone`, `Hash` and `Eq`",
+    note = "consider adding `#[derive(Debug, Clone, Hash, Eq, PartialEq)]` to the key type"
+)]
 pub trait QueryKey: std::fmt::Debug + Clone + std::hash::Hash + Eq {}
 impl<K> QueryKey for K where K: std::fmt::Debug + Clone + std::hash::Hash + Eq {}
 
 /// Convenience trait for query value requirements.
+///
+/// Implemented for every type that is `Debug + Clone + leptos::Serializable`.
+///
+/// [`Serializable`](leptos::Serializable) is how query values are sent from the server to the client, and how they are persisted.
+/// By default, Leptos implements it for types that are `serde::Serialize + serde::de::DeserializeOwned`.
+/// Other codecs (e.g. `serde-lite`, `miniserde`, `rkyv`) are chosen with the matching `leptos` feature flag.
+///
+/// ```compile_fail
+/// use leptos_query::*;
+///
+/// #[derive(Debug, Clone)]
+/// struct NotSerializable;
+///
+/// async fn fetch(_: u32) -> NotSerializable {
+///     NotSerializable
+/// }
+///
+/// // error: `NotSerializable` cannot be used as a query value
+/// let scope = create_query(fetch, QueryOptions::default());
+/// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be used as a query value",
+    label = "not a query value",
+    note = "query values must implement `Debug`, `Clone` and `leptos::Serializable`",
+    note = "`leptos::Serializable` is implemented for `serde::Serialize + serde::de::DeserializeOwned` types; to use another codec, like `serde-lite`, enable the matching `leptos` feature"
+)]
 pub trait QueryValue: std::fmt::Debug + Clone + leptos::Serializable {}
 impl<V> QueryValue for V where V: std::fmt::Debug + Clone + leptos::Serializable {}
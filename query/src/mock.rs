@@ -0,0 +1,257 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::util::sleep;
+
+/// Simulated network with latency and error injection, to prototype against the cache before a backend exists.
+///
+/// Responses are computed locally, after the configured latency.
+/// Requests can fail at random, or on demand, to try out error states and retries.
+/// Clones share their settings, so they can be adjusted while the app runs, e.g. from a debug panel.
+/// The network is `Send + Sync`, so it can also back server functions and be shared between tests.
+///
+/// Without one of the `csr`, `hydrate`, or `ssr` features there is no timer, so requests respond immediately.
+///
+/// # Example
+///
+/// ```
+/// use leptos_query::{mock::*, *};
+/// use std::time::Duration;
+///
+/// fn todos_query(network: &MockNetwork) -> QueryScope<(), Result<Vec<String>, MockError>> {
+///     create_query(
+///         network.fetcher(|_: ()| vec!["Write docs".to_string()]),
+///         QueryOptions::default(),
+///     )
+/// }
+///
+/// fn App() {
+///     provide_query_client();
+///     let network = MockNetwork::new()
+///         .with_latency(Duration::from_millis(300))
+///         .with_jitter(Duration::from_millis(200))
+///         .with_failure_rate(0.1);
+///     let query = todos_query(&network).use_query(|| ());
+///
+///     // Fail the next refetch.
+///     network.fail_next(1);
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MockNetwork {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    latency: Duration,
+    jitter: Duration,
+    failure_rate: f64,
+    fail_next: usize,
+    offline: bool,
+    requests: usize,
+    // Xorshift state, so runs with the same seed fail the same requests.
+    rng: u64,
+}
+
+impl Default for MockState {
+    fn default() -> Self {
+        MockState {
+            latency: Duration::from_millis(500),
+            jitter: Duration::ZERO,
+            failure_rate: 0.0,
+            fail_next: 0,
+            offline: false,
+            requests: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+impl MockState {
+    // Uniform in [0, 1).
+    fn random(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // The delay and outcome of the next request.
+    fn next_request(&mut self) -> (Duration, Result<(), MockError>) {
+        self.requests += 1;
+        if self.offline {
+            return (Duration::ZERO, Err(MockError::Offline));
+        }
+        let delay = self.latency + self.jitter.mul_f64(self.random());
+        let outcome = if self.fail_next > 0 {
+            self.fail_next -= 1;
+            Err(MockError::Failed)
+        } else if self.random() < self.failure_rate {
+            Err(MockError::Failed)
+        } else {
+            Ok(())
+        };
+        (delay, outcome)
+    }
+}
+
+impl MockNetwork {
+    /// A network with 500ms of latency, that never fails.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the latency of every request.
+    pub fn with_latency(self, latency: Duration) -> Self {
+        self.set_latency(latency);
+        self
+    }
+
+    /// Sets the maximum random latency added to each request. Default is none.
+    pub fn with_jitter(self, jitter: Duration) -> Self {
+        self.state().jitter = jitter;
+        self
+    }
+
+    /// Sets the chance of a request failing, from `0.0` (never) to `1.0` (always).
+    pub fn with_failure_rate(self, failure_rate: f64) -> Self {
+        self.set_failure_rate(failure_rate);
+        self
+    }
+
+    /// Seeds the random jitter and failures, to reproduce a run.
+    pub fn with_seed(self, seed: u64) -> Self {
+        // Xorshift gets stuck at zero.
+        self.state().rng = seed.max(1);
+        self
+    }
+
+    /// Changes the latency of subsequent requests.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state().latency = latency;
+    }
+
+    /// Changes the chance of subsequent requests failing.
+    pub fn set_failure_rate(&self, failure_rate: f64) {
+        self.state().failure_rate = failure_rate.clamp(0.0, 1.0);
+    }
+
+    /// While offline, requests fail immediately with [`MockError::Offline`].
+    pub fn set_offline(&self, offline: bool) {
+        self.state().offline = offline;
+    }
+
+    /// Fails the next `count` requests with [`MockError::Failed`], on top of the failure rate.
+    pub fn fail_next(&self, count: usize) {
+        self.state().fail_next = count;
+    }
+
+    /// How many requests were made so far, including failed ones.
+    pub fn requests(&self) -> usize {
+        self.state().requests
+    }
+
+    /// Sends a request, which is answered by `respond` after the latency, unless it fails.
+    pub async fn request<T>(&self, respond: impl FnOnce() -> T) -> Result<T, MockError> {
+        let (delay, outcome) = self.state().next_request();
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+        outcome.map(|_| respond())
+    }
+
+    /// A query fetcher that answers each key with `respond`, through this network.
+    pub fn fetcher<K, V>(
+        &self,
+        respond: impl Fn(K) -> V + 'static,
+    ) -> impl Fn(K) -> Pin<Box<dyn Future<Output = Result<V, MockError>>>> + Clone
+    where
+        K: 'static,
+        V: 'static,
+    {
+        let network = self.clone();
+        let respond = Rc::new(respond);
+        move |key| {
+            let network = network.clone();
+            let respond = respond.clone();
+            Box::pin(async move { network.request(|| respond(key)).await })
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        // Settings stay valid even if a holder panicked.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Why a [`MockNetwork`] request failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MockError {
+    /// The network is offline. See [`MockNetwork::set_offline`].
+    Offline,
+    /// The request failed, from the failure rate or [`MockNetwork::fail_next`].
+    Failed,
+}
+
+impl std::fmt::Display for MockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MockError::Offline => write!(f, "Mock network is offline."),
+            MockError::Failed => write!(f, "Mock request failed."),
+        }
+    }
+}
+
+impl std::error::Error for MockError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn injects_failures() {
+        let network = MockNetwork::new().with_latency(Duration::ZERO);
+        assert_eq!(Ok(1), block_on(network.request(|| 1)));
+
+        network.fail_next(2);
+        assert_eq!(Err(MockError::Failed), block_on(network.request(|| 1)));
+        assert_eq!(Err(MockError::Failed), block_on(network.request(|| 1)));
+        assert_eq!(Ok(1), block_on(network.request(|| 1)));
+
+        network.set_offline(true);
+        assert_eq!(Err(MockError::Offline), block_on(network.request(|| 1)));
+        network.set_offline(false);
+
+        let fetch = network.fetcher(|n: u32| n * 2);
+        assert_eq!(Ok(4), block_on(fetch(2)));
+        assert_eq!(6, network.requests());
+    }
+
+    #[test]
+    fn failure_rate_is_reproducible() {
+        let failures = |seed| {
+            let network = MockNetwork::new()
+                .with_latency(Duration::ZERO)
+                .with_failure_rate(0.5)
+                .with_seed(seed);
+            (0..200)
+                .map(|_| block_on(network.request(|| ())).is_err())
+                .collect::<Vec<_>>()
+        };
+        let run = failures(7);
+        assert_eq!(run, failures(7));
+        let failed = run.iter().filter(|failed| **failed).count();
+        assert!((60..140).contains(&failed), "{failed} of 200 failed");
+
+        let jittered = MockNetwork::new().with_jitter(Duration::from_millis(100));
+        let (delay, _) = jittered.state().next_request();
+        assert!((Duration::from_millis(500)..Duration::from_millis(600)).contains(&delay));
+    }
+}
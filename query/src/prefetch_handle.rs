@@ -0,0 +1,92 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use futures::FutureExt;
+use futures_channel::oneshot;
+
+/// A prefetch started with [`QueryClient::prefetch_query`](crate::QueryClient::prefetch_query).
+///
+/// Await it to wait until the prefetch has finished, or [`cancel`](Self::cancel) it if the data is no longer needed.
+/// Dropping the handle doesn't cancel the prefetch.
+pub struct PrefetchHandle {
+    cancel: Option<Rc<dyn Fn() -> bool>>,
+    done: Option<oneshot::Receiver<()>>,
+}
+
+impl PrefetchHandle {
+    #[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+    pub(crate) fn new(cancel: Rc<dyn Fn() -> bool>, done: oneshot::Receiver<()>) -> Self {
+        Self {
+            cancel: Some(cancel),
+            done: Some(done),
+        }
+    }
+
+    // Nothing is prefetched on the server.
+    #[cfg_attr(any(feature = "csr", feature = "hydrate"), allow(dead_code))]
+    pub(crate) fn finished() -> Self {
+        Self {
+            cancel: None,
+            done: None,
+        }
+    }
+
+    /// Cancels the prefetch, and returns whether it was cancelled.
+    ///
+    /// A prefetch that has finished, or whose query has gained observers, is not cancelled, as its data is then needed.
+    pub fn cancel(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|cancel| cancel())
+    }
+}
+
+impl Future for PrefetchHandle {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        match self.done.as_mut() {
+            // Resolves whether the prefetch finished or was cancelled.
+            Some(done) => done.poll_unpin(cx).map(|_| ()),
+            None => Poll::Ready(()),
+        }
+    }
+}
+
+impl std::fmt::Debug for PrefetchHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrefetchHandle").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn resolves_when_done() {
+        let (tx, rx) = oneshot::channel();
+        let cancelled = Rc::new(Cell::new(false));
+        let mut handle = PrefetchHandle::new(
+            {
+                let cancelled = cancelled.clone();
+                Rc::new(move || !cancelled.replace(true))
+            },
+            rx,
+        );
+
+        assert_eq!(None, (&mut handle).now_or_never());
+        assert!(handle.cancel());
+        assert!(!handle.cancel());
+
+        tx.send(()).unwrap();
+        assert_eq!(Some(()), handle.now_or_never());
+
+        let finished = PrefetchHandle::finished();
+        assert!(!finished.cancel());
+        assert_eq!(Some(()), finished.now_or_never());
+    }
+}
@@ -0,0 +1,67 @@
+use leptos::*;
+
+use crate::{use_query_client, QueryKey, QueryScope, QueryValue};
+
+/// Prefetches a query when rendered, to warm up data from a template without writing effects.
+///
+/// Nothing is fetched if the query already has fresh data. Prefetches again when the key changes.
+/// Only prefetches on the client, as queries used during SSR are already loaded with the page.
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// #[component]
+/// fn UserLink(id: u32) -> impl IntoView {
+///     view! {
+///         <a href=format!("/users/{id}")>"User"</a>
+///         // Warm up the user page once the link is scrolled into view.
+///         <PrefetchQuery scope=user_query() key=id when_visible=true/>
+///     }
+/// }
+///
+/// fn user_query() -> QueryScope<u32, String> {
+///     create_query(|id| async move { todo!() }, QueryOptions::default())
+/// }
+/// ```
+#[component]
+pub fn PrefetchQuery<K, V>(
+    /// The query to prefetch.
+    scope: QueryScope<K, V>,
+    /// The key to prefetch.
+    #[prop(into)]
+    key: MaybeSignal<K>,
+    /// Waits until the component is scrolled into view, by rendering an empty marker element.
+    #[prop(optional)]
+    when_visible: bool,
+) -> impl IntoView
+where
+    K: QueryKey + 'static,
+    V: QueryValue + 'static,
+{
+    let client = use_query_client();
+    let visible = RwSignal::new(!when_visible);
+    let marker = NodeRef::<html::Span>::new();
+
+    #[cfg(any(feature = "csr", feature = "hydrate"))]
+    if when_visible {
+        crate::visibility::on_visible(marker.into(), move || visible.set(true));
+    }
+    // The marker is only observed on the client.
+    #[cfg(not(any(feature = "csr", feature = "hydrate")))]
+    let _ = marker;
+
+    // Effects only run on the client.
+    create_effect(move |_| {
+        if !visible.get() {
+            return;
+        }
+        let key = scope.normalize_key(key.get());
+        if !untrack(|| client.has_fresh_data::<K, V>(&key)) {
+            scope.prefetch_query(key);
+        }
+    });
+
+    when_visible.then(|| view! { <span node_ref=marker aria-hidden="true"></span> })
+}
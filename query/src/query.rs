@@ -1,37 +1,80 @@
 use std::{
     cell::{Cell, RefCell},
-    collections::HashMap,
     future::Future,
     rc::Rc,
     time::Duration,
 };
 
 use futures_channel::oneshot;
-use leptos::*;
+use indexmap::{map::Entry, IndexMap};
+use leptos::{
+    leptos_dom::helpers::{IntervalHandle, TimeoutHandle},
+    *,
+};
 
 use crate::{
-    garbage_collector::GarbageCollector,
-    query_cache::CacheNotification,
+    garbage_collector::{GarbageCollector, GcScheduler},
+    query_cache::{CacheNotification, QueryId},
     query_is_suppressed,
     query_observer::{ObserverKey, QueryObserver},
+    query_options::{merge_max_interval, merge_max_time, merge_min_time},
+    query_persister::PersistQueryData,
+    query_stats::Mount,
+    tab_lease::RefetchLease,
     use_query_client,
-    util::time_until_stale,
-    QueryData, QueryState,
+    util::{run_or_defer, time_until_stale, try_borrow, try_borrow_mut},
+    Instant, InvalidateMode, QueryData, QueryState, QueryStateKind, QueryStats,
 };
 
 #[derive(Clone)]
 pub struct Query<K, V> {
+    // Assigned when the query is inserted into the cache, which may be deferred.
+    id: Rc<Cell<QueryId>>,
     key: K,
 
-    // Cancellation
+    // Cancellation. Each execution is numbered, so that a cancelled execution doesn't clobber the one that replaced it.
     current_request: Rc<Cell<Option<oneshot::Sender<()>>>>,
+    execution: Rc<Cell<u32>>,
 
     // State
     state: Rc<RefCell<QueryState<V>>>,
+    // The latest state written while the state was borrowed, e.g. by `QueryClient::with_query_data`. Applied once the borrow is released.
+    lent_write: Rc<RefCell<Option<QueryState<V>>>>,
+    // Why the latest fetched value was rejected by a validator. Cleared once a value is accepted.
+    validation_error: RwSignal<Option<String>>,
+
+    // Staleness. The stale time is the minimum across current observers, and is kept once the last observer is removed.
+    stale_time: Rc<Cell<Option<Duration>>>,
+    stale: RwSignal<bool>,
+    stale_timer: Rc<Cell<Option<TimeoutHandle>>>,
+
+    // A single refetch interval, at the minimum interval across observers.
+    refetch: Rc<Cell<Option<(Duration, IntervalHandle)>>>,
+    // A single refetch hinted by fetched data, which replaces the interval until it runs.
+    hinted_refetch: Rc<Cell<Option<TimeoutHandle>>>,
+    // Shared by all queries of the client. Without it, interval refetches are left to another tab.
+    lease: RefetchLease,
+
+    // Rate limiting. When the last fetch started, and the single refetch delayed by the min fetch interval.
+    last_fetch: Rc<Cell<Option<Instant>>>,
+    delayed_fetch: Rc<Cell<Option<TimeoutHandle>>>,
+    // Pending refetch of a debounced invalidation.
+    debounced_fetch: Rc<Cell<Option<TimeoutHandle>>>,
 
     // Synchronization
-    observers: Rc<RefCell<HashMap<ObserverKey, QueryObserver<K, V>>>>,
-    garbage_collector: Rc<RefCell<Option<GarbageCollector<K, V>>>>,
+    // In subscription order, so that observers are notified deterministically.
+    observers: Rc<RefCell<IndexMap<ObserverKey, QueryObserver<K, V>>>>,
+    garbage_collector: GarbageCollector,
+    // The stale and gc times last sent to cache observers, so that mounts with the same options aren't reported.
+    #[allow(clippy::type_complexity)]
+    notified_times: Rc<Cell<Option<(Option<Duration>, Option<Duration>)>>>,
+
+    // Shared by all queries with the same <K, V> types.
+    stats: RwSignal<QueryStats>,
+
+    // Ephemeral UI state, created on first use.
+    #[cfg(feature = "ui_state")]
+    ui_state: Rc<std::cell::OnceCell<RwSignal<serde_json::Value>>>,
 }
 
 impl<K: PartialEq, V> PartialEq for Query<K, V> {
@@ -49,9 +92,18 @@ where
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Query")
+            .field("id", &self.id.get())
             .field("key", &self.key)
             .field("state", &self.state)
-            .field("observers", &self.observers.borrow().len())
+            .field("stale_time", &self.stale_time.get())
+            .field(
+                "refetch_interval",
+                &self.refetch.get().map(|(duration, _)| duration),
+            )
+            .field(
+                "observers",
+                &self.observers.try_borrow().map(|o| o.len()).ok(),
+            )
             .field("gc", &self.garbage_collector)
             .finish()
     }
@@ -62,32 +114,69 @@ where
     K: crate::QueryKey + 'static,
     V: crate::QueryValue + 'static,
 {
-    pub fn new(key: K) -> Self {
-        let query = Query {
-            key: key.clone(),
+    pub fn new(
+        id: QueryId,
+        key: K,
+        scheduler: GcScheduler,
+        lease: RefetchLease,
+        stats: RwSignal<QueryStats>,
+    ) -> Self {
+        let id = Rc::new(Cell::new(id));
+        Query {
+            id: id.clone(),
+            key,
             current_request: Rc::new(Cell::new(None)),
-            observers: Rc::new(RefCell::new(HashMap::new())),
+            execution: Rc::new(Cell::new(0)),
+            observers: Rc::new(RefCell::new(IndexMap::new())),
             state: Rc::new(RefCell::new(QueryState::Created)),
-            garbage_collector: Rc::new(RefCell::new(None)),
-        };
-
-        let gc = GarbageCollector::new(query.clone());
-
-        *query.garbage_collector.borrow_mut() = Some(gc);
-
-        query
+            lent_write: Rc::new(RefCell::new(None)),
+            validation_error: RwSignal::new(None),
+            stale_time: Rc::new(Cell::new(None)),
+            stale: RwSignal::new(false),
+            stale_timer: Rc::new(Cell::new(None)),
+            refetch: Rc::new(Cell::new(None)),
+            hinted_refetch: Rc::new(Cell::new(None)),
+            lease,
+            last_fetch: Rc::new(Cell::new(None)),
+            delayed_fetch: Rc::new(Cell::new(None)),
+            debounced_fetch: Rc::new(Cell::new(None)),
+            garbage_collector: GarbageCollector::new(id, scheduler),
+            notified_times: Rc::new(Cell::new(None)),
+            stats,
+            #[cfg(feature = "ui_state")]
+            ui_state: Rc::new(std::cell::OnceCell::new()),
+        }
     }
 
     pub fn set_state(&self, state: QueryState<V>) {
+        if self.is_lent() {
+            self.lent_write.replace(Some(state));
+            return;
+        }
+        let from = self.with_state(QueryState::kind);
+        self.set_state_from(from, state)
+    }
+
+    // The current state may have been taken out of the cell, so the previous kind is passed in.
+    fn set_state_from(&self, from: QueryStateKind, state: QueryState<V>) {
+        self.check_transition(from, state.kind());
+
         // Notify observers.
-        let observers = self.observers.try_borrow().expect("set state borrow");
-        for observer in observers.values() {
+        for observer in self.get_observers() {
             observer.notify(state.clone())
         }
 
         let invalid = matches!(state, QueryState::Invalid(_));
 
-        *self.state.borrow_mut() = state;
+        match try_borrow_mut(&self.state, "set_state") {
+            Ok(mut current) => *current = state,
+            Err(_) => {
+                // An observer is reading the state, so the write is applied once it is released.
+                self.lent_write.replace(Some(state));
+                return;
+            }
+        }
+        self.update_stale();
 
         // Notify cache. This has to be at the end due to sending the entire query in the notif.
         use_query_client()
@@ -95,14 +184,125 @@ where
             .notify(CacheNotification::UpdatedState(self.clone()));
 
         if invalid {
-            self.execute();
+            self.refetch_invalidated();
+        }
+    }
+
+    // Refetches an invalidated query according to the most eager invalidate mode across observers.
+    fn refetch_invalidated(&self) {
+        let mode = InvalidateMode::merge(
+            self.get_observers()
+                .iter()
+                .map(|o| o.get_options().invalidate_mode),
+        );
+        match mode {
+            InvalidateMode::Immediate => self.execute(),
+            InvalidateMode::Debounced(delay) => self.debounce_execute(delay),
+            // Refetched by `ensure_execute` or `revalidate_if_stale`.
+            InvalidateMode::OnNextAccess => {}
+        }
+    }
+
+    // Restarts the delay on every invalidation, so that a burst of invalidations refetches once.
+    fn debounce_execute(&self, delay: Duration) {
+        if let Some(handle) = self.debounced_fetch.take() {
+            handle.clear();
+        }
+
+        #[cfg(any(feature = "csr", feature = "hydrate"))]
+        {
+            let query = self.clone();
+            let handle = set_timeout_with_handle(
+                move || {
+                    query.debounced_fetch.set(None);
+                    // It may have been refetched in the meantime, e.g. by a new observer.
+                    if query.with_state(|state| matches!(state, QueryState::Invalid(_))) {
+                        query.execute();
+                    }
+                },
+                delay,
+            )
+            .ok();
+            self.debounced_fetch.set(handle);
+        }
+        #[cfg(not(any(feature = "csr", feature = "hydrate")))]
+        let _ = delay;
+    }
+
+    /// Applies data restored from the persister, which may arrive after the query was fetched or updated.
+    ///
+    /// - A query without data is loaded with the restored data. If its first fetch is in flight, it is fetching with the restored data instead.
+    /// - A query with data only takes the restored data if it is newer, keeping its state. Newer data is never downgraded.
+    ///
+    /// Returns whether the restored data was applied.
+    pub fn restore(&self, restored: QueryData<V>) -> bool {
+        let state = self.with_state(|state| match state {
+            QueryState::Created | QueryState::Rejected(_) => Some(QueryState::Loaded(restored)),
+            QueryState::Loading => Some(QueryState::Fetching(restored)),
+            QueryState::Fetching(data) if restored.updated_at > data.updated_at => {
+                Some(QueryState::Fetching(restored))
+            }
+            QueryState::Loaded(data) if restored.updated_at > data.updated_at => {
+                Some(QueryState::Loaded(restored))
+            }
+            QueryState::Invalid(data) if restored.updated_at > data.updated_at => {
+                Some(QueryState::Invalid(restored))
+            }
+            QueryState::Fetching(_) | QueryState::Loaded(_) | QueryState::Invalid(_) => None,
+        });
+
+        match state {
+            Some(state) => {
+                self.set_state(state);
+                use_query_client()
+                    .cache
+                    .notify(CacheNotification::Restored(self.clone()));
+                true
+            }
+            None => false,
         }
     }
 
     pub fn update_state(&self, update_fn: impl FnOnce(&mut QueryState<V>)) {
+        if self.is_lent() {
+            let mut state = self
+                .lent_write
+                .take()
+                .unwrap_or_else(|| self.state.borrow().clone());
+            update_fn(&mut state);
+            self.lent_write.replace(Some(state));
+            return;
+        }
         let mut state = self.state.take();
+        let from = state.kind();
         update_fn(&mut state);
-        self.set_state(state);
+        self.set_state_from(from, state);
+    }
+
+    // See `QueryStateKind::can_transition_to` for the allowed transitions.
+    fn check_transition(&self, from: QueryStateKind, to: QueryStateKind) {
+        let allowed = from.can_transition_to(to);
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "state_assertions")] {
+                use_query_client()
+                    .cache
+                    .record_transition(crate::StateTransition {
+                        key: (&self.key).into(),
+                        from,
+                        to,
+                        allowed,
+                        at: Instant::now(),
+                    });
+                debug_assert!(allowed, "Invalid state transition for query {:?}: {from:?} -> {to:?}", self.key);
+            } else if #[cfg(debug_assertions)] {
+                if !allowed {
+                    logging::debug_warn!("Invalid state transition for query {:?}: {from:?} -> {to:?}", self.key);
+                }
+            } else {
+                let _ = allowed;
+            }
+        }
     }
 
     /// Be careful with this function. Used to avoid cloning.
@@ -113,11 +313,29 @@ where
         &self,
         update_fn: impl FnOnce(QueryState<V>) -> Result<QueryState<V>, QueryState<V>>,
     ) -> bool {
+        if self.is_lent() {
+            let written = self.lent_write.take();
+            let was_written = written.is_some();
+            let current = written.unwrap_or_else(|| self.state.borrow().clone());
+            return match update_fn(current) {
+                Ok(new_state) => {
+                    self.lent_write.replace(Some(new_state));
+                    true
+                }
+                Err(old_state) => {
+                    if was_written {
+                        self.lent_write.replace(Some(old_state));
+                    }
+                    false
+                }
+            };
+        }
         let current_state = self.state.take();
+        let from = current_state.kind();
 
         match update_fn(current_state) {
             Ok(new_state) => {
-                self.set_state(new_state);
+                self.set_state_from(from, new_state);
                 true
             }
             Err(old_state) => {
@@ -142,68 +360,149 @@ where
     }
 
     pub fn subscribe(&self, observer: &QueryObserver<K, V>) {
-        let observer_id = observer.get_id();
-        let mut observers = self
-            .observers
-            .try_borrow_mut()
-            .expect("subscribe borrow_mut");
+        let query = self.clone();
+        let observer = observer.clone();
+        run_or_defer(move || {
+            // Check if the observer is already subscribed to avoid duplicate subscriptions
+            let inserted =
+                match try_borrow_mut(&query.observers, "subscribe")?.entry(observer.get_id()) {
+                    Entry::Vacant(e) => {
+                        e.insert(observer.clone());
+                        true
+                    }
+                    Entry::Occupied(_) => false,
+                };
 
-        // Check if the observer is already subscribed to avoid duplicate subscriptions
-        if let std::collections::hash_map::Entry::Vacant(e) = observers.entry(observer_id) {
-            e.insert(observer.clone());
-            self.disable_gc();
-            self.update_gc_time(observer.get_options().gc_time);
+            if inserted {
+                if observer.has_fetcher() {
+                    let mount = query.mount_kind();
+                    query.stats.update(|stats| stats.record(mount));
+                }
+                if let Some(group) = &observer.get_options().group {
+                    use_query_client().cache.join_group(group, query.get_id());
+                }
+                query.disable_gc();
+                query.update_gc_time(observer.get_options().gc_time);
+                query.update_stale_time();
+                query.update_refetch_interval();
 
-            use_query_client()
-                .cache
-                .notify::<K, V>(CacheNotification::NewObserver(
-                    crate::query_cache::NewObserver {
-                        key: self.key.clone(),
-                        options: observer.get_options().clone(),
-                    },
-                ));
-        }
+                use_query_client()
+                    .cache
+                    .notify::<K, V>(CacheNotification::NewObserver(
+                        crate::query_cache::NewObserver {
+                            key: query.key.clone(),
+                            observer: observer.get_id(),
+                            options: observer.get_options().clone(),
+                            location: observer.get_location(),
+                        },
+                    ));
+                query.notify_if_times_changed();
+            }
+            Ok(())
+        })
+    }
+
+    // How a new observer is served, before it triggers any fetch.
+    fn mount_kind(&self) -> Mount {
+        self.with_state(|state| match state {
+            QueryState::Loading | QueryState::Fetching(_) => Mount::Deduped,
+            QueryState::Loaded(_) if !self.is_stale() => Mount::CacheHit,
+            QueryState::Created
+            | QueryState::Rejected(_)
+            | QueryState::Loaded(_)
+            | QueryState::Invalid(_) => Mount::Fetch,
+        })
+    }
+
+    /// Mount statistics for all queries with the same <K, V> types.
+    pub fn stats_signal(&self) -> Signal<QueryStats> {
+        self.stats.into()
     }
 
     pub fn unsubscribe(&self, observer: &QueryObserver<K, V>) {
-        let mut observers = self
-            .observers
-            .try_borrow_mut()
-            .expect("unsubscribe borrow_mut");
-        if observers.remove(&observer.get_id()).is_some() {
-            use_query_client()
-                .cache
-                .notify::<K, V>(CacheNotification::ObserverRemoved(self.key.clone()))
-        }
+        let query = self.clone();
+        let observer_id = observer.get_id();
+        run_or_defer(move || {
+            let (removed, is_empty) = {
+                let mut observers = try_borrow_mut(&query.observers, "unsubscribe")?;
+                (
+                    observers.shift_remove(&observer_id).is_some(),
+                    observers.is_empty(),
+                )
+            };
 
-        if observers.is_empty() {
-            drop(observers);
-            self.enable_gc();
+            if removed {
+                use_query_client()
+                    .cache
+                    .notify::<K, V>(CacheNotification::ObserverRemoved(
+                        query.key.clone(),
+                        observer_id,
+                    ));
+                query.update_refetch_interval();
+            }
+
+            if is_empty {
+                query.enable_gc();
+            } else if removed {
+                query.update_stale_time();
+                query.notify_if_times_changed();
+            }
+            Ok(())
+        })
+    }
+
+    pub fn has_observers(&self) -> bool {
+        try_borrow(&self.observers, "has_observers").map_or(true, |o| !o.is_empty())
+    }
+
+    // Observers are copied out, so that they can be notified without holding the borrow.
+    pub fn get_observers(&self) -> Vec<QueryObserver<K, V>> {
+        match try_borrow(&self.observers, "get_observers") {
+            Ok(observers) => observers.values().cloned().collect(),
+            Err(e) => {
+                logging::debug_warn!("{e}");
+                Vec::new()
+            }
         }
     }
 
     pub fn update_gc_time(&self, gc_time: Option<Duration>) {
-        self.garbage_collector
-            .borrow()
-            .as_ref()
-            .expect("update_gc_time borrow")
-            .update_gc_time(gc_time);
+        self.garbage_collector.update_gc_time(gc_time);
     }
 
     pub fn enable_gc(&self) {
-        self.garbage_collector
-            .borrow()
-            .as_ref()
-            .expect("enable_gc borrow")
-            .enable_gc();
+        self.garbage_collector.enable_gc(self.get_updated_at());
     }
 
     pub fn disable_gc(&self) {
-        self.garbage_collector
-            .borrow()
-            .as_ref()
-            .expect("disable_gc borrow")
-            .disable_gc();
+        self.garbage_collector.disable_gc();
+    }
+
+    pub fn retain_until(&self, until: crate::Instant) {
+        self.garbage_collector.retain_until(until);
+    }
+
+    pub fn postpone_gc(&self, until: crate::Instant) {
+        self.garbage_collector.postpone(until);
+    }
+
+    // Created under the current owner on first use, which should be the cache owner.
+    #[cfg(feature = "ui_state")]
+    pub fn ui_state(&self) -> RwSignal<serde_json::Value> {
+        *self
+            .ui_state
+            .get_or_init(|| RwSignal::new(serde_json::Value::Null))
+    }
+
+    pub fn hold(&self) {
+        self.garbage_collector.hold();
+    }
+
+    // Collection resumes once the last hold is released, unless the query is observed.
+    pub fn release(&self) {
+        if self.garbage_collector.release() && !self.has_observers() {
+            self.enable_gc();
+        }
     }
 
     pub fn get_state(&self) -> QueryState<V> {
@@ -212,40 +511,130 @@ where
 
     // Useful to avoid clones.
     pub fn with_state<T>(&self, func: impl FnOnce(&QueryState<V>) -> T) -> T {
-        let state = self.state.borrow();
-        func(&state)
+        let result = func(&self.state.borrow());
+        self.apply_lent_write();
+        result
+    }
+
+    // If the state is borrowed, so that writes have to wait until it is released.
+    fn is_lent(&self) -> bool {
+        self.state.try_borrow_mut().is_err()
     }
 
-    /**
-     * Execution and Cancellation.
-     */
+    fn apply_lent_write(&self) {
+        if self.lent_write.borrow().is_none() || self.is_lent() {
+            return;
+        }
+        if let Some(state) = self.lent_write.take() {
+            self.set_state(state);
+        }
+    }
+
+    // Execution and Cancellation.
 
     pub fn execute(&self) {
-        let observers = self.observers.try_borrow().expect("execute borrow");
-        let fetcher = observers.values().find_map(|f| f.get_fetcher());
+        let query = self.clone();
+        run_or_defer(move || {
+            if use_query_client().cache.is_fetch_paused(query.get_id()) {
+                return Ok(());
+            }
+            let (fetcher, priority) = {
+                let observers = try_borrow(&query.observers, "execute")?;
+                let fetcher = observers.values().find_map(|f| f.get_fetcher());
+                // The highest priority across observers applies.
+                let priority = observers
+                    .values()
+                    .map(|o| o.get_options().priority)
+                    .max()
+                    .unwrap_or_default();
+                (fetcher, priority)
+            };
 
-        if let Some(fetcher) = fetcher {
-            if !query_is_suppressed() {
-                spawn_local(execute_query(self.clone(), move |k| fetcher(k)));
+            if let Some(fetcher) = fetcher {
+                if let Some(delay) = query.fetch_delay() {
+                    query.delay_execute(delay);
+                } else if !query_is_suppressed() {
+                    let permit = use_query_client().cache.fetch_scheduler().acquire(priority);
+                    let query = query.clone();
+                    spawn_local(async move {
+                        let _permit = permit.await;
+                        execute_query(query, move |k| fetcher(k)).await
+                    });
+                }
             }
+            Ok(())
+        })
+    }
+
+    /// Time left until the min fetch interval since the last fetch has passed.
+    pub fn fetch_delay(&self) -> Option<Duration> {
+        let min_fetch_interval = merge_max_interval(
+            self.get_observers()
+                .iter()
+                .map(|o| o.get_options().min_fetch_interval),
+        )?;
+        let delay = time_until_stale(self.last_fetch.get()?, min_fetch_interval);
+        (!delay.is_zero()).then_some(delay)
+    }
+
+    // Coalesces refetches within the min fetch interval into a single delayed one.
+    fn delay_execute(&self, delay: Duration) {
+        let delayed = self.delayed_fetch.take();
+        if delayed.is_some() {
+            self.delayed_fetch.set(delayed);
+            return;
         }
+
+        #[cfg(any(feature = "csr", feature = "hydrate"))]
+        {
+            let query = self.clone();
+            let handle = set_timeout_with_handle(
+                move || {
+                    query.delayed_fetch.set(None);
+                    query.execute();
+                },
+                delay,
+            )
+            .ok();
+            self.delayed_fetch.set(handle);
+        }
+        #[cfg(not(any(feature = "csr", feature = "hydrate")))]
+        let _ = delay;
     }
 
     // Only scenario where two requests can exist at the same time is the first is cancelled.
-    pub fn new_execution(&self) -> Option<oneshot::Receiver<()>> {
+    pub fn new_execution(&self) -> Option<(u32, oneshot::Receiver<()>)> {
         let current_request = self.current_request.take();
         if current_request.is_none() {
             let (sender, receiver) = oneshot::channel();
             self.current_request.set(Some(sender));
-            Some(receiver)
+            self.last_fetch.set(Some(Instant::now()));
+            let execution = self.execution.get().wrapping_add(1);
+            self.execution.set(execution);
+            Some((execution, receiver))
         } else {
             self.current_request.set(current_request);
             None
         }
     }
 
-    pub fn finalize_execution(&self) {
-        self.current_request.set(None);
+    pub fn is_current_execution(&self, execution: u32) -> bool {
+        self.execution.get() == execution
+    }
+
+    pub fn finalize_execution(&self, execution: u32) {
+        if self.is_current_execution(execution) {
+            self.current_request.set(None);
+            self.realign_refetch_interval();
+        }
+    }
+
+    /// If an execution is in flight.
+    pub fn is_executing(&self) -> bool {
+        let current_request = self.current_request.take();
+        let executing = current_request.is_some();
+        self.current_request.set(current_request);
+        executing
     }
 
     pub fn cancel(&self) -> bool {
@@ -261,27 +650,55 @@ where
     }
 
     pub fn needs_execute(&self) -> bool {
-        self.with_state(|s| matches!(s, QueryState::Created))
+        self.with_state(|s| matches!(s, QueryState::Created | QueryState::Rejected(_)))
             || self.with_state(|s| matches!(s, QueryState::Invalid(_)))
             || self.is_stale()
     }
 
+    /// Refetches if the query is stale or invalid, and has an active observer.
+    pub fn revalidate_if_stale(&self) -> bool {
+        let observed = match try_borrow(&self.observers, "revalidate_if_stale") {
+            Ok(observers) => !observers.is_empty(),
+            Err(_) => false,
+        };
+        let invalid = self.with_state(|s| matches!(s, QueryState::Invalid(_)));
+        let revalidate = observed && (invalid || self.is_stale());
+        if revalidate {
+            self.execute();
+        }
+        revalidate
+    }
+
+    /// If any observer is visible. Interval refetches are skipped otherwise.
+    pub fn has_visible_observer(&self) -> bool {
+        self.get_observers().iter().any(|o| o.is_visible())
+    }
+
     pub fn ensure_execute(&self) {
         if self.needs_execute() {
             self.execute();
         }
     }
 
-    pub fn is_stale(&self) -> bool {
-        let stale_time = self
-            .observers
-            .borrow()
-            .iter()
-            .flat_map(|(_, o)| o.get_options().stale_time)
-            .min();
-        let updated_at = self.with_state(|s| s.updated_at());
+    // The maximum window across observers. An observer without a window allows data of any age.
+    fn revalidate_window(&self) -> Option<Duration> {
+        merge_max_time(
+            self.get_observers()
+                .iter()
+                .map(|o| o.get_options().revalidate_window),
+        )
+    }
 
-        match (updated_at, stale_time) {
+    /// If the data is too old to be shown while refetching.
+    pub fn is_outside_revalidate_window(&self) -> bool {
+        match (self.get_updated_at(), self.revalidate_window()) {
+            (Some(updated_at), Some(window)) => time_until_stale(updated_at, window).is_zero(),
+            _ => false,
+        }
+    }
+
+    pub fn is_stale(&self) -> bool {
+        match (self.get_updated_at(), self.stale_time.get()) {
             (Some(updated_at), Some(stale_time)) => {
                 time_until_stale(updated_at, stale_time).is_zero()
             }
@@ -289,16 +706,246 @@ where
         }
     }
 
+    /// Reactive version of [`Self::is_stale`], which flips once the stale time elapses.
+    /// The signal is disposed with the query.
+    pub fn stale_signal(&self) -> ReadSignal<bool> {
+        self.stale.read_only()
+    }
+
+    pub fn validation_error_signal(&self) -> ReadSignal<Option<String>> {
+        self.validation_error.read_only()
+    }
+
+    /// Restores persisted data, migrating it if it no longer deserializes. See [`Query::restore`].
+    ///
+    /// Returns whether the restored data was applied.
+    pub fn restore_persisted(&self, persisted: PersistQueryData) -> bool {
+        let data = match V::de(persisted.value.as_str()) {
+            Ok(data) => data,
+            Err(e) => match self.migrate(&persisted.value) {
+                Some(data) => data,
+                None => {
+                    logging::debug_warn!("Error deserializing query state: {:?}", e);
+                    return false;
+                }
+            },
+        };
+        self.restore(QueryData {
+            data,
+            updated_at: Instant::from_unix_millis(persisted.updated_at),
+        })
+    }
+
+    /// Converts serialized data that no longer deserializes, with the migration of the earliest observer that provides one.
+    pub fn migrate(&self, serialized: &str) -> Option<V> {
+        let migration = self
+            .get_observers()
+            .iter()
+            .find_map(|o| o.get_options().migration.clone())?;
+        migration.migrate(serialized)
+    }
+
+    // Checks a fetched value against the validators of every observer, and records the outcome.
+    fn accept_value(&self, value: &V) -> bool {
+        let error = self
+            .get_observers()
+            .iter()
+            .filter_map(|o| o.get_options().validator.clone())
+            .find_map(|validator| validator.check(value).err());
+        let accepted = error.is_none();
+        if let Some(ref error) = error {
+            logging::debug_warn!("Query {:?} rejected fetched value: {error}", self.key);
+        }
+        self.validation_error.try_set(error);
+        accepted
+    }
+
+    fn update_stale_time(&self) {
+        let stale_time = merge_min_time(
+            self.get_observers()
+                .iter()
+                .map(|o| o.get_options().stale_time),
+        );
+        self.stale_time.set(stale_time);
+        self.update_stale();
+    }
+
+    // Notifies cache observers once the merged stale or gc time changes.
+    fn notify_if_times_changed(&self) {
+        let times = (self.stale_time.get(), self.garbage_collector.gc_time());
+        if self.notified_times.replace(Some(times)) != Some(times) {
+            use_query_client()
+                .cache
+                .notify::<K, V>(CacheNotification::TimesChanged(
+                    self.key.clone(),
+                    times.0,
+                    times.1,
+                ));
+        }
+    }
+
+    // Syncs the stale signal, and schedules the transition to stale.
+    fn update_stale(&self) {
+        if let Some(handle) = self.stale_timer.take() {
+            handle.clear();
+        }
+
+        let is_stale = self.is_stale();
+        if self.stale.try_get_untracked() != Some(is_stale) {
+            self.stale.try_set(is_stale);
+        }
+
+        #[cfg(any(feature = "csr", feature = "hydrate"))]
+        if let (false, Some(updated_at), Some(stale_time)) =
+            (is_stale, self.get_updated_at(), self.stale_time.get())
+        {
+            let stale = self.stale;
+            let handle = set_timeout_with_handle(
+                move || {
+                    stale.try_set(true);
+                },
+                time_until_stale(updated_at, stale_time),
+            )
+            .ok();
+            self.stale_timer.set(handle);
+        }
+    }
+
+    // Reschedules the refetch interval, if the minimum interval across observers has changed.
+    fn update_refetch_interval(&self) {
+        let interval = merge_min_time(
+            self.get_observers()
+                .iter()
+                .map(|o| o.get_options().refetch_interval),
+        );
+
+        let current = self.refetch.take();
+        if current.map(|(duration, _)| duration) == interval {
+            self.refetch.set(current);
+            return;
+        }
+
+        if let Some((_, handle)) = current {
+            handle.clear();
+        }
+        if let Some(interval) = interval {
+            self.schedule_refetch_interval(interval);
+        }
+    }
+
+    // Restarts the refetch interval from now, if any observer aligns it to fetch completion.
+    fn realign_refetch_interval(&self) {
+        let align = self
+            .get_observers()
+            .iter()
+            .any(|o| o.get_options().align_refetch_to_completion);
+        if !align {
+            return;
+        }
+        if let Some((interval, handle)) = self.refetch.take() {
+            handle.clear();
+            self.schedule_refetch_interval(interval);
+        }
+    }
+
+    fn schedule_refetch_interval(&self, interval: Duration) {
+        #[cfg(any(feature = "csr", feature = "hydrate"))]
+        {
+            let query = self.clone();
+            let refetch = move || {
+                query.refetch_tick();
+            };
+            match set_interval_with_handle(refetch, interval) {
+                Ok(handle) => self.refetch.set(Some((interval, handle))),
+                Err(_) => logging::debug_warn!("Query: Failed to set refetch interval"),
+            }
+        }
+        #[cfg(not(any(feature = "csr", feature = "hydrate")))]
+        let _ = interval;
+    }
+
+    /// The delay until the next refetch hinted by the current data, from the earliest observer with a hint.
+    pub fn hinted_refetch_delay(&self) -> Option<Duration> {
+        let hint = self
+            .get_observers()
+            .iter()
+            .find_map(|o| o.get_options().refetch_hint.clone())?;
+        // The hint runs outside of the state borrow.
+        let data = self.with_state(|state| state.data().cloned())?;
+        hint.delay(&data)
+    }
+
+    // Replaces the refetch interval with a single refetch after the hinted delay, then resumes the interval.
+    fn schedule_hinted_refetch(&self) {
+        let Some(delay) = self.hinted_refetch_delay() else {
+            return;
+        };
+        if let Some(handle) = self.hinted_refetch.take() {
+            handle.clear();
+        }
+        if let Some((_, handle)) = self.refetch.take() {
+            handle.clear();
+        }
+
+        #[cfg(any(feature = "csr", feature = "hydrate"))]
+        {
+            let query = self.clone();
+            let handle = set_timeout_with_handle(
+                move || {
+                    query.hinted_refetch.set(None);
+                    query.refetch_tick();
+                    query.update_refetch_interval();
+                },
+                delay,
+            )
+            .ok();
+            self.hinted_refetch.set(handle);
+        }
+        #[cfg(not(any(feature = "csr", feature = "hydrate")))]
+        let _ = delay;
+    }
+
+    // Ticks are skipped rather than queued while a fetch is in flight, as the fetch would be deduplicated anyway.
+    #[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+    pub fn refetch_tick(&self) -> bool {
+        let refetch =
+            self.has_visible_observer() && !self.is_executing() && self.may_refetch_on_interval();
+        if refetch {
+            self.execute();
+        }
+        refetch
+    }
+
+    // Without the lease, the tab holding it fetches and shares the data.
+    // Unless the data fell more than an interval behind, e.g. because the other tab's queries aren't visible.
+    fn may_refetch_on_interval(&self) -> bool {
+        if self.lease.is_held() {
+            return true;
+        }
+        let refetch = self.refetch.take();
+        let interval = refetch.as_ref().map(|(interval, _)| *interval);
+        self.refetch.set(refetch);
+        match (interval, self.get_updated_at()) {
+            (Some(interval), Some(updated_at)) => Instant::now() - updated_at > interval * 2,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
     pub fn get_updated_at(&self) -> Option<crate::Instant> {
         self.with_state(|s| s.updated_at())
     }
 
-    pub fn get_key(&self) -> &K {
-        &self.key
+    pub fn get_id(&self) -> QueryId {
+        self.id.get()
     }
 
-    pub fn get_gc(&self) -> Option<GarbageCollector<K, V>> {
-        self.garbage_collector.borrow().clone()
+    pub(crate) fn assign_id(&self, id: QueryId) {
+        self.id.set(id);
+    }
+
+    pub fn get_key(&self) -> &K {
+        &self.key
     }
 }
 
@@ -308,13 +955,49 @@ where
     V: crate::QueryValue + 'static,
 {
     pub fn dispose(&self) {
+        self.disable_gc();
+        if let Some(handle) = self.stale_timer.take() {
+            handle.clear();
+        }
+        if let Some((_, handle)) = self.refetch.take() {
+            handle.clear();
+        }
+        if let Some(handle) = self.delayed_fetch.take() {
+            handle.clear();
+        }
+        if let Some(handle) = self.debounced_fetch.take() {
+            handle.clear();
+        }
+        if let Some(handle) = self.hinted_refetch.take() {
+            handle.clear();
+        }
+        self.stale.dispose();
+        self.validation_error.dispose();
+        #[cfg(feature = "ui_state")]
+        if let Some(ui_state) = self.ui_state.get() {
+            ui_state.dispose();
+        }
+
         #[cfg(debug_assertions)]
-        if !self.observers.borrow().is_empty() {
+        if self.observers.try_borrow().is_ok_and(|o| !o.is_empty()) {
             logging::debug_warn!("Query has active observers");
         }
     }
 }
 
+// Runs the fetcher, then any fetch middleware, so that cancellation covers both.
+async fn fetch_value<K, V, Fu>(query: &Query<K, V>, fetcher: &impl Fn(K) -> Fu) -> V
+where
+    K: crate::QueryKey + 'static,
+    V: crate::QueryValue + 'static,
+    Fu: Future<Output = V>,
+{
+    let middlewares = use_query_client().cache.fetch_middlewares().clone();
+    let value = fetcher(query.key.clone()).await;
+    middlewares.after_fetch(&query.key).await;
+    value
+}
+
 pub async fn execute_query<K, V, Fu>(query: Query<K, V>, fetcher: impl Fn(K) -> Fu)
 where
     K: crate::QueryKey + 'static,
@@ -324,53 +1007,84 @@ where
     if !crate::query_is_suppressed() {
         match query.new_execution() {
             None => {}
-            Some(cancellation) => {
+            Some((execution, cancellation)) => {
                 match query.get_state() {
-                    // First load.
-                    QueryState::Created => {
+                    // First load. Loading if a cancelled execution hasn't settled yet.
+                    QueryState::Created | QueryState::Loading | QueryState::Rejected(_) => {
                         query.set_state(QueryState::Loading);
-                        let fetch = std::pin::pin!(fetcher(query.key.clone()));
+                        let fetch = std::pin::pin!(fetch_value(&query, &fetcher));
                         match execute_with_cancellation(fetch, cancellation).await {
-                            Ok(data) => {
+                            Ok(data) if query.accept_value(&data) => {
                                 let data = QueryData::now(data);
                                 query.set_state(QueryState::Loaded(data));
+                                query.schedule_hinted_refetch();
                             }
-                            Err(_) => {
+                            Ok(_) if query.is_current_execution(execution) => {
+                                let error = query
+                                    .validation_error
+                                    .try_get_untracked()
+                                    .flatten()
+                                    .unwrap_or_default();
+                                query.set_state(QueryState::Rejected(error));
+                            }
+                            Err(_) if query.is_current_execution(execution) => {
                                 query.set_state(QueryState::Created);
                             }
+                            // Replaced by a newer execution, which settles the state.
+                            Ok(_) | Err(_) => {}
                         }
                     }
-                    // Subsequent loads.
-                    QueryState::Loaded(data) | QueryState::Invalid(data) => {
-                        query.set_state(QueryState::Fetching(data));
-                        let fetch = std::pin::pin!(fetcher(query.key.clone()));
+                    // Subsequent loads. Fetching if a cancelled execution hasn't settled yet.
+                    QueryState::Loaded(data)
+                    | QueryState::Invalid(data)
+                    | QueryState::Fetching(data) => {
+                        // Data outside of the revalidate window is hidden until the refetch completes.
+                        let expired = if query.is_outside_revalidate_window() {
+                            query.set_state(QueryState::Loading);
+                            Some(data)
+                        } else {
+                            query.set_state(QueryState::Fetching(data));
+                            None
+                        };
+                        let fetch = std::pin::pin!(fetch_value(&query, &fetcher));
                         match execute_with_cancellation(fetch, cancellation).await {
-                            Ok(data) => {
+                            Ok(data) if query.accept_value(&data) => {
                                 let data = QueryData::now(data);
                                 query.set_state(QueryState::Loaded(data));
+                                query.schedule_hinted_refetch();
                             }
-                            Err(_) => {
-                                query.maybe_map_state(|state| {
-                                    if let QueryState::Fetching(data) = state {
+                            Err(_) if !query.is_current_execution(execution) => {}
+                            // Rejected or cancelled, so the previous data is kept.
+                            Ok(_) | Err(_) => {
+                                query.maybe_map_state(|state| match (state, expired) {
+                                    (QueryState::Fetching(data), _)
+                                    | (QueryState::Loading, Some(data)) => {
                                         Ok(QueryState::Loaded(data))
-                                    } else {
-                                        Err(state)
                                     }
+                                    (state, _) => Err(state),
                                 });
                             }
                         }
                     }
-                    QueryState::Loading | QueryState::Fetching(_) => {
-                        logging::debug_warn!("Query is already loading, this is likely a bug.");
-                        debug_assert!(false, "Query is already loading, this is likely a bug.");
-                    }
                 }
-                query.finalize_execution();
+                query.finalize_execution(execution);
             }
         }
     }
 }
 
+/// Cancels any in-flight execution, and executes the query regardless of its staleness.
+#[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+pub async fn execute_query_force<K, V, Fu>(query: Query<K, V>, fetcher: impl Fn(K) -> Fu)
+where
+    K: crate::QueryKey + 'static,
+    V: crate::QueryValue + 'static,
+    Fu: Future<Output = V>,
+{
+    query.cancel();
+    execute_query(query, fetcher).await
+}
+
 #[cfg(any(feature = "hydrate", feature = "csr"))]
 async fn execute_with_cancellation<V, Fu>(
     fut: Fu,
@@ -386,7 +1100,7 @@ where
     match result {
         Either::Left((result, _)) => Ok(result),
         Either::Right((cancelled, _)) => {
-            if let Err(_) = cancelled {
+            if cancelled.is_err() {
                 logging::debug_warn!("Query cancellation was incorrectly dropped.");
             }
 
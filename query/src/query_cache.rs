@@ -1,193 +1,469 @@
 use std::{
     any::{Any, TypeId},
-    cell::RefCell,
-    collections::{hash_map::Entry, HashMap},
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
     rc::Rc,
+    time::Duration,
 };
 
+use indexmap::{map::Entry, IndexMap};
 use leptos::*;
 use slotmap::SlotMap;
 
 use crate::{
-    cache_observer::{CacheEvent, CacheObserver},
+    cache_observer::{CacheEvent, CacheObserver, ObserverRemoved, QueryCacheKey, QueryTypes},
+    fetch_middleware::FetchMiddlewares,
+    fetch_scheduler::FetchScheduler,
+    garbage_collector::{GcDecision, GcScheduler},
     query::Query,
-    query_persister::QueryPersister,
-    QueryKey, QueryOptions, QueryValue,
+    query_group::{QueryGroup, QueryGroupStatus, QueryGroups},
+    query_observer::ObserverKey,
+    query_persister::{PersistQueryData, QueryPersister},
+    tab_lease::RefetchLease,
+    util::{run_or_defer, try_borrow, try_borrow_mut, ReentrantBorrow},
+    ImportQueryError, Instant, InvalidationManifest, QueryKey, QueryOptions, QueryState,
+    QueryStateKind, QueryStats, QueryValue, SerializedQueryExport, TypeSize,
 };
 
 #[derive(Clone)]
 pub struct QueryCache {
     owner: Owner,
+    cache: Rc<RefCell<QueryStore>>,
     #[allow(clippy::type_complexity)]
-    cache: Rc<RefCell<HashMap<(TypeId, TypeId), Box<dyn CacheEntryTrait>>>>,
-    #[allow(clippy::type_complexity)]
-    observers: Rc<RefCell<SlotMap<CacheObserverKey, Box<dyn CacheObserver>>>>,
+    observers: Rc<RefCell<IndexMap<CacheObserverKey, Rc<dyn CacheObserver>>>>,
+    next_observer_key: Rc<Cell<u32>>,
+    // Observers that an `ObserverAdded` event was sent for, so that every `ObserverRemoved` is paired with one.
+    added_observers: Rc<RefCell<HashSet<(QueryCacheKey, ObserverKey)>>>,
     persister: Rc<RefCell<Option<Rc<dyn QueryPersister>>>>,
+    // Resolves to whether restored data should be invalidated. See `ColdStart`.
+    #[cfg(any(feature = "hydrate", feature = "csr"))]
+    cold_start: Rc<RefCell<Option<ColdStartCheck>>>,
+    gc: GcScheduler,
+    fetches: FetchScheduler,
+    middlewares: FetchMiddlewares,
+    lease: RefetchLease,
+    groups: QueryGroups,
     size: RwSignal<usize>,
+    // Per <K, V> sizes, so that inserts/evictions only notify subscribers of the same type.
+    #[allow(clippy::type_complexity)]
+    type_sizes: Rc<RefCell<IndexMap<(TypeId, TypeId), RwSignal<usize>>>>,
+    // Per <K, V> mount statistics, kept when queries are evicted.
+    #[allow(clippy::type_complexity)]
+    stats: Rc<RefCell<IndexMap<(TypeId, TypeId), RwSignal<QueryStats>>>>,
+    // Per <K, V> triggers, notified whenever a query of the type changes state.
+    type_states: Rc<RefCell<IndexMap<(TypeId, TypeId), Trigger>>>,
+    // Per <K, V> hooks, consulted before a query of the type is garbage collected.
+    #[allow(clippy::type_complexity)]
+    gc_hooks: Rc<RefCell<HashMap<(TypeId, TypeId), Rc<dyn Any>>>>,
+    // The most recent state transitions, oldest first.
+    #[cfg(feature = "state_assertions")]
+    transitions: Rc<RefCell<std::collections::VecDeque<crate::StateTransition>>>,
+    // The most recent cache events, oldest first.
+    #[cfg(feature = "debug_report")]
+    events: Rc<RefCell<std::collections::VecDeque<crate::debug_report::EventReport>>>,
 }
 
+#[cfg(feature = "state_assertions")]
+const MAX_TRANSITIONS: usize = 500;
+
+type BeforeGc<K, V> = Rc<dyn Fn(&K, &QueryState<V>) -> GcDecision>;
+
+#[cfg(any(feature = "hydrate", feature = "csr"))]
+pub type ColdStartCheck = futures::future::Shared<futures::future::LocalBoxFuture<'static, bool>>;
+
+// Keys are allocated up front, so that registration can be deferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheObserverKey(u32);
+
 slotmap::new_key_type! {
-    pub struct CacheObserverKey;
+    /// Stable, copyable handle to a query in the cache.
+    pub struct QueryId;
 }
 
-struct CacheEntry<K, V>(HashMap<K, Query<K, V>>);
-
-// Trait to enable cache introspection among distinct cache entry maps.
-trait CacheEntryTrait: CacheSize + CacheInvalidate + CacheClear + CacheUpdateObserver {
-    fn as_any(&self) -> &dyn Any;
-    fn as_any_mut(&mut self) -> &mut dyn Any;
+#[derive(Default)]
+struct QueryStore {
+    queries: SlotMap<QueryId, StoredQuery>,
+    // Secondary index to look up queries by key.
+    // Kept in insertion order, so that cache-wide operations and their events are deterministic.
+    // Touched queries move to the end, so the order is also least to most recently used.
+    index: IndexMap<CacheKey, QueryId>,
 }
 
-impl<K, V> CacheEntryTrait for CacheEntry<K, V>
-where
-    K: crate::QueryKey + 'static,
-    V: crate::QueryValue + 'static,
-{
-    fn as_any(&self) -> &dyn Any {
-        self
+impl QueryStore {
+    // Queries in index order. Slot order differs once slots are reused.
+    fn iter(&self) -> impl Iterator<Item = (QueryId, &StoredQuery)> {
+        self.index.values().map(|id| (*id, &self.queries[*id]))
     }
 
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self
+    fn values(&self) -> impl Iterator<Item = &StoredQuery> {
+        self.iter().map(|(_, stored)| stored)
     }
 }
 
-trait CacheSize {
-    #[allow(dead_code)]
-    fn size(&self) -> usize;
+struct StoredQuery {
+    key: CacheKey,
+    query: Box<dyn AnyQuery>,
 }
 
-impl<K, V> CacheSize for CacheEntry<K, V> {
-    fn size(&self) -> usize {
-        self.0.len()
-    }
+/// Identifies a query across all key/value types.
+/// The type ids allow the same serialized key to be used with different value types.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    types: (TypeId, TypeId),
+    // Recorded for tooling that can only address queries by name.
+    type_names: (&'static str, &'static str),
+    key: QueryCacheKey,
 }
 
-trait CacheInvalidate {
-    fn invalidate(&self);
-}
+impl CacheKey {
+    fn new<K, V>(key: &K) -> Self
+    where
+        K: QueryKey + 'static,
+        V: 'static,
+    {
+        CacheKey {
+            types: (TypeId::of::<K>(), TypeId::of::<V>()),
+            type_names: (std::any::type_name::<K>(), std::any::type_name::<V>()),
+            key: key.into(),
+        }
+    }
 
-impl<K, V> CacheInvalidate for CacheEntry<K, V>
-where
-    K: QueryKey + 'static,
-    V: QueryValue + 'static,
-{
-    fn invalidate(&self) {
-        for (_, query) in self.0.iter() {
-            query.mark_invalid();
+    fn is_type<K: 'static, V: 'static>(&self) -> bool {
+        self.types == (TypeId::of::<K>(), TypeId::of::<V>())
+    }
+
+    fn query_types(&self) -> QueryTypes {
+        QueryTypes {
+            key: self.type_names.0,
+            value: self.type_names.1,
         }
     }
+
+    fn has_type_name(&self, type_name: &str) -> bool {
+        self.type_names.0 == type_name || self.type_names.1 == type_name
+    }
 }
 
-trait CacheClear {
-    fn clear(&mut self, cache: &QueryCache);
+// Type erased query, to enable cache-wide operations without knowing the key/value types.
+trait AnyQuery {
+    fn as_any(&self) -> &dyn Any;
+    fn clone_query(&self) -> Box<dyn AnyQuery>;
+    fn mark_invalid(&self) -> bool;
+    fn cancel(&self) -> bool;
+    fn revalidate_if_stale(&self) -> bool;
+    fn resume(&self) -> bool;
+    fn state_kind(&self) -> QueryStateKind;
+    fn retain_until(&self, until: Instant);
+    fn before_gc(&self, cache: &QueryCache) -> GcDecision;
+    fn postpone_gc(&self, until: Instant);
+    fn restore_persisted(&self, data: PersistQueryData) -> bool;
+    fn observer_keys(&self) -> Vec<ObserverKey>;
+    fn dispose(&self);
+    #[cfg(feature = "debug_report")]
+    fn debug_report(&self, redact: crate::debug_report::Redact)
+        -> crate::debug_report::QueryReport;
+    fn created_event(&self) -> CacheEvent;
 }
 
-impl<K, V> CacheClear for CacheEntry<K, V>
+impl<K, V> AnyQuery for Query<K, V>
 where
     K: QueryKey + 'static,
     V: QueryValue + 'static,
 {
-    fn clear(&mut self, cache: &QueryCache) {
-        for (_, query) in self.0.drain() {
-            query.dispose();
-            cache.notify_query_eviction(query.get_key());
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn clone_query(&self) -> Box<dyn AnyQuery> {
+        Box::new(self.clone())
+    }
+
+    fn mark_invalid(&self) -> bool {
+        Query::mark_invalid(self)
+    }
+
+    fn cancel(&self) -> bool {
+        Query::cancel(self)
+    }
+
+    fn revalidate_if_stale(&self) -> bool {
+        Query::revalidate_if_stale(self)
+    }
+
+    // Fetches an observed query that missed a fetch, or went stale, while paused.
+    fn resume(&self) -> bool {
+        let resume = self.has_observers() && self.needs_execute();
+        if resume {
+            self.execute();
         }
+        resume
+    }
+
+    fn state_kind(&self) -> QueryStateKind {
+        self.with_state(QueryState::kind)
+    }
+
+    fn retain_until(&self, until: Instant) {
+        Query::retain_until(self, until)
+    }
+
+    fn before_gc(&self, cache: &QueryCache) -> GcDecision {
+        match cache.before_gc::<K, V>() {
+            // The state is cloned, as the hook may update the query.
+            Some(hook) => hook(self.get_key(), &self.get_state()),
+            None => GcDecision::Collect,
+        }
+    }
+
+    fn postpone_gc(&self, until: Instant) {
+        Query::postpone_gc(self, until)
+    }
+
+    fn restore_persisted(&self, data: PersistQueryData) -> bool {
+        Query::restore_persisted(self, data)
+    }
+
+    fn observer_keys(&self) -> Vec<ObserverKey> {
+        self.get_observers().iter().map(|o| o.get_id()).collect()
+    }
+
+    fn dispose(&self) {
+        Query::dispose(self)
     }
-}
 
-// Update an observer with all existing cache entries, upon subscription.
-trait CacheUpdateObserver {
-    fn update_observer(&self, observer: &dyn CacheObserver);
+    #[cfg(feature = "debug_report")]
+    fn debug_report(
+        &self,
+        redact: crate::debug_report::Redact,
+    ) -> crate::debug_report::QueryReport {
+        crate::debug_report::query_report(self, redact)
+    }
+
+    fn created_event(&self) -> CacheEvent {
+        CacheEvent::created(self.clone())
+    }
 }
 
-impl<K, V> CacheUpdateObserver for CacheEntry<K, V>
+fn downcast<K, V>(query: &dyn AnyQuery) -> &Query<K, V>
 where
     K: QueryKey + 'static,
     V: QueryValue + 'static,
 {
-    fn update_observer(&self, observer: &dyn CacheObserver) {
-        for (_, query) in self.0.iter() {
-            let event = CacheEvent::created(query.clone());
-            observer.process_cache_event(event);
-        }
-    }
+    query
+        .as_any()
+        .downcast_ref::<Query<K, V>>()
+        .expect(EXPECT_CACHE_ERROR)
 }
 
 impl QueryCache {
     pub fn new(owner: Owner) -> Self {
         Self {
             owner,
-            cache: Rc::new(RefCell::new(HashMap::new())),
-            observers: Rc::new(RefCell::new(SlotMap::with_key())),
+            cache: Rc::new(RefCell::new(QueryStore::default())),
+            observers: Rc::new(RefCell::new(IndexMap::new())),
+            next_observer_key: Rc::new(Cell::new(0)),
+            added_observers: Rc::new(RefCell::new(HashSet::new())),
             size: RwSignal::new(0),
+            type_sizes: Rc::new(RefCell::new(IndexMap::new())),
+            stats: Rc::new(RefCell::new(IndexMap::new())),
+            type_states: Rc::new(RefCell::new(IndexMap::new())),
+            gc_hooks: Rc::new(RefCell::new(HashMap::new())),
+            #[cfg(feature = "state_assertions")]
+            transitions: Rc::new(RefCell::new(Default::default())),
+            #[cfg(feature = "debug_report")]
+            events: Rc::new(RefCell::new(Default::default())),
             persister: Rc::new(RefCell::new(None)),
+            #[cfg(any(feature = "hydrate", feature = "csr"))]
+            cold_start: Rc::new(RefCell::new(None)),
+            gc: GcScheduler::new(owner),
+            fetches: FetchScheduler::default(),
+            middlewares: FetchMiddlewares::default(),
+            lease: RefetchLease::default(),
+            groups: QueryGroups::new(owner),
+        }
+    }
+
+    #[cfg(feature = "state_assertions")]
+    pub fn record_transition(&self, transition: crate::StateTransition) {
+        if let Ok(mut transitions) = try_borrow_mut(&self.transitions, "record_transition") {
+            if transitions.len() == MAX_TRANSITIONS {
+                transitions.pop_front();
+            }
+            transitions.push_back(transition);
+        }
+    }
+
+    #[cfg(feature = "state_assertions")]
+    pub fn transitions(&self) -> Vec<crate::StateTransition> {
+        try_borrow(&self.transitions, "transitions")
+            .map(|t| t.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "debug_report")]
+    pub fn debug_report(
+        &self,
+        redact: crate::debug_report::Redact,
+    ) -> crate::debug_report::DebugReport {
+        let queries = self
+            .read_store("debug_report", |cache| {
+                cache
+                    .values()
+                    .map(|stored| stored.query.clone_query())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        crate::debug_report::DebugReport {
+            generated_at: Instant::now().as_unix_millis(),
+            queries: queries
+                .iter()
+                .map(|query| query.debug_report(redact))
+                .collect(),
+            events: try_borrow(&self.events, "debug_report")
+                .map(|events| events.iter().cloned().collect())
+                .unwrap_or_default(),
         }
     }
 
+    pub fn owner(&self) -> Owner {
+        self.owner
+    }
+
+    pub fn gc_scheduler(&self) -> &GcScheduler {
+        &self.gc
+    }
+
+    pub fn fetch_scheduler(&self) -> &FetchScheduler {
+        &self.fetches
+    }
+
+    pub fn fetch_middlewares(&self) -> &FetchMiddlewares {
+        &self.middlewares
+    }
+
     pub fn get_or_create_query<K, V>(&self, key: K) -> Query<K, V>
     where
         K: QueryKey + 'static,
         V: QueryValue + 'static,
     {
-        let query_cache = self;
+        // Existing queries only need shared access, so lookups succeed while the store is being read elsewhere.
+        if let Ok(cache) = try_borrow(&self.cache, "get_or_create_query") {
+            if let Some(id) = cache.index.get(&CacheKey::new::<K, V>(&key)) {
+                return downcast::<K, V>(cache.queries[*id].query.as_ref()).clone();
+            }
+        }
 
         let mut created = false;
+        // Resolve before inserting, as a newly created signal counts existing entries.
+        let type_size = self.type_size_signal((TypeId::of::<K>(), TypeId::of::<V>()));
+        let stats = self.stats_signal::<K, V>();
 
-        let query = self.use_cache(|cache| {
-            let entry = cache.entry(key.clone());
+        let query = {
+            let mut cache = match try_borrow_mut(&self.cache, "get_or_create_query") {
+                Ok(cache) => cache,
+                Err(_) => {
+                    // The store is being written to, so the query is inserted once it is released.
+                    let query = with_owner(self.owner, || {
+                        Query::new(
+                            QueryId::default(),
+                            key,
+                            self.gc.clone(),
+                            self.lease.clone(),
+                            stats,
+                        )
+                    });
+                    let cache = self.clone();
+                    let pending = query.clone();
+                    run_or_defer(move || cache.try_insert_query(&pending));
+                    return query;
+                }
+            };
+            let QueryStore { queries, index } = &mut *cache;
 
-            let query = match entry {
+            match index.entry(CacheKey::new::<K, V>(&key)) {
                 Entry::Occupied(entry) => {
-                    let entry = entry.into_mut();
-                    entry
+                    downcast::<K, V>(queries[*entry.get()].query.as_ref()).clone()
                 }
                 Entry::Vacant(entry) => {
-                    let query = with_owner(query_cache.owner, || Query::new(key));
-                    query_cache.notify_new_query(query.clone());
+                    let id = queries.insert_with_key(|id| StoredQuery {
+                        key: entry.key().clone(),
+                        query: Box::new(with_owner(self.owner, || {
+                            Query::<K, V>::new(id, key, self.gc.clone(), self.lease.clone(), stats)
+                        })),
+                    });
+                    entry.insert(id);
                     created = true;
-                    entry.insert(query)
+                    downcast::<K, V>(queries[id].query.as_ref()).clone()
                 }
-            };
-            query.clone()
-        });
+            }
+        };
 
-        #[cfg(any(feature = "hydrate", feature = "csr"))]
         if created {
+            self.on_query_created(&query, type_size);
+        }
+
+        query
+    }
+
+    // Inserts a query that was created while the store was being written to.
+    fn try_insert_query<K, V>(&self, query: &Query<K, V>) -> Result<(), ReentrantBorrow>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        let type_size = self.type_size_signal((TypeId::of::<K>(), TypeId::of::<V>()));
+        {
+            let mut cache = try_borrow_mut(&self.cache, "get_or_create_query")?;
+            let QueryStore { queries, index } = &mut *cache;
+
+            match index.entry(CacheKey::new::<K, V>(query.get_key())) {
+                Entry::Occupied(_) => {
+                    // Created again before the store was released, so this handle stays untracked.
+                    logging::debug_warn!("Query {:?} was created twice.", query.get_key());
+                    return Ok(());
+                }
+                Entry::Vacant(entry) => {
+                    let id = queries.insert_with_key(|id| {
+                        query.assign_id(id);
+                        StoredQuery {
+                            key: entry.key().clone(),
+                            query: Box::new(query.clone()),
+                        }
+                    });
+                    entry.insert(id);
+                }
+            }
+        }
+
+        self.on_query_created(query, type_size);
+        Ok(())
+    }
+
+    fn on_query_created<K, V>(&self, query: &Query<K, V>, type_size: RwSignal<usize>)
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.notify_new_query(query.clone());
+
+        #[cfg(any(feature = "hydrate", feature = "csr"))]
+        {
             if let Some(persister) = self.persister.borrow().clone() {
                 let query = query.clone();
+                let cold_start = self.cold_start.borrow().clone();
                 spawn_local({
                     async move {
                         let key = crate::cache_observer::make_cache_key(query.get_key());
                         let result = persister.retrieve(key.as_str()).await;
 
-                        // ensure query is not already loaded.
-                        if query.with_state(|s| matches!(s, crate::QueryState::Loaded(_))) {
-                            return;
-                        }
-
+                        // The query may have been fetched or updated while retrieving, which `restore` accounts for.
                         if let Some(serialized) = result {
-                            match serialized.try_into() {
-                                Ok(data) => {
-                                    // If the query is currently fetching, then we should preserve the fetching state.
-                                    if query.with_state(|s| {
-                                        matches!(
-                                            s,
-                                            crate::QueryState::Loading
-                                                | crate::QueryState::Fetching(_)
-                                        )
-                                    }) {
-                                        query.set_state(crate::QueryState::Fetching(data));
-                                    } else {
-                                        query.set_state(crate::QueryState::Loaded(data));
+                            if query.restore_persisted(serialized) {
+                                if let Some(cold_start) = cold_start {
+                                    if cold_start.await {
+                                        query.mark_invalid();
                                     }
                                 }
-                                Err(e) => {
-                                    logging::debug_warn!(
-                                        "Error deserializing query state: {:?}",
-                                        e
-                                    );
-                                }
                             }
                         }
                     }
@@ -196,11 +472,8 @@ impl QueryCache {
         }
 
         // It's necessary to delay the size update until we are out of the borrow, to avoid borrow errors.
-        if created {
-            self.size.update(|size| *size += 1);
-        }
-
-        query
+        self.size.update(|size| *size += 1);
+        type_size.update(|size| *size += 1);
     }
 
     pub fn get_query<K, V>(&self, key: &K) -> Option<Query<K, V>>
@@ -208,10 +481,51 @@ impl QueryCache {
         K: QueryKey + 'static,
         V: QueryValue + 'static,
     {
-        self.use_cache_option(move |cache| cache.get(key).cloned())
+        self.read_store("get_query", |cache| {
+            let id = cache.index.get(&CacheKey::new::<K, V>(key))?;
+            Some(downcast::<K, V>(cache.queries[*id].query.as_ref()).clone())
+        })
+        .flatten()
+    }
+
+    /// Look up a query by its handle.
+    /// Returns None if the query has been evicted, or if the handle belongs to a query of a different type.
+    pub fn get_query_by_id<K, V>(&self, id: QueryId) -> Option<Query<K, V>>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.read_store("get_query_by_id", |cache| {
+            cache
+                .queries
+                .get(id)
+                .and_then(|stored| stored.query.as_any().downcast_ref::<Query<K, V>>())
+                .cloned()
+        })
+        .flatten()
+    }
+
+    /// Typed view of all queries with a common <K, V> type.
+    pub fn get_queries<K, V>(&self) -> Vec<Query<K, V>>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.read_store("get_queries", |cache| {
+            cache
+                .values()
+                .filter(|stored| stored.key.is_type::<K, V>())
+                .map(|stored| downcast::<K, V>(stored.query.as_ref()).clone())
+                .collect()
+        })
+        .unwrap_or_default()
     }
 
-    pub fn get_query_signal<K, V>(&self, key: impl Fn() -> K + 'static) -> Memo<Query<K, V>>
+    /// No query is created while the key is None.
+    pub fn get_query_signal<K, V>(
+        &self,
+        key: impl Fn() -> Option<K> + 'static,
+    ) -> Memo<Option<Query<K, V>>>
     where
         K: QueryKey + 'static,
         V: QueryValue + 'static,
@@ -220,8 +534,8 @@ impl QueryCache {
 
         // This memo is crucial to avoid crazy amounts of lookups.
         create_memo(move |_| {
-            let key = key();
-            client.get_or_create_query(key)
+            let key = key()?;
+            Some(client.get_or_create_query(key))
         })
     }
 
@@ -232,9 +546,9 @@ impl QueryCache {
                 let cache = self.cache.clone();
                 create_memo(move |_| {
                     let size = size_signal.get();
-                    let cache = RefCell::try_borrow(&cache).expect("size borrow");
-                    let real_size: usize = cache.values().map(|b| b.size()).sum();
-                    assert!(size == real_size, "Cache size mismatch");
+                    if let Ok(cache) = try_borrow(&cache, "size") {
+                        assert!(size == cache.queries.len(), "Cache size mismatch");
+                    }
                     size
                 }).into()
             } else {
@@ -243,171 +557,594 @@ impl QueryCache {
         }
     }
 
-    pub fn evict_query<K, V>(&self, key: &K) -> bool
+    /// Sizes of the cache per <K, V> type, largest first.
+    pub fn size_by_type(&self) -> Signal<Vec<TypeSize>> {
+        let size = self.size;
+        let cache = self.clone();
+        Signal::derive(move || {
+            // Every insert and eviction updates the total size.
+            size.track();
+            let counts = cache
+                .read_store("size_by_type", |cache| {
+                    let mut counts = IndexMap::<_, usize>::new();
+                    for stored in cache.values() {
+                        *counts.entry(stored.key.type_names).or_default() += 1;
+                    }
+                    counts
+                })
+                .unwrap_or_default();
+
+            let mut sizes = counts
+                .into_iter()
+                .map(|((key_type, value_type), count)| TypeSize {
+                    key_type,
+                    value_type,
+                    count,
+                })
+                .collect::<Vec<_>>();
+            sizes.sort_by_key(|size| std::cmp::Reverse(size.count));
+            sizes
+        })
+    }
+
+    /// Size of the cache for a single <K, V> type.
+    pub fn type_size<K, V>(&self) -> Signal<usize>
     where
         K: QueryKey + 'static,
         V: QueryValue + 'static,
     {
-        let result = self.use_cache_option_mut::<K, V, _, _>(move |cache| cache.remove(key));
-
-        if let Some(query) = result {
-            self.notify_query_eviction(query.get_key());
-            // With cache clears, the size may already be zero.
-            self.size.update(|size| {
-                if *size > 0 {
-                    *size -= 1
-                }
-            });
-            query.dispose();
-            true
-        } else {
-            false
-        }
-    }
-
-    pub fn invalidate_all_queries(&self) {
-        for cache in RefCell::try_borrow(&self.cache)
-            .expect("invalidate_all_queries borrow")
-            .values()
-        {
-            cache.invalidate();
-        }
+        self.type_size_signal((TypeId::of::<K>(), TypeId::of::<V>()))
+            .into()
     }
 
-    pub fn clear_all_queries(&self) {
-        let mut caches =
-            RefCell::try_borrow_mut(&self.cache).expect("clear_all_queries borrow mut");
+    fn type_size_signal(&self, types: (TypeId, TypeId)) -> RwSignal<usize> {
+        let new_signal = || {
+            let size = self
+                .read_store("type_size", |cache| {
+                    cache
+                        .values()
+                        .filter(|stored| stored.key.types == types)
+                        .count()
+                })
+                .unwrap_or_default();
+            with_owner(self.owner, || RwSignal::new(size))
+        };
 
-        for cache in caches.values_mut() {
-            cache.clear(self);
-        }
-        // Though persister receives removal events, there may be queries in persister that are not yet in cache.
-        // So we should clear them all.
-        #[cfg(any(feature = "hydrate", feature = "csr"))]
-        if let Some(persister) = self.persister.borrow().clone() {
-            spawn_local(async move {
-                persister.clear().await;
-            });
+        match try_borrow_mut(&self.type_sizes, "type_size") {
+            Ok(mut type_sizes) => *type_sizes.entry(types).or_insert_with(new_signal),
+            Err(e) => {
+                logging::debug_warn!("{e}");
+                new_signal()
+            }
         }
+    }
 
-        // Need to queue microtask to avoid borrow errors.
-        let size = self.size;
-        queue_microtask(move || {
-            size.set(0);
+    /// The serialized keys of all queries in the cache.
+    pub fn keys(&self) -> Vec<QueryCacheKey> {
+        self.read_store("keys", |cache| {
+            cache
+                .values()
+                .map(|stored| stored.key.key.clone())
+                .collect()
         })
+        .unwrap_or_default()
     }
 
-    pub fn use_cache_option<K, V, F, R>(&self, func: F) -> Option<R>
+    /// Exports the state of a query, if it is in the cache.
+    pub fn export_query<K, V>(&self, key: &K) -> Option<SerializedQueryExport>
     where
         K: QueryKey + 'static,
         V: QueryValue + 'static,
-        F: FnOnce(&HashMap<K, Query<K, V>>) -> Option<R>,
-        R: 'static,
     {
-        let cache = RefCell::try_borrow(&self.cache).expect("use_cache_option borrow");
-        let type_key = (TypeId::of::<K>(), TypeId::of::<V>());
-        let cache = cache.get(&type_key)?;
-        let cache = cache
-            .as_any()
-            .downcast_ref::<CacheEntry<K, V>>()
-            .expect(EXPECT_CACHE_ERROR);
-        func(&cache.0)
+        let query = self.get_query::<K, V>(key)?;
+        Some(query.with_state(|state| SerializedQueryExport::new(key, state)))
     }
 
-    pub fn use_cache_option_mut<K, V, F, R>(&self, func: F) -> Option<R>
+    /// Replaces the state of a query with an exported state, creating the query if needed.
+    /// An export without data leaves an existing query unchanged.
+    pub fn import_query<K, V>(
+        &self,
+        key: K,
+        export: SerializedQueryExport,
+    ) -> Result<(), ImportQueryError>
     where
         K: QueryKey + 'static,
         V: QueryValue + 'static,
-        F: FnOnce(&mut HashMap<K, Query<K, V>>) -> Option<R>,
-        R: 'static,
     {
-        let mut cache = RefCell::try_borrow_mut(&self.cache).expect("use_cache_option_mut borrow");
-        let type_key = (TypeId::of::<K>(), TypeId::of::<V>());
-        let cache = cache.get_mut(&type_key)?;
-        let cache = cache
-            .as_any_mut()
-            .downcast_mut::<CacheEntry<K, V>>()
-            .expect(EXPECT_CACHE_ERROR);
-        func(&mut cache.0)
+        let state = export.into_state::<K, V>(&key)?;
+        let query = self.get_or_create_query::<K, V>(key);
+        if !matches!(state, QueryState::Created) {
+            // Run under the cache owner, so that the state update notifies this client.
+            with_owner(self.owner, || query.set_state(state));
+        }
+        Ok(())
     }
 
-    pub fn use_cache<K, V, R>(&self, func: impl FnOnce(&mut HashMap<K, Query<K, V>>) -> R) -> R
+    /// Marks the queries with the given serialized keys as most recently used, by moving them to the end of the cache order.
+    /// If `retain_for` is set, they are also kept from being garbage collected for at least that long. Returns the number of queries touched.
+    pub fn touch_queries(&self, keys: &[QueryCacheKey], retain_for: Option<Duration>) -> usize {
+        let touched = {
+            let Ok(mut cache) = try_borrow_mut(&self.cache, "touch_queries") else {
+                return 0;
+            };
+            let touched = cache
+                .iter()
+                .filter(|(_, stored)| keys.contains(&stored.key.key))
+                .map(|(id, stored)| (id, stored.query.clone_query()))
+                .collect::<Vec<_>>();
+
+            let last = cache.index.len().saturating_sub(1);
+            for (id, _) in &touched {
+                let key = &cache.queries[*id].key;
+                if let Some(from) = cache.index.get_index_of(key) {
+                    cache.index.move_index(from, last);
+                }
+            }
+            touched
+        };
+
+        if let Some(retain_for) = retain_for {
+            // Saturates, so that an overflowing retention keeps the queries indefinitely.
+            let until = Instant(Instant::now().0.saturating_add(retain_for));
+            for (_, query) in &touched {
+                query.retain_until(until);
+            }
+        }
+        touched.len()
+    }
+
+    /// Restores serialized data into the existing queries with the given key, of any type, if it is newer than theirs.
+    /// Returns the number of queries updated.
+    pub fn restore_by_key(&self, key: &QueryCacheKey, data: PersistQueryData) -> usize {
+        let queries = self
+            .read_store("restore_by_key", |cache| {
+                cache
+                    .values()
+                    .filter(|stored| &stored.key.key == key)
+                    .map(|stored| stored.query.clone_query())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        queries
+            .iter()
+            .filter(|query| query.restore_persisted(data.clone()))
+            .count()
+    }
+
+    /// Whether this tab runs interval refetches. See [`CrossTabLease`](crate::CrossTabLease).
+    pub fn refetch_lease(&self) -> &RefetchLease {
+        &self.lease
+    }
+
+    /// Mount statistics for a single <K, V> type.
+    pub fn type_stats<K, V>(&self) -> Signal<QueryStats>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.stats_signal::<K, V>().into()
+    }
+
+    /// Mount statistics for every <K, V> type that has been in the cache.
+    pub fn all_stats(&self) -> Vec<QueryStats> {
+        match try_borrow(&self.stats, "all_stats") {
+            Ok(stats) => stats.values().map(|s| s.get_untracked()).collect(),
+            Err(e) => {
+                logging::debug_warn!("{e}");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Reactive aggregate over all queries with a common <K, V> type.
+    /// Recomputed whenever one of them changes state, or is added or removed.
+    pub fn type_aggregate<K, V, T>(
+        &self,
+        aggregate: impl Fn(&[Query<K, V>]) -> T + 'static,
+    ) -> Signal<T>
     where
         K: QueryKey + 'static,
         V: QueryValue + 'static,
+        T: PartialEq + 'static,
     {
-        let mut cache = RefCell::try_borrow_mut(&self.cache).expect("use_cache borrow");
+        let cache = self.clone();
+        let size = self.type_size::<K, V>();
+        let states = self.type_state_trigger((TypeId::of::<K>(), TypeId::of::<V>()));
+        create_memo(move |_| {
+            size.track();
+            states.track();
+            aggregate(&cache.get_queries::<K, V>())
+        })
+        .into()
+    }
+
+    fn type_state_trigger(&self, types: (TypeId, TypeId)) -> Trigger {
+        let new_trigger = || with_owner(self.owner, create_trigger);
+        match try_borrow_mut(&self.type_states, "type_states") {
+            Ok(mut type_states) => *type_states.entry(types).or_insert_with(new_trigger),
+            Err(e) => {
+                logging::debug_warn!("{e}");
+                new_trigger()
+            }
+        }
+    }
+
+    fn stats_signal<K: 'static, V: 'static>(&self) -> RwSignal<QueryStats> {
+        let new_signal = || with_owner(self.owner, || RwSignal::new(QueryStats::new::<K, V>()));
+        match try_borrow_mut(&self.stats, "stats") {
+            Ok(mut stats) => *stats
+                .entry((TypeId::of::<K>(), TypeId::of::<V>()))
+                .or_insert_with(new_signal),
+            Err(e) => {
+                logging::debug_warn!("{e}");
+                new_signal()
+            }
+        }
+    }
 
-        let type_key = (TypeId::of::<K>(), TypeId::of::<V>());
+    /// Returns false if the query was not in the cache.
+    /// On re-entrant access the eviction is deferred and counted as done, so true is returned.
+    pub fn evict_query(&self, id: QueryId) -> bool {
+        match self.try_evict_query(id) {
+            Ok(evicted) => evicted,
+            Err(_) => {
+                let cache = self.clone();
+                run_or_defer(move || cache.try_evict_query(id).map(|_| ()));
+                true
+            }
+        }
+    }
 
-        let cache: &mut Box<dyn CacheEntryTrait> = match cache.entry(type_key) {
-            Entry::Occupied(o) => o.into_mut(),
-            Entry::Vacant(v) => {
-                let wrapped: CacheEntry<K, V> = CacheEntry(HashMap::new());
-                v.insert(Box::new(wrapped))
+    fn try_evict_query(&self, id: QueryId) -> Result<bool, ReentrantBorrow> {
+        let result = {
+            let mut cache = try_borrow_mut(&self.cache, "evict_query")?;
+            let stored = cache.queries.remove(id);
+            if let Some(ref stored) = stored {
+                cache.index.shift_remove(&stored.key);
             }
+            stored
         };
 
-        let cache: &mut CacheEntry<K, V> = cache
-            .as_any_mut()
-            .downcast_mut::<CacheEntry<K, V>>()
-            .expect(EXPECT_CACHE_ERROR);
+        if let Some(StoredQuery { key, query }) = result {
+            let type_size = self.type_size_signal(key.types);
+            self.groups.leave(id);
+            self.notify_removed(&key, query.as_ref());
+            // With cache clears, the size may already be zero.
+            for size in [self.size, type_size] {
+                size.update(|size| {
+                    if *size > 0 {
+                        *size -= 1
+                    }
+                });
+            }
+            query.dispose();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
 
-        func(&mut cache.0)
+    /// Evicts a query whose gc time has elapsed, unless the hook for its type postpones it.
+    /// Returns whether the query was evicted.
+    pub fn collect_query(&self, id: QueryId) -> bool {
+        let query = self
+            .read_store("collect_query", |cache| {
+                cache
+                    .queries
+                    .get(id)
+                    .map(|stored| stored.query.clone_query())
+            })
+            .flatten();
+        let Some(query) = query else {
+            return false;
+        };
+        match query.before_gc(self) {
+            GcDecision::Collect => self.evict_query(id),
+            GcDecision::Postpone(delay) => {
+                // Saturates, so that an overflowing delay postpones collection indefinitely.
+                query.postpone_gc(Instant(Instant::now().0.saturating_add(delay)));
+                false
+            }
+        }
     }
 
-    pub fn use_cache_entry<K, V>(
-        &self,
-        key: K,
-        func: impl FnOnce((Owner, Option<&Query<K, V>>)) -> Option<Query<K, V>>,
-    ) where
+    pub fn set_before_gc<K, V>(&self, hook: impl Fn(&K, &QueryState<V>) -> GcDecision + 'static)
+    where
         K: QueryKey + 'static,
         V: QueryValue + 'static,
     {
-        let query_cache = self;
+        let hook: BeforeGc<K, V> = Rc::new(hook);
+        match try_borrow_mut(&self.gc_hooks, "set_before_gc") {
+            Ok(mut hooks) => {
+                hooks.insert((TypeId::of::<K>(), TypeId::of::<V>()), Rc::new(hook));
+            }
+            Err(e) => logging::debug_warn!("{e}"),
+        }
+    }
 
-        let mut created = false;
+    fn before_gc<K, V>(&self) -> Option<BeforeGc<K, V>>
+    where
+        K: 'static,
+        V: 'static,
+    {
+        try_borrow(&self.gc_hooks, "before_gc")
+            .ok()?
+            .get(&(TypeId::of::<K>(), TypeId::of::<V>()))?
+            .downcast_ref::<BeforeGc<K, V>>()
+            .cloned()
+    }
 
-        self.use_cache(|cache| match cache.entry(key) {
-            Entry::Vacant(entry) => {
-                if let Some(query) = func((query_cache.owner, None)) {
-                    entry.insert(query.clone());
-                    // Report insert.
-                    created = true;
-                    self.notify_new_query(query)
-                }
+    pub fn invalidate_all_queries(&self) {
+        let cache = self.cache.clone();
+        run_or_defer(move || {
+            let queries = try_borrow(&cache, "invalidate_all_queries")?
+                .queries
+                .values()
+                .map(|stored| stored.query.clone_query())
+                .collect::<Vec<_>>();
+
+            for query in queries {
+                query.mark_invalid();
             }
-            Entry::Occupied(mut entry) => {
-                let query = entry.get();
-                if let Some(query) = func((query_cache.owner, Some(query))) {
-                    entry.insert(query);
+            Ok(())
+        })
+    }
+
+    /// Invalidates every query whose serialized key is in the manifest. Returns the number of queries invalidated.
+    pub fn apply_invalidations(&self, manifest: &InvalidationManifest) -> usize {
+        if manifest.is_empty() {
+            return 0;
+        }
+        let queries = self
+            .read_store("apply_invalidations", |cache| {
+                cache
+                    .values()
+                    .filter(|stored| manifest.keys().contains(&stored.key.key))
+                    .map(|stored| stored.query.clone_query())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        queries
+            .into_iter()
+            .filter(|query| query.mark_invalid())
+            .count()
+    }
+
+    /// Invalidates every query whose key or value type has the given name. Returns the number of queries invalidated.
+    pub fn invalidate_queries_by_type_name(&self, type_name: &str) -> usize {
+        let queries = self
+            .read_store("invalidate_queries_by_type_name", |cache| {
+                cache
+                    .values()
+                    .filter(|stored| stored.key.has_type_name(type_name))
+                    .map(|stored| stored.query.clone_query())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        queries
+            .into_iter()
+            .filter(|query| query.mark_invalid())
+            .count()
+    }
+
+    /// Evicts every query whose key or value type has the given name. Returns the number of queries evicted.
+    pub fn evict_queries_by_type_name(&self, type_name: &str) -> usize {
+        let ids = self
+            .read_store("evict_queries_by_type_name", |cache| {
+                cache
+                    .iter()
+                    .filter(|(_, stored)| stored.key.has_type_name(type_name))
+                    .map(|(id, _)| id)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        ids.into_iter().filter(|id| self.evict_query(*id)).count()
+    }
+
+    /// Returns the number of queries that were refetched.
+    pub fn revalidate_stale(&self) -> usize {
+        let queries = self
+            .read_store("revalidate_stale", |cache| {
+                cache
+                    .values()
+                    .map(|stored| stored.query.clone_query())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        queries
+            .into_iter()
+            .filter(|query| query.revalidate_if_stale())
+            .count()
+    }
+
+    pub fn join_group(&self, group: &QueryGroup, id: QueryId) {
+        self.groups.join(group, id)
+    }
+
+    /// If fetches of the query are paused by one of its groups.
+    pub fn is_fetch_paused(&self, id: QueryId) -> bool {
+        self.groups.is_member_paused(id)
+    }
+
+    pub fn is_group_paused(&self, group: &QueryGroup) -> bool {
+        self.groups.is_paused(group)
+    }
+
+    /// Returns the number of queries fetched on resume.
+    pub fn set_group_paused(&self, group: &QueryGroup, paused: bool) -> usize {
+        if !self.groups.set_paused(group, paused) || paused {
+            return 0;
+        }
+        self.group_queries(group)
+            .into_iter()
+            .filter(|query| query.resume())
+            .count()
+    }
+
+    /// Returns the number of queries invalidated.
+    pub fn invalidate_group(&self, group: &QueryGroup) -> usize {
+        self.group_queries(group)
+            .into_iter()
+            .filter(|query| query.mark_invalid())
+            .count()
+    }
+
+    /// Returns the number of fetches cancelled.
+    pub fn cancel_group(&self, group: &QueryGroup) -> usize {
+        self.group_queries(group)
+            .into_iter()
+            .filter(|query| query.cancel())
+            .count()
+    }
+
+    /// Returns the number of queries evicted.
+    pub fn evict_group(&self, group: &QueryGroup) -> usize {
+        self.groups
+            .members(group)
+            .into_iter()
+            .filter(|id| self.evict_query(*id))
+            .count()
+    }
+
+    pub fn group_status(&self, group: &QueryGroup) -> Signal<QueryGroupStatus> {
+        let cache = self.clone();
+        let group = group.clone();
+        let changed = self.groups.changed(&group);
+        create_memo(move |_| {
+            if let Some(changed) = changed {
+                changed.track();
+            }
+            let mut status = QueryGroupStatus {
+                paused: cache.groups.is_paused(&group),
+                ..QueryGroupStatus::default()
+            };
+            for query in cache.group_queries(&group) {
+                let kind = query.state_kind();
+                status.queries += 1;
+                if matches!(kind, QueryStateKind::Loading | QueryStateKind::Fetching) {
+                    status.fetching += 1;
+                }
+                if matches!(
+                    kind,
+                    QueryStateKind::Fetching | QueryStateKind::Loaded | QueryStateKind::Invalid
+                ) {
+                    status.loaded += 1;
+                }
+                if kind == QueryStateKind::Invalid {
+                    status.invalid += 1;
                 }
             }
-        });
+            status
+        })
+        .into()
+    }
 
-        // It's necessary to delay the size update until we are out of the borrow, to avoid borrow errors.
-        if created {
-            self.size.update(|size| *size += 1);
+    // Members in join order.
+    fn group_queries(&self, group: &QueryGroup) -> Vec<Box<dyn AnyQuery>> {
+        let members = self.groups.members(group);
+        self.read_store("group_queries", |cache| {
+            members
+                .iter()
+                .filter_map(|id| cache.queries.get(*id))
+                .map(|stored| stored.query.clone_query())
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+
+    // Observers of a removed query can't unsubscribe from it, so their removal is sent first to keep observer events paired.
+    fn notify_removed(&self, key: &CacheKey, query: &dyn AnyQuery) {
+        for observer in query.observer_keys() {
+            if self.pair_observer_event(key.key.clone(), observer, false) {
+                self.notify_observers(CacheEvent::ObserverRemoved(ObserverRemoved {
+                    key: key.key.clone(),
+                    types: key.query_types(),
+                    observer,
+                }));
+            }
         }
+        self.notify_observers(CacheEvent::Removed(key.key.clone(), key.query_types()));
     }
 
-    pub fn register_observer(&self, observer: impl CacheObserver + 'static) -> CacheObserverKey {
-        // Update all existing cache entries with the new observer.
-        self.cache.borrow().values().for_each(|cache| {
-            cache.update_observer(&observer);
+    pub fn clear_all_queries(&self) {
+        let cache = self.clone();
+        run_or_defer(move || cache.try_clear_all_queries())
+    }
+
+    fn try_clear_all_queries(&self) -> Result<(), ReentrantBorrow> {
+        let queries = {
+            let mut cache = try_borrow_mut(&self.cache, "clear_all_queries")?;
+            let QueryStore { queries, index } = &mut *cache;
+            index
+                .drain(..)
+                .filter_map(|(_, id)| queries.remove(id))
+                .collect::<Vec<_>>()
+        };
+
+        for StoredQuery { key, query } in queries {
+            self.notify_removed(&key, query.as_ref());
+            query.dispose();
+        }
+        self.groups.clear_members();
+        // Though persister receives removal events, there may be queries in persister that are not yet in cache.
+        // So we should clear them all.
+        #[cfg(any(feature = "hydrate", feature = "csr"))]
+        if let Some(persister) = self.persister.borrow().clone() {
+            spawn_local(async move {
+                persister.clear().await;
+            });
+        }
+
+        // Need to queue microtask to avoid borrow errors.
+        let size = self.size;
+        let type_sizes = try_borrow(&self.type_sizes, "clear_all_queries")?
+            .values()
+            .copied()
+            .collect::<Vec<_>>();
+        queue_microtask(move || {
+            size.set(0);
+            for size in type_sizes {
+                size.set(0);
+            }
         });
+        Ok(())
+    }
+
+    pub fn register_observer(&self, observer: impl CacheObserver + 'static) -> CacheObserverKey {
+        let key = CacheObserverKey(self.next_observer_key.get());
+        self.next_observer_key.set(key.0 + 1);
+
+        let observer = Rc::new(observer) as Rc<dyn CacheObserver>;
+        let cache = self.cache.clone();
+        let observers = self.observers.clone();
+        run_or_defer(move || {
+            // Update all existing cache entries with the new observer.
+            let events = try_borrow(&cache, "register_observer")?
+                .queries
+                .values()
+                .map(|stored| stored.query.created_event())
+                .collect::<Vec<_>>();
+
+            try_borrow_mut(&observers, "register_observer")?.insert(key, observer.clone());
 
-        self.observers
-            .try_borrow_mut()
-            .expect("register_query_observer borrow mut")
-            .insert(Box::new(observer))
+            for event in events {
+                observer.process_cache_event(event);
+            }
+            Ok(())
+        });
+        key
     }
 
-    pub fn unregister_observer(&self, key: CacheObserverKey) -> Option<Box<dyn CacheObserver>> {
-        self.observers
-            .try_borrow_mut()
-            .expect("unregister_query_observer borrow mut")
-            .remove(key)
+    pub fn unregister_observer(&self, key: CacheObserverKey) {
+        let observers = self.observers.clone();
+        run_or_defer(move || {
+            try_borrow_mut(&observers, "unregister_observer")?.shift_remove(&key);
+            Ok(())
+        })
     }
 
     pub fn add_persister(&self, persister: impl QueryPersister + 'static) {
@@ -415,6 +1152,17 @@ impl QueryCache {
         *self.persister.borrow_mut() = Some(persister);
     }
 
+    #[cfg(any(feature = "hydrate", feature = "csr"))]
+    pub fn persister(&self) -> Option<Rc<dyn QueryPersister>> {
+        self.persister.borrow().clone()
+    }
+
+    /// Invalidates restored data once `check` resolves to true.
+    #[cfg(any(feature = "hydrate", feature = "csr"))]
+    pub fn set_cold_start(&self, check: ColdStartCheck) {
+        *self.cold_start.borrow_mut() = Some(check);
+    }
+
     pub fn remove_persister(&self) -> Option<Rc<dyn QueryPersister>> {
         self.persister.borrow_mut().take()
     }
@@ -425,15 +1173,69 @@ impl QueryCache {
         V: QueryValue + 'static,
     {
         let event = match notification {
-            CacheNotification::UpdatedState(query) => CacheEvent::updated(query),
+            CacheNotification::UpdatedState(query) => {
+                self.type_state_trigger((TypeId::of::<K>(), TypeId::of::<V>()))
+                    .notify();
+                self.groups.notify_member(query.get_id());
+                CacheEvent::updated(query)
+            }
+            CacheNotification::Restored(query) => CacheEvent::restored(query),
             CacheNotification::NewObserver(observer) => {
-                CacheEvent::observer_added(&observer.key, observer.options)
+                if !self.pair_observer_event((&observer.key).into(), observer.observer, true) {
+                    return;
+                }
+                CacheEvent::observer_added(
+                    &observer.key,
+                    observer.observer,
+                    observer.options,
+                    observer.location,
+                )
+            }
+            CacheNotification::ObserverRemoved(key, observer) => {
+                if !self.pair_observer_event((&key).into(), observer, false) {
+                    return;
+                }
+                CacheEvent::observer_removed::<K, V>(&key, observer)
+            }
+            CacheNotification::TimesChanged(key, stale_time, gc_time) => {
+                CacheEvent::times_changed::<K, V>(&key, stale_time, gc_time)
             }
-            CacheNotification::ObserverRemoved(key) => CacheEvent::observer_removed(&key),
         };
         self.notify_observers(event);
     }
 
+    // Whether an observer event keeps additions and removals paired. Duplicate additions and unmatched removals are dropped.
+    fn pair_observer_event(&self, key: QueryCacheKey, observer: ObserverKey, added: bool) -> bool {
+        let Ok(mut added_observers) = try_borrow_mut(&self.added_observers, "observer event")
+        else {
+            return true;
+        };
+        let entry = (key, observer);
+        let paired = if added {
+            added_observers.insert(entry.clone())
+        } else {
+            added_observers.remove(&entry)
+        };
+        drop(added_observers);
+
+        if !paired {
+            let (key, observer) = entry;
+            let event = if added {
+                "Duplicate ObserverAdded"
+            } else {
+                "Unmatched ObserverRemoved"
+            };
+            cfg_if::cfg_if! {
+                if #[cfg(feature = "state_assertions")] {
+                    debug_assert!(paired, "{event} for observer {observer:?} of query {key:?}");
+                } else {
+                    logging::debug_warn!("{event} for observer {observer:?} of query {key:?}");
+                }
+            }
+        }
+        paired
+    }
+
     pub fn notify_new_query<K, V>(&self, query: Query<K, V>)
     where
         K: QueryKey + 'static,
@@ -443,35 +1245,165 @@ impl QueryCache {
         self.notify_observers(event);
     }
 
-    pub fn notify_query_eviction<K>(&self, key: &K)
-    where
-        K: QueryKey + 'static,
-    {
-        let event = CacheEvent::removed(key);
-        self.notify_observers(event);
+    pub fn notify_observers(&self, notification: CacheEvent) {
+        #[cfg(feature = "debug_report")]
+        if let Ok(mut events) = try_borrow_mut(&self.events, "record_event") {
+            if events.len() == crate::debug_report::MAX_EVENTS {
+                events.pop_front();
+            }
+            events.push_back(crate::debug_report::EventReport::new(&notification));
+        }
+        let observers = self.observers.clone();
+        run_or_defer(move || {
+            // Observers may call back into the cache, so they are invoked outside of the borrow.
+            let observers = try_borrow(&observers, "notify_observers")?
+                .values()
+                .cloned()
+                .collect::<Vec<_>>();
+            for observer in observers {
+                observer.process_cache_event(notification.clone())
+            }
+            Ok(())
+        })
     }
 
-    pub fn notify_observers(&self, notification: CacheEvent) {
-        let observers = self
-            .observers
-            .try_borrow()
-            .expect("notify_observers borrow");
-        for observer in observers.values() {
-            observer.process_cache_event(notification.clone())
+    // Reads degrade gracefully, as the store is never borrowed while user code runs.
+    fn read_store<R>(&self, context: &'static str, f: impl FnOnce(&QueryStore) -> R) -> Option<R> {
+        match try_borrow(&self.cache, context) {
+            Ok(cache) => Some(f(&cache)),
+            Err(e) => {
+                logging::debug_warn!("{e}");
+                None
+            }
         }
     }
 }
 
 pub enum CacheNotification<K, V> {
     UpdatedState(Query<K, V>),
+    Restored(Query<K, V>),
     NewObserver(NewObserver<K, V>),
-    ObserverRemoved(K),
+    ObserverRemoved(K, ObserverKey),
+    // The merged stale and gc times.
+    TimesChanged(K, Option<Duration>, Option<Duration>),
 }
 
 pub struct NewObserver<K, V> {
     pub key: K,
+    pub observer: ObserverKey,
     pub options: QueryOptions<V>,
+    pub location: &'static std::panic::Location<'static>,
 }
 
 const EXPECT_CACHE_ERROR: &str =
     "Error: Query Cache Type Mismatch. This should not happen. Please file a bug report.";
+
+#[cfg(all(test, not(any(feature = "csr", feature = "hydrate"))))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_while_store_is_read() {
+        let _ = create_runtime();
+        let cache = QueryCache::new(Owner::current().unwrap());
+
+        let query = cache.get_or_create_query::<u32, u32>(0);
+
+        let store = cache.cache.borrow();
+        // Existing queries resolve to the cached query.
+        assert_eq!(
+            query.get_id(),
+            cache.get_or_create_query::<u32, u32>(0).get_id()
+        );
+        // New queries can't be inserted yet, so insertion is deferred.
+        let deferred = cache.get_or_create_query::<u32, u32>(1);
+        drop(store);
+        assert!(cache.get_query::<u32, u32>(&1).is_none());
+
+        // Outside the browser the retry runs immediately, so it is replayed once the store is released.
+        cache.try_insert_query(&deferred).unwrap();
+        let inserted = cache.get_query::<u32, u32>(&1).unwrap();
+        assert_eq!(deferred.get_id(), inserted.get_id());
+        assert_ne!(deferred.get_id(), QueryId::default());
+        assert_eq!(cache.size.get_untracked(), 2);
+    }
+
+    #[test]
+    fn iterates_in_insertion_order() {
+        let _ = create_runtime();
+        let cache = QueryCache::new(Owner::current().unwrap());
+
+        for key in 0..3 {
+            cache.get_or_create_query::<u32, u32>(key);
+        }
+        let first = cache.get_query::<u32, u32>(&0).unwrap();
+        assert!(cache.evict_query(first.get_id()));
+        // Reuses the evicted slot, but is still last.
+        cache.get_or_create_query::<u32, u32>(3);
+
+        let keys = cache
+            .get_queries::<u32, u32>()
+            .iter()
+            .map(|query| *query.get_key())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![1, 2, 3], keys);
+    }
+
+    #[test]
+    fn touch_moves_to_end() {
+        let _ = create_runtime();
+        let cache = QueryCache::new(Owner::current().unwrap());
+
+        for key in 0..4 {
+            cache.get_or_create_query::<u32, u32>(key);
+        }
+        let touched = cache.touch_queries(&[(&2).into(), (&0).into()], None);
+        assert_eq!(2, touched);
+
+        let keys = cache
+            .get_queries::<u32, u32>()
+            .iter()
+            .map(|query| *query.get_key())
+            .collect::<Vec<_>>();
+        // Touched queries keep their relative order.
+        assert_eq!(vec![1, 3, 0, 2], keys);
+    }
+
+    #[test]
+    fn gc_hook_postpones_collection() {
+        let _ = create_runtime();
+        let cache = QueryCache::new(Owner::current().unwrap());
+
+        cache.set_before_gc::<u32, u32>(|key, _| {
+            if *key == 0 {
+                GcDecision::Postpone(Duration::from_secs(60))
+            } else {
+                GcDecision::Collect
+            }
+        });
+        let kept = cache.get_or_create_query::<u32, u32>(0).get_id();
+        let collected = cache.get_or_create_query::<u32, u32>(1).get_id();
+        // Hooks only apply to their own type.
+        let other = cache.get_or_create_query::<u32, String>(0).get_id();
+
+        assert!(!cache.collect_query(kept));
+        assert!(cache.get_query::<u32, u32>(&0).is_some());
+        assert!(cache.gc_scheduler().is_scheduled(kept));
+        assert!(cache.collect_query(collected));
+        assert!(cache.collect_query(other));
+        assert_eq!(1, cache.size().get_untracked());
+    }
+
+    #[test]
+    fn gc_hook_postpones_indefinitely() {
+        let _ = create_runtime();
+        let cache = QueryCache::new(Owner::current().unwrap());
+
+        cache.set_before_gc::<u32, u32>(|_, _| GcDecision::Postpone(Duration::MAX));
+        let kept = cache.get_or_create_query::<u32, u32>(0).get_id();
+
+        assert!(!cache.collect_query(kept));
+        assert!(cache.get_query::<u32, u32>(&0).is_some());
+        assert!(!cache.gc_scheduler().is_scheduled(kept));
+    }
+}
@@ -1,36 +1,42 @@
 use crate::{query_observer::ListenerKey, *};
 use leptos::*;
-use std::{borrow::Borrow, cell::Cell, collections::HashMap, future::Future, rc::Rc};
+use std::{
+    any::{Any, TypeId},
+    borrow::Borrow,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    future::Future,
+    rc::Rc,
+};
 
 use self::{
-    cache_observer::CacheObserver, query::Query, query_cache::QueryCache,
-    query_observer::QueryObserver, query_persister::QueryPersister,
+    cache_observer::CacheObserver, query_cache::QueryCache, query_observer::QueryObserver,
+    query_persister::QueryPersister,
 };
 
 /// Provides a Query Client to the current scope.
 pub fn provide_query_client() {
-    provide_query_client_with_options(DefaultQueryOptions::default());
+    QueryClientBuilder::new().provide();
 }
 
 /// Provides a Query Client to the current scope with custom options.
+///
+/// Shorthand for [`QueryClientBuilder`] with [`options`](QueryClientBuilder::options).
 pub fn provide_query_client_with_options(options: DefaultQueryOptions) {
-    let owner = Owner::current().expect("Owner to be present");
-
-    provide_context(QueryClient::new(owner, options));
+    QueryClientBuilder::new().options(options).provide();
 }
 
 /// Provides a Query Client to the current scope with custom options and a persister.
+///
+/// Shorthand for [`QueryClientBuilder`] with [`options`](QueryClientBuilder::options) and [`persister`](QueryClientBuilder::persister).
 pub fn provide_query_client_with_options_and_persister(
     options: DefaultQueryOptions,
     persister: impl QueryPersister + Clone + 'static,
 ) {
-    let owner = Owner::current().expect("Owner to be present");
-
-    let client = QueryClient::new(owner, options);
-
-    client.add_persister(persister);
-
-    provide_context(client);
+    QueryClientBuilder::new()
+        .options(options)
+        .persister(persister)
+        .provide();
 }
 
 /// Retrieves a Query Client from the current scope.
@@ -54,6 +60,11 @@ pub fn use_query_client() -> QueryClient {
 pub struct QueryClient {
     pub(crate) cache: QueryCache,
     pub(crate) default_options: DefaultQueryOptions,
+    // Shared scopes, by id and key/value types.
+    #[allow(clippy::type_complexity)]
+    scopes: Rc<RefCell<HashMap<(&'static str, TypeId, TypeId), Rc<dyn Any>>>>,
+    // App-defined option presets, by name.
+    presets: Rc<RefCell<HashMap<&'static str, QueryOptions<()>>>>,
 }
 
 impl QueryClient {
@@ -62,6 +73,8 @@ impl QueryClient {
         Self {
             cache: QueryCache::new(owner),
             default_options,
+            scopes: Rc::new(RefCell::new(HashMap::new())),
+            presets: Rc::new(RefCell::new(HashMap::new())),
         }
     }
 
@@ -74,6 +87,21 @@ impl QueryClient {
         key: K,
         fetcher: impl Fn(K) -> Fu + 'static,
     ) -> QueryState<V>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+        Fu: Future<Output = V> + 'static,
+    {
+        self.fetch_query_with_priority(key, fetcher, QueryPriority::Normal)
+            .await
+    }
+
+    pub(crate) async fn fetch_query_with_priority<K, V, Fu>(
+        &self,
+        key: K,
+        fetcher: impl Fn(K) -> Fu + 'static,
+        priority: QueryPriority,
+    ) -> QueryState<V>
     where
         K: QueryKey + 'static,
         V: QueryValue + 'static,
@@ -83,11 +111,43 @@ impl QueryClient {
         {
             let query = self.cache.get_or_create_query::<K, V>(key);
 
+            let _permit = self.cache.fetch_scheduler().acquire(priority).await;
             query::execute_query(query.clone(), fetcher).await;
 
             query.get_state()
         }
         #[cfg(not(any(feature = "hydrate", feature = "csr")))]
+        {
+            let _ = key;
+            let _ = fetcher;
+            let _ = priority;
+            QueryState::Created
+        }
+    }
+
+    /// Fetch a query and store it in cache, even if the data is fresh.
+    /// Any in-flight fetch for the query is cancelled first.
+    ///
+    /// Useful for explicit refreshes (e.g. pull to refresh), where stale time shouldn't apply.
+    pub async fn refetch_query_force<K, V, Fu>(
+        &self,
+        key: K,
+        fetcher: impl Fn(K) -> Fu + 'static,
+    ) -> QueryState<V>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+        Fu: Future<Output = V> + 'static,
+    {
+        #[cfg(any(feature = "hydrate", feature = "csr"))]
+        {
+            let query = self.cache.get_or_create_query::<K, V>(key);
+
+            query::execute_query_force(query.clone(), fetcher).await;
+
+            query.get_state()
+        }
+        #[cfg(not(any(feature = "hydrate", feature = "csr")))]
         {
             let _ = key;
             let _ = fetcher;
@@ -98,8 +158,67 @@ impl QueryClient {
     /// Prefetch a query and store it in cache.
     /// If the entry already exists it will still be refetched.
     ///
+    /// The prefetch starts immediately. The returned [`PrefetchHandle`] can be awaited until it finishes,
+    /// or cancelled, e.g. when the user navigates away. Prefetches are also cancelled after [`DefaultQueryOptions::prefetch_timeout`], if the query has no observers by then.
+    ///
     /// If you need the result opt for [`fetch_query()`](Self::fetch_query)
-    pub async fn prefetch_query<K, V, Fu>(&self, key: K, fetcher: impl Fn(K) -> Fu + 'static)
+    pub fn prefetch_query<K, V, Fu>(
+        &self,
+        key: K,
+        fetcher: impl Fn(K) -> Fu + 'static,
+    ) -> PrefetchHandle
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+        Fu: Future<Output = V> + 'static,
+    {
+        self.prefetch_query_with_priority(key, fetcher, QueryPriority::Normal)
+    }
+
+    /// Prefetch a query once the browser is idle, to warm up data (e.g. for secondary routes) without competing with interactive work.
+    /// The fetch runs at [`QueryPriority::Low`], so it also waits for any high priority fetch.
+    /// If the query has fresh data by then, nothing is fetched.
+    ///
+    /// Uses `requestIdleCallback`, with a timer as fallback where it isn't supported.
+    pub fn prefetch_on_idle<K, V, Fu>(&self, key: K, fetcher: impl Fn(K) -> Fu + 'static)
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+        Fu: Future<Output = V> + 'static,
+    {
+        #[cfg(any(feature = "hydrate", feature = "csr"))]
+        {
+            let client = self.clone();
+            crate::util::on_idle(move || {
+                if !client.has_fresh_data::<K, V>(&key) {
+                    client.prefetch_query_with_priority(key, fetcher, QueryPriority::Low);
+                }
+            });
+        }
+        #[cfg(not(any(feature = "hydrate", feature = "csr")))]
+        {
+            let _ = key;
+            let _ = fetcher;
+        }
+    }
+
+    // Loaded and not stale, so prefetching can be skipped.
+    pub(crate) fn has_fresh_data<K, V>(&self, key: &K) -> bool
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.cache.get_query::<K, V>(key).is_some_and(|query| {
+            query.with_state(|state| matches!(state, QueryState::Loaded(_))) && !query.is_stale()
+        })
+    }
+
+    pub(crate) fn prefetch_query_with_priority<K, V, Fu>(
+        &self,
+        key: K,
+        fetcher: impl Fn(K) -> Fu + 'static,
+        priority: QueryPriority,
+    ) -> PrefetchHandle
     where
         K: QueryKey + 'static,
         V: QueryValue + 'static,
@@ -108,18 +227,58 @@ impl QueryClient {
         #[cfg(any(feature = "hydrate", feature = "csr"))]
         {
             let query = self.cache.get_or_create_query::<K, V>(key);
+            let cancelled = Rc::new(Cell::new(false));
+            let finished = Rc::new(Cell::new(false));
+            let (done_tx, done) = futures_channel::oneshot::channel();
+
+            leptos::spawn_local({
+                let cache = self.cache.clone();
+                let query = query.clone();
+                let (cancelled, finished) = (cancelled.clone(), finished.clone());
+                async move {
+                    let _permit = cache.fetch_scheduler().acquire(priority).await;
+                    // May be cancelled while waiting for a permit.
+                    if !cancelled.get() {
+                        query::execute_query(query, fetcher).await;
+                    }
+                    finished.set(true);
+                    let _ = done_tx.send(());
+                }
+            });
 
-            query::execute_query(query.clone(), fetcher).await;
+            // Once the query is observed, the fetch is no longer speculative.
+            let cancel: Rc<dyn Fn() -> bool> = Rc::new(move || {
+                if finished.get() || cancelled.get() || query.has_observers() {
+                    return false;
+                }
+                cancelled.set(true);
+                query.cancel();
+                true
+            });
+
+            if let Some(timeout) = self.default_options.prefetch_timeout {
+                let cancel = cancel.clone();
+                set_timeout(
+                    move || {
+                        cancel();
+                    },
+                    timeout,
+                );
+            }
+            PrefetchHandle::new(cancel, done)
         }
         #[cfg(not(any(feature = "hydrate", feature = "csr")))]
         {
             let _ = key;
             let _ = fetcher;
+            let _ = priority;
+            PrefetchHandle::finished()
         }
     }
 
     /// Retrieve the current state for an existing query.
     /// If the query does not exist, [`None`](Option::None) will be returned.
+    #[track_caller]
     pub fn get_query_state<K, V>(
         &self,
         key: impl Fn() -> K + 'static,
@@ -129,17 +288,18 @@ impl QueryClient {
         V: QueryValue + 'static,
     {
         let cache = self.cache.clone();
-        let size = self.size();
+        let size = self.cache.type_size::<K, V>();
 
         // // Memoize state to avoid unnecessary hashmap lookups.
         let maybe_query = create_memo(move |_| {
             let key = key();
-            // Subscribe to inserts/deletions.
+            // Subscribe to inserts/deletions of the same type.
             size.track();
             cache.get_query::<K, V>(&key)
         });
 
         let observer = Rc::new(QueryObserver::no_fetcher(
+            self.cache.clone(),
             QueryOptions::default(),
             maybe_query.get_untracked(),
         ));
@@ -180,6 +340,102 @@ impl QueryClient {
         self.cache.get_query::<K, V>(key).map(|q| q.get_state())
     }
 
+    /// Computes over the data of an existing query without cloning it, unlike [`peek_query_state`](Self::peek_query_state).
+    /// Returns [`None`](Option::None) if the query does not exist or has no data. Not reactive.
+    ///
+    /// The data is borrowed while `f` runs, so updates of the same query made by `f` are applied once it returns.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// fn comment_count(post_id: u32) -> usize {
+    ///     use_query_client()
+    ///         .with_query_data::<u32, Vec<String>, _>(&post_id, |comments| comments.len())
+    ///         .unwrap_or_default()
+    /// }
+    /// ```
+    pub fn with_query_data<K, V, R>(&self, key: &K, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.cache
+            .get_query::<K, V>(key)?
+            .with_state(|state| state.data().map(f))
+    }
+
+    /// Waits until an existing query is no longer loading or fetching, and returns its state.
+    /// If the query is not loading or fetching, the current state is returned immediately.
+    ///
+    /// Returns [`None`](Option::None) if the query does not exist, or if the timeout elapses first.
+    /// Useful for sequencing imperative flows (e.g. bootstrapping after login) on cache readiness.
+    ///
+    /// Example:
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// async fn bootstrap() {
+    ///     let client = use_query_client();
+    ///     let settings = client
+    ///         .await_query_settled::<SettingsKey, Settings>(SettingsKey, TimeSetting::secs(5))
+    ///         .await
+    ///         .and_then(|state| state.data().cloned());
+    /// }
+    ///
+    /// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+    /// struct SettingsKey;
+    ///
+    /// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    /// struct Settings;
+    /// ```
+    pub async fn await_query_settled<K, V>(
+        &self,
+        key: K,
+        timeout: impl Into<TimeSetting>,
+    ) -> Option<QueryState<V>>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        fn is_settled<V>(state: &QueryState<V>) -> bool {
+            !matches!(state, QueryState::Loading | QueryState::Fetching(_))
+        }
+
+        let query = self.cache.get_query::<K, V>(&key)?;
+        if query.with_state(is_settled) {
+            return Some(query.get_state());
+        }
+
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        let sender = Cell::new(Some(sender));
+        let observer =
+            QueryObserver::no_fetcher(self.cache.clone(), QueryOptions::default(), Some(query));
+        let listener = observer.add_listener(move |state| {
+            if is_settled(state) {
+                if let Some(sender) = sender.take() {
+                    let _ = sender.send(state.clone());
+                }
+            }
+        });
+
+        let settled = match timeout.into().duration() {
+            Some(timeout) => {
+                let timeout = std::pin::pin!(crate::util::sleep(timeout));
+                match futures::future::select(receiver, timeout).await {
+                    futures::future::Either::Left((state, _)) => state.ok(),
+                    futures::future::Either::Right(_) => None,
+                }
+            }
+            None => receiver.await.ok(),
+        };
+
+        observer.remove_listener(listener);
+        observer.cleanup();
+        settled
+    }
+
     /// Attempts to invalidate an entry in the Query Cache.
     /// Matching query is marked as invalid, and will be refetched in background once it's active.
     ///
@@ -201,11 +457,8 @@ impl QueryClient {
         V: QueryValue + 'static,
     {
         self.cache
-            .use_cache_option(|cache: &HashMap<K, Query<K, V>>| {
-                cache
-                    .get(Borrow::borrow(&key))
-                    .map(|state| state.mark_invalid())
-            })
+            .get_query::<K, V>(Borrow::borrow(&key))
+            .map(|query| query.mark_invalid())
             .unwrap_or(false)
     }
 
@@ -231,19 +484,16 @@ impl QueryClient {
         V: crate::QueryValue + 'static,
         Q: Borrow<K> + 'static,
     {
-        self.cache
-            .use_cache_option(|cache: &HashMap<K, Query<K, V>>| {
-                let result = keys
-                    .into_iter()
-                    .filter(|key| {
-                        cache
-                            .get(Borrow::borrow(key))
-                            .map(|query| query.mark_invalid())
-                            .unwrap_or(false)
-                    })
-                    .collect::<Vec<_>>();
-                Some(result)
+        let result = keys
+            .into_iter()
+            .filter(|key| {
+                self.cache
+                    .get_query::<K, V>(Borrow::borrow(key))
+                    .map(|query| query.mark_invalid())
+                    .unwrap_or(false)
             })
+            .collect::<Vec<_>>();
+        Some(result)
     }
 
     /// Invalidate all queries with a common <K, V> type.
@@ -272,13 +522,9 @@ impl QueryClient {
         K: QueryKey + 'static,
         V: QueryValue + 'static,
     {
-        self.cache
-            .use_cache_option(|cache: &HashMap<K, Query<K, V>>| {
-                for q in cache.values() {
-                    q.mark_invalid();
-                }
-                Some(())
-            });
+        for query in self.cache.get_queries::<K, V>() {
+            query.mark_invalid();
+        }
     }
 
     /// Invalidates all queries in the cache.
@@ -301,6 +547,114 @@ impl QueryClient {
         self.cache.invalidate_all_queries()
     }
 
+    /// Invalidates the queries listed in an [`InvalidationManifest`], e.g. one returned by a server function alongside its result.
+    /// Active queries are refetched in the background.
+    ///
+    /// Returns the number of queries that were invalidated.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// fn on_mutation(manifest: InvalidationManifest) {
+    ///     let client = use_query_client();
+    ///     let invalidated = client.apply_invalidations(&manifest);
+    /// }
+    ///
+    /// ```
+    pub fn apply_invalidations(&self, manifest: &InvalidationManifest) -> usize {
+        self.cache.apply_invalidations(manifest)
+    }
+
+    /// Invalidates all queries whose key or value type has the given name, and returns how many were invalidated.
+    ///
+    /// For tooling that only has type names, such as devtools or a scripting console. Names are those of [`std::any::type_name`], as listed by [`QueryClient::all_query_stats`].
+    /// Prefer [`QueryClient::invalidate_query_type`] in app code.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// fn invalidate_strings() {
+    ///     let client = use_query_client();
+    ///     let invalidated = client.invalidate_queries_by_type_name("alloc::string::String");
+    /// }
+    ///
+    /// ```
+    pub fn invalidate_queries_by_type_name(&self, type_name: &str) -> usize {
+        self.cache.invalidate_queries_by_type_name(type_name)
+    }
+
+    /// Removes all queries whose key or value type has the given name from the cache, and returns how many were removed.
+    /// See [`QueryClient::invalidate_queries_by_type_name`].
+    pub fn evict_queries_by_type_name(&self, type_name: &str) -> usize {
+        self.cache.evict_queries_by_type_name(type_name)
+    }
+
+    /// Exports a query, including its data, when it was updated, and whether it was invalidated.
+    /// Returns `None` if the query is not in the cache.
+    ///
+    /// The export can be imported into another client with [`QueryClient::import_query`], e.g. for micro-frontends or detached windows.
+    /// With the `serde` feature, it can be serialized to be sent across windows.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// fn transfer_user(from: &QueryClient, to: &QueryClient, id: u32) {
+    ///     if let Some(export) = from.export_query::<u32, String>(&id) {
+    ///         to.import_query::<u32, String>(id, export).expect("Same key and value types");
+    ///     }
+    /// }
+    /// ```
+    pub fn export_query<K, V>(&self, key: &K) -> Option<SerializedQueryExport>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.cache.export_query::<K, V>(key)
+    }
+
+    /// Imports a query exported with [`QueryClient::export_query`], replacing the state of the query with the same key.
+    ///
+    /// Fails if the export was created for a different key, or for different key or value types.
+    /// An invalidated export is refetched once it has an observer with a fetcher.
+    pub fn import_query<K, V>(
+        &self,
+        key: K,
+        export: SerializedQueryExport,
+    ) -> Result<(), ImportQueryError>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.cache.import_query::<K, V>(key, export)
+    }
+
+    /// Refetches all stale or invalid queries that have active observers, and returns how many were refetched.
+    ///
+    /// Useful for app-defined triggers, such as push messages, timers, or user actions.
+    /// Refetches are rate limited by each query's `min_fetch_interval`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    ///
+    /// fn on_push_message() {
+    ///     let client = use_query_client();
+    ///     let refetched = client.revalidate_stale();
+    /// }
+    ///
+    /// ```
+    pub fn revalidate_stale(&self) -> usize {
+        self.cache.revalidate_stale()
+    }
+
     /// Returns the current size of the cache.
     ///
     /// Example:
@@ -318,6 +672,26 @@ impl QueryClient {
         self.cache.size()
     }
 
+    /// Returns the size of the cache per key and value type, largest first.
+    /// Useful to spot query families that grow unexpectedly, e.g. from unstable keys.
+    ///
+    /// Example:
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    ///
+    /// fn log_sizes() {
+    ///    let client = use_query_client();
+    ///    for size in client.size_by_type().get_untracked() {
+    ///        logging::log!("{} -> {}: {}", size.key_type, size.value_type, size.count);
+    ///    }
+    /// }
+    ///
+    /// ```
+    pub fn size_by_type(&self) -> Signal<Vec<TypeSize>> {
+        self.cache.size_by_type()
+    }
+
     /// A synchronous function that can be used to immediately set a query's data.
     ///
     /// If the query does not exist, it will be created.
@@ -365,51 +739,46 @@ impl QueryClient {
         K: QueryKey + 'static,
         V: QueryValue + 'static,
     {
-        self.cache
-            .use_cache_entry(key.clone(), move |(owner, entry)| match entry {
-                Some(query) => {
-                    query.maybe_map_state(|state| match state {
-                        QueryState::Created | QueryState::Loading => {
-                            if let Some(result) = updater(None) {
-                                Ok(QueryState::Loaded(QueryData::now(result)))
-                            } else {
-                                Err(state)
-                            }
+        match self.cache.get_query::<K, V>(&key) {
+            Some(query) => {
+                query.maybe_map_state(|state| match state {
+                    QueryState::Created | QueryState::Loading | QueryState::Rejected(_) => {
+                        if let Some(result) = updater(None) {
+                            Ok(QueryState::Loaded(QueryData::now(result)))
+                        } else {
+                            Err(state)
                         }
-                        QueryState::Fetching(ref data) => {
-                            if let Some(result) = updater(Some(&data.data)) {
-                                Ok(QueryState::Fetching(QueryData::now(result)))
-                            } else {
-                                Err(state)
-                            }
+                    }
+                    QueryState::Fetching(ref data) => {
+                        if let Some(result) = updater(Some(&data.data)) {
+                            Ok(QueryState::Fetching(QueryData::now(result)))
+                        } else {
+                            Err(state)
                         }
-                        QueryState::Loaded(ref data) => {
-                            if let Some(result) = updater(Some(&data.data)) {
-                                Ok(QueryState::Loaded(QueryData::now(result)))
-                            } else {
-                                Err(state)
-                            }
+                    }
+                    QueryState::Loaded(ref data) => {
+                        if let Some(result) = updater(Some(&data.data)) {
+                            Ok(QueryState::Loaded(QueryData::now(result)))
+                        } else {
+                            Err(state)
                         }
-                        QueryState::Invalid(ref data) => {
-                            if let Some(result) = updater(Some(&data.data)) {
-                                Ok(QueryState::Loaded(QueryData::now(result)))
-                            } else {
-                                Err(state)
-                            }
+                    }
+                    QueryState::Invalid(ref data) => {
+                        if let Some(result) = updater(Some(&data.data)) {
+                            Ok(QueryState::Loaded(QueryData::now(result)))
+                        } else {
+                            Err(state)
                         }
-                    });
-                    None
-                }
-                None => {
-                    if let Some(result) = updater(None) {
-                        let query = with_owner(owner, || Query::new(key));
-                        query.set_state(QueryState::Loaded(QueryData::now(result)));
-                        Some(query)
-                    } else {
-                        None
                     }
+                });
+            }
+            None => {
+                if let Some(result) = updater(None) {
+                    let query = self.cache.get_or_create_query::<K, V>(key);
+                    query.set_state(QueryState::Loaded(QueryData::now(result)));
                 }
-            });
+            }
+        }
     }
 
     /// Update the query's data.
@@ -424,6 +793,8 @@ impl QueryClient {
 
     /// Mutate the existing data if it exists.
     /// All listeners will be notified, regardless of whether the data was updated or not.
+    ///
+    /// Data wrapped in a [`QueryValueRc`] is mutated in place, and only cloned if it is still shared, e.g. with a listener.
     pub fn update_query_data_mut<K, V>(
         &self,
         key: impl Borrow<K>,
@@ -433,36 +804,216 @@ impl QueryClient {
         K: QueryKey + 'static,
         V: QueryValue + 'static,
     {
-        self.cache.use_cache::<K, V, bool>(move |cache| {
-            let mut updated = false;
-            if let Some(query) = cache.get(key.borrow()) {
-                query.update_state(|state| {
-                    if let Some(data) = state.data_mut() {
-                        updater(data);
-                        updated = true;
-                    }
-                });
-            }
-            updated
-        })
+        let mut updated = false;
+        if let Some(query) = self.cache.get_query::<K, V>(key.borrow()) {
+            query.update_state(|state| {
+                if let Some(data) = state.data_mut() {
+                    updater(data);
+                    updated = true;
+                }
+            });
+        }
+        updated
     }
 
-    /// Cancel any currently executing query.
-    /// Returns whether the query was cancelled or not.
-    pub fn cancel_query<K, V>(&self, key: K) -> bool
+    /// Mutate the existing data if it exists, only notifying listeners if the data was mutably accessed.
+    /// Returns whether the data was changed.
+    ///
+    /// Reads through the [`DataMut`] guard don't count as changes, so updates that turn out to be no-ops skip notifying.
+    /// If `bump_updated_at` is set, a change also marks the data as just updated, which resets its stale time.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// fn mark_done(todo_id: u32) {
+    ///     let changed = use_query_client().update_query_data_tracked::<(), Vec<(u32, bool)>>(
+    ///         (),
+    ///         |todos| {
+    ///             // Only a mutable access counts as a change.
+    ///             if let Some(index) = todos.iter().position(|(id, done)| *id == todo_id && !done) {
+    ///                 todos[index].1 = true;
+    ///             }
+    ///         },
+    ///         false,
+    ///     );
+    /// }
+    /// ```
+    pub fn update_query_data_tracked<K, V>(
+        &self,
+        key: impl Borrow<K>,
+        updater: impl FnOnce(&mut DataMut<V>),
+        bump_updated_at: bool,
+    ) -> bool
     where
         K: QueryKey + 'static,
         V: QueryValue + 'static,
     {
-        self.cache.use_cache::<K, V, bool>(move |cache| {
-            if let Some(query) = cache.get(&key) {
-                query.cancel()
-            } else {
-                false
+        let Some(query) = self.cache.get_query::<K, V>(key.borrow()) else {
+            return false;
+        };
+        query.maybe_map_state(|mut state| {
+            let changed = match &mut state {
+                QueryState::Fetching(data)
+                | QueryState::Loaded(data)
+                | QueryState::Invalid(data) => {
+                    let mut guard = DataMut::new(&mut data.data);
+                    updater(&mut guard);
+                    let changed = guard.is_changed();
+                    if changed && bump_updated_at {
+                        data.updated_at = Instant::now();
+                    }
+                    changed
+                }
+                QueryState::Created | QueryState::Loading | QueryState::Rejected(_) => false,
+            };
+            if changed {
+                Ok(state)
+            } else {
+                Err(state)
             }
         })
     }
 
+    /// Cancel any currently executing query.
+    /// Returns whether the query was cancelled or not.
+    pub fn cancel_query<K, V>(&self, key: K) -> bool
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        if let Some(query) = self.cache.get_query::<K, V>(&key) {
+            query.cancel()
+        } else {
+            false
+        }
+    }
+
+    /// Keeps a query in the cache until the returned guard is dropped, creating it if needed.
+    ///
+    /// The query isn't garbage collected while held, even once all of its observers are removed.
+    /// Useful for optimistic mutations, so that the data is still there to roll back or settle once the mutation completes.
+    /// Explicitly clearing or evicting queries still removes it.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// async fn rename_user(id: u32, name: String) {
+    ///     let client = use_query_client();
+    ///     let _hold = client.hold_query::<u32, String>(id);
+    ///
+    ///     let previous = client.peek_query_state::<u32, String>(&id);
+    ///     client.set_query_data::<u32, String>(id, name.clone());
+    ///
+    ///     if save_name(id, name).await.is_err() {
+    ///         if let Some(previous) = previous.and_then(|state| state.data().cloned()) {
+    ///             client.set_query_data::<u32, String>(id, previous);
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// async fn save_name(id: u32, name: String) -> Result<(), ()> {
+    ///     todo!()
+    /// }
+    /// ```
+    pub fn hold_query<K, V>(&self, key: impl Borrow<K>) -> HoldGuard
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        let query = self.cache.get_or_create_query::<K, V>(key.borrow().clone());
+        query.hold();
+        HoldGuard::new(move || query.release())
+    }
+
+    /// Defers all garbage collection until the returned guard is dropped.
+    ///
+    /// Useful while debugging, so that entries don't disappear mid-inspection or during long breakpoint pauses.
+    /// Queries that become due while paused are collected once the last guard is dropped. Explicitly clearing or evicting queries still removes them.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    ///
+    /// fn inspect_cache() {
+    ///     let client = use_query_client();
+    ///     let _pause = client.pause_gc();
+    ///     assert!(client.is_gc_paused().get_untracked());
+    /// }
+    /// ```
+    pub fn pause_gc(&self) -> GcPauseGuard {
+        let scheduler = self.cache.gc_scheduler().clone();
+        scheduler.pause();
+        GcPauseGuard::new(move || scheduler.resume())
+    }
+
+    /// Whether garbage collection is paused, see [`QueryClient::pause_gc`].
+    pub fn is_gc_paused(&self) -> Signal<bool> {
+        self.cache.gc_scheduler().paused_signal()
+    }
+
+    /// Sets a hook that decides whether queries of a single <K, V> type are collected once their gc time has elapsed.
+    /// Replaces any hook previously set for the type.
+    ///
+    /// Useful to keep queries that are still in use without observers, e.g. rows of a virtualized list that are scrolled back into view.
+    /// The hook is only consulted by the garbage collector. Explicitly clearing or evicting queries still removes them.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    /// use std::time::Duration;
+    ///
+    /// fn keep_visible_rows(visible: Signal<std::ops::Range<u32>>) {
+    ///     use_query_client().on_before_gc::<u32, String>(move |row, _state| {
+    ///         if visible.with_untracked(|visible| visible.contains(row)) {
+    ///             GcDecision::Postpone(Duration::from_secs(30))
+    ///         } else {
+    ///             GcDecision::Collect
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    pub fn on_before_gc<K, V>(&self, hook: impl Fn(&K, &QueryState<V>) -> GcDecision + 'static)
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.cache.set_before_gc(hook)
+    }
+
+    /// Ephemeral UI state for a query, such as a scroll offset or a set of expanded rows, creating the query if needed.
+    ///
+    /// Stored alongside the cache entry, so it survives remounts for as long as the query is cached.
+    /// It starts as `null`, isn't persisted, and is disposed once the query is evicted.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    ///
+    /// fn use_scroll_offset(page: u32) -> Signal<f64> {
+    ///     let ui_state = use_query_client().query_ui_state::<u32, Vec<String>>(page);
+    ///     Signal::derive(move || ui_state.with(|state| state["scroll"].as_f64().unwrap_or_default()))
+    /// }
+    /// ```
+    #[cfg(feature = "ui_state")]
+    pub fn query_ui_state<K, V>(&self, key: impl Borrow<K>) -> RwSignal<serde_json::Value>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        let query = self.cache.get_or_create_query::<K, V>(key.borrow().clone());
+        with_owner(self.cache.owner(), || query.ui_state())
+    }
+
     /// Registers the cache observer.
     pub fn register_cache_observer(&self, observer: impl CacheObserver + 'static) {
         let key = self.cache.register_observer(observer);
@@ -473,12 +1024,211 @@ impl QueryClient {
         })
     }
 
+    /// Registers a middleware that runs on every fetch. See [`FetchMiddleware`].
+    pub fn register_fetch_middleware(&self, middleware: impl FetchMiddleware + 'static) {
+        let key = self.cache.fetch_middlewares().register(middleware);
+        let cache = self.cache.clone();
+
+        on_cleanup(move || {
+            cache.fetch_middlewares().unregister(key);
+        })
+    }
+
     /// Adds a persister to the cache.
     pub fn add_persister(&self, persister: impl QueryPersister + Clone + 'static) {
         self.register_cache_observer(persister.clone());
         self.cache.add_persister(persister);
     }
 
+    /// Returns the [`QueryScope`] shared under `id`, creating it on first use.
+    ///
+    /// Every caller gets the same fetcher and options, no matter which component asks first.
+    /// Scopes with the same id but different key or value types are kept apart.
+    /// See [`create_shared_query`].
+    pub fn shared_scope<K, V>(
+        &self,
+        id: &'static str,
+        create: impl FnOnce() -> QueryScope<K, V>,
+    ) -> QueryScope<K, V>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        let registry_key = (id, TypeId::of::<K>(), TypeId::of::<V>());
+        if let Some(scope) = RefCell::borrow(&self.scopes).get(&registry_key) {
+            return scope
+                .downcast_ref::<QueryScope<K, V>>()
+                .expect("Shared scope registered with matching types")
+                .clone();
+        }
+
+        // Created outside of the borrow, as creating a scope may read the client.
+        let scope = create();
+        self.scopes
+            .borrow_mut()
+            .entry(registry_key)
+            .or_insert_with(|| Rc::new(scope))
+            .downcast_ref::<QueryScope<K, V>>()
+            .expect("Shared scope registered with matching types")
+            .clone()
+    }
+
+    /// Mount statistics for queries of a single <K, V> type: how often [`use_query`](crate::use_query()) was served from cache, or triggered a fetch.
+    ///
+    /// Example:
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    ///
+    /// fn log_stats() {
+    ///     let client = use_query_client();
+    ///     let stats = client.query_stats::<u32, String>().get_untracked();
+    ///     logging::log!("{} of {} mounts were served from cache", stats.cache_hits, stats.mounts());
+    /// }
+    /// ```
+    pub fn query_stats<K, V>(&self) -> Signal<QueryStats>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.cache.type_stats::<K, V>()
+    }
+
+    /// Whether any query of a single <K, V> type is fetching.
+    ///
+    /// Useful for a single spinner on a page with many queries of the same type, without collecting every [`QueryResult`].
+    ///
+    /// Example:
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    ///
+    /// #[component]
+    /// fn PostsSpinner() -> impl IntoView {
+    ///     let client = use_query_client();
+    ///     let fetching = client.is_any_fetching::<u32, String>();
+    ///     move || fetching.get().then(|| view! { <p>"Loading posts..."</p> })
+    /// }
+    /// ```
+    pub fn is_any_fetching<K, V>(&self) -> Signal<bool>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.cache.type_aggregate::<K, V, _>(|queries| {
+            queries.iter().any(|query| {
+                query.with_state(|state| {
+                    matches!(state, QueryState::Loading | QueryState::Fetching(_))
+                })
+            })
+        })
+    }
+
+    /// Number of queries of a single <K, V> type that have data, including those that are refetching or invalid.
+    pub fn count_loaded<K, V>(&self) -> Signal<usize>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        self.cache.type_aggregate::<K, V, _>(|queries| {
+            queries
+                .iter()
+                .filter(|query| query.with_state(|state| state.data().is_some()))
+                .count()
+        })
+    }
+
+    /// The most recent query state transitions, oldest first. Useful for debugging invalid transitions.
+    /// See [`QueryStateKind::can_transition_to`] for the allowed transitions.
+    ///
+    /// Requires the `state_assertions` feature, which also panics on invalid transitions in debug builds.
+    #[cfg(feature = "state_assertions")]
+    pub fn state_transitions(&self) -> Vec<StateTransition> {
+        self.cache.transitions()
+    }
+
+    /// A JSON snapshot of the cache to attach to bug reports.
+    ///
+    /// Lists every query with its key and value types, state, last update, staleness, and the options and call site of each observer,
+    /// followed by the most recent cache events. Values are left out, see [`debug_report_with`](Self::debug_report_with) to include them.
+    ///
+    /// Requires the `debug_report` feature.
+    #[cfg(feature = "debug_report")]
+    pub fn debug_report(&self) -> String {
+        self.debug_report_with(|_, _| None)
+    }
+
+    /// Like [`debug_report`](Self::debug_report), with the values returned by `redact`.
+    ///
+    /// `redact` receives the serialized key and value of each query with data, and returns what to include as its value,
+    /// e.g. the value with personal data removed, or `None` to leave it out.
+    ///
+    /// Example:
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// fn bug_report() -> String {
+    ///     // Only include values of public queries.
+    ///     use_query_client().debug_report_with(|key, value| {
+    ///         key.0.starts_with("Public").then(|| value.to_string())
+    ///     })
+    /// }
+    /// ```
+    #[cfg(feature = "debug_report")]
+    pub fn debug_report_with(
+        &self,
+        redact: impl Fn(&cache_observer::QueryCacheKey, &str) -> Option<String>,
+    ) -> String {
+        let report = self.cache.debug_report(&redact);
+        serde_json::to_string_pretty(&report).expect("Serialize debug report")
+    }
+
+    /// Mount statistics for every <K, V> type that has been in the cache.
+    pub fn all_query_stats(&self) -> Vec<QueryStats> {
+        self.cache.all_stats()
+    }
+
+    /// Registers app-defined options under a name, to be used with [`QueryOptions::preset`].
+    /// Replaces any preset with the same name. The preset's default value is ignored.
+    ///
+    /// Example:
+    /// ```
+    /// use leptos_query::*;
+    /// use std::time::Duration;
+    ///
+    /// fn register() {
+    ///     let client = use_query_client();
+    ///     client.register_preset("feed", QueryOptions::realtime().set_refetch_interval(Duration::from_secs(30)));
+    /// }
+    ///
+    /// fn feed_query() -> QueryScope<u32, Vec<String>> {
+    ///     create_query(get_feed, QueryOptions::preset("feed"))
+    /// }
+    ///
+    /// async fn get_feed(page: u32) -> Vec<String> {
+    ///     todo!()
+    /// }
+    /// ```
+    pub fn register_preset(&self, name: &'static str, options: QueryOptions<()>) {
+        self.presets
+            .borrow_mut()
+            .insert(name, options.set_default_value(None));
+    }
+
+    /// Retrieves a preset registered with [`register_preset`](Self::register_preset).
+    pub fn get_preset<V>(&self, name: &str) -> Option<QueryOptions<V>> {
+        RefCell::borrow(&self.presets)
+            .get(name)
+            .cloned()
+            // Presets have no default value, so the mapping is never called.
+            .map(|options| options.map_value(|()| unreachable!()))
+    }
+
+    /// Installs a [`QueryPlugin`], which may register any number of observers or persisters.
+    pub fn use_plugin(&self, plugin: impl QueryPlugin) {
+        plugin.install(self);
+    }
+
     /// Removes the persister from the cache.
     pub fn remove_persister(&self) -> bool {
         self.cache.remove_persister().is_some()
@@ -733,6 +1483,1282 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn invalidate_query_type_ignores_other_types() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.update_query_data::<u32, u32>(0, |_| Some(1234));
+        client.update_query_data::<u32, String>(0, |_| Some("1234".into()));
+
+        let state_u32 = client.get_query_state::<u32, u32>(|| 0);
+        let state_string = client.get_query_state::<u32, String>(|| 0);
+
+        client.invalidate_query_type::<u32, String>();
+
+        assert!(matches!(
+            state_u32.get_untracked(),
+            Some(QueryState::Loaded { .. })
+        ));
+        assert!(matches!(
+            state_string.get_untracked(),
+            Some(QueryState::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn type_size_only_counts_same_type() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+
+        let u32_size = client.cache.type_size::<u32, u32>();
+        let string_size = client.cache.type_size::<u32, String>();
+
+        assert_eq!(1, u32_size.get_untracked());
+        assert_eq!(0, string_size.get_untracked());
+
+        client.set_query_data::<u32, String>(0, "1234".into());
+        client.set_query_data::<u32, String>(1, "5678".into());
+
+        assert_eq!(1, u32_size.get_untracked());
+        assert_eq!(2, string_size.get_untracked());
+        assert_eq!(3, client.size().get_untracked());
+
+        let id = client
+            .cache
+            .get_query::<u32, String>(&1)
+            .map(|q| q.get_id())
+            .expect("query should exist");
+        client.cache.evict_query(id);
+
+        assert_eq!(1, u32_size.get_untracked());
+        assert_eq!(1, string_size.get_untracked());
+    }
+
+    #[test]
+    fn query_handle_is_invalidated_by_eviction() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        let id = client
+            .cache
+            .get_query::<u32, u32>(&0)
+            .map(|q| q.get_id())
+            .expect("query should exist");
+
+        assert!(client.cache.get_query_by_id::<u32, u32>(id).is_some());
+        assert!(
+            client.cache.get_query_by_id::<u32, String>(id).is_none(),
+            "Handle should not resolve to a query of a different type"
+        );
+
+        assert!(client.cache.evict_query(id));
+        assert!(client.cache.get_query_by_id::<u32, u32>(id).is_none());
+        assert!(client.cache.get_query::<u32, u32>(&0).is_none());
+
+        // A re-created query receives a fresh handle.
+        client.set_query_data::<u32, u32>(0, 5678);
+        let new_id = client.cache.get_query::<u32, u32>(&0).map(|q| q.get_id());
+        assert_ne!(Some(id), new_id);
+    }
+
+    #[test]
+    fn cache_observer_can_reenter_client() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        struct Reentrant;
+        impl CacheObserver for Reentrant {
+            fn process_cache_event(&self, event: crate::cache_observer::CacheEvent) {
+                if let crate::cache_observer::CacheEvent::Created(query) = event {
+                    // Creating a query from within a cache event should not panic.
+                    if query.key.0 == "0" {
+                        use_query_client().set_query_data::<u32, u32>(1, 5678);
+                    }
+                }
+            }
+        }
+
+        client.register_cache_observer(Reentrant);
+        client.set_query_data::<u32, u32>(0, 1234);
+
+        assert_eq!(
+            Some(1234),
+            client
+                .peek_query_state::<u32, u32>(&0)
+                .and_then(|s| s.data().cloned())
+        );
+        assert_eq!(
+            Some(5678),
+            client
+                .peek_query_state::<u32, u32>(&1)
+                .and_then(|s| s.data().cloned())
+        );
+        assert_eq!(2, client.size().get_untracked());
+    }
+
+    #[test]
+    fn stale_signal_follows_observer_stale_time() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+
+        // Without observers there is no stale time.
+        assert!(!query.stale_signal().get_untracked());
+
+        let observe = |stale_time| {
+            QueryObserver::no_fetcher(
+                client.cache.clone(),
+                QueryOptions {
+                    stale_time,
+                    ..QueryOptions::default()
+                },
+                Some(query.clone()),
+            )
+        };
+
+        let fresh = observe(Some(std::time::Duration::from_secs(60)));
+        assert!(!query.stale_signal().get_untracked());
+        assert!(!query.is_stale());
+
+        // The minimum stale time across observers applies.
+        let stale = observe(Some(std::time::Duration::ZERO));
+        assert!(query.stale_signal().get_untracked());
+        assert!(query.is_stale());
+
+        stale.cleanup();
+        assert!(!query.stale_signal().get_untracked());
+        fresh.cleanup();
+    }
+
+    #[test]
+    fn revalidate_window_hides_expired_data() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+
+        let observe = |revalidate_window| {
+            QueryObserver::no_fetcher(
+                client.cache.clone(),
+                QueryOptions::default().set_revalidate_window(revalidate_window),
+                Some(query.clone()),
+            )
+        };
+
+        // Records whether the data was hidden while fetching.
+        let refetch = |value: u32| {
+            let loading = Rc::new(Cell::new(false));
+            let fetcher = {
+                let query = query.clone();
+                let loading = loading.clone();
+                move |_| {
+                    loading.set(matches!(query.get_state(), QueryState::Loading));
+                    async move { value }
+                }
+            };
+            futures::executor::block_on(crate::query::execute_query(query.clone(), fetcher));
+            loading.get()
+        };
+
+        let recent = observe(TimeSetting::secs(60));
+        assert!(!refetch(1));
+
+        let expired = observe(TimeSetting::millis(0));
+        // The maximum window across observers applies.
+        assert!(!refetch(2));
+
+        recent.cleanup();
+        assert!(refetch(3));
+        assert_eq!(Some(3), query.get_state().data().cloned());
+
+        expired.cleanup();
+    }
+
+    #[test]
+    fn validator_rejects_values() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let query = client.cache.get_or_create_query::<u32, String>(0);
+        let observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default().set_validator(|value: &String| {
+                if value.is_empty() {
+                    Err("empty".to_string())
+                } else {
+                    Ok(())
+                }
+            }),
+            Some(query.clone()),
+        );
+        let fetch = |value: &'static str| {
+            futures::executor::block_on(crate::query::execute_query(
+                query.clone(),
+                move |_| async move { value.to_string() },
+            ))
+        };
+        let error = || query.validation_error_signal().get_untracked();
+
+        // A rejected first load leaves the query without data, in the rejected state.
+        fetch("");
+        assert_eq!(QueryState::Rejected("empty".to_string()), query.get_state());
+        assert!(query.needs_execute());
+        assert_eq!(Some("empty".to_string()), error());
+
+        fetch("first");
+        assert_eq!(Some("first".to_string()), query.get_state().data().cloned());
+        assert_eq!(None, error());
+
+        // A rejected refetch keeps the previous data.
+        let updated_at = query.get_updated_at();
+        fetch("");
+        assert!(matches!(query.get_state(), QueryState::Loaded(_)));
+        assert_eq!(Some("first".to_string()), query.get_state().data().cloned());
+        assert_eq!(updated_at, query.get_updated_at());
+        assert_eq!(Some("empty".to_string()), error());
+
+        observer.cleanup();
+    }
+
+    #[test]
+    fn min_fetch_interval_delays_refetch() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+
+        let observe = |min_fetch_interval| {
+            QueryObserver::no_fetcher(
+                client.cache.clone(),
+                QueryOptions::default().set_min_fetch_interval(min_fetch_interval),
+                Some(query.clone()),
+            )
+        };
+        let refetch = |value: u32| {
+            futures::executor::block_on(crate::query::execute_query(
+                query.clone(),
+                move |_| async move { value },
+            ))
+        };
+
+        let short = observe(TimeSetting::millis(0));
+        refetch(1);
+        assert_eq!(None, query.fetch_delay());
+
+        // The maximum interval across observers applies.
+        let long = observe(TimeSetting::secs(60));
+        let delay = query.fetch_delay().expect("within min fetch interval");
+        assert!(delay > std::time::Duration::from_secs(59));
+
+        long.cleanup();
+        assert_eq!(None, query.fetch_delay());
+        short.cleanup();
+    }
+
+    #[test]
+    fn interval_requires_visible_observer() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+        let observe = || {
+            QueryObserver::no_fetcher(
+                client.cache.clone(),
+                Default::default(),
+                Some(query.clone()),
+            )
+        };
+
+        let visible = observe();
+        assert!(query.has_visible_observer());
+
+        let tab_visible = RwSignal::new(false);
+        crate::provide_query_visibility(tab_visible);
+        let hidden = observe();
+        assert!(query.has_visible_observer());
+
+        visible.cleanup();
+        assert!(!query.has_visible_observer());
+
+        tab_visible.set(true);
+        assert!(query.has_visible_observer());
+
+        hidden.cleanup();
+    }
+
+    #[test]
+    fn apply_invalidation_manifest() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1);
+        client.set_query_data::<u32, String>(0, "0".to_string());
+        client.set_query_data::<u32, u32>(1, 1);
+
+        let manifest = crate::InvalidationManifest::new().key(&0_u32).key(&0_u32);
+        assert_eq!(1, manifest.keys().len());
+
+        // Matches every value type with the same key.
+        assert_eq!(2, client.apply_invalidations(&manifest));
+        assert!(matches!(
+            client.get_query_state::<u32, String>(|| 0).get_untracked(),
+            Some(QueryState::Invalid(_))
+        ));
+        assert!(matches!(
+            client.get_query_state::<u32, u32>(|| 1).get_untracked(),
+            Some(QueryState::Loaded(_))
+        ));
+
+        assert_eq!(0, client.apply_invalidations(&Default::default()));
+    }
+
+    #[test]
+    fn optional_key_is_idle() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        // Fresh data, so nothing is fetched once the key is set.
+        client.set_query_data::<u32, u32>(0, 1234);
+
+        let key = RwSignal::new(None::<u32>);
+        let query = client.cache.get_query_signal::<u32, u32>(move || key.get());
+        let state = crate::use_query::register_observer_handle_cleanup(
+            |key: u32| async move { key },
+            query,
+            QueryOptions::default(),
+        );
+        assert!(matches!(state.get_untracked(), QueryState::Created));
+        assert_eq!(1, client.size().get_untracked());
+        assert_eq!(0, client.query_stats::<u32, u32>().get_untracked().mounts());
+
+        key.set(Some(0));
+        assert_eq!(Some(1234), state.get_untracked().data().copied());
+        assert_eq!(
+            1,
+            client.query_stats::<u32, u32>().get_untracked().cache_hits
+        );
+
+        // Back to idle.
+        key.set(None);
+        assert!(matches!(state.get_untracked(), QueryState::Created));
+        assert_eq!(1, client.size().get_untracked());
+    }
+
+    #[test]
+    fn size_by_type() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+        let sizes = client.size_by_type();
+        assert!(sizes.get_untracked().is_empty());
+
+        client.set_query_data::<u32, String>(0, "a".to_string());
+        client.set_query_data::<u32, bool>(0, true);
+        client.set_query_data::<u32, bool>(1, false);
+
+        let size = |key_type, value_type, count| TypeSize {
+            key_type,
+            value_type,
+            count,
+        };
+        let string = std::any::type_name::<String>();
+        assert_eq!(
+            vec![size("u32", "bool", 2), size("u32", string, 1)],
+            sizes.get_untracked()
+        );
+    }
+
+    #[test]
+    fn type_name_operations() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, String>(0, "a".to_string());
+        client.set_query_data::<String, bool>("b".to_string(), true);
+
+        assert_eq!(0, client.invalidate_queries_by_type_name("Unknown"));
+        assert_eq!(1, client.invalidate_queries_by_type_name("bool"));
+        assert!(matches!(
+            client.peek_query_state::<u32, String>(&0),
+            Some(QueryState::Loaded(_))
+        ));
+        assert!(matches!(
+            client.peek_query_state::<String, bool>(&"b".to_string()),
+            Some(QueryState::Invalid(_))
+        ));
+
+        // Matches both key and value types.
+        let string = std::any::type_name::<String>();
+        assert_eq!(2, client.evict_queries_by_type_name(string));
+        assert_eq!(0, client.size().get_untracked());
+    }
+
+    #[test]
+    fn mount_stats() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let query = client.cache.get_or_create_query::<u32, u32>(0);
+        let mount = || {
+            QueryObserver::with_fetcher(
+                client.cache.clone(),
+                |_: u32| async { 0 },
+                QueryOptions::default(),
+                Some(query.clone()),
+            )
+        };
+
+        let first = mount();
+        query.set_state(QueryState::Loading);
+        let second = mount();
+        query.set_state(QueryState::Loaded(QueryData::now(1)));
+        let third = mount();
+
+        // Observers without fetchers aren't mounts.
+        let no_fetcher = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            Default::default(),
+            Some(query.clone()),
+        );
+
+        let stats = client.query_stats::<u32, u32>().get_untracked();
+        assert_eq!((1, 1, 1), (stats.fetches, stats.deduped, stats.cache_hits));
+        assert_eq!(Some(2.0 / 3.0), stats.hit_rate());
+        assert_eq!("u32", stats.key_type);
+        assert_eq!(vec![stats], client.all_query_stats());
+
+        for observer in [first, second, third, no_fetcher] {
+            observer.cleanup();
+        }
+    }
+
+    #[test]
+    fn registered_presets() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let hour = std::time::Duration::from_secs(60 * 60);
+        client.register_preset("hourly", QueryOptions::default().set_stale_time(hour));
+
+        assert_eq!(Some(hour), QueryOptions::<u32>::preset("hourly").stale_time);
+        assert_eq!(
+            QueryOptions::<u32>::default().stale_time,
+            QueryOptions::<u32>::preset("missing").stale_time
+        );
+        assert_eq!(None, QueryOptions::<u32>::static_content().stale_time);
+    }
+
+    #[test]
+    #[cfg_attr(
+        feature = "state_assertions",
+        should_panic(expected = "Unmatched ObserverRemoved")
+    )]
+    fn observer_events_are_paired() {
+        use crate::cache_observer::{CacheEvent, ObserverKey};
+
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        client.register_cache_observer({
+            struct Recorder(Rc<RefCell<Vec<(bool, ObserverKey)>>>);
+            impl CacheObserver for Recorder {
+                fn process_cache_event(&self, event: CacheEvent) {
+                    match event {
+                        CacheEvent::ObserverAdded(added) => {
+                            self.0.borrow_mut().push((true, added.observer))
+                        }
+                        CacheEvent::ObserverRemoved(removed) => {
+                            self.0.borrow_mut().push((false, removed.observer))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Recorder(events.clone())
+        });
+
+        let query = client.cache.get_or_create_query::<u32, u32>(0);
+        let observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default(),
+            Some(query.clone()),
+        );
+        let id = observer.get_id();
+        observer.cleanup();
+
+        // An unmatched removal is dropped.
+        client
+            .cache
+            .notify::<u32, u32>(crate::query_cache::CacheNotification::ObserverRemoved(
+                0, id,
+            ));
+
+        assert_eq!(vec![(true, id), (false, id)], *RefCell::borrow(&events));
+    }
+
+    #[test]
+    fn observers_of_evicted_queries_are_removed() {
+        use crate::cache_observer::{CacheEvent, ObserverKey};
+
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        client.register_cache_observer({
+            struct Recorder(Rc<RefCell<Vec<(bool, ObserverKey)>>>);
+            impl CacheObserver for Recorder {
+                fn process_cache_event(&self, event: CacheEvent) {
+                    match event {
+                        CacheEvent::ObserverAdded(added) => {
+                            self.0.borrow_mut().push((true, added.observer))
+                        }
+                        CacheEvent::ObserverRemoved(removed) => {
+                            self.0.borrow_mut().push((false, removed.observer))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Recorder(events.clone())
+        });
+
+        let query = client.cache.get_or_create_query::<u32, u32>(0);
+        let observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default(),
+            Some(query.clone()),
+        );
+        let id = observer.get_id();
+
+        // Re-subscribing to the recreated query is a new addition, not a duplicate.
+        assert!(client.cache.evict_query(query.get_id()));
+        observer.update_query(Some(client.cache.get_or_create_query::<u32, u32>(0)));
+        client.clear();
+        observer.update_query(Some(client.cache.get_or_create_query::<u32, u32>(0)));
+        observer.cleanup();
+
+        assert_eq!(
+            vec![
+                (true, id),
+                (false, id),
+                (true, id),
+                (false, id),
+                (true, id),
+                (false, id)
+            ],
+            *RefCell::borrow(&events)
+        );
+    }
+
+    #[test]
+    fn times_changed_only_when_merged_times_change() {
+        use crate::cache_observer::{CacheEvent, QueryTypes, TimesChanged};
+        use std::time::Duration;
+
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        client.register_cache_observer({
+            struct Recorder(Rc<RefCell<Vec<TimesChanged>>>);
+            impl CacheObserver for Recorder {
+                fn process_cache_event(&self, event: CacheEvent) {
+                    if let CacheEvent::TimesChanged(times) = event {
+                        self.0.borrow_mut().push(times)
+                    }
+                }
+            }
+            Recorder(events.clone())
+        });
+        let times = |stale: u64, gc: u64| TimesChanged {
+            key: (&0).into(),
+            types: QueryTypes::of::<u32, u32>(),
+            stale_time: Some(Duration::from_secs(stale)),
+            gc_time: Some(Duration::from_secs(gc)),
+        };
+
+        let query = client.cache.get_or_create_query::<u32, u32>(0);
+        let observe = |stale: u64, gc: u64| {
+            QueryObserver::no_fetcher(
+                client.cache.clone(),
+                QueryOptions::default()
+                    .set_stale_time(Duration::from_secs(stale))
+                    .set_gc_time(Duration::from_secs(gc)),
+                Some(query.clone()),
+            )
+        };
+        let first = observe(10, 60);
+        // Same options, so nothing changes.
+        let same = observe(10, 60);
+        assert_eq!(vec![times(10, 60)], *RefCell::borrow(&events));
+
+        let shorter = observe(5, 120);
+        assert_eq!(times(5, 120), *RefCell::borrow(&events).last().unwrap());
+        same.cleanup();
+        assert_eq!(2, RefCell::borrow(&events).len());
+
+        // The gc time is kept once its observer is removed.
+        shorter.cleanup();
+        assert_eq!(times(10, 120), *RefCell::borrow(&events).last().unwrap());
+        first.cleanup();
+        assert_eq!(3, RefCell::borrow(&events).len());
+    }
+
+    #[test]
+    fn with_query_data_defers_writes() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+        client.set_query_data::<u32, u32>(0, 1);
+
+        let read = client.with_query_data::<u32, u32, _>(&0, |data| {
+            client.set_query_data::<u32, u32>(0, data + 1);
+            // Sees the earlier write.
+            assert!(client.update_query_data_mut::<u32, u32>(&0, |data| *data *= 10));
+            client.with_query_data::<u32, u32, _>(&0, |nested| *nested)
+        });
+
+        assert_eq!(Some(Some(1)), read);
+        assert_eq!(
+            Some(20),
+            client
+                .peek_query_state::<u32, u32>(&0)
+                .and_then(|state| state.data().copied())
+        );
+    }
+
+    #[test]
+    fn revalidate_stale_requires_observer() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        client.set_query_data::<u32, u32>(1, 1234);
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+
+        let observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions {
+                stale_time: Some(std::time::Duration::ZERO),
+                ..Default::default()
+            },
+            Some(query.clone()),
+        );
+        assert!(query.is_stale());
+
+        // Only the observed query is revalidated.
+        assert_eq!(1, client.revalidate_stale());
+
+        observer.cleanup();
+        assert_eq!(0, client.revalidate_stale());
+    }
+
+    #[test]
+    fn with_query_data_borrows() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        assert_eq!(
+            None,
+            client.with_query_data::<u32, Vec<u32>, _>(&0, Vec::len)
+        );
+        client.cache.get_or_create_query::<u32, Vec<u32>>(0);
+        assert_eq!(
+            None,
+            client.with_query_data::<u32, Vec<u32>, _>(&0, Vec::len)
+        );
+
+        client.set_query_data::<u32, Vec<u32>>(0, vec![1, 2, 3]);
+        assert_eq!(
+            Some(6),
+            client.with_query_data::<u32, Vec<u32>, _>(&0, |data| data.iter().sum::<u32>())
+        );
+    }
+
+    #[test]
+    fn revalidate_invalid_on_next_access() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+        let _observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default()
+                .set_stale_time(TimeSetting::INFINITY)
+                .set_invalidate_mode(InvalidateMode::OnNextAccess),
+            Some(query.clone()),
+        );
+        assert_eq!(0, client.revalidate_stale());
+
+        // Kept until accessed.
+        assert!(query.mark_invalid());
+        assert!(matches!(query.get_state(), QueryState::Invalid(_)));
+        assert_eq!(1, client.revalidate_stale());
+    }
+
+    #[test]
+    fn cancelled_execution_does_not_clobber_replacement() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+
+        let (cancelled, _cancelled) = query.new_execution().unwrap();
+        // Deduplicated while in flight.
+        assert!(query.new_execution().is_none());
+
+        assert!(query.cancel());
+        let (current, _current) = query.new_execution().unwrap();
+
+        // The cancelled execution settles after its replacement started.
+        query.finalize_execution(cancelled);
+        assert!(query.is_current_execution(current));
+        assert!(query.cancel());
+
+        // Forcing a refetch replaces an unsettled execution.
+        query.set_state(QueryState::Fetching(QueryData::now(1234)));
+        futures::executor::block_on(crate::query::execute_query_force(
+            query.clone(),
+            |_| async { 5678 },
+        ));
+        assert_eq!(
+            Some(5678),
+            client
+                .peek_query_state::<u32, u32>(&0)
+                .and_then(|s| s.data().cloned())
+        );
+        assert!(query.new_execution().is_some());
+    }
+
+    #[test]
+    fn refetch_tick_skips_in_flight_fetch() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+        let observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default(),
+            Some(query.clone()),
+        );
+
+        let (execution, _cancellation) = query.new_execution().unwrap();
+        assert!(query.is_executing());
+        assert!(!query.refetch_tick());
+
+        query.finalize_execution(execution);
+        assert!(!query.is_executing());
+        assert!(query.refetch_tick());
+
+        observer.cleanup();
+    }
+
+    #[test]
+    fn refetch_tick_requires_lease() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+        let observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default(),
+            Some(query.clone()),
+        );
+
+        // Another tab fetches and shares the data.
+        client.cache.refetch_lease().set_held(false);
+        assert!(!query.refetch_tick());
+        let shared = |value: &str, updated_at: Instant| crate::query_persister::PersistQueryData {
+            value: value.to_string(),
+            updated_at: updated_at.as_unix_millis(),
+        };
+        let newer = Instant(Instant::now().0 + std::time::Duration::from_secs(1));
+        let key = crate::cache_observer::QueryCacheKey::from(&0u32);
+        assert_eq!(1, client.cache.restore_by_key(&key, shared("5678", newer)));
+        assert_eq!(
+            Some(5678),
+            client
+                .peek_query_state::<u32, u32>(&0)
+                .and_then(|s| s.data().copied())
+        );
+        // Older data is ignored.
+        assert_eq!(
+            0,
+            client
+                .cache
+                .restore_by_key(&key, shared("1", Instant(std::time::Duration::ZERO)))
+        );
+
+        client.cache.refetch_lease().set_held(true);
+        assert!(query.refetch_tick());
+
+        observer.cleanup();
+    }
+
+    #[test]
+    fn refetch_hint_from_data() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 30);
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+        assert_eq!(None, query.hinted_refetch_delay());
+
+        let options = QueryOptions::default().set_refetch_hint(|v: &u32| {
+            (*v > 0).then(|| std::time::Duration::from_secs(*v as u64))
+        });
+        let observer =
+            QueryObserver::no_fetcher(client.cache.clone(), options, Some(query.clone()));
+        assert_eq!(
+            Some(std::time::Duration::from_secs(30)),
+            query.hinted_refetch_delay()
+        );
+
+        client.set_query_data::<u32, u32>(0, 0);
+        assert_eq!(None, query.hinted_refetch_delay());
+
+        observer.cleanup();
+    }
+
+    #[test]
+    fn transfer_query_between_clients() {
+        let runtime = create_runtime();
+
+        let source = QueryClientBuilder::new().provide();
+        let target = run_as_child(|| QueryClientBuilder::new().provide());
+
+        assert_eq!(None, source.export_query::<u32, String>(&0));
+
+        source.set_query_data::<u32, String>(0, "zero".to_string());
+        let query = source.cache.get_query::<u32, String>(&0).unwrap();
+        assert!(query.mark_invalid());
+        let export = source.export_query::<u32, String>(&0).unwrap();
+
+        assert!(matches!(
+            target.import_query::<u32, u32>(0, export.clone()),
+            Err(ImportQueryError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            target.import_query::<u32, String>(1, export.clone()),
+            Err(ImportQueryError::KeyMismatch { .. })
+        ));
+        assert_eq!(0, target.size().get_untracked());
+
+        target.import_query::<u32, String>(0, export).unwrap();
+        let imported = target.cache.get_query::<u32, String>(&0).unwrap();
+        assert_eq!(query.get_state(), imported.get_state());
+        assert!(matches!(imported.get_state(), QueryState::Invalid(_)));
+
+        // The child owner must be disposed with the runtime.
+        runtime.dispose();
+    }
+
+    #[cfg(feature = "state_assertions")]
+    #[test]
+    fn records_state_transitions() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        client.set_query_data::<u32, u32>(0, 1);
+        client.invalidate_query::<u32, u32>(0);
+
+        let transitions = client
+            .state_transitions()
+            .into_iter()
+            .map(|t| (t.from, t.to, t.allowed))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (QueryStateKind::Created, QueryStateKind::Loaded, true),
+                (QueryStateKind::Loaded, QueryStateKind::Invalid, true),
+            ],
+            transitions
+        );
+    }
+
+    #[cfg(feature = "ui_state")]
+    #[test]
+    fn ui_state_lives_with_query() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let ui_state = client.query_ui_state::<u32, String>(0);
+        assert_eq!(serde_json::Value::Null, ui_state.get_untracked());
+
+        ui_state.set(serde_json::json!({ "scroll": 120 }));
+        assert_eq!(
+            serde_json::json!({ "scroll": 120 }),
+            client.query_ui_state::<u32, String>(0).get_untracked()
+        );
+        assert_eq!(
+            serde_json::Value::Null,
+            client.query_ui_state::<u32, String>(1).get_untracked()
+        );
+
+        let id = client.cache.get_query::<u32, String>(&0).unwrap().get_id();
+        assert!(client.cache.evict_query(id));
+        assert_eq!(None, ui_state.try_get_untracked());
+        assert_eq!(
+            serde_json::Value::Null,
+            client.query_ui_state::<u32, String>(0).get_untracked()
+        );
+    }
+
+    #[test]
+    fn tracked_update_skips_noop() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        assert!(!client.update_query_data_tracked::<u32, Vec<u32>>(0, |v| v.push(1), true));
+
+        let data = QueryData {
+            data: vec![1],
+            updated_at: Instant(std::time::Duration::ZERO),
+        };
+        let query = client.cache.get_or_create_query::<u32, Vec<u32>>(0);
+        query.set_state(QueryState::Loaded(data.clone()));
+        let observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default(),
+            Some(query.clone()),
+        );
+        let notified = Rc::new(Cell::new(0));
+        let listener = observer.add_listener({
+            let notified = notified.clone();
+            move |_| notified.set(notified.get() + 1)
+        });
+
+        // Only reads.
+        assert!(!client.update_query_data_tracked::<u32, Vec<u32>>(
+            0,
+            |v| assert_eq!(1, v.len()),
+            true
+        ));
+        assert_eq!(0, notified.get());
+        assert_eq!(QueryState::Loaded(data.clone()), query.get_state());
+
+        // Changes keep the timestamp.
+        assert!(client.update_query_data_tracked::<u32, Vec<u32>>(0, |v| v.push(2), false));
+        assert_eq!(1, notified.get());
+        assert_eq!(Some(data.updated_at), query.get_updated_at());
+
+        assert!(client.update_query_data_tracked::<u32, Vec<u32>>(0, |v| v.push(3), true));
+        assert_eq!(2, notified.get());
+        assert!(query.get_updated_at() > Some(data.updated_at));
+        assert_eq!(Some(vec![1, 2, 3]), query.with_state(|s| s.data().cloned()));
+
+        assert!(observer.remove_listener(listener));
+        observer.cleanup();
+    }
+
+    #[test]
+    fn builder_registers_in_order() {
+        let _ = create_runtime();
+
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        struct Created(Rc<RefCell<Vec<&'static str>>>);
+        impl CacheObserver for Created {
+            fn process_cache_event(&self, event: crate::cache_observer::CacheEvent) {
+                if let crate::cache_observer::CacheEvent::Created(_) = event {
+                    self.0.borrow_mut().push("created");
+                }
+            }
+        }
+
+        let client = QueryClientBuilder::new()
+            .options(DefaultQueryOptions {
+                stale_time: Some(std::time::Duration::from_secs(1)),
+                ..DefaultQueryOptions::default()
+            })
+            .plugin({
+                let order = order.clone();
+                move |_: &QueryClient| order.borrow_mut().push("first")
+            })
+            .observer(Created(order.clone()))
+            .plugin({
+                let order = order.clone();
+                move |client: &QueryClient| {
+                    // The client is provided before plugins are installed.
+                    assert!(use_context::<QueryClient>().is_some());
+                    client.set_query_data::<u32, u32>(0, 1234);
+                    order.borrow_mut().push("second")
+                }
+            })
+            .provide();
+
+        assert_eq!(vec!["first", "created", "second"], *RefCell::borrow(&order));
+        assert_eq!(
+            Some(std::time::Duration::from_secs(1)),
+            client.default_options.stale_time
+        );
+    }
+
+    #[test]
+    fn restore_never_downgrades_newer_data() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let restored = Rc::new(RefCell::new(Vec::new()));
+        client.register_cache_observer({
+            struct Restored(Rc<RefCell<Vec<String>>>);
+            impl CacheObserver for Restored {
+                fn process_cache_event(&self, event: crate::cache_observer::CacheEvent) {
+                    if let crate::cache_observer::CacheEvent::RestoredFromPersister(query) = event {
+                        self.0.borrow_mut().push(query.key.0);
+                    }
+                }
+            }
+            Restored(restored.clone())
+        });
+
+        let at = |secs| QueryData {
+            data: secs as u32,
+            updated_at: Instant(std::time::Duration::from_secs(secs)),
+        };
+        let query = client.cache.get_or_create_query::<u32, u32>(0);
+
+        // No data yet.
+        assert!(query.restore(at(10)));
+        assert_eq!(QueryState::Loaded(at(10)), query.get_state());
+
+        // Older, or as old, as the current data.
+        assert!(!query.restore(at(5)));
+        assert!(!query.restore(at(10)));
+        assert_eq!(QueryState::Loaded(at(10)), query.get_state());
+
+        // Newer data keeps the state.
+        query.mark_invalid();
+        assert!(query.restore(at(20)));
+        assert_eq!(QueryState::Invalid(at(20)), query.get_state());
+
+        // The first fetch is in flight.
+        let loading = client.cache.get_or_create_query::<u32, u32>(1);
+        loading.set_state(QueryState::Loading);
+        assert!(loading.restore(at(10)));
+        assert_eq!(QueryState::Fetching(at(10)), loading.get_state());
+
+        assert_eq!(vec!["0", "0", "1"], *RefCell::borrow(&restored));
+    }
+
+    #[cfg(feature = "migrate")]
+    #[test]
+    fn restore_migrates_persisted_data() {
+        use crate::query_persister::PersistQueryData;
+
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let persisted = |value: &str| PersistQueryData {
+            value: value.to_string(),
+            updated_at: 10_000,
+        };
+        let at = |data| QueryData {
+            data,
+            updated_at: Instant(std::time::Duration::from_secs(10)),
+        };
+
+        // Without a migration, undeserializable data is discarded.
+        let query = client.cache.get_or_create_query::<u32, u32>(0);
+        assert!(!query.restore_persisted(persisted(r#"{"count":3}"#)));
+        assert_eq!(QueryState::Created, query.get_state());
+
+        let _observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default()
+                .set_migration(|old| old.get("count")?.as_u64()?.try_into().ok()),
+            Some(query.clone()),
+        );
+
+        // The migration only applies to data that doesn't deserialize.
+        let current = client.cache.get_or_create_query::<u32, u32>(1);
+        let _current_observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default().set_migration(|_| Some(0)),
+            Some(current.clone()),
+        );
+        assert!(current.restore_persisted(persisted("7")));
+        assert_eq!(QueryState::Loaded(at(7)), current.get_state());
+
+        assert!(!query.restore_persisted(persisted(r#"{"total":3}"#)));
+        assert!(query.restore_persisted(persisted(r#"{"count":3}"#)));
+        assert_eq!(QueryState::Loaded(at(3)), query.get_state());
+    }
+
+    #[test]
+    fn await_query_settled() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        let settled = |key: u32| {
+            futures::executor::block_on(
+                client.await_query_settled::<u32, u32>(key, TimeSetting::INFINITY),
+            )
+        };
+        assert_eq!(None, settled(0));
+
+        client.set_query_data::<u32, u32>(0, 1234);
+        assert_eq!(Some(1234), settled(0).and_then(|s| s.data().cloned()));
+
+        let query = client.cache.get_query::<u32, u32>(&0).unwrap();
+        query.set_state(QueryState::Fetching(QueryData::now(1234)));
+
+        // Resolves once the fetch completes.
+        let (state, _) = futures::executor::block_on(futures::future::join(
+            client.await_query_settled::<u32, u32>(0, TimeSetting::INFINITY),
+            async { query.set_state(QueryState::Loaded(QueryData::now(5678))) },
+        ));
+        assert_eq!(Some(5678), state.and_then(|s| s.data().cloned()));
+    }
+
+    #[test]
+    fn shared_scopes_are_registered_once() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        async fn fetch(key: u32) -> u32 {
+            key
+        }
+
+        let scope = |stale_time: u64| {
+            create_shared_query(
+                "numbers",
+                fetch,
+                QueryOptions::default().set_stale_time(TimeSetting::secs(stale_time)),
+            )
+        };
+
+        let first = scope(1);
+        let second = scope(2);
+        assert_eq!(
+            first.get_options().stale_time,
+            second.get_options().stale_time
+        );
+
+        // Different value types don't collide.
+        let strings = client.shared_scope("numbers", || {
+            create_query(
+                |key: u32| async move { key.to_string() },
+                QueryOptions::default(),
+            )
+        });
+        assert_ne!(
+            first.get_options().stale_time,
+            strings.get_options().stale_time
+        );
+    }
+
+    #[test]
+    fn plugin_registers_observers() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+
+        struct CountCreated(Rc<Cell<usize>>);
+        impl CacheObserver for CountCreated {
+            fn process_cache_event(&self, event: crate::cache_observer::CacheEvent) {
+                if let crate::cache_observer::CacheEvent::Created(_) = event {
+                    self.0.set(self.0.get() + 1);
+                }
+            }
+        }
+
+        client.set_query_data::<u32, u32>(0, 1234);
+
+        let created = Rc::new(Cell::new(0));
+        client.use_plugin({
+            let created = created.clone();
+            move |client: &QueryClient| {
+                client.register_cache_observer(CountCreated(created.clone()));
+            }
+        });
+
+        // Existing queries are replayed to the new observer.
+        assert_eq!(1, created.get());
+
+        client.set_query_data::<u32, u32>(1, 5678);
+        assert_eq!(2, created.get());
+    }
+
     #[test]
     fn update_query_data_mut() {
         let _ = create_runtime();
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use leptos::{provide_context, Owner};
+
+use crate::{
+    cache_observer::CacheObserver, query_persister::QueryPersister, ColdStart, DefaultQueryOptions,
+    FetchMiddleware, QueryClient, QueryOptions, QueryPlugin, RefetchOnWake, WakeAction,
+};
+
+/// Builds and provides a [`QueryClient`], registering its extensions in one place.
+///
+/// Extensions are registered in the order they are added, once the client has been provided.
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::{cache_observer::OptionConflictLogger, *};
+/// use std::time::Duration;
+///
+/// #[component]
+/// fn App() -> impl IntoView {
+///     QueryClientBuilder::new()
+///         .options(DefaultQueryOptions {
+///             stale_time: Some(Duration::from_secs(30)),
+///             ..DefaultQueryOptions::default()
+///         })
+///         .observer(OptionConflictLogger::default())
+///         .provide();
+///
+///     // Rest of App...
+/// }
+/// ```
+#[derive(Default)]
+pub struct QueryClientBuilder {
+    options: DefaultQueryOptions,
+    #[allow(clippy::type_complexity)]
+    registrations: Vec<Box<dyn FnOnce(&QueryClient)>>,
+}
+
+impl QueryClientBuilder {
+    /// Creates a builder with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the default options for all queries under the client.
+    pub fn options(self, options: DefaultQueryOptions) -> Self {
+        QueryClientBuilder { options, ..self }
+    }
+
+    /// Adds a persister to the cache. See [`QueryClient::add_persister`].
+    pub fn persister(self, persister: impl QueryPersister + Clone + 'static) -> Self {
+        self.register(move |client| client.add_persister(persister))
+    }
+
+    /// Registers a cache observer. See [`QueryClient::register_cache_observer`].
+    pub fn observer(self, observer: impl CacheObserver + 'static) -> Self {
+        self.register(move |client| client.register_cache_observer(observer))
+    }
+
+    /// Registers a fetch middleware. See [`QueryClient::register_fetch_middleware`].
+    pub fn middleware(self, middleware: impl FetchMiddleware + 'static) -> Self {
+        self.register(move |client| client.register_fetch_middleware(middleware))
+    }
+
+    /// Registers an option preset. See [`QueryClient::register_preset`].
+    pub fn preset(self, name: &'static str, options: QueryOptions<()>) -> Self {
+        self.register(move |client| client.register_preset(name, options))
+    }
+
+    /// Installs a plugin. See [`QueryClient::use_plugin`].
+    pub fn plugin(self, plugin: impl QueryPlugin + 'static) -> Self {
+        self.register(move |client| client.use_plugin(plugin))
+    }
+
+    /// Invalidates restored data if the app was closed for longer than `window`. Add after the persister. See [`ColdStart`].
+    pub fn cold_start_window(self, window: Duration) -> Self {
+        self.plugin(ColdStart::new(window))
+    }
+
+    /// Refreshes the cache once the page wakes from a suspend longer than `threshold`. See [`RefetchOnWake`].
+    pub fn refetch_on_wake(self, threshold: Duration, action: WakeAction) -> Self {
+        self.plugin(RefetchOnWake::new(threshold).action(action))
+    }
+
+    fn register(mut self, registration: impl FnOnce(&QueryClient) + 'static) -> Self {
+        self.registrations.push(Box::new(registration));
+        self
+    }
+
+    /// Provides the client to the current scope, then registers the extensions.
+    pub fn provide(self) -> QueryClient {
+        let owner = Owner::current().expect("Owner to be present");
+        let client = QueryClient::new(owner, self.options);
+        provide_context(client.clone());
+
+        for registration in self.registrations {
+            registration(&client);
+        }
+        client
+    }
+}
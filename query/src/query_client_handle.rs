@@ -0,0 +1,101 @@
+use leptos::ScopedFuture;
+
+use crate::{QueryClient, QueryKey, QueryScope, QueryValue};
+
+/// A lightweight handle to a [`QueryClient`], passed to fetchers created with [`create_query_with_client`](crate::create_query_with_client).
+///
+/// Unlike [`use_query_client`](crate::use_query_client), it can be used after an `.await`, as it doesn't depend on the reactive context.
+#[derive(Clone)]
+pub struct QueryClientHandle {
+    client: QueryClient,
+}
+
+impl QueryClientHandle {
+    pub(crate) fn new(client: QueryClient) -> Self {
+        Self { client }
+    }
+
+    /// The client. Its methods may require the reactive context, prefer the methods of the handle inside of async blocks.
+    pub fn client(&self) -> &QueryClient {
+        &self.client
+    }
+
+    /// Returns the data of a query, from the cache if it is fresh, and otherwise fetches it.
+    ///
+    /// Useful for dependent lookups inside of a fetcher.
+    /// On the server, missing data is fetched without being cached.
+    /// Returns `None` if the fetch was cancelled, or if the client was disposed.
+    pub async fn ensure_query_data<K, V>(&self, scope: &QueryScope<K, V>, key: K) -> Option<V>
+    where
+        K: QueryKey + 'static,
+        V: QueryValue + 'static,
+    {
+        let key = scope.normalize_key(key);
+        if self.client.has_fresh_data::<K, V>(&key) {
+            if let Some(data) = self
+                .client
+                .peek_query_state::<K, V>(&key)
+                .and_then(|state| state.data().cloned())
+            {
+                return Some(data);
+            }
+        }
+
+        cfg_if::cfg_if! {
+            if #[cfg(any(feature = "csr", feature = "hydrate"))] {
+                // Fetching relies on the client being provided, which isn't guaranteed after an await.
+                let (client, fetcher) = (self.client.clone(), scope.make_fetcher());
+                let priority = scope.get_options().priority;
+                let fetch = async move {
+                    client.fetch_query_with_priority(key, fetcher, priority).await
+                };
+                let state = ScopedFuture::new(self.client.cache.owner(), fetch).await?;
+                state.data().cloned()
+            } else {
+                let fetch = scope.make_fetcher()(key);
+                ScopedFuture::new(self.client.cache.owner(), fetch).await
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(any(feature = "csr", feature = "hydrate"))))]
+mod tests {
+    use super::*;
+    use crate::*;
+    use futures::FutureExt;
+    use leptos::*;
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn ensure_query_data_uses_fresh_data() {
+        let _ = create_runtime();
+        provide_query_client();
+        let client = QueryClientHandle::new(use_query_client());
+
+        let fetches = Rc::new(Cell::new(0));
+        let scope = create_query(
+            {
+                let fetches = fetches.clone();
+                move |key: u32| {
+                    fetches.set(fetches.get() + 1);
+                    async move { key * 10 }
+                }
+            },
+            QueryOptions::default(),
+        );
+
+        use_query_client().set_query_data::<u32, u32>(1, 1);
+        assert_eq!(
+            Some(Some(1)),
+            client.ensure_query_data(&scope, 1).now_or_never()
+        );
+        assert_eq!(0, fetches.get());
+
+        assert_eq!(
+            Some(Some(20)),
+            client.ensure_query_data(&scope, 2).now_or_never()
+        );
+        assert_eq!(1, fetches.get());
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{cache_observer::QueryCacheKey, QueryData, QueryState};
+
+/// A query exported from one [`QueryClient`](crate::QueryClient), to be imported into another.
+///
+/// Created with [`QueryClient::export_query`](crate::QueryClient::export_query) and applied with [`QueryClient::import_query`](crate::QueryClient::import_query).
+/// Useful for micro-frontends, or when detaching part of an app into another window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializedQueryExport {
+    /// Serialized query key.
+    pub key: QueryCacheKey,
+    /// The name of the key type, from [`std::any::type_name`].
+    pub key_type: String,
+    /// The name of the value type, from [`std::any::type_name`].
+    pub value_type: String,
+    /// Serialized query state, including when the data was last updated and whether it was invalidated.
+    /// In flight fetches are not exported, so the state is never `Loading` or `Fetching`.
+    pub state: QueryState<String>,
+}
+
+impl SerializedQueryExport {
+    pub(crate) fn new<K, V>(key: &K, state: &QueryState<V>) -> Self
+    where
+        K: crate::QueryKey + 'static,
+        V: crate::QueryValue + 'static,
+    {
+        let state = match state {
+            // A rejection is specific to the validators of this client, so the importer fetches again.
+            QueryState::Created | QueryState::Loading | QueryState::Rejected(_) => {
+                QueryState::Created
+            }
+            QueryState::Fetching(data) | QueryState::Loaded(data) => {
+                QueryState::Loaded(serialize(data))
+            }
+            QueryState::Invalid(data) => QueryState::Invalid(serialize(data)),
+        };
+        SerializedQueryExport {
+            key: key.into(),
+            key_type: std::any::type_name::<K>().to_string(),
+            value_type: std::any::type_name::<V>().to_string(),
+            state,
+        }
+    }
+
+    /// Ensures the export belongs to the given key, and deserializes its state.
+    pub(crate) fn into_state<K, V>(self, key: &K) -> Result<QueryState<V>, ImportQueryError>
+    where
+        K: crate::QueryKey + 'static,
+        V: crate::QueryValue + 'static,
+    {
+        let expected = (std::any::type_name::<K>(), std::any::type_name::<V>());
+        if (self.key_type.as_str(), self.value_type.as_str()) != expected {
+            return Err(ImportQueryError::TypeMismatch {
+                expected: format!("<{}, {}>", expected.0, expected.1),
+                found: format!("<{}, {}>", self.key_type, self.value_type),
+            });
+        }
+        let key: QueryCacheKey = key.into();
+        if self.key != key {
+            return Err(ImportQueryError::KeyMismatch {
+                expected: key,
+                found: self.key,
+            });
+        }
+
+        let deserialize = |data: QueryData<String>| -> Result<QueryData<V>, ImportQueryError> {
+            let value = leptos::Serializable::de(data.data.as_str())
+                .map_err(|e| ImportQueryError::Deserialize(e.to_string()))?;
+            Ok(QueryData {
+                data: value,
+                updated_at: data.updated_at,
+            })
+        };
+        match self.state {
+            QueryState::Created | QueryState::Loading | QueryState::Rejected(_) => {
+                Ok(QueryState::Created)
+            }
+            QueryState::Fetching(data) | QueryState::Loaded(data) => {
+                deserialize(data).map(QueryState::Loaded)
+            }
+            QueryState::Invalid(data) => deserialize(data).map(QueryState::Invalid),
+        }
+    }
+}
+
+fn serialize<V: crate::QueryValue>(data: &QueryData<V>) -> QueryData<String> {
+    QueryData {
+        data: leptos::Serializable::ser(&data.data).expect("Serialize Query State"),
+        updated_at: data.updated_at,
+    }
+}
+
+/// Error when importing a [`SerializedQueryExport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportQueryError {
+    /// The export was created for different key or value types.
+    TypeMismatch {
+        /// The types of the import.
+        expected: String,
+        /// The types of the export.
+        found: String,
+    },
+    /// The export was created for a different key.
+    KeyMismatch {
+        /// The key of the import.
+        expected: QueryCacheKey,
+        /// The key of the export.
+        found: QueryCacheKey,
+    },
+    /// The exported data could not be deserialized.
+    Deserialize(String),
+}
+
+impl std::fmt::Display for ImportQueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportQueryError::TypeMismatch { expected, found } => {
+                write!(f, "Query export has types {found}, expected {expected}.")
+            }
+            ImportQueryError::KeyMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Query export has key {}, expected {}.",
+                    found.0, expected.0
+                )
+            }
+            ImportQueryError::Deserialize(e) => {
+                write!(f, "Error deserializing query export: {e}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportQueryError {}
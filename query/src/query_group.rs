@@ -0,0 +1,328 @@
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, rc::Rc};
+
+use indexmap::IndexSet;
+use leptos::*;
+
+use crate::{query_cache::QueryId, use_query_client, util::try_borrow_mut};
+
+/// A named set of queries that are managed together, e.g. all queries of a feature area.
+///
+/// Queries join a group through [`QueryOptions::set_group`](crate::QueryOptions::set_group), once they are first observed.
+/// A query stays in the group until it is evicted, and may be in several groups.
+/// Unlike hierarchical keys, groups can span queries of different key and value types.
+///
+/// Groups with the same name are the same group, so a group can be recreated wherever it is needed.
+/// Operations apply to the [`QueryClient`](crate::QueryClient) in context.
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// fn billing() -> QueryGroup {
+///     QueryGroup::new("billing")
+/// }
+///
+/// fn invoices_query() -> QueryScope<(), Vec<String>> {
+///     create_query(
+///         |_| async move { vec![] },
+///         QueryOptions::default().set_group(billing()),
+///     )
+/// }
+///
+/// #[component]
+/// fn BillingPanel() -> impl IntoView {
+///     let invoices = invoices_query().use_query(|| ());
+///     let status = billing().status();
+///
+///     view! {
+///         <Show when=move || status.get().is_fetching()>
+///             <p>"Syncing billing..."</p>
+///         </Show>
+///         <button on:click=move |_| { billing().invalidate_all(); }>"Refresh"</button>
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryGroup {
+    name: Cow<'static, str>,
+}
+
+impl QueryGroup {
+    /// Refers to the group with the given name.
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self { name: name.into() }
+    }
+
+    /// The name of the group.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Invalidates every query in the group. Active queries are refetched according to their [`InvalidateMode`](crate::InvalidateMode).
+    /// Returns the number of queries invalidated.
+    pub fn invalidate_all(&self) -> usize {
+        use_query_client().cache.invalidate_group(self)
+    }
+
+    /// Cancels every in-flight fetch in the group. Returns the number of fetches cancelled.
+    pub fn cancel_all(&self) -> usize {
+        use_query_client().cache.cancel_group(self)
+    }
+
+    /// Stops queries in the group from fetching, until [`resume_all`](Self::resume_all) is called.
+    ///
+    /// In-flight fetches complete. Mounts, invalidations and refetch intervals don't fetch while paused.
+    /// Explicit fetches, such as [`QueryClient::fetch_query`](crate::QueryClient::fetch_query), are not paused.
+    /// A query in several groups is paused while any of them is.
+    pub fn pause_all(&self) {
+        use_query_client().cache.set_group_paused(self, true);
+    }
+
+    /// Resumes fetching, and fetches the observed queries that missed a fetch while paused, or are stale.
+    /// Returns the number of queries fetched.
+    pub fn resume_all(&self) -> usize {
+        use_query_client().cache.set_group_paused(self, false)
+    }
+
+    /// If the group is paused.
+    pub fn is_paused(&self) -> bool {
+        use_query_client().cache.is_group_paused(self)
+    }
+
+    /// Removes every query in the group from the cache. Returns the number of queries removed.
+    pub fn clear_all(&self) -> usize {
+        use_query_client().cache.evict_group(self)
+    }
+
+    /// Reactive status of the group, which updates whenever a query in the group changes state, or joins or leaves it.
+    pub fn status(&self) -> Signal<QueryGroupStatus> {
+        use_query_client().cache.group_status(self)
+    }
+}
+
+/// Aggregate status of the queries in a [`QueryGroup`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryGroupStatus {
+    /// Number of queries in the group.
+    pub queries: usize,
+    /// Number of queries that are loading or refetching.
+    pub fetching: usize,
+    /// Number of queries that have data, including those that are refetching or invalid.
+    pub loaded: usize,
+    /// Number of queries that are invalid, and not yet refetched.
+    pub invalid: usize,
+    /// If the group is paused.
+    pub paused: bool,
+}
+
+impl QueryGroupStatus {
+    /// If any query in the group is loading or refetching.
+    pub fn is_fetching(&self) -> bool {
+        self.fetching > 0
+    }
+}
+
+// Members and pause state of every group, by group.
+#[derive(Clone)]
+pub(crate) struct QueryGroups {
+    owner: Owner,
+    groups: Rc<RefCell<HashMap<QueryGroup, GroupEntry>>>,
+}
+
+struct GroupEntry {
+    members: IndexSet<QueryId>,
+    paused: bool,
+    // Notified whenever a member changes state, or the group changes.
+    changed: Trigger,
+}
+
+impl QueryGroups {
+    pub fn new(owner: Owner) -> Self {
+        Self {
+            owner,
+            groups: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    pub fn join(&self, group: &QueryGroup, query: QueryId) {
+        let joined = self.with_entry(group, |entry| {
+            entry.members.insert(query).then_some(entry.changed)
+        });
+        if let Some(joined) = joined.flatten() {
+            joined.notify();
+        }
+    }
+
+    /// Removes an evicted query from all of its groups.
+    pub fn leave(&self, query: QueryId) {
+        for changed in self.with_member_groups(query, |entry| {
+            entry.members.shift_remove(&query);
+        }) {
+            changed.notify();
+        }
+    }
+
+    /// Empties every group, once the cache is cleared.
+    pub fn clear_members(&self) {
+        let changed = match try_borrow_mut(&self.groups, "query groups") {
+            Ok(mut groups) => groups
+                .values_mut()
+                .filter(|entry| !entry.members.is_empty())
+                .map(|entry| {
+                    entry.members.clear();
+                    entry.changed
+                })
+                .collect(),
+            Err(e) => {
+                logging::debug_warn!("{e}");
+                Vec::new()
+            }
+        };
+        for changed in changed {
+            changed.notify();
+        }
+    }
+
+    pub fn members(&self, group: &QueryGroup) -> Vec<QueryId> {
+        self.with_entry(group, |entry| entry.members.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns whether the pause state changed.
+    pub fn set_paused(&self, group: &QueryGroup, paused: bool) -> bool {
+        let changed = self.with_entry(group, |entry| {
+            let changed = entry.paused != paused;
+            entry.paused = paused;
+            changed.then_some(entry.changed)
+        });
+        match changed.flatten() {
+            Some(changed) => {
+                changed.notify();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_paused(&self, group: &QueryGroup) -> bool {
+        self.with_entry(group, |entry| entry.paused)
+            .unwrap_or_default()
+    }
+
+    /// If any group of the query is paused.
+    pub fn is_member_paused(&self, query: QueryId) -> bool {
+        self.groups
+            .try_borrow()
+            .map(|groups| {
+                groups
+                    .values()
+                    .any(|entry| entry.paused && entry.members.contains(&query))
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn notify_member(&self, query: QueryId) {
+        for changed in self.with_member_groups(query, |_| ()) {
+            changed.notify();
+        }
+    }
+
+    pub fn changed(&self, group: &QueryGroup) -> Option<Trigger> {
+        self.with_entry(group, |entry| entry.changed)
+    }
+
+    // Triggers are notified after the borrow is released, as effects may call back into the groups.
+    fn with_entry<R>(&self, group: &QueryGroup, f: impl FnOnce(&mut GroupEntry) -> R) -> Option<R> {
+        match try_borrow_mut(&self.groups, "query groups") {
+            Ok(mut groups) => {
+                let entry = groups.entry(group.clone()).or_insert_with(|| GroupEntry {
+                    members: IndexSet::new(),
+                    paused: false,
+                    changed: with_owner(self.owner, create_trigger),
+                });
+                Some(f(entry))
+            }
+            Err(e) => {
+                logging::debug_warn!("{e}");
+                None
+            }
+        }
+    }
+
+    fn with_member_groups(&self, query: QueryId, f: impl Fn(&mut GroupEntry)) -> Vec<Trigger> {
+        match try_borrow_mut(&self.groups, "query groups") {
+            Ok(mut groups) => groups
+                .values_mut()
+                .filter(|entry| entry.members.contains(&query))
+                .map(|entry| {
+                    f(entry);
+                    entry.changed
+                })
+                .collect(),
+            Err(e) => {
+                logging::debug_warn!("{e}");
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(any(feature = "csr", feature = "hydrate"))))]
+mod tests {
+    use leptos::*;
+
+    use super::*;
+    use crate::{provide_query_client, query_observer::QueryObserver, QueryOptions};
+
+    #[test]
+    fn group_lifecycle() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+        let group = QueryGroup::new("billing");
+        let status = group.status();
+        assert_eq!(QueryGroupStatus::default(), status.get_untracked());
+
+        // Queries of different types join once observed.
+        client.set_query_data::<u32, u32>(0, 1);
+        client.set_query_data::<String, String>("a".to_string(), "a".to_string());
+        client.set_query_data::<u32, u32>(1, 1);
+        let observe = |query| {
+            QueryObserver::no_fetcher(
+                client.cache.clone(),
+                QueryOptions::default().set_group(QueryGroup::new("billing")),
+                Some(query),
+            )
+        };
+        let _numbers = observe(client.cache.get_query::<u32, u32>(&0).unwrap());
+        let _strings = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default().set_group(group.clone()),
+            client.cache.get_query::<String, String>(&"a".to_string()),
+        );
+        assert_eq!(2, status.get_untracked().queries);
+        assert_eq!(2, status.get_untracked().loaded);
+
+        assert_eq!(2, group.invalidate_all());
+        assert_eq!(2, status.get_untracked().invalid);
+        assert_eq!(0, group.cancel_all());
+
+        group.pause_all();
+        assert!(group.is_paused());
+        assert!(status.get_untracked().paused);
+        assert!(!QueryGroup::new("other").is_paused());
+        let member = client.cache.get_query::<u32, u32>(&0).unwrap();
+        let other = client.cache.get_query::<u32, u32>(&1).unwrap();
+        assert!(client.cache.is_fetch_paused(member.get_id()));
+        assert!(!client.cache.is_fetch_paused(other.get_id()));
+
+        // Only members are removed.
+        assert_eq!(2, group.clear_all());
+        assert_eq!(0, status.get_untracked().queries);
+        assert!(client.peek_query_state::<u32, u32>(&1).is_some());
+        assert_eq!(0, group.resume_all());
+        assert!(!group.is_paused());
+    }
+}
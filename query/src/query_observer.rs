@@ -1,29 +1,36 @@
 use std::cell::{Cell, RefCell};
 use std::future::Future;
+use std::panic::Location;
 use std::{pin::Pin, rc::Rc};
 
-use leptos::leptos_dom::helpers::IntervalHandle;
-use slotmap::{new_key_type, SlotMap};
+use indexmap::IndexMap;
 
 use crate::query::Query;
+use crate::query_cache::{QueryCache, QueryId};
+use crate::util::{run_or_defer, try_borrow, try_borrow_mut};
+use crate::visibility::use_query_visibility;
 use crate::{QueryKey, QueryOptions, QueryState, QueryValue};
+use leptos::{Signal, SignalGetUntracked};
 
 #[derive(Clone)]
 pub struct QueryObserver<K, V> {
     id: ObserverKey,
-    query: Rc<RefCell<Option<Query<K, V>>>>,
+    query: Rc<Cell<Option<QueryId>>>,
+    cache: QueryCache,
     fetcher: Option<Fetcher<K, V>>,
-    refetch: Rc<Cell<Option<IntervalHandle>>>,
     options: QueryOptions<V>,
+    // Deferred observers don't provide their fetcher, until resumed.
+    deferred: Rc<Cell<bool>>,
+    // Reported by the surrounding component tree, see `provide_query_visibility`.
+    visible: Option<Signal<bool>>,
+    location: &'static Location<'static>,
     #[allow(clippy::type_complexity)]
-    listeners: Rc<RefCell<SlotMap<ListenerKey, Box<dyn Fn(&QueryState<V>)>>>>,
+    listeners: Rc<RefCell<IndexMap<ListenerKey, Listener<V>>>>,
 }
 
 type Fetcher<K, V> = Rc<dyn Fn(K) -> Pin<Box<dyn Future<Output = V>>>>;
 
-new_key_type! {
-    pub struct ListenerKey;
-}
+type Listener<V> = Rc<dyn Fn(&QueryState<V>)>;
 
 impl<K, V> std::fmt::Debug for QueryObserver<K, V>
 where
@@ -33,11 +40,16 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("QueryObserver")
             .field("id", &self.id)
-            .field("query", &self.query)
+            .field("query", &self.query.get())
             .field("fetcher", &self.fetcher.is_some())
-            .field("refetch", &self.refetch.get().is_some())
+            .field("deferred", &self.deferred.get())
+            .field("visible", &self.is_visible())
+            .field("location", &self.location)
             .field("options", &self.options)
-            .field("listeners", &self.listeners.borrow().len())
+            .field(
+                "listeners",
+                &self.listeners.try_borrow().map(|l| l.len()).ok(),
+            )
             .finish()
     }
 }
@@ -47,7 +59,13 @@ where
     K: QueryKey + 'static,
     V: QueryValue + 'static,
 {
-    pub fn with_fetcher<F, Fu>(fetcher: F, options: QueryOptions<V>, query: Query<K, V>) -> Self
+    #[track_caller]
+    pub fn with_fetcher<F, Fu>(
+        cache: QueryCache,
+        fetcher: F,
+        options: QueryOptions<V>,
+        query: Option<Query<K, V>>,
+    ) -> Self
     where
         F: Fn(K) -> Fu + 'static,
         Fu: Future<Output = V> + 'static,
@@ -57,52 +75,24 @@ where
                 Rc::new(move |s| Box::pin(fetcher(s)) as Pin<Box<dyn Future<Output = V>>>)
                     as Fetcher<K, V>,
             );
-        let query = Rc::new(RefCell::new(Some(query)));
         let id = next_id();
-
-        #[cfg(any(feature = "csr", feature = "hydrate"))]
-        let refetch = {
-            use leptos::logging;
-
-            let interval = {
-                if let Some(refetch_interval) = options.refetch_interval {
-                    let query = query.clone();
-                    let timeout = leptos::set_interval_with_handle(
-                        move || {
-                            if let Ok(query) = query.try_borrow() {
-                                if let Some(query) = query.as_ref() {
-                                    query.execute()
-                                }
-                            } else {
-                                logging::debug_warn!("QueryObserver: Query is already borrowed");
-                            }
-                        },
-                        refetch_interval,
-                    )
-                    .ok();
-                    if timeout.is_none() {
-                        logging::debug_warn!("QueryObserver: Failed to set refetch interval");
-                    }
-                    timeout
-                } else {
-                    None
-                }
-            };
-            Rc::new(Cell::new(interval))
-        };
-        #[cfg(not(any(feature = "csr", feature = "hydrate")))]
-        let refetch = Rc::new(Cell::new(None));
+        // Visibility is only observed on the client.
+        let deferred =
+            cfg!(any(feature = "csr", feature = "hydrate")) && options.fetch_on_visible.is_some();
 
         let observer = Self {
             id,
-            query: query.clone(),
+            query: Rc::new(Cell::new(query.as_ref().map(|q| q.get_id()))),
+            cache,
             fetcher,
-            refetch,
             options,
-            listeners: Rc::new(RefCell::new(SlotMap::with_key())),
+            deferred: Rc::new(Cell::new(deferred)),
+            visible: use_query_visibility(),
+            location: Location::caller(),
+            listeners: Rc::new(RefCell::new(IndexMap::new())),
         };
 
-        if let Some(query) = query.borrow().as_ref() {
+        if let Some(query) = query {
             query.subscribe(&observer);
             if query.is_stale() {
                 query.execute()
@@ -112,28 +102,65 @@ where
         observer
     }
 
-    pub fn no_fetcher(options: QueryOptions<V>, query: Option<Query<K, V>>) -> Self {
-        let query = Rc::new(RefCell::new(query));
+    #[track_caller]
+    pub fn no_fetcher(
+        cache: QueryCache,
+        options: QueryOptions<V>,
+        query: Option<Query<K, V>>,
+    ) -> Self {
         let id = next_id();
 
         let observer = Self {
             id,
-            query: query.clone(),
+            query: Rc::new(Cell::new(query.as_ref().map(|q| q.get_id()))),
+            cache,
             fetcher: None,
-            refetch: Rc::new(Cell::new(None)),
             options,
-            listeners: Rc::new(RefCell::new(SlotMap::with_key())),
+            deferred: Rc::new(Cell::new(false)),
+            visible: use_query_visibility(),
+            location: Location::caller(),
+            listeners: Rc::new(RefCell::new(IndexMap::new())),
         };
 
-        if let Some(query) = query.borrow().as_ref() {
+        if let Some(query) = query {
             query.subscribe(&observer);
         }
 
         observer
     }
 
+    /// Observers are visible, unless created under a hidden [`provide_query_visibility`](crate::provide_query_visibility).
+    pub fn is_visible(&self) -> bool {
+        self.visible
+            .and_then(|visible| visible.try_get_untracked())
+            .unwrap_or(true)
+    }
+
+    /// If the observer fetches, e.g. from `use_query`, even if the first fetch is deferred.
+    pub fn has_fetcher(&self) -> bool {
+        self.fetcher.is_some()
+    }
+
     pub fn get_fetcher(&self) -> Option<Fetcher<K, V>> {
-        self.fetcher.clone()
+        if self.deferred.get() {
+            None
+        } else {
+            self.fetcher.clone()
+        }
+    }
+
+    /// Provides the fetcher of a deferred observer, and executes the query if needed.
+    #[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+    pub fn resume(&self) {
+        if self.deferred.replace(false) {
+            if let Some(query) = self
+                .query
+                .get()
+                .and_then(|id| self.cache.get_query_by_id::<K, V>(id))
+            {
+                query.ensure_execute();
+            }
+        }
     }
 
     pub fn get_id(&self) -> ObserverKey {
@@ -144,38 +171,56 @@ where
         &self.options
     }
 
+    pub fn get_location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
     pub fn notify(&self, state: QueryState<V>) {
-        let listeners = self.listeners.try_borrow().expect("notify borrow");
-        for listener in listeners.values() {
-            listener(&state);
-        }
+        let listeners = self.listeners.clone();
+        run_or_defer(move || {
+            // Listeners may call back into the observer, so they are invoked outside of the borrow.
+            let listeners = try_borrow(&listeners, "notify")?
+                .values()
+                .cloned()
+                .collect::<Vec<_>>();
+            for listener in listeners {
+                listener(&state);
+            }
+            Ok(())
+        })
     }
 
     pub fn add_listener(&self, listener: impl Fn(&QueryState<V>) + 'static) -> ListenerKey {
-        let listener = Box::new(listener);
-        let key = self
-            .listeners
-            .try_borrow_mut()
-            .expect("add_listener borrow_mut")
-            .insert(listener);
+        let key = ListenerKey(next_id().0);
+        let listener: Listener<V> = Rc::new(listener);
+        let listeners = self.listeners.clone();
+        run_or_defer(move || {
+            try_borrow_mut(&listeners, "add_listener")?.insert(key, listener.clone());
+            Ok(())
+        });
         key
     }
 
+    /// Returns false if the listener was not registered.
+    /// On re-entrant access the removal is deferred and counted as done, so true is returned.
     pub fn remove_listener(&self, key: ListenerKey) -> bool {
-        self.listeners
-            .try_borrow_mut()
-            .expect("remove_listener borrow_mut")
-            .remove(key)
-            .is_some()
+        match try_borrow_mut(&self.listeners, "remove_listener") {
+            Ok(mut listeners) => listeners.shift_remove(&key).is_some(),
+            Err(_) => {
+                let listeners = self.listeners.clone();
+                run_or_defer(move || {
+                    try_borrow_mut(&listeners, "remove_listener")?.shift_remove(&key);
+                    Ok(())
+                });
+                true
+            }
+        }
     }
 
     pub fn update_query(&self, new_query: Option<Query<K, V>>) {
         // Determine if the new query is the same as the current one.
-        let is_same_query = self.query.borrow().as_ref().map_or(false, |current_query| {
-            new_query.as_ref().map_or(false, |new_query| {
-                new_query.get_key() == current_query.get_key()
-            })
-        });
+        let new_id = new_query.as_ref().map(|q| q.get_id());
+        let is_same_query = new_id.is_some() && new_id == self.query.get();
 
         // If the new query is the same as the current, do nothing.
         if is_same_query {
@@ -183,12 +228,10 @@ where
         }
 
         // If there's an existing query, unsubscribe from it.
-        if let Some(current_query) = self.query.take() {
-            current_query.unsubscribe(self);
-        }
+        self.unsubscribe_current();
 
         // Set the new query (if any) and subscribe to it.
-        *self.query.borrow_mut() = new_query.clone(); // Use clone to keep ownership with the caller.
+        self.query.set(new_id);
 
         if let Some(ref query) = new_query {
             // Subscribe to the new query and ensure it's executed.
@@ -198,35 +241,39 @@ where
     }
 
     pub fn cleanup(&self) {
-        if let Some(query) = self.query.take() {
-            query.unsubscribe(self);
-        }
-
-        if let Some(interval) = self.refetch.take() {
-            interval.clear();
-        }
+        self.unsubscribe_current();
 
-        if !self
-            .listeners
-            .try_borrow()
-            .expect("cleanup borrow")
-            .is_empty()
-        {
+        if try_borrow(&self.listeners, "cleanup").is_ok_and(|listeners| !listeners.is_empty()) {
             leptos::logging::debug_warn!(
                 "QueryObserver::cleanup: QueryObserver::listeners is not empty"
             );
         }
     }
+
+    // The observers of an evicted query were removed along with it.
+    fn unsubscribe_current(&self) {
+        if let Some(query) = self
+            .query
+            .take()
+            .and_then(|id| self.cache.get_query_by_id::<K, V>(id))
+        {
+            query.unsubscribe(self);
+        }
+    }
 }
 
 thread_local! {
     static NEXT_ID: Cell<u32> = const { Cell::new(1) } ;
 }
 
+/// Identifies an observer of a query, such as a single `use_query` call.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ObserverKey(u32);
 
-fn next_id() -> ObserverKey {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerKey(u32);
+
+pub(crate) fn next_id() -> ObserverKey {
     NEXT_ID.with(|id| {
         let current_id = id.get();
         id.set(current_id + 1);
@@ -1,4 +1,6 @@
-use std::time::Duration;
+use std::{fmt::Debug, rc::Rc, time::Duration};
+
+use crate::{QueryGroup, VisibilityTarget};
 
 /// Default options for all queries under this client.
 /// Only differs from [`QueryOptions`] in that it doesn't have a default value.
@@ -12,6 +14,9 @@ pub struct DefaultQueryOptions {
     pub refetch_interval: Option<Duration>,
     /// Determines which type of resource to use.
     pub resource_option: ResourceOption,
+    /// Time after which a prefetch is cancelled if its query still has no observers, e.g. when the user never navigated to it.
+    /// Default is no timeout.
+    pub prefetch_timeout: Option<Duration>,
 }
 
 impl Default for DefaultQueryOptions {
@@ -21,6 +26,7 @@ impl Default for DefaultQueryOptions {
             gc_time: Some(DEFAULT_GC_TIME),
             refetch_interval: None,
             resource_option: ResourceOption::default(),
+            prefetch_timeout: None,
         }
     }
 }
@@ -50,12 +56,139 @@ pub struct QueryOptions<V> {
     /// NOTE: If different cache times are used for the same key, the MAXIMUM time will be used.
     pub gc_time: Option<Duration>,
     /// If no refetch interval, the query will never refetch.
+    /// Interval refetches are skipped while every observer is hidden, see [`provide_query_visibility`](crate::provide_query_visibility), and while a fetch is in flight.
+    /// NOTE: If different refetch intervals are used for the same key, the MINIMUM interval will be used.
     pub refetch_interval: Option<Duration>,
+    /// Restarts the refetch interval whenever a fetch completes, so the interval is the time between the end of one fetch and the start of the next.
+    /// Useful when fetches can take longer than the interval. Otherwise the interval ticks at fixed times.
+    /// Default is false.
+    /// NOTE: If any observer of a key aligns the interval to completion, it is aligned for all observers.
+    pub align_refetch_to_completion: bool,
+    /// Derives the delay until the next refetch from fetched data, e.g. a server-sent `Retry-After`.
+    /// Overrides the refetch interval for one cycle, after which the interval resumes. Also applies without a refetch interval.
+    /// Default is no hint.
+    /// NOTE: If different observers of the same key provide hints, the hint of the earliest observer is used.
+    pub refetch_hint: Option<RefetchHint<V>>,
+    /// Rejects fetched values that are structurally valid but unusable, e.g. an empty payload from a misbehaving backend.
+    /// A rejected value isn't stored, and the error is exposed as [`QueryResult::validation_error`](crate::QueryResult::validation_error).
+    /// The query keeps its previous data. If there is none, e.g. on the first load, the query is [`Rejected`](crate::QueryState::Rejected): it resolves without data, and is fetched again on the next mount or refetch.
+    /// Default is no validation.
+    /// NOTE: If different observers of the same key provide validators, a value must pass all of them.
+    pub validator: Option<Validator<V>>,
+    /// Converts persisted or server-rendered data that no longer deserializes into the current value type, e.g. after a field was renamed between app versions.
+    /// Applies when restoring from a [`QueryPersister`](crate::query_persister::QueryPersister) and when hydrating. If the migration returns `None`, the data is discarded and the query is fetched.
+    /// Default is no migration, so undeserializable data is discarded. Set with `set_migration`, which requires the `migrate` feature.
+    /// NOTE: If different observers of the same key provide migrations, the migration of the earliest observer is used.
+    pub migration: Option<Migration<V>>,
+    /// The minimum time between the start of two fetches of the same key, whatever triggered them.
+    /// Refetches within this time are delayed until it has passed, and coalesced into a single fetch.
+    /// Unlike stale_time, this also applies to invalidation. Explicit fetches, such as [`QueryClient::refetch_query_force`](crate::QueryClient::refetch_query_force), are not delayed.
+    /// If no min fetch interval, refetches are never delayed.
+    /// NOTE: If different min fetch intervals are used for the same key, the MAXIMUM interval will be used.
+    pub min_fetch_interval: Option<Duration>,
+    /// The maximum age of data that is still shown while the query refetches.
+    /// Data older than the window is hidden, and the query is loading until the refetch completes.
+    /// If no revalidate window, data is always shown while refetching.
+    /// Default is no revalidate window.
+    /// NOTE: If different revalidate windows are used for the same key, the MAXIMUM window will be used.
+    pub revalidate_window: Option<Duration>,
+    /// Determines the order in which fetches run. Low priority fetches are delayed while high priority fetches are active.
+    /// Default is normal.
+    /// NOTE: If different priorities are used for the same key, the HIGHEST priority will be used.
+    pub priority: QueryPriority,
+    /// Determines when an observed query refetches after it is invalidated.
+    /// Useful for bulk invalidations, e.g. after a websocket reconnect, so they don't cause dozens of simultaneous refetches.
+    /// Default is immediate.
+    /// NOTE: If different modes are used for the same key, the most eager mode will be used.
+    pub invalidate_mode: InvalidateMode,
+    /// Defers the first fetch until the element is scrolled into view, so long pages don't fetch everything at mount.
+    /// Only applies on the client (`csr` or `hydrate`). Cached data is still shown immediately.
+    /// Default is to fetch on mount.
+    pub fetch_on_visible: Option<VisibilityTarget>,
     /// Determines which type of resource to use.
     pub resource_option: Option<ResourceOption>,
+    /// The group the query joins once it is observed. See [`QueryGroup`].
+    /// Default is no group.
+    /// NOTE: If different observers of the same key set different groups, the query joins all of them.
+    pub group: Option<QueryGroup>,
 }
 
 impl<V> QueryOptions<V> {
+    /// Preset for frequently changing data, e.g. prices or notifications.
+    ///
+    /// Always stale, so it is refetched on every mount, and refetched every 5 seconds while visible.
+    /// Fetches are at least 1 second apart.
+    pub fn realtime() -> Self {
+        Self::default()
+            .set_stale_time(Duration::ZERO)
+            .set_refetch_interval(Duration::from_secs(5))
+            .set_min_fetch_interval(Duration::from_secs(1))
+    }
+
+    /// Preset for data that doesn't change while the app is running, e.g. documentation or configuration.
+    ///
+    /// Never stale and never evicted, so it is fetched once. Invalidate it explicitly if it does change.
+    pub fn static_content() -> Self {
+        Self::default().set_stale_time(None).set_gc_time(None)
+    }
+
+    /// Preset for data that only needs to be fetched once while it is in use, e.g. the details of a finished order.
+    ///
+    /// Never stale, so mounting it again reuses the cached data. It is evicted after the gc time without observers, and fetched again on its next use.
+    ///
+    /// - Persistence: data restored from a persister is never stale either, so it is used without refetching. Eviction also removes it from the persister.
+    /// - Refetching: only on invalidation, or on the client-wide refetch interval if one is set. Queries aren't refetched on window focus.
+    pub fn once() -> Self {
+        Self::default().set_stale_time(None)
+    }
+
+    /// Preset for data that should be fetched at most once while the app runs, e.g. the current user's profile or feature flags.
+    ///
+    /// Like [`once`](Self::once), but never evicted, so it stays cached even without observers.
+    ///
+    /// - Persistence: with a persister, the data is stored on every fetch and restored on the next page load, where it is used without refetching.
+    ///   The session therefore lasts as long as the persisted data, e.g. until the browser's storage is cleared. Without a persister, each page load is a new session.
+    /// - Refetching: only on invalidation, or on the client-wide refetch interval if one is set. Queries aren't refetched on window focus.
+    pub fn once_per_session() -> Self {
+        Self::once().set_gc_time(None)
+    }
+
+    /// Preset for data that only changes when the app changes it, e.g. a list that is only edited through mutations that invalidate it.
+    ///
+    /// Like [`once_per_session`](Self::once_per_session), but never refetched on an interval, even if the client sets a default refetch interval.
+    ///
+    /// - Persistence: restored data is used without refetching, until the query is invalidated.
+    /// - Refetching: only on invalidation, e.g. with [`QueryScope::invalidate_query`](crate::QueryScope::invalidate_query). Queries aren't refetched on window focus.
+    pub fn until_invalidated() -> Self {
+        QueryOptions {
+            refetch_interval: None,
+            ..Self::once_per_session()
+        }
+    }
+
+    /// Preset for data that is revisited while navigating, e.g. a user's projects.
+    ///
+    /// Stale after 5 minutes, and kept for 30 minutes without observers, so navigating back shows it instantly.
+    pub fn session() -> Self {
+        Self::default()
+            .set_stale_time(Duration::from_secs(5 * 60))
+            .set_gc_time(Duration::from_secs(30 * 60))
+    }
+
+    /// An app-defined preset, registered with [`QueryClient::register_preset`](crate::QueryClient::register_preset).
+    /// Uses the default options if the preset is not registered.
+    pub fn preset(name: &str) -> Self {
+        let preset =
+            leptos::use_context::<crate::QueryClient>().and_then(|client| client.get_preset(name));
+        match preset {
+            Some(preset) => preset,
+            None => {
+                leptos::logging::debug_warn!("Query preset {name:?} is not registered");
+                Self::default()
+            }
+        }
+    }
+
     /// Set the default value.
     pub fn set_default_value(self, default_value: Option<V>) -> Self {
         QueryOptions {
@@ -65,19 +198,144 @@ impl<V> QueryOptions<V> {
     }
 
     /// Set the stale_time.
-    pub fn set_stale_time(self, stale_time: Option<Duration>) -> Self {
-        QueryOptions { stale_time, ..self }
+    pub fn set_stale_time(self, stale_time: impl Into<TimeSetting>) -> Self {
+        QueryOptions {
+            stale_time: stale_time.into().duration(),
+            ..self
+        }
     }
 
     /// Set the gc time.
-    pub fn set_gc_time(self, gc_time: Option<Duration>) -> Self {
-        QueryOptions { gc_time, ..self }
+    pub fn set_gc_time(self, gc_time: impl Into<TimeSetting>) -> Self {
+        QueryOptions {
+            gc_time: gc_time.into().duration(),
+            ..self
+        }
     }
 
     /// Set the refetch interval.
-    pub fn set_refetch_interval(self, refetch_interval: Option<Duration>) -> Self {
+    pub fn set_refetch_interval(self, refetch_interval: impl Into<TimeSetting>) -> Self {
+        QueryOptions {
+            refetch_interval: refetch_interval.into().duration(),
+            ..self
+        }
+    }
+
+    /// Set whether the refetch interval restarts when a fetch completes.
+    pub fn set_align_refetch_to_completion(self, align_refetch_to_completion: bool) -> Self {
+        QueryOptions {
+            align_refetch_to_completion,
+            ..self
+        }
+    }
+
+    /// Set the hint for the delay until the next refetch, derived from fetched data.
+    ///
+    /// ```
+    /// use leptos_query::*;
+    /// use std::time::Duration;
+    ///
+    /// #[derive(Clone, serde::Serialize, serde::Deserialize)]
+    /// struct Job {
+    ///     done: bool,
+    ///     poll_after_secs: Option<u64>,
+    /// }
+    ///
+    /// let options = QueryOptions::<Job>::default()
+    ///     .set_refetch_interval(Duration::from_secs(10))
+    ///     .set_refetch_hint(|job| job.poll_after_secs.map(Duration::from_secs));
+    /// ```
+    pub fn set_refetch_hint(self, hint: impl Fn(&V) -> Option<Duration> + 'static) -> Self {
+        QueryOptions {
+            refetch_hint: Some(RefetchHint(Rc::new(hint))),
+            ..self
+        }
+    }
+
+    /// Set the validator for fetched values.
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// let options = QueryOptions::<Vec<String>>::default().set_validator(|names| {
+    ///     if names.is_empty() {
+    ///         Err("no names returned".to_string())
+    ///     } else {
+    ///         Ok(())
+    ///     }
+    /// });
+    /// ```
+    pub fn set_validator(self, validator: impl Fn(&V) -> Result<(), String> + 'static) -> Self {
+        QueryOptions {
+            validator: Some(Validator(Rc::new(validator))),
+            ..self
+        }
+    }
+
+    /// Set the migration for persisted or server-rendered data that no longer deserializes.
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// #[derive(Clone, serde::Serialize, serde::Deserialize)]
+    /// struct User {
+    ///     display_name: String,
+    /// }
+    ///
+    /// // `display_name` used to be called `name`.
+    /// let options = QueryOptions::<User>::default().set_migration(|old| {
+    ///     let name = old.get("name")?.as_str()?;
+    ///     Some(User {
+    ///         display_name: name.to_string(),
+    ///     })
+    /// });
+    /// ```
+    #[cfg(feature = "migrate")]
+    pub fn set_migration(
+        self,
+        migration: impl Fn(serde_json::Value) -> Option<V> + 'static,
+    ) -> Self {
+        let migration =
+            move |serialized: &str| serde_json::from_str(serialized).ok().and_then(&migration);
+        QueryOptions {
+            migration: Some(Migration(Rc::new(migration))),
+            ..self
+        }
+    }
+
+    /// Set the min fetch interval.
+    pub fn set_min_fetch_interval(self, min_fetch_interval: impl Into<TimeSetting>) -> Self {
+        QueryOptions {
+            min_fetch_interval: min_fetch_interval.into().duration(),
+            ..self
+        }
+    }
+
+    /// Set the revalidate window.
+    pub fn set_revalidate_window(self, revalidate_window: impl Into<TimeSetting>) -> Self {
+        QueryOptions {
+            revalidate_window: revalidate_window.into().duration(),
+            ..self
+        }
+    }
+
+    /// Set the priority.
+    pub fn set_priority(self, priority: QueryPriority) -> Self {
+        QueryOptions { priority, ..self }
+    }
+
+    /// Set the invalidate mode.
+    pub fn set_invalidate_mode(self, invalidate_mode: InvalidateMode) -> Self {
+        QueryOptions {
+            invalidate_mode,
+            ..self
+        }
+    }
+
+    /// Set the element to wait on before the first fetch.
+    pub fn set_fetch_on_visible(self, target: impl Into<VisibilityTarget>) -> Self {
         QueryOptions {
-            refetch_interval,
+            fetch_on_visible: Some(target.into()),
             ..self
         }
     }
@@ -90,14 +348,32 @@ impl<V> QueryOptions<V> {
         }
     }
 
-    /// Transform the default value.
+    /// Set the group the query joins.
+    pub fn set_group(self, group: QueryGroup) -> Self {
+        QueryOptions {
+            group: Some(group),
+            ..self
+        }
+    }
+
+    /// Transform the default value. The refetch hint, validator and migration can't be transformed, so they are removed.
     pub fn map_value<R>(self, func: impl FnOnce(V) -> R) -> QueryOptions<R> {
         QueryOptions {
             default_value: self.default_value.map(func),
             stale_time: self.stale_time,
             gc_time: self.gc_time,
             refetch_interval: self.refetch_interval,
+            align_refetch_to_completion: self.align_refetch_to_completion,
+            refetch_hint: None,
+            validator: None,
+            migration: None,
+            min_fetch_interval: self.min_fetch_interval,
+            revalidate_window: self.revalidate_window,
+            priority: self.priority,
+            invalidate_mode: self.invalidate_mode,
+            fetch_on_visible: self.fetch_on_visible,
             resource_option: self.resource_option,
+            group: self.group,
         }
     }
 
@@ -113,7 +389,17 @@ impl<V> QueryOptions<V> {
             stale_time,
             gc_time: self.gc_time,
             refetch_interval: self.refetch_interval,
+            align_refetch_to_completion: self.align_refetch_to_completion,
+            refetch_hint: self.refetch_hint,
+            validator: self.validator,
+            migration: self.migration,
+            min_fetch_interval: self.min_fetch_interval,
+            revalidate_window: self.revalidate_window,
+            priority: self.priority,
+            invalidate_mode: self.invalidate_mode,
+            fetch_on_visible: self.fetch_on_visible,
             resource_option: self.resource_option,
+            group: self.group,
         }
     }
 }
@@ -129,24 +415,288 @@ impl<V> Default for QueryOptions<V> {
             stale_time: default_options.stale_time,
             gc_time: default_options.gc_time,
             refetch_interval: default_options.refetch_interval,
+            align_refetch_to_completion: false,
+            refetch_hint: None,
+            validator: None,
+            migration: None,
+            min_fetch_interval: None,
+            revalidate_window: None,
+            priority: QueryPriority::default(),
+            invalidate_mode: InvalidateMode::default(),
+            fetch_on_visible: None,
             resource_option: Some(default_options.resource_option),
+            group: None,
         }
         .validate()
     }
 }
 
+/// Derives the delay until the next refetch from fetched data. See [`QueryOptions::set_refetch_hint`].
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct RefetchHint<V>(Rc<dyn Fn(&V) -> Option<Duration>>);
+
+impl<V> RefetchHint<V> {
+    /// The delay until the next refetch, if the data provides one.
+    pub fn delay(&self, data: &V) -> Option<Duration> {
+        (self.0)(data)
+    }
+}
+
+impl<V> Debug for RefetchHint<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RefetchHint")
+    }
+}
+
+/// Checks fetched values before they are stored. See [`QueryOptions::set_validator`].
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct Validator<V>(Rc<dyn Fn(&V) -> Result<(), String>>);
+
+impl<V> Validator<V> {
+    /// Returns the reason the value is rejected, if it is.
+    pub fn check(&self, value: &V) -> Result<(), String> {
+        (self.0)(value)
+    }
+}
+
+impl<V> Debug for Validator<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Validator")
+    }
+}
+
+/// Converts serialized data from an older app version into the current value type. See [`QueryOptions::migration`].
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct Migration<V>(Rc<dyn Fn(&str) -> Option<V>>);
+
+impl<V> Migration<V> {
+    /// Migrates the serialized data, if possible.
+    pub fn migrate(&self, serialized: &str) -> Option<V> {
+        (self.0)(serialized)
+    }
+}
+
+impl<V> Debug for Migration<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Migration")
+    }
+}
+
 /// Determines which type of resource to use.
+///
+/// With SSR, this decides how a query takes part in streaming. It can be set per query or scope, e.g. with [`QueryScope::with_resource_option`](crate::QueryScope::with_resource_option),
+/// so a page can block on the queries it needs for its `<head>`, and stream the rest.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum ResourceOption {
     /// Query will use [`create_resource()`](leptos::create_resource)
+    ///
+    /// With out-of-order streaming, the `Suspense` fallback is rendered, and the data is streamed in once loaded.
     #[default]
     NonBlocking,
     /// Query will use [`create_blocking_resource()`](leptos::create_blocking_resource)
+    ///
+    /// The shell is not flushed until the data is loaded.
     Blocking,
     /// Query will use [`create_local_resource()`](leptos::create_local_resource)
+    ///
+    /// Not fetched on the server, the fallback is rendered and the query only runs in the browser.
     Local,
 }
 
+/// Priority of a query's fetches. See [`QueryOptions::priority`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QueryPriority {
+    /// Fetched after high priority queries, such as prefetching or below-the-fold data.
+    Low,
+    /// Fetched immediately.
+    #[default]
+    Normal,
+    /// Fetched immediately, and delays low priority fetches until done. Such as above-the-fold data.
+    High,
+}
+
+/// When an observed query refetches after it is invalidated. See [`QueryOptions::invalidate_mode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum InvalidateMode {
+    /// Refetched as soon as it is invalidated.
+    #[default]
+    Immediate,
+    /// Refetched once no further invalidation happened for the duration, so a burst of invalidations only refetches once.
+    Debounced(Duration),
+    /// Refetched the next time it is accessed, i.e. when an observer mounts, or when stale queries are revalidated, such as on window focus.
+    /// The data is still shown until then.
+    OnNextAccess,
+}
+
+impl InvalidateMode {
+    /// The most eager mode. Immediate over debounced, and the shortest debounce over on next access.
+    pub(crate) fn merge(modes: impl IntoIterator<Item = Self>) -> Self {
+        use InvalidateMode::*;
+        modes
+            .into_iter()
+            .reduce(|a, b| match (a, b) {
+                (Immediate, _) | (_, Immediate) => Immediate,
+                (Debounced(a), Debounced(b)) => Debounced(a.min(b)),
+                (Debounced(delay), OnNextAccess) | (OnNextAccess, Debounced(delay)) => {
+                    Debounced(delay)
+                }
+                (OnNextAccess, OnNextAccess) => OnNextAccess,
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// A time option, such as `stale_time`, `gc_time`, or `refetch_interval`. Infinity means the time never elapses.
+///
+/// Can be created from a [`Duration`], an `Option<Duration>` (where `None` is infinity),
+/// or a string such as `"500ms"`, `"30s"`, `"5m"`, `"1h"`, `"1d"` or `"infinity"`.
+///
+/// ```
+/// use leptos_query::*;
+/// use std::time::Duration;
+///
+/// let options = QueryOptions::<String>::default()
+///     .set_stale_time("30s")
+///     .set_gc_time(TimeSetting::mins(5))
+///     .set_refetch_interval(TimeSetting::INFINITY);
+///
+/// assert_eq!(options.stale_time, Some(Duration::from_secs(30)));
+/// assert_eq!(options.refetch_interval, None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeSetting(Option<Duration>);
+
+impl TimeSetting {
+    /// The time never elapses.
+    pub const INFINITY: TimeSetting = TimeSetting(None);
+
+    /// Time in milliseconds.
+    pub const fn millis(millis: u64) -> Self {
+        TimeSetting(Some(Duration::from_millis(millis)))
+    }
+
+    /// Time in seconds.
+    pub const fn secs(secs: u64) -> Self {
+        TimeSetting(Some(Duration::from_secs(secs)))
+    }
+
+    /// Time in minutes.
+    pub const fn mins(mins: u64) -> Self {
+        TimeSetting(Some(Duration::from_secs(mins * 60)))
+    }
+
+    /// Time in hours.
+    pub const fn hours(hours: u64) -> Self {
+        TimeSetting(Some(Duration::from_secs(hours * 60 * 60)))
+    }
+
+    /// The duration, or None if infinite.
+    pub const fn duration(self) -> Option<Duration> {
+        self.0
+    }
+}
+
+impl From<Duration> for TimeSetting {
+    fn from(duration: Duration) -> Self {
+        TimeSetting(Some(duration))
+    }
+}
+
+impl From<Option<Duration>> for TimeSetting {
+    fn from(duration: Option<Duration>) -> Self {
+        TimeSetting(duration)
+    }
+}
+
+impl From<TimeSetting> for Option<Duration> {
+    fn from(setting: TimeSetting) -> Self {
+        setting.0
+    }
+}
+
+/// Panics if the string is not a valid time. Use [`str::parse`] to handle the error instead.
+impl From<&str> for TimeSetting {
+    fn from(value: &str) -> Self {
+        value.parse().unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+impl std::str::FromStr for TimeSetting {
+    type Err = ParseTimeSettingError;
+
+    // Keep in sync with the parsing in the `query` macro.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value == "infinity" {
+            return Ok(TimeSetting::INFINITY);
+        }
+
+        let error = || ParseTimeSettingError(value.to_string());
+
+        let split = value
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(error)?;
+        let (amount, unit) = value.split_at(split);
+        let amount = amount.parse::<u64>().map_err(|_| error())?;
+
+        let millis = match unit {
+            "ms" => Some(amount),
+            "s" => amount.checked_mul(1000),
+            "m" => amount.checked_mul(60 * 1000),
+            "h" => amount.checked_mul(60 * 60 * 1000),
+            "d" => amount.checked_mul(24 * 60 * 60 * 1000),
+            _ => None,
+        };
+
+        millis.map(TimeSetting::millis).ok_or_else(error)
+    }
+}
+
+/// Error when parsing a [`TimeSetting`] from a string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTimeSettingError(String);
+
+impl std::fmt::Display for ParseTimeSettingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid time \"{}\". Expected a number followed by one of ms, s, m, h, d, or \"infinity\".",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseTimeSettingError {}
+
+// Merge rules for observers of the same key, where `None` is infinity.
+
+/// The shortest time, used for `stale_time` and `refetch_interval`.
+pub(crate) fn merge_min_time(
+    times: impl IntoIterator<Item = Option<Duration>>,
+) -> Option<Duration> {
+    times.into_iter().flatten().min()
+}
+
+/// The longest time, used for `gc_time` and `revalidate_window`.
+pub(crate) fn merge_max_time(
+    times: impl IntoIterator<Item = Option<Duration>>,
+) -> Option<Duration> {
+    times
+        .into_iter()
+        .reduce(|a, b| a.zip(b).map(|(a, b)| a.max(b)))
+        .flatten()
+}
+
+/// The longest interval, where `None` is no interval. Used for `min_fetch_interval`.
+pub(crate) fn merge_max_interval(
+    times: impl IntoIterator<Item = Option<Duration>>,
+) -> Option<Duration> {
+    times.into_iter().flatten().max()
+}
+
 fn ensure_valid_stale_time(
     stale_time: &Option<Duration>,
     gc_time: &Option<Duration>,
@@ -182,6 +732,30 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn once_presets() {
+        let _ = leptos::create_runtime();
+        provide_query_client_with_options(DefaultQueryOptions {
+            refetch_interval: Some(Duration::from_secs(30)),
+            ..DefaultQueryOptions::default()
+        });
+
+        let once = QueryOptions::<i32>::once();
+        assert_eq!(None, once.stale_time);
+        assert_eq!(Some(DEFAULT_GC_TIME), once.gc_time);
+        assert_eq!(Some(Duration::from_secs(30)), once.refetch_interval);
+
+        let per_session = QueryOptions::<i32>::once_per_session();
+        assert_eq!(None, per_session.stale_time);
+        assert_eq!(None, per_session.gc_time);
+        assert_eq!(Some(Duration::from_secs(30)), per_session.refetch_interval);
+
+        let until_invalidated = QueryOptions::<i32>::until_invalidated();
+        assert_eq!(None, until_invalidated.stale_time);
+        assert_eq!(None, until_invalidated.gc_time);
+        assert_eq!(None, until_invalidated.refetch_interval);
+    }
+
     #[test]
     fn validate_stale_time_less_than_gc_time() {
         let options = QueryOptions::<i32> {
@@ -189,7 +763,17 @@ mod tests {
             stale_time: Some(Duration::from_secs(5)),
             gc_time: Some(Duration::from_secs(10)),
             refetch_interval: None,
+            align_refetch_to_completion: false,
+            refetch_hint: None,
+            validator: None,
+            migration: None,
+            min_fetch_interval: None,
+            revalidate_window: None,
+            priority: QueryPriority::Normal,
+            invalidate_mode: InvalidateMode::Immediate,
+            fetch_on_visible: None,
             resource_option: None,
+            group: None,
         }
         .validate();
 
@@ -212,7 +796,17 @@ mod tests {
             stale_time: Some(Duration::from_secs(15)),
             gc_time: Some(Duration::from_secs(10)),
             refetch_interval: None,
+            align_refetch_to_completion: false,
+            refetch_hint: None,
+            validator: None,
+            migration: None,
+            min_fetch_interval: None,
+            revalidate_window: None,
+            priority: QueryPriority::Normal,
+            invalidate_mode: InvalidateMode::Immediate,
+            fetch_on_visible: None,
             resource_option: None,
+            group: None,
         }
         .validate();
 
@@ -235,7 +829,17 @@ mod tests {
             stale_time: Some(Duration::from_secs(5)),
             gc_time: None,
             refetch_interval: None,
+            align_refetch_to_completion: false,
+            refetch_hint: None,
+            validator: None,
+            migration: None,
+            min_fetch_interval: None,
+            revalidate_window: None,
+            priority: QueryPriority::Normal,
+            invalidate_mode: InvalidateMode::Immediate,
+            fetch_on_visible: None,
             resource_option: None,
+            group: None,
         }
         .validate();
 
@@ -254,7 +858,17 @@ mod tests {
             stale_time: None,
             gc_time: Some(Duration::from_secs(10)),
             refetch_interval: None,
+            align_refetch_to_completion: false,
+            refetch_hint: None,
+            validator: None,
+            migration: None,
+            min_fetch_interval: None,
+            revalidate_window: None,
+            priority: QueryPriority::Normal,
+            invalidate_mode: InvalidateMode::Immediate,
+            fetch_on_visible: None,
             resource_option: None,
+            group: None,
         }
         .validate();
         assert_eq!(
@@ -276,7 +890,17 @@ mod tests {
             stale_time: None,
             gc_time: None,
             refetch_interval: None,
+            align_refetch_to_completion: false,
+            refetch_hint: None,
+            validator: None,
+            migration: None,
+            min_fetch_interval: None,
+            revalidate_window: None,
+            priority: QueryPriority::Normal,
+            invalidate_mode: InvalidateMode::Immediate,
+            fetch_on_visible: None,
             resource_option: None,
+            group: None,
         }
         .validate();
 
@@ -293,6 +917,7 @@ mod tests {
             gc_time: Some(Duration::from_secs(2)),
             refetch_interval: Some(Duration::from_secs(3)),
             resource_option: ResourceOption::NonBlocking,
+            prefetch_timeout: None,
         });
 
         // Action: Create a QueryOptions instance using Default::default()
@@ -327,4 +952,57 @@ mod tests {
             "After validation, gc_time should not be less than stale_time"
         );
     }
+
+    #[test]
+    fn parse_time_setting() {
+        let parse = |s: &str| s.parse::<TimeSetting>().map(|t| t.duration());
+
+        assert_eq!(parse("500ms"), Ok(Some(Duration::from_millis(500))));
+        assert_eq!(parse("30s"), Ok(Some(Duration::from_secs(30))));
+        assert_eq!(parse("5m"), Ok(Some(Duration::from_secs(5 * 60))));
+        assert_eq!(parse("1h"), Ok(Some(Duration::from_secs(60 * 60))));
+        assert_eq!(parse("1d"), Ok(Some(Duration::from_secs(24 * 60 * 60))));
+        assert_eq!(parse("infinity"), Ok(None));
+
+        assert!(parse("5").is_err());
+        assert!(parse("s").is_err());
+        assert!(parse("5 minutes").is_err());
+
+        assert_eq!(TimeSetting::mins(5), TimeSetting::from("5m"));
+        assert_eq!(TimeSetting::INFINITY, TimeSetting::from(None));
+    }
+
+    #[test]
+    fn merge_invalidate_modes() {
+        use InvalidateMode::*;
+        let secs = Duration::from_secs;
+
+        assert_eq!(InvalidateMode::merge([]), Immediate);
+        assert_eq!(
+            InvalidateMode::merge([OnNextAccess, OnNextAccess]),
+            OnNextAccess
+        );
+        assert_eq!(
+            InvalidateMode::merge([OnNextAccess, Debounced(secs(5)), Debounced(secs(1))]),
+            Debounced(secs(1))
+        );
+        assert_eq!(
+            InvalidateMode::merge([Debounced(secs(5)), Immediate, OnNextAccess]),
+            Immediate
+        );
+    }
+
+    #[test]
+    fn merge_times() {
+        let secs = |s| Some(Duration::from_secs(s));
+
+        // Minimum ignores infinity, unless every observer is infinite.
+        assert_eq!(merge_min_time([secs(10), None, secs(5)]), secs(5));
+        assert_eq!(merge_min_time([None, None]), None);
+
+        // Maximum is infinite if any observer is.
+        assert_eq!(merge_max_time([secs(10), secs(5)]), secs(10));
+        assert_eq!(merge_max_time([secs(10), None]), None);
+        assert_eq!(merge_max_time([]), None);
+    }
 }
@@ -20,7 +20,7 @@ impl QueryPersister for LocalStoragePersister {
     async fn persist(&self, key: &str, query: PersistQueryData) {
         if let Some(storage) = local_storage() {
             let value = miniserde::json::to_string(&query);
-            let _ = storage.set(&key, &value);
+            let _ = storage.set(key, &value);
         }
     }
 
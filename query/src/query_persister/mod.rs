@@ -43,7 +43,7 @@ where
                 }
             }
             #[cfg(any(feature = "hydrate", feature = "csr"))]
-            CacheEvent::Removed(key) => {
+            CacheEvent::Removed(key, _) => {
                 let persister = self.clone();
                 leptos::spawn_local(async move {
                     let _ = persister.remove(&key.0).await;
@@ -75,7 +75,7 @@ where
 
     fn try_from(value: PersistQueryData) -> Result<Self, Self::Error> {
         let data = leptos::Serializable::de(value.value.as_str())?;
-        let updated_at = crate::Instant(std::time::Duration::from_millis(value.updated_at));
+        let updated_at = crate::Instant::from_unix_millis(value.updated_at);
         Ok(crate::QueryData { data, updated_at })
     }
 }
@@ -90,6 +90,7 @@ impl TryFrom<crate::QueryState<String>> for PersistQueryData {
             // Ignore other states.
             crate::QueryState::Loading
             | crate::QueryState::Created
+            | crate::QueryState::Rejected(_)
             | crate::QueryState::Invalid(_)
             | crate::QueryState::Fetching(_) => Err(()),
         }
@@ -99,7 +100,7 @@ impl TryFrom<crate::QueryState<String>> for PersistQueryData {
 impl From<crate::QueryData<String>> for PersistQueryData {
     fn from(data: crate::QueryData<String>) -> Self {
         let value = data.data;
-        let updated_at = data.updated_at.0.as_millis() as u64;
+        let updated_at = data.updated_at.as_unix_millis();
         PersistQueryData { value, updated_at }
     }
 }
@@ -0,0 +1,50 @@
+use crate::QueryClient;
+
+/// A bundle of cache observers, persisters, and other client extensions.
+///
+/// Plugins let ecosystem crates (persistence, metrics, broadcast sync, etc.) register everything they need with one call to [`QueryClient::use_plugin`], in the order they need it.
+///
+/// Any `Fn(&QueryClient)` is also a plugin.
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::{cache_observer::*, *};
+///
+/// struct Logger;
+///
+/// impl CacheObserver for Logger {
+///     fn process_cache_event(&self, event: CacheEvent) {
+///         logging::log!("{:?}", event);
+///     }
+/// }
+///
+/// struct LoggingPlugin;
+///
+/// impl QueryPlugin for LoggingPlugin {
+///     fn install(&self, client: &QueryClient) {
+///         client.register_cache_observer(Logger);
+///     }
+/// }
+///
+/// #[component]
+/// fn App() -> impl IntoView {
+///     provide_query_client();
+///     use_query_client().use_plugin(LoggingPlugin);
+///
+///     // Rest of App...
+/// }
+/// ```
+pub trait QueryPlugin {
+    /// Register the plugin with the client.
+    fn install(&self, client: &QueryClient);
+}
+
+impl<F> QueryPlugin for F
+where
+    F: Fn(&QueryClient),
+{
+    fn install(&self, client: &QueryClient) {
+        self(client)
+    }
+}
@@ -1,4 +1,5 @@
-use crate::QueryState;
+use crate::{Instant, QueryState};
+use futures::{Future, StreamExt};
 use leptos::*;
 
 /// Reactive query result.
@@ -19,11 +20,525 @@ where
     pub is_fetching: Signal<bool>,
     /// If the query data has been marked as invalid.
     pub is_invalid: Signal<bool>,
+    /// If the query data is older than the stale time.
+    pub is_stale: Signal<bool>,
+    /// Why the latest fetched value was rejected, see [`QueryOptions::validator`](crate::QueryOptions::validator).
+    /// None once a fetched value is accepted.
+    pub validation_error: Signal<Option<String>>,
 
     /// Refetch the query.
     pub refetch: R,
 }
 
+impl<V, R> QueryResult<V, R>
+where
+    V: Clone + 'static,
+    R: RefetchFn,
+{
+    /// Selects a projection of the data, which only notifies when the projection changes according to `eq`.
+    ///
+    /// Useful when [`PartialEq`] is too strict or too loose for the projection, e.g. comparing floats within a tolerance.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use leptos_query::*;
+    ///
+    /// fn test() {
+    ///     provide_query_client();
+    ///     let query_scope = create_query(fetch_price, QueryOptions::default());
+    ///     let price = query_scope
+    ///         .use_query(|| 1)
+    ///         .select_with(|price| price.amount, |a, b| (a - b).abs() < 0.01);
+    /// }
+    ///
+    /// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    /// struct Price {
+    ///     amount: f64,
+    /// }
+    ///
+    /// async fn fetch_price(id: u32) -> Price {
+    ///    todo!()
+    /// }
+    /// ```
+    pub fn select_with<T>(
+        &self,
+        select: impl Fn(&V) -> T + 'static,
+        eq: impl Fn(&T, &T) -> bool + 'static,
+    ) -> Signal<Option<T>>
+    where
+        T: Clone + 'static,
+    {
+        select_with(self.data, select, eq)
+    }
+
+    /// Maps the data, keeping the loading, fetching, invalid and stale flags of the query.
+    ///
+    /// The result can be composed further, or passed to components that take a [`QueryResult`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    ///
+    /// #[component]
+    /// fn UserName(id: u32) -> impl IntoView {
+    ///     let name = user_query().use_query(move || id).map(|user| user.name.clone());
+    ///     let is_fetching = name.is_fetching;
+    ///     let name = name.unwrap_or_default();
+    ///
+    ///     view! {
+    ///         <Transition fallback=|| "Loading...">
+    ///             <span class:refreshing=is_fetching>{name}</span>
+    ///         </Transition>
+    ///     }
+    /// }
+    ///
+    /// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+    /// struct User {
+    ///     name: String,
+    /// }
+    ///
+    /// fn user_query() -> QueryScope<u32, User> {
+    ///     create_query(|id| async move { todo!() }, QueryOptions::default())
+    /// }
+    /// ```
+    pub fn map<T>(&self, f: impl Fn(&V) -> T + 'static) -> QueryResult<T, R>
+    where
+        T: Clone + 'static,
+    {
+        let f = std::rc::Rc::new(f);
+        let (data, state) = (self.data, self.state);
+        QueryResult {
+            data: Signal::derive({
+                let f = f.clone();
+                move || data.with(|data| data.as_ref().map(|data| f(data)))
+            }),
+            state: Signal::derive(move || state.with(|state| state.map_data(|data| f(data)))),
+            ..self.keep_flags()
+        }
+    }
+
+    /// Maps the data to an optional value, e.g. a field that may be missing.
+    ///
+    /// Where `f` returns `None`, the result has no data, as if the query hadn't loaded yet:
+    /// its state is [`Loading`](QueryState::Loading) while fetching, and [`Created`](QueryState::Created) otherwise.
+    /// The flags of the query are kept.
+    pub fn and_then<T>(&self, f: impl Fn(&V) -> Option<T> + 'static) -> QueryResult<T, R>
+    where
+        T: Clone + 'static,
+    {
+        let f = std::rc::Rc::new(f);
+        let (data, state) = (self.data, self.state);
+        QueryResult {
+            data: Signal::derive({
+                let f = f.clone();
+                move || data.with(|data| data.as_ref().and_then(|data| f(data)))
+            }),
+            state: Signal::derive(move || {
+                state.with(|state| and_then_state(state, |data| f(data)))
+            }),
+            ..self.keep_flags()
+        }
+    }
+
+    /// The data, or the default value while there is none.
+    pub fn unwrap_or_default(&self) -> Signal<V>
+    where
+        V: Default,
+    {
+        let data = self.data;
+        Signal::derive(move || data.get().unwrap_or_default())
+    }
+
+    /// The data, or `default` while there is none.
+    pub fn unwrap_or(&self, default: V) -> Signal<V> {
+        let data = self.data;
+        Signal::derive(move || data.get().unwrap_or_else(|| default.clone()))
+    }
+
+    // The flags and refetch of this result, with placeholder data to be replaced.
+    fn keep_flags<T>(&self) -> QueryResult<T, R> {
+        QueryResult {
+            data: Signal::derive(|| None),
+            state: Signal::derive(|| QueryState::Created),
+            is_loading: self.is_loading,
+            is_fetching: self.is_fetching,
+            is_invalid: self.is_invalid,
+            is_stale: self.is_stale,
+            validation_error: self.validation_error,
+            refetch: self.refetch.clone(),
+        }
+    }
+
+    /// Returns a future of the data, for async composition, e.g. inside of a [`create_resource`](leptos::create_resource).
+    ///
+    /// Resolves to the first `Loaded` data that is not stale, or that was fetched after this was called.
+    /// Stale data that isn't being refetched is refetched.
+    /// Resolves to `None` if the reactive scope is cleaned up before then.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use leptos::*;
+    /// use leptos_query::*;
+    ///
+    /// fn use_greeting(id: u32) -> Resource<(), Option<String>> {
+    ///     let user = user_query().use_query(move || id);
+    ///     create_local_resource(|| (), move |_| {
+    ///         let user = user.suspend();
+    ///         async move { user.await.map(|name| format!("Hello, {name}")) }
+    ///     })
+    /// }
+    ///
+    /// fn user_query() -> QueryScope<u32, String> {
+    ///     create_query(|id| async move { todo!() }, QueryOptions::default())
+    /// }
+    /// ```
+    pub fn suspend(&self) -> impl Future<Output = Option<V>> {
+        let (state, is_stale) = (self.state, self.is_stale);
+        let start = Instant::now();
+
+        let ready = Signal::derive(move || {
+            state.with(|state| match state {
+                QueryState::Loaded(data) if data.updated_at >= start || !is_stale.get() => {
+                    Some(data.data.clone())
+                }
+                _ => None,
+            })
+        });
+
+        if state.with_untracked(|state| matches!(state, QueryState::Loaded(_)))
+            && is_stale.get_untracked()
+        {
+            (self.refetch)();
+        }
+
+        let mut ready = ready.to_stream();
+        async move {
+            while let Some(data) = ready.next().await {
+                if data.is_some() {
+                    return data;
+                }
+            }
+            None
+        }
+    }
+}
+
 /// Convenience Trait alias for a Query Result's refetch function.
 pub trait RefetchFn: Fn() + Clone {}
 impl<R: Fn() + Clone> RefetchFn for R {}
+
+/// Reactive query result for a query whose value is a [`Result`], with the data and error split.
+/// Created with [`QueryResult::split`].
+#[derive(Clone)]
+pub struct SplitQueryResult<T, E, R>
+where
+    T: 'static,
+    E: 'static,
+    R: RefetchFn,
+{
+    /// The latest `Ok` value of the query. None if it has not been fetched successfully yet.
+    /// Kept while a refetch fails.
+    /// Should be called inside of a [`Transition`](leptos::Transition) or [`Suspense`](leptos::Suspense) component.
+    pub data: Signal<Option<T>>,
+    /// The error, if the latest fetch failed.
+    /// Should be called inside of a [`Transition`](leptos::Transition) or [`Suspense`](leptos::Suspense) component.
+    pub error: Signal<Option<E>>,
+    /// The current state of the data.
+    pub state: Signal<QueryState<Result<T, E>>>,
+    /// If the query is fetching for the first time.
+    pub is_loading: Signal<bool>,
+    /// If the query is actively fetching.
+    pub is_fetching: Signal<bool>,
+    /// If the query data has been marked as invalid.
+    pub is_invalid: Signal<bool>,
+    /// If the query data is older than the stale time.
+    pub is_stale: Signal<bool>,
+    /// Why the latest fetched value was rejected, see [`QueryOptions::validator`](crate::QueryOptions::validator).
+    /// None once a fetched value is accepted.
+    pub validation_error: Signal<Option<String>>,
+
+    /// Refetch the query.
+    pub refetch: R,
+}
+
+impl<T, E, R> QueryResult<Result<T, E>, R>
+where
+    T: Clone + 'static,
+    E: Clone + 'static,
+    R: RefetchFn,
+{
+    /// Splits the `Result` value into separate data and error signals.
+    pub fn split(self) -> SplitQueryResult<T, E, R> {
+        let QueryResult {
+            data: result,
+            state,
+            is_loading,
+            is_fetching,
+            is_invalid,
+            is_stale,
+            validation_error,
+            refetch,
+        } = self;
+
+        let (data, error) = split_result(result);
+
+        SplitQueryResult {
+            data,
+            error,
+            state,
+            is_loading,
+            is_fetching,
+            is_invalid,
+            is_stale,
+            validation_error,
+            refetch,
+        }
+    }
+}
+
+fn and_then_state<V, T>(state: &QueryState<V>, f: impl Fn(&V) -> Option<T>) -> QueryState<T> {
+    let mapped = |data: &crate::QueryData<V>| {
+        f(&data.data).map(|mapped| crate::QueryData {
+            data: mapped,
+            updated_at: data.updated_at,
+        })
+    };
+    match state {
+        QueryState::Created => QueryState::Created,
+        QueryState::Loading => QueryState::Loading,
+        QueryState::Rejected(error) => QueryState::Rejected(error.clone()),
+        QueryState::Fetching(data) => {
+            mapped(data).map_or(QueryState::Loading, QueryState::Fetching)
+        }
+        QueryState::Loaded(data) => mapped(data).map_or(QueryState::Created, QueryState::Loaded),
+        QueryState::Invalid(data) => mapped(data).map_or(QueryState::Created, QueryState::Invalid),
+    }
+}
+
+fn split_result<T, E>(
+    result: Signal<Option<Result<T, E>>>,
+) -> (Signal<Option<T>>, Signal<Option<E>>)
+where
+    T: Clone + 'static,
+    E: Clone + 'static,
+{
+    // Cleared once there is no result at all, e.g. when the key changes.
+    let last_ok = store_value(None::<T>);
+    let data = Signal::derive(move || match result.get() {
+        Some(Ok(data)) => {
+            last_ok.set_value(Some(data.clone()));
+            Some(data)
+        }
+        Some(Err(_)) => last_ok.get_value(),
+        None => {
+            last_ok.set_value(None);
+            None
+        }
+    });
+    let error = Signal::derive(move || result.get().and_then(Result::err));
+    (data, error)
+}
+
+// The memo compares versions, which only change when `eq` considers the selection changed.
+#[derive(Clone)]
+struct Versioned<T> {
+    version: u64,
+    value: T,
+}
+
+impl<T> PartialEq for Versioned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.version == other.version
+    }
+}
+
+fn select_with<V, T>(
+    data: Signal<Option<V>>,
+    select: impl Fn(&V) -> T + 'static,
+    eq: impl Fn(&T, &T) -> bool + 'static,
+) -> Signal<Option<T>>
+where
+    V: 'static,
+    T: Clone + 'static,
+{
+    let selected = create_memo(move |prev: Option<&Versioned<Option<T>>>| {
+        let value = data.with(|data| data.as_ref().map(&select));
+        match prev {
+            Some(prev) => {
+                let unchanged = match (&prev.value, &value) {
+                    (Some(prev), Some(value)) => eq(prev, value),
+                    (None, None) => true,
+                    _ => false,
+                };
+                if unchanged {
+                    prev.clone()
+                } else {
+                    Versioned {
+                        version: prev.version + 1,
+                        value,
+                    }
+                }
+            }
+            None => Versioned { version: 0, value },
+        }
+    });
+    Signal::derive(move || selected.with(|selected| selected.value.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_result_keeps_ok_data() {
+        let _ = create_runtime();
+
+        let result = RwSignal::new(None::<Result<u32, String>>);
+        let (data, error) = split_result(result.into());
+        assert_eq!(None, data.get());
+        assert_eq!(None, error.get());
+
+        result.set(Some(Ok(1)));
+        assert_eq!(Some(1), data.get());
+        assert_eq!(None, error.get());
+
+        // Failed refetch.
+        result.set(Some(Err("failed".to_string())));
+        assert_eq!(Some(1), data.get());
+        assert_eq!(Some("failed".to_string()), error.get());
+
+        result.set(Some(Ok(2)));
+        assert_eq!(Some(2), data.get());
+        assert_eq!(None, error.get());
+
+        // A new key without data.
+        result.set(None);
+        assert_eq!(None, data.get());
+        result.set(Some(Err("failed".to_string())));
+        assert_eq!(None, data.get());
+    }
+
+    #[test]
+    fn select_with_custom_eq() {
+        let _ = create_runtime();
+
+        let data = RwSignal::new(None::<f64>);
+        let selected = select_with(data.into(), |v| *v, |a: &f64, b: &f64| (a - b).abs() < 0.1);
+
+        let notified = store_value(0);
+        create_isomorphic_effect(move |_| {
+            selected.track();
+            notified.update_value(|n| *n += 1);
+        });
+        assert_eq!(1, notified.get_value());
+
+        data.set(Some(1.0));
+        assert_eq!(Some(1.0), selected.get());
+        assert_eq!(2, notified.get_value());
+
+        // Within tolerance, so the previous selection is kept.
+        data.set(Some(1.05));
+        assert_eq!(Some(1.0), selected.get());
+        assert_eq!(2, notified.get_value());
+
+        data.set(Some(2.0));
+        assert_eq!(Some(2.0), selected.get());
+        assert_eq!(3, notified.get_value());
+
+        data.set(None);
+        assert_eq!(None, selected.get());
+        assert_eq!(4, notified.get_value());
+    }
+    #[test]
+    fn suspend_waits_for_fresh_data() {
+        use futures::FutureExt;
+
+        let _ = create_runtime();
+
+        let stale_data = crate::QueryData {
+            data: 1,
+            updated_at: Instant(std::time::Duration::ZERO),
+        };
+        let state = RwSignal::new(QueryState::Loaded(stale_data.clone()));
+        let is_stale = RwSignal::new(true);
+        let refetched = store_value(0);
+        let result = QueryResult {
+            data: Signal::derive(move || state.with(|s| s.data().cloned())),
+            state: state.into(),
+            is_loading: Signal::derive(|| false),
+            is_fetching: Signal::derive(|| false),
+            is_invalid: Signal::derive(|| false),
+            is_stale: is_stale.into(),
+            validation_error: Signal::derive(|| None),
+            refetch: move || refetched.update_value(|n| *n += 1),
+        };
+
+        // Stale data is refetched, rather than returned.
+        let mut suspended = Box::pin(result.suspend());
+        assert_eq!(1, refetched.get_value());
+        assert_eq!(None, suspended.as_mut().now_or_never());
+
+        state.set(QueryState::Fetching(stale_data));
+        assert_eq!(None, suspended.as_mut().now_or_never());
+
+        // Fetched after suspending, even if already stale.
+        state.set(QueryState::Loaded(crate::QueryData::now(2)));
+        assert_eq!(Some(Some(2)), suspended.as_mut().now_or_never());
+
+        // Fresh data resolves immediately.
+        is_stale.set(false);
+        assert_eq!(Some(Some(2)), result.suspend().now_or_never());
+        assert_eq!(1, refetched.get_value());
+    }
+
+    #[test]
+    fn combinators_keep_flags() {
+        let _ = create_runtime();
+
+        let state = RwSignal::new(QueryState::<Vec<u32>>::Loading);
+        let is_fetching = RwSignal::new(true);
+        let result = QueryResult {
+            data: Signal::derive(move || state.with(|s| s.data().cloned())),
+            state: state.into(),
+            is_loading: Signal::derive(|| false),
+            is_fetching: is_fetching.into(),
+            is_invalid: Signal::derive(|| false),
+            is_stale: Signal::derive(|| true),
+            validation_error: Signal::derive(|| None),
+            refetch: || {},
+        };
+        let len = result.map(|list| list.len());
+        let first = result.and_then(|list| list.first().copied());
+        let first_or_default = first.unwrap_or_default();
+
+        assert_eq!(None, len.data.get());
+        assert!(len.is_fetching.get());
+        assert!(first.is_stale.get());
+        assert_eq!(0, first_or_default.get());
+
+        state.set(QueryState::Fetching(crate::QueryData::now(vec![])));
+        assert_eq!(Some(0), len.data.get());
+        assert_eq!(Some(0), len.state.get().data().copied());
+        // Missing data reads as loading.
+        assert_eq!(None, first.data.get());
+        assert_eq!(QueryState::Loading, first.state.get());
+
+        state.set(QueryState::Loaded(crate::QueryData::now(vec![3, 4])));
+        is_fetching.set(false);
+        assert_eq!(Some(2), len.data.get());
+        assert!(!len.is_fetching.get());
+        assert_eq!(Some(3), first.state.get().data().copied());
+        assert_eq!(3, first_or_default.get());
+        assert_eq!(
+            7,
+            result
+                .map(|list| list.iter().sum::<u32>())
+                .unwrap_or(0)
+                .get()
+        );
+    }
+}
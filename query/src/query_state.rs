@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use crate::Instant;
 
 /// The lifecycle of a query.
@@ -5,6 +7,7 @@ use crate::Instant;
 /// Each variant in the enum corresponds to a particular state of a query in its lifecycle,
 /// starting from creation and covering all possible transitions up to invalidation.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum QueryState<V> {
     /// The initial state of a Query upon its creation.
     ///
@@ -36,13 +39,31 @@ pub enum QueryState<V> {
     ///
     /// The associated `QueryData<V>` object holds the invalidated data.
     Invalid(QueryData<V>),
+
+    /// The state indicating that the first fetch returned a value rejected by a [`validator`](crate::QueryOptions::validator).
+    ///
+    /// The rejected value isn't stored, so there is no data. The associated `String` holds the validation error.
+    /// The query is fetched again on the next mount or refetch.
+    Rejected(String),
 }
 
 impl<V> QueryState<V> {
+    /// The variant of the state, without its data.
+    pub fn kind(&self) -> QueryStateKind {
+        match self {
+            QueryState::Created => QueryStateKind::Created,
+            QueryState::Loading => QueryStateKind::Loading,
+            QueryState::Fetching(_) => QueryStateKind::Fetching,
+            QueryState::Loaded(_) => QueryStateKind::Loaded,
+            QueryState::Invalid(_) => QueryStateKind::Invalid,
+            QueryState::Rejected(_) => QueryStateKind::Rejected,
+        }
+    }
+
     /// Returns the QueryData for the current QueryState, if present.
     pub fn query_data(&self) -> Option<&QueryData<V>> {
         match self {
-            QueryState::Loading | QueryState::Created => None,
+            QueryState::Loading | QueryState::Created | QueryState::Rejected(_) => None,
             QueryState::Fetching(data) | QueryState::Loaded(data) | QueryState::Invalid(data) => {
                 Some(data)
             }
@@ -59,10 +80,15 @@ impl<V> QueryState<V> {
         self.query_data().map(|s| s.updated_at)
     }
 
+    /// Returns the age of the data for the QueryState, if present. See [`QueryData::age`].
+    pub fn age(&self) -> Option<Duration> {
+        self.query_data().map(QueryData::age)
+    }
+
     /// Returns the mutable data contained within the QueryState, if present.
     pub fn data_mut(&mut self) -> Option<&mut V> {
         match self {
-            QueryState::Loading | QueryState::Created => None,
+            QueryState::Loading | QueryState::Created | QueryState::Rejected(_) => None,
             QueryState::Fetching(data) | QueryState::Loaded(data) | QueryState::Invalid(data) => {
                 Some(&mut data.data)
             }
@@ -74,6 +100,7 @@ impl<V> QueryState<V> {
         match self {
             QueryState::Loading => QueryState::Loading,
             QueryState::Created => QueryState::Created,
+            QueryState::Rejected(error) => QueryState::Rejected(error.clone()),
             QueryState::Fetching(data) => QueryState::Fetching(QueryData {
                 data: mapper(&data.data),
                 updated_at: data.updated_at,
@@ -90,8 +117,68 @@ impl<V> QueryState<V> {
     }
 }
 
+/// The variant of a [`QueryState`], without its data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QueryStateKind {
+    /// See [`QueryState::Created`].
+    Created,
+    /// See [`QueryState::Loading`].
+    Loading,
+    /// See [`QueryState::Fetching`].
+    Fetching,
+    /// See [`QueryState::Loaded`].
+    Loaded,
+    /// See [`QueryState::Invalid`].
+    Invalid,
+    /// See [`QueryState::Rejected`].
+    Rejected,
+}
+
+impl QueryStateKind {
+    /// Whether a query may move from this state to `to`. All state changes are checked against this.
+    ///
+    /// | From \ To   | Created | Loading | Fetching | Loaded | Invalid | Rejected |
+    /// |-------------|---------|---------|----------|--------|---------|----------|
+    /// | Created     | yes     | yes     | no       | yes    | yes     | yes      |
+    /// | Loading     | yes     | yes     | yes      | yes    | yes     | yes      |
+    /// | Fetching    | no      | yes     | yes      | yes    | yes     | no       |
+    /// | Loaded      | no      | yes     | yes      | yes    | yes     | no       |
+    /// | Invalid     | no      | yes     | yes      | yes    | yes     | no       |
+    /// | Rejected    | yes     | yes     | no       | yes    | yes     | yes      |
+    ///
+    /// - Data is never dropped: a query with data can't go back to `Created` or `Rejected`. Only a cancelled or rejected first fetch can.
+    /// - `Fetching` keeps the previous data, so it needs data or a first fetch that was restored from the persister.
+    /// - Data can be set from any state, e.g. with [`set_query_data`](crate::QueryClient::set_query_data) or by importing a query.
+    /// - A refetch is `Loading` when the previous data is outside of the [`revalidate_window`](crate::QueryOptions::revalidate_window).
+    pub fn can_transition_to(self, to: QueryStateKind) -> bool {
+        use QueryStateKind::*;
+        !matches!(
+            (self, to),
+            (Created | Rejected, Fetching) | (Fetching | Loaded | Invalid, Created | Rejected)
+        )
+    }
+}
+
+/// A state change of a query, recorded with the `state_assertions` feature. See [`QueryClient::state_transitions`](crate::QueryClient::state_transitions).
+#[cfg(feature = "state_assertions")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateTransition {
+    /// The key of the query.
+    pub key: crate::cache_observer::QueryCacheKey,
+    /// The previous state.
+    pub from: QueryStateKind,
+    /// The new state.
+    pub to: QueryStateKind,
+    /// Whether the transition is allowed by [`QueryStateKind::can_transition_to`].
+    pub allowed: bool,
+    /// When the state changed.
+    pub at: Instant,
+}
+
 /// The latest data for a Query.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QueryData<V> {
     /// The Data.
     pub data: V,
@@ -107,4 +194,79 @@ impl<V> QueryData<V> {
             updated_at: Instant::now(),
         }
     }
+
+    /// How long ago this data was retrieved.
+    pub fn age(&self) -> Duration {
+        Instant::now().0.saturating_sub(self.updated_at.0)
+    }
+
+    /// Whether this data was retrieved more than `duration` ago. Useful for app-level heuristics, e.g. forcing a refresh of data older than a day.
+    pub fn is_older_than(&self, duration: Duration) -> bool {
+        self.age() > duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_age() {
+        let data = QueryData {
+            data: (),
+            updated_at: Instant(Instant::now().0 - Duration::from_secs(60)),
+        };
+        assert!(data.age() >= Duration::from_secs(60));
+        assert!(data.is_older_than(Duration::from_secs(30)));
+        assert!(!data.is_older_than(Duration::from_secs(60 * 60)));
+
+        // Timestamps in the future, e.g. from clock skew, have no age.
+        let future = QueryData {
+            data: (),
+            updated_at: Instant(Instant::now().0 + Duration::from_secs(60)),
+        };
+        assert_eq!(Duration::ZERO, future.age());
+
+        assert_eq!(None, QueryState::<()>::Loading.age());
+        assert!(QueryState::Loaded(data).age().is_some());
+    }
+
+    #[test]
+    fn transitions() {
+        use QueryStateKind::*;
+
+        // First fetch, cancelled first fetch.
+        assert!(Created.can_transition_to(Loading));
+        assert!(Loading.can_transition_to(Created));
+        // Refetch, and a refetch of expired data.
+        assert!(Invalid.can_transition_to(Fetching));
+        assert!(Loaded.can_transition_to(Loading));
+        assert!(Loading.can_transition_to(Fetching));
+
+        // Rejected first fetch, and its refetch.
+        assert!(Loading.can_transition_to(Rejected));
+        assert!(Rejected.can_transition_to(Loading));
+
+        assert!(!Created.can_transition_to(Fetching));
+        assert!(!Rejected.can_transition_to(Fetching));
+        for with_data in [Fetching, Loaded, Invalid] {
+            assert!(!with_data.can_transition_to(Created));
+            assert!(!with_data.can_transition_to(Rejected));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let state = QueryState::Loaded(QueryData {
+            data: "value".to_string(),
+            updated_at: Instant::from_unix_millis(1_700_000_000_000),
+        });
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(
+            r#"{"Loaded":{"data":"value","updated_at":1700000000000}}"#,
+            json
+        );
+        assert_eq!(state, serde_json::from_str(&json).unwrap());
+    }
 }
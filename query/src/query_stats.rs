@@ -0,0 +1,64 @@
+/// How `use_query` mounts of a single <K, V> type were served.
+///
+/// Useful for tuning `stale_time`: few cache hits suggest it is too short for how often the data is used.
+/// A mount is counted when an observer with a fetcher subscribes to a query, e.g. when a component using [`use_query`](crate::use_query()) is mounted or its key changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    /// The name of the key type.
+    pub key_type: &'static str,
+    /// The name of the value type.
+    pub value_type: &'static str,
+    /// Mounts served by fresh cached data.
+    pub cache_hits: u32,
+    /// Mounts that joined a fetch that was already in flight.
+    pub deduped: u32,
+    /// Mounts that needed a fetch, because there was no data, or it was stale or invalid.
+    pub fetches: u32,
+}
+
+impl QueryStats {
+    pub(crate) fn new<K: 'static, V: 'static>() -> Self {
+        QueryStats {
+            key_type: std::any::type_name::<K>(),
+            value_type: std::any::type_name::<V>(),
+            ..Default::default()
+        }
+    }
+
+    /// Total number of mounts.
+    pub fn mounts(&self) -> u32 {
+        self.cache_hits + self.deduped + self.fetches
+    }
+
+    /// The fraction of mounts that didn't start a fetch. None if there were no mounts.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let mounts = self.mounts();
+        (mounts > 0).then(|| f64::from(self.cache_hits + self.deduped) / f64::from(mounts))
+    }
+
+    pub(crate) fn record(&mut self, mount: Mount) {
+        match mount {
+            Mount::CacheHit => self.cache_hits += 1,
+            Mount::Deduped => self.deduped += 1,
+            Mount::Fetch => self.fetches += 1,
+        }
+    }
+}
+
+/// The number of queries in the cache for a single <K, V> type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeSize {
+    /// The name of the key type.
+    pub key_type: &'static str,
+    /// The name of the value type.
+    pub value_type: &'static str,
+    /// The number of queries.
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mount {
+    CacheHit,
+    Deduped,
+    Fetch,
+}
@@ -0,0 +1,152 @@
+use std::{
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
+
+use leptos::{Serializable, SerializationError};
+
+/// Query value stored behind an [`Rc`], for large values that are expensive to clone.
+///
+/// Query values are cloned whenever their state is read or observers are notified.
+/// Wrapping the value makes those clones cheap, as they only share the [`Rc`].
+/// It serializes as the inner value, so it can be rendered on the server and persisted like any other value.
+///
+/// Mutable access clones the inner value only if it is shared, like [`Rc::make_mut`].
+/// So [`QueryClient::update_query_data_mut`](crate::QueryClient::update_query_data_mut) only clones a value that is still referenced elsewhere.
+///
+/// ```
+/// use leptos_query::*;
+///
+/// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// struct Report {
+///     rows: Vec<String>,
+/// }
+///
+/// fn report_query() -> QueryScope<u32, QueryValueRc<Report>> {
+///     create_query(
+///         |id| async move { QueryValueRc::new(get_report(id).await) },
+///         QueryOptions::default(),
+///     )
+/// }
+///
+/// fn add_row(id: u32, row: String) {
+///     use_query_client().update_query_data_mut::<u32, QueryValueRc<Report>>(id, |report| {
+///         report.rows.push(row)
+///     });
+/// }
+///
+/// async fn get_report(id: u32) -> Report {
+///     todo!()
+/// }
+/// ```
+pub struct QueryValueRc<V>(Rc<V>);
+
+impl<V> QueryValueRc<V> {
+    /// Wraps the value.
+    pub fn new(value: V) -> Self {
+        Self(Rc::new(value))
+    }
+
+    /// The shared value.
+    pub fn into_rc(self) -> Rc<V> {
+        self.0
+    }
+
+    /// Whether both point to the same value.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<V> Clone for QueryValueRc<V> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<V: Debug> Debug for QueryValueRc<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<V: PartialEq> PartialEq for QueryValueRc<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.0 == other.0
+    }
+}
+
+impl<V: Eq> Eq for QueryValueRc<V> {}
+
+impl<V> Deref for QueryValueRc<V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.0
+    }
+}
+
+impl<V: Clone> DerefMut for QueryValueRc<V> {
+    fn deref_mut(&mut self) -> &mut V {
+        Rc::make_mut(&mut self.0)
+    }
+}
+
+impl<V> AsRef<V> for QueryValueRc<V> {
+    fn as_ref(&self) -> &V {
+        &self.0
+    }
+}
+
+impl<V> From<V> for QueryValueRc<V> {
+    fn from(value: V) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<V> From<Rc<V>> for QueryValueRc<V> {
+    fn from(value: Rc<V>) -> Self {
+        Self(value)
+    }
+}
+
+impl<V: Serializable> Serializable for QueryValueRc<V> {
+    fn ser(&self) -> Result<String, SerializationError> {
+        self.0.ser()
+    }
+
+    fn de(bytes: &str) -> Result<Self, SerializationError> {
+        V::de(bytes).map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_on_write_only_if_shared() {
+        let mut value = QueryValueRc::new(vec![1, 2]);
+        let shared = value.clone();
+        assert!(value.ptr_eq(&shared));
+
+        value.push(3);
+        assert!(!value.ptr_eq(&shared));
+        assert_eq!(vec![1, 2], *shared);
+
+        // No longer shared, so mutated in place.
+        let before = Rc::as_ptr(&value.0);
+        value.push(4);
+        assert_eq!(before, Rc::as_ptr(&value.0));
+        assert_eq!(vec![1, 2, 3, 4], *value);
+    }
+
+    #[test]
+    fn serializes_as_inner_value() {
+        let value = QueryValueRc::new(vec![1, 2]);
+        let serialized = value.ser().unwrap();
+        assert_eq!(Vec::<i32>::ser(&vec![1, 2]).unwrap(), serialized);
+        assert_eq!(value, QueryValueRc::<Vec<i32>>::de(&serialized).unwrap());
+    }
+}
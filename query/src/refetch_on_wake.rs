@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use crate::{Instant, QueryClient, QueryPlugin};
+
+/// What to do once the page wakes from a long suspend. See [`RefetchOnWake`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WakeAction {
+    /// Invalidates every query. Observed queries refetch according to their [`invalidate_mode`](crate::QueryOptions::invalidate_mode), the rest when they are next used.
+    #[default]
+    InvalidateAll,
+    /// Only refetches observed queries that are stale or invalid. See [`QueryClient::revalidate_stale`].
+    RevalidateStale,
+}
+
+/// Plugin that refreshes the cache once the page wakes from a long suspend, e.g. after a laptop was closed.
+///
+/// Timers don't fire while the device sleeps, so stale times and refetch intervals silently lapse, and the data shown may be hours old.
+/// A timer compares the wall clock with when it was expected to fire. If it is late by more than the threshold, the page was asleep.
+/// Only runs on the client (`csr` or `hydrate`).
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+/// use std::time::Duration;
+///
+/// #[component]
+/// fn App() -> impl IntoView {
+///     QueryClientBuilder::new()
+///         .plugin(RefetchOnWake::new(Duration::from_secs(5 * 60)).action(WakeAction::RevalidateStale))
+///         .provide();
+///
+///     // Rest of App...
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RefetchOnWake {
+    threshold: Duration,
+    action: WakeAction,
+}
+
+// How often the clock is checked.
+#[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+impl RefetchOnWake {
+    /// Refreshes the cache if the page was asleep for longer than `threshold`. Invalidates every query by default.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            action: WakeAction::default(),
+        }
+    }
+
+    /// Sets what to do once the page wakes.
+    pub fn action(self, action: WakeAction) -> Self {
+        Self { action, ..self }
+    }
+
+    // Whether a check that was due `CHECK_INTERVAL` after `last` ran too late.
+    #[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+    fn woke(&self, last: Instant, now: Instant) -> bool {
+        let elapsed = now.0.saturating_sub(last.0);
+        elapsed.saturating_sub(CHECK_INTERVAL) > self.threshold
+    }
+
+    #[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+    fn on_wake(&self, client: &QueryClient) {
+        match self.action {
+            WakeAction::InvalidateAll => client.invalidate_all_queries(),
+            WakeAction::RevalidateStale => {
+                client.revalidate_stale();
+            }
+        }
+    }
+}
+
+impl QueryPlugin for RefetchOnWake {
+    fn install(&self, client: &QueryClient) {
+        cfg_if::cfg_if! {
+            if #[cfg(any(feature = "csr", feature = "hydrate"))] {
+                use leptos::*;
+                use std::cell::Cell;
+
+                let detector = *self;
+                let owner = client.cache.owner();
+                let client = client.clone();
+                let last = Cell::new(Instant::now());
+                let handle = set_interval_with_handle(
+                    move || {
+                        let now = Instant::now();
+                        if detector.woke(last.replace(now), now) {
+                            // Timers run outside the reactive tree, so the refetches run under the client's owner.
+                            with_owner(owner, || detector.on_wake(&client));
+                        }
+                    },
+                    CHECK_INTERVAL,
+                );
+                match handle {
+                    Ok(handle) => {
+                        with_owner(owner, || on_cleanup(move || handle.clear()))
+                    }
+                    Err(e) => logging::debug_warn!("Failed to start wake detection: {e:?}"),
+                }
+            } else {
+                let _ = client;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_late_checks() {
+        let detector = RefetchOnWake::new(Duration::from_secs(60));
+        let at = |secs| Instant(Duration::from_secs(secs));
+
+        // On time, or slightly late.
+        assert!(!detector.woke(at(0), at(10)));
+        assert!(!detector.woke(at(0), at(70)));
+
+        assert!(detector.woke(at(0), at(71)));
+        assert!(detector.woke(at(0), at(3600)));
+    }
+}
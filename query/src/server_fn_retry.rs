@@ -0,0 +1,223 @@
+use std::{future::Future, pin::Pin, rc::Rc, time::Duration};
+
+use leptos::{ServerFnError, Signal, SignalWith};
+
+use crate::{util::sleep, QueryResult, RefetchFn};
+
+/// What went wrong in a server function call, to decide whether to retry it and what to tell the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServerFnErrorKind {
+    /// The server couldn't be reached, e.g. while offline. Usually transient.
+    Network,
+    /// The arguments or the response couldn't be serialized or deserialized, e.g. when the client and server are different versions.
+    Deserialization,
+    /// The server function ran and failed, or returned an app error. Retrying would fail the same way.
+    Server,
+}
+
+impl ServerFnErrorKind {
+    /// Classifies a server function error.
+    pub fn of<E>(error: &ServerFnError<E>) -> Self {
+        match error {
+            ServerFnError::Request(_) => ServerFnErrorKind::Network,
+            ServerFnError::Deserialization(_)
+            | ServerFnError::Serialization(_)
+            | ServerFnError::Args(_)
+            | ServerFnError::MissingArg(_) => ServerFnErrorKind::Deserialization,
+            ServerFnError::WrappedServerError(_)
+            | ServerFnError::Registration(_)
+            | ServerFnError::Response(_)
+            | ServerFnError::ServerError(_) => ServerFnErrorKind::Server,
+        }
+    }
+
+    /// If a call that failed this way may succeed when retried. Only network errors are.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ServerFnErrorKind::Network)
+    }
+}
+
+/// Retries server function calls that fail with a [network error](ServerFnErrorKind::Network), with exponential backoff.
+///
+/// Other errors are returned right away, as retrying would fail the same way.
+/// Only the final outcome is stored in the cache, so the query keeps fetching while it retries.
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// #[server]
+/// async fn get_user(id: u32) -> Result<String, ServerFnError> {
+///     Ok(format!("User {id}"))
+/// }
+///
+/// fn user_query() -> QueryScope<u32, Result<String, ServerFnError>> {
+///     create_query(ServerFnRetry::default().fetcher(get_user), QueryOptions::default())
+/// }
+///
+/// #[component]
+/// fn User(id: u32) -> impl IntoView {
+///     let user = user_query().use_query(move || id);
+///     let error_kind = user.error_kind();
+///
+///     view! {
+///         {move || match error_kind.get() {
+///             Some(ServerFnErrorKind::Network) => "You're offline.",
+///             Some(_) => "Something went wrong.",
+///             None => "",
+///         }}
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ServerFnRetry {
+    retries: u32,
+    delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for ServerFnRetry {
+    /// Retries 3 times, after 1, 2 and 4 seconds.
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ServerFnRetry {
+    /// Sets how many times a call is retried. Default is 3.
+    pub fn retries(self, retries: u32) -> Self {
+        Self { retries, ..self }
+    }
+
+    /// Sets the delay before the first retry, which doubles on every retry. Default is 1 second.
+    pub fn delay(self, delay: Duration) -> Self {
+        Self { delay, ..self }
+    }
+
+    /// Sets the longest delay between retries. Default is 30 seconds.
+    pub fn max_delay(self, max_delay: Duration) -> Self {
+        Self { max_delay, ..self }
+    }
+
+    /// The delay before a retry, counting from zero.
+    fn backoff(&self, retry: u32) -> Duration {
+        self.delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay)
+    }
+
+    /// Wraps a server function, or any fetcher returning a [`ServerFnError`], to retry it.
+    #[allow(clippy::type_complexity)]
+    pub fn fetcher<K, T, E, Fu>(
+        self,
+        fetcher: impl Fn(K) -> Fu + 'static,
+    ) -> impl Fn(K) -> Pin<Box<dyn Future<Output = Result<T, ServerFnError<E>>>>> + Clone
+    where
+        K: Clone + 'static,
+        T: 'static,
+        E: 'static,
+        Fu: Future<Output = Result<T, ServerFnError<E>>> + 'static,
+    {
+        let fetcher = Rc::new(fetcher);
+        move |key: K| {
+            let fetcher = fetcher.clone();
+            Box::pin(async move {
+                let mut retry = 0;
+                loop {
+                    match fetcher(key.clone()).await {
+                        Err(e)
+                            if retry < self.retries && ServerFnErrorKind::of(&e).is_retryable() =>
+                        {
+                            let delay = self.backoff(retry);
+                            if !delay.is_zero() {
+                                sleep(delay).await;
+                            }
+                            retry += 1;
+                        }
+                        result => return result,
+                    }
+                }
+            })
+        }
+    }
+}
+
+impl<T, E, R> QueryResult<Result<T, ServerFnError<E>>, R>
+where
+    T: 'static,
+    E: 'static,
+    R: RefetchFn,
+{
+    /// The kind of error, if the latest fetch failed. Useful to tailor error messages, e.g. for being offline.
+    pub fn error_kind(&self) -> Signal<Option<ServerFnErrorKind>> {
+        let data = self.data;
+        Signal::derive(move || {
+            data.with(|data| match data {
+                Some(Err(e)) => Some(ServerFnErrorKind::of(e)),
+                _ => None,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn retries_network_errors() {
+        let calls = Rc::new(Cell::new(0));
+        let fetcher = {
+            let calls = calls.clone();
+            move |fail_with: &'static str| {
+                calls.set(calls.get() + 1);
+                let calls = calls.get();
+                async move {
+                    match fail_with {
+                        // Reachable on the third call.
+                        "network" if calls < 3 => Err(ServerFnError::Request("offline".into())),
+                        "network" => Ok(calls),
+                        _ => Err(
+                            ServerFnError::<leptos::server_fn::error::NoCustomError>::ServerError(
+                                "not found".into(),
+                            ),
+                        ),
+                    }
+                }
+            }
+        };
+        let fetch = ServerFnRetry::default()
+            .delay(Duration::ZERO)
+            .fetcher(fetcher);
+
+        assert_eq!(Ok(3), block_on(fetch("network")));
+        // App errors aren't retried.
+        calls.set(0);
+        let error = block_on(fetch("server")).unwrap_err();
+        assert_eq!(ServerFnErrorKind::Server, ServerFnErrorKind::of(&error));
+        assert_eq!(1, calls.get());
+
+        // Gives up after the last retry.
+        calls.set(-10);
+        let error = block_on(fetch("network")).unwrap_err();
+        assert_eq!(ServerFnErrorKind::Network, ServerFnErrorKind::of(&error));
+        assert_eq!(-6, calls.get());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let retry = ServerFnRetry::default().max_delay(Duration::from_secs(3));
+        assert_eq!(Duration::from_secs(1), retry.backoff(0));
+        assert_eq!(Duration::from_secs(2), retry.backoff(1));
+        assert_eq!(Duration::from_secs(3), retry.backoff(2));
+        assert_eq!(Duration::from_secs(3), retry.backoff(40));
+    }
+}
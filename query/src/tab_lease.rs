@@ -0,0 +1,211 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::{QueryClient, QueryPlugin};
+
+/// Plugin that elects a single tab to perform interval refetches, and shares its results with the other tabs.
+///
+/// When many tabs poll the same queries, each would hit the backend on every interval.
+/// With this plugin, the tabs elect a leader through a [Web Lock](https://developer.mozilla.org/en-US/docs/Web/API/Web_Locks_API).
+/// Only the leader runs interval refetches, and broadcasts the fetched data on a [`BroadcastChannel`](https://developer.mozilla.org/en-US/docs/Web/API/BroadcastChannel).
+/// The other tabs apply it to their own queries, if newer, without fetching.
+/// Once the leader closes, another tab takes over the lock.
+///
+/// A tab still refetches on its own if its data falls more than one interval behind, e.g. because the leader's queries aren't visible.
+/// Fetches that aren't from refetch intervals, such as on mount or after invalidation, are unaffected.
+/// Tabs only share data with others using the same `name`. Data is matched by serialized key, like a [persister](crate::query_persister).
+///
+/// Only runs on the client (`csr` or `hydrate`). In browsers without Web Locks every tab refetches, as without the plugin.
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// #[component]
+/// fn App() -> impl IntoView {
+///     QueryClientBuilder::new()
+///         .plugin(CrossTabLease::new("my-app"))
+///         .provide();
+///
+///     // Rest of App...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CrossTabLease {
+    #[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+    name: String,
+}
+
+impl CrossTabLease {
+    /// Shares the lease between tabs of the app with the same name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl QueryPlugin for CrossTabLease {
+    fn install(&self, client: &QueryClient) {
+        cfg_if::cfg_if! {
+            if #[cfg(any(feature = "csr", feature = "hydrate"))] {
+                browser::install(&self.name, client);
+            } else {
+                let _ = client;
+            }
+        }
+    }
+}
+
+/// Whether this tab may run interval refetches. Held unless [`CrossTabLease`] elected another tab.
+#[derive(Clone, Debug)]
+pub struct RefetchLease(Rc<Cell<bool>>);
+
+impl Default for RefetchLease {
+    fn default() -> Self {
+        RefetchLease(Rc::new(Cell::new(true)))
+    }
+}
+
+impl RefetchLease {
+    pub fn is_held(&self) -> bool {
+        self.0.get()
+    }
+
+    #[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+    pub fn set_held(&self, held: bool) {
+        self.0.set(held)
+    }
+}
+
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+mod browser {
+    use js_sys::{
+        wasm_bindgen::{closure::Closure, JsCast, JsValue},
+        Function, Object, Promise, Reflect,
+    };
+    use leptos::*;
+    use web_sys::{BroadcastChannel, MessageEvent};
+
+    use crate::{
+        cache_observer::{CacheEvent, CacheObserver, QueryCacheKey},
+        query_persister::PersistQueryData,
+        QueryClient,
+    };
+
+    use super::RefetchLease;
+
+    pub(super) fn install(name: &str, client: &QueryClient) {
+        let lease = client.cache.refetch_lease().clone();
+        let owner = client.cache.owner();
+
+        let channel = match BroadcastChannel::new(&format!("leptos-query:{name}")) {
+            Ok(channel) => channel,
+            Err(e) => {
+                logging::debug_warn!("Failed to open cross-tab channel: {e:?}");
+                return;
+            }
+        };
+
+        // Follow until the lock is granted.
+        let release = match request_lock(&format!("leptos-query-lease:{name}"), lease.clone()) {
+            Ok(release) => {
+                lease.set_held(false);
+                Some(release)
+            }
+            Err(e) => {
+                logging::debug_warn!("Web Locks unavailable, every tab refetches: {e:?}");
+                None
+            }
+        };
+
+        let on_message = {
+            let cache = client.cache.clone();
+            let lease = lease.clone();
+            Closure::<dyn Fn(MessageEvent)>::new(move |event: MessageEvent| {
+                if lease.is_held() {
+                    return;
+                }
+                if let Some((key, data)) = decode(&event.data()) {
+                    with_owner(owner, || cache.restore_by_key(&key, data));
+                }
+            })
+        };
+        channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        client.register_cache_observer(Broadcaster {
+            channel: channel.clone(),
+            lease,
+        });
+
+        with_owner(owner, || {
+            on_cleanup(move || {
+                channel.set_onmessage(None);
+                channel.close();
+                drop(on_message);
+                if let Some(release) = release {
+                    let _ = release.call0(&JsValue::UNDEFINED);
+                }
+            })
+        });
+    }
+
+    // Requests the lock, which is held until the returned function is called or the tab closes.
+    fn request_lock(name: &str, lease: RefetchLease) -> Result<Function, JsValue> {
+        let navigator = Reflect::get(&window(), &"navigator".into())?;
+        let locks = Reflect::get(&navigator, &"locks".into())?;
+        if locks.is_undefined() {
+            return Err("navigator.locks is undefined".into());
+        }
+        let request = Reflect::get(&locks, &"request".into())?.dyn_into::<Function>()?;
+
+        // The lock is released once the promise returned from the callback resolves.
+        let mut release = None;
+        let held = Promise::new(&mut |resolve, _| release = Some(resolve));
+        let granted = Closure::once_into_js(move |_: JsValue| {
+            lease.set_held(true);
+            held
+        });
+        request.call2(&locks, &name.into(), &granted)?;
+        release.ok_or_else(|| "Promise executor did not run".into())
+    }
+
+    // Broadcasts loaded data while this tab holds the lease.
+    struct Broadcaster {
+        channel: BroadcastChannel,
+        lease: RefetchLease,
+    }
+
+    impl CacheObserver for Broadcaster {
+        fn process_cache_event(&self, event: CacheEvent) {
+            if !self.lease.is_held() {
+                return;
+            }
+            if let CacheEvent::Updated(query) = event {
+                if let Ok(data) = PersistQueryData::try_from(query.state) {
+                    if let Err(e) = self.channel.post_message(&encode(&query.key, &data)) {
+                        logging::debug_warn!("Failed to broadcast query {:?}: {e:?}", query.key.0);
+                    }
+                }
+            }
+        }
+    }
+
+    fn encode(key: &QueryCacheKey, data: &PersistQueryData) -> JsValue {
+        let message = Object::new();
+        let _ = Reflect::set(&message, &"key".into(), &key.0.as_str().into());
+        let _ = Reflect::set(&message, &"value".into(), &data.value.as_str().into());
+        let _ = Reflect::set(
+            &message,
+            &"updatedAt".into(),
+            &(data.updated_at as f64).into(),
+        );
+        message.into()
+    }
+
+    fn decode(message: &JsValue) -> Option<(QueryCacheKey, PersistQueryData)> {
+        let field = |name: &str| Reflect::get(message, &name.into()).ok();
+        let key = field("key")?.as_string()?;
+        let value = field("value")?.as_string()?;
+        let updated_at = field("updatedAt")?.as_f64()? as u64;
+        Some((QueryCacheKey(key), PersistQueryData { value, updated_at }))
+    }
+}
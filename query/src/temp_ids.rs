@@ -0,0 +1,154 @@
+use std::{cell::RefCell, collections::HashMap, future::Future, pin::Pin, rc::Rc};
+
+use crate::{use_query_client, QueryClient, QueryKey, QueryScope, QueryValue};
+
+/// Reconciles optimistically inserted list items, which use a temporary id until the server responds with the real one.
+/// Created with [`QueryScope::temp_ids`].
+///
+/// Once the real item arrives, the temporary item in the list is replaced, and cached data of any registered detail scopes is moved from the temporary id to the real one.
+/// The mapping is kept, so that components still holding a temporary id can look up the real one with [`TempIds::resolve`].
+///
+/// # Example
+///
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// fn add(content: String) {
+///     let temp_ids = all_todos_query().temp_ids(|todo: &Todo| todo.id).with_detail(&todo_query());
+///
+///     // Negative ids are never assigned by the server.
+///     let temp = Todo { id: -1, content: content.clone() };
+///     let insert = temp_ids.insert(AllTodos, temp, add_todo(content));
+///     spawn_local(async move {
+///         let todo = insert.await;
+///     });
+/// }
+///
+/// fn all_todos_query() -> QueryScope<AllTodos, Vec<Todo>> {
+///     create_query(get_todos, QueryOptions::default())
+/// }
+///
+/// fn todo_query() -> QueryScope<i64, Option<Todo>> {
+///     create_query(get_todo, QueryOptions::default())
+/// }
+///
+/// #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+/// struct AllTodos;
+///
+/// #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// struct Todo {
+///     id: i64,
+///     content: String,
+/// }
+///
+/// async fn get_todos(_: AllTodos) -> Vec<Todo> {
+///     todo!()
+/// }
+///
+/// async fn get_todo(id: i64) -> Option<Todo> {
+///     todo!()
+/// }
+///
+/// async fn add_todo(content: String) -> Todo {
+///     todo!()
+/// }
+/// ```
+#[derive(Clone)]
+pub struct TempIds<K, T, Id> {
+    // The client is retrieved up front, as reconciliation happens after awaiting, when the reactive owner may be gone.
+    client: QueryClient,
+    list: QueryScope<K, Vec<T>>,
+    id_of: Rc<dyn Fn(&T) -> Id>,
+    // Moves cached detail data from the temporary id to the real one.
+    #[allow(clippy::type_complexity)]
+    details: Vec<Rc<dyn Fn(&Id, &Id)>>,
+    resolved: Rc<RefCell<HashMap<Id, Id>>>,
+}
+
+impl<K, T, Id> TempIds<K, T, Id>
+where
+    K: QueryKey + 'static,
+    T: Clone + 'static,
+    Vec<T>: QueryValue,
+    Id: QueryKey + 'static,
+{
+    pub(crate) fn new(list: QueryScope<K, Vec<T>>, id_of: impl Fn(&T) -> Id + 'static) -> Self {
+        TempIds {
+            client: use_query_client(),
+            list,
+            id_of: Rc::new(id_of),
+            details: Vec::new(),
+            resolved: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    /// Registers a scope for single items, keyed by id, whose cached data is moved to the real id on reconciliation.
+    pub fn with_detail<V>(mut self, detail: &QueryScope<Id, V>) -> Self
+    where
+        V: QueryValue + 'static,
+    {
+        let client = self.client.clone();
+        let detail = detail.clone();
+        self.details
+            .push(Rc::new(move |temp_id: &Id, real_id: &Id| {
+                let temp_id = &detail.normalize_key(temp_id.clone());
+                let real_id = &detail.normalize_key(real_id.clone());
+                let has_real = client
+                    .peek_query_state::<Id, V>(real_id)
+                    .is_some_and(|state| state.data().is_some());
+                let temp = client
+                    .peek_query_state::<Id, V>(temp_id)
+                    .and_then(|state| state.data().cloned());
+                if let (false, Some(data)) = (has_real, temp) {
+                    client.set_query_data::<Id, V>(real_id.clone(), data);
+                }
+            }));
+        self
+    }
+
+    /// Optimistically inserts an item with a temporary id, and reconciles it with the item returned by the mutation.
+    /// The list is invalidated once the mutation completes. See [`QueryScope::optimistically_insert`].
+    pub fn insert(
+        &self,
+        key: K,
+        item: T,
+        mutation: impl Future<Output = T> + 'static,
+    ) -> Pin<Box<dyn Future<Output = T>>> {
+        let temp_id = (self.id_of)(&item);
+        let temp_ids = self.clone();
+        let list_key = key.clone();
+        self.list.optimistically_insert(key, item, async move {
+            let item = mutation.await;
+            temp_ids.reconcile(&list_key, temp_id, item.clone());
+            item
+        })
+    }
+
+    /// Replaces the item with the temporary id in the list, and moves detail data to the real id.
+    /// If the item is no longer in the list, e.g. because the list was refetched, the real item is added unless already present.
+    pub fn reconcile(&self, key: &K, temp_id: Id, item: T) {
+        let real_id = (self.id_of)(&item);
+        let key = self.list.normalize_key(key.clone());
+        self.client.update_query_data_mut::<K, Vec<T>>(key, |list| {
+            if let Some(existing) = list.iter_mut().find(|t| (self.id_of)(t) == temp_id) {
+                *existing = item;
+            } else if !list.iter().any(|t| (self.id_of)(t) == real_id) {
+                list.push(item);
+            }
+        });
+        for detail in &self.details {
+            detail(&temp_id, &real_id);
+        }
+        self.resolved.borrow_mut().insert(temp_id, real_id);
+    }
+
+    /// The real id for a temporary id once reconciled, otherwise the id itself.
+    pub fn resolve(&self, id: &Id) -> Id {
+        self.resolved
+            .borrow()
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| id.clone())
+    }
+}
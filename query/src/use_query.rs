@@ -1,6 +1,7 @@
 use crate::query::Query;
 use crate::query_observer::{ListenerKey, QueryObserver};
 use crate::query_result::QueryResult;
+use crate::util::sleep;
 use crate::{
     query_is_suppressed, use_query_client, QueryOptions, QueryState, RefetchFn, ResourceOption,
 };
@@ -59,11 +60,117 @@ use std::time::Duration;
 ///
 /// ```
 ///
+#[track_caller]
 pub fn use_query<K, V, Fu>(
     key: impl Fn() -> K + 'static,
     fetcher: impl Fn(K) -> Fu + 'static,
     options: QueryOptions<V>,
 ) -> QueryResult<V, impl RefetchFn>
+where
+    K: crate::QueryKey + 'static,
+    V: crate::QueryValue + 'static,
+    Fu: Future<Output = V> + 'static,
+{
+    use_optional_query(move || Some(key()), fetcher, options)
+}
+
+/// Creates a query whose key may not be known yet, e.g. while waiting for a route param or a parent query.
+///
+/// While the key is `None` the query is idle: nothing is fetched, no cache entry is created, and the data is `None` without suspending.
+/// Once the key is `Some`, it behaves like [`use_query`].
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+/// use serde::*;
+///
+/// #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+/// struct UserId(i32);
+///
+/// #[derive(Debug, Clone, Deserialize, Serialize)]
+/// struct UserData {
+///     name: String,
+/// }
+///
+/// async fn get_user(id: UserId) -> UserData {
+///     todo!()
+/// }
+///
+/// // Idle until a user is selected.
+/// fn use_selected_user_query(
+///     selected: Signal<Option<UserId>>,
+/// ) -> QueryResult<UserData, impl RefetchFn> {
+///     use_optional_query(move || selected.get(), get_user, QueryOptions::default())
+/// }
+/// ```
+#[track_caller]
+pub fn use_optional_query<K, V, Fu>(
+    key: impl Fn() -> Option<K> + 'static,
+    fetcher: impl Fn(K) -> Fu + 'static,
+    options: QueryOptions<V>,
+) -> QueryResult<V, impl RefetchFn>
+where
+    K: crate::QueryKey + 'static,
+    V: crate::QueryValue + 'static,
+    Fu: Future<Output = V> + 'static,
+{
+    use_query_inner(key, fetcher, options)
+}
+
+/// Creates a query from a key that is already a signal, e.g. a [`Memo`] or a component prop.
+///
+/// The query lookup is memoized, so the key is read directly instead of through another closure, and needn't be memoized itself.
+/// Useful for long lists of queries, where the extra reactive nodes add up.
+/// A static key is never tracked. Otherwise it behaves like [`use_query`].
+///
+/// Example
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+/// use serde::*;
+///
+/// #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+/// struct UserId(i32);
+///
+/// #[derive(Debug, Clone, Deserialize, Serialize)]
+/// struct UserData {
+///     name: String,
+/// }
+///
+/// async fn get_user(id: UserId) -> UserData {
+///     todo!()
+/// }
+///
+/// #[component]
+/// fn User(#[prop(into)] id: MaybeSignal<UserId>) -> impl IntoView {
+///     let QueryResult { data, .. } = use_query_signal(id, get_user, QueryOptions::default());
+///     view! {
+///         <Transition fallback=|| ()>{move || data.get().map(|user| user.name)}</Transition>
+///     }
+/// }
+/// ```
+#[track_caller]
+pub fn use_query_signal<K, V, Fu>(
+    key: impl Into<MaybeSignal<K>>,
+    fetcher: impl Fn(K) -> Fu + 'static,
+    options: QueryOptions<V>,
+) -> QueryResult<V, impl RefetchFn>
+where
+    K: crate::QueryKey + 'static,
+    V: crate::QueryValue + 'static,
+    Fu: Future<Output = V> + 'static,
+{
+    let key = key.into();
+    use_query_inner(move || Some(key.get()), fetcher, options)
+}
+
+#[track_caller]
+fn use_query_inner<K, V, Fu>(
+    key: impl Fn() -> Option<K> + 'static,
+    fetcher: impl Fn(K) -> Fu + 'static,
+    options: QueryOptions<V>,
+) -> QueryResult<V, impl RefetchFn>
 where
     K: crate::QueryKey + 'static,
     V: crate::QueryValue + 'static,
@@ -75,30 +182,30 @@ where
 
     let query_state = register_observer_handle_cleanup(fetcher, query, options.clone());
 
-    let resource_fetcher = move |query: Query<K, V>| {
+    let resource_fetcher = move |query: Option<Query<K, V>>| {
         async move {
-            match query.get_state() {
-                // Immediately provide cached value.
-                QueryState::Loaded(data)
-                | QueryState::Invalid(data)
-                | QueryState::Fetching(data) => ResourceData(Some(data.data)),
-
+            // Idle until there is a key.
+            let Some(query) = query else {
+                return ResourceData::new(None);
+            };
+            match resolved_data(&query) {
+                Some(data) => data,
                 // Suspend indefinitely and wait for interruption.
-                QueryState::Created | QueryState::Loading => {
+                None => {
                     sleep(LONG_TIME).await;
-                    ResourceData(None)
+                    ResourceData::new(None)
                 }
             }
         }
     };
 
-    let resource: Resource<Query<K, V>, ResourceData<V>> = {
+    let resource: Resource<Option<Query<K, V>>, ResourceData<V>> = {
         let default = options.default_value;
         match options.resource_option.unwrap_or_default() {
             ResourceOption::NonBlocking => create_resource_with_initial_value(
                 move || query.get(),
                 resource_fetcher,
-                default.map(|default| ResourceData(Some(default))),
+                default.map(|default| ResourceData::new(Some(default))),
             ),
             ResourceOption::Blocking => {
                 create_blocking_resource(move || query.get(), resource_fetcher)
@@ -106,7 +213,7 @@ where
             ResourceOption::Local => create_local_resource_with_initial_value(
                 move || query.get(),
                 resource_fetcher,
-                default.map(|default| ResourceData(Some(default))),
+                default.map(|default| ResourceData::new(Some(default))),
             ),
         }
     };
@@ -121,12 +228,11 @@ where
     });
 
     // First read.
-    {
-        let query = query.get_untracked();
-
+    if let Some(query) = query.get_untracked() {
         if resource.loading().get_untracked()
             && !HydrationCtx::is_hydrating()
-            && query.with_state(|state| matches!(state, QueryState::Created))
+            && query
+                .with_state(|state| matches!(state, QueryState::Created | QueryState::Rejected(_)))
         {
             query.execute()
         }
@@ -134,16 +240,22 @@ where
 
     let data = Signal::derive({
         move || {
-            let read = resource.get().and_then(|r| r.0);
-            let _ = read;
+            let read = resource.get().and_then(|r| {
+                #[cfg(feature = "migrate")]
+                if let Some(unparsed) = r.unparsed {
+                    return migrate(query, &unparsed);
+                }
+                r.data
+            });
 
             // SSR edge case.
             // Given hydrate can happen before resource resolves, signals on the client can be out of sync with resource.
             // Need to force insert the resource data into the query state.
             #[cfg(feature = "hydrate")]
-            if let Some(ref data) = read {
-                let query = query.get_untracked();
-                if query.with_state(|state| matches!(state, QueryState::Created)) {
+            if let (Some(ref data), Some(query)) = (&read, query.get_untracked()) {
+                if query.with_state(|state| {
+                    matches!(state, QueryState::Created | QueryState::Rejected(_))
+                }) {
                     let data = crate::QueryData::now(data.clone());
                     query.set_state(QueryState::Loaded(data));
                 }
@@ -164,36 +276,72 @@ where
         is_invalid: Signal::derive(move || {
             query_state.with(|state| matches!(state, QueryState::Invalid(_)))
         }),
-        refetch: move || query.with_untracked(|q| q.execute()),
+        is_stale: Signal::derive(move || {
+            query.with(|q| {
+                q.as_ref()
+                    .is_some_and(|q| q.stale_signal().try_get().unwrap_or_default())
+            })
+        }),
+        validation_error: Signal::derive(move || {
+            query.with(|q| {
+                q.as_ref()
+                    .and_then(|q| q.validation_error_signal().try_get().flatten())
+            })
+        }),
+        refetch: move || {
+            query.with_untracked(|q| {
+                if let Some(q) = q {
+                    q.execute()
+                }
+            })
+        },
     }
 }
 
 const LONG_TIME: Duration = Duration::from_secs(60 * 60 * 24);
 
-async fn sleep(duration: Duration) {
-    use cfg_if::cfg_if;
-    cfg_if! {
-        if #[cfg(any(feature = "hydrate", feature = "csr"))] {
-            gloo_timers::future::sleep(duration).await;
-        } else if #[cfg(feature = "ssr")] {
-            tokio::time::sleep(duration).await;
-        } else {
-            let _ = duration;
-            logging::debug_warn!("You are missing a Cargo feature for leptos_query. Please enable one of 'ssr', 'hydrate', or 'csr'.");
+// What the resource resolves to, or `None` while the first load is pending.
+fn resolved_data<K, V>(query: &Query<K, V>) -> Option<ResourceData<V>>
+where
+    K: crate::QueryKey + 'static,
+    V: crate::QueryValue + 'static,
+{
+    match query.get_state() {
+        // Immediately provide cached value.
+        QueryState::Loaded(data) | QueryState::Invalid(data) | QueryState::Fetching(data) => {
+            Some(ResourceData::new(Some(data.data)))
         }
+        // A rejected first load resolves without data, so that suspense doesn't wait for a value that isn't coming.
+        QueryState::Rejected(_) => Some(ResourceData::new(None)),
+        QueryState::Created | QueryState::Loading => None,
     }
 }
 
 /// Wrapper type to enable using `Serializable`
 #[derive(Clone, Debug)]
-pub struct ResourceData<V>(Option<V>);
+pub struct ResourceData<V> {
+    data: Option<V>,
+    // Server-rendered data that doesn't deserialize, e.g. from an older app version. Migrated when read.
+    #[cfg(feature = "migrate")]
+    unparsed: Option<String>,
+}
+
+impl<V> ResourceData<V> {
+    fn new(data: Option<V>) -> Self {
+        ResourceData {
+            data,
+            #[cfg(feature = "migrate")]
+            unparsed: None,
+        }
+    }
+}
 
 impl<V> Serializable for ResourceData<V>
 where
     V: Serializable,
 {
     fn ser(&self) -> Result<String, SerializationError> {
-        if let Some(ref value) = self.0 {
+        if let Some(ref value) = self.data {
             value.ser()
         } else {
             Ok("null".to_string())
@@ -202,15 +350,43 @@ where
 
     fn de(bytes: &str) -> Result<Self, SerializationError> {
         match bytes {
-            "" | "null" => Ok(ResourceData(None)),
-            v => <V>::de(v).map(Some).map(ResourceData),
+            "" | "null" => Ok(ResourceData::new(None)),
+            #[cfg(not(feature = "migrate"))]
+            v => <V>::de(v).map(Some).map(ResourceData::new),
+            #[cfg(feature = "migrate")]
+            v => Ok(match <V>::de(v) {
+                Ok(data) => ResourceData::new(Some(data)),
+                Err(_) => ResourceData {
+                    data: None,
+                    unparsed: Some(v.to_string()),
+                },
+            }),
         }
     }
 }
 
+// Falls back to fetching if the data can't be migrated.
+#[cfg(feature = "migrate")]
+fn migrate<K, V>(query: Memo<Option<Query<K, V>>>, unparsed: &str) -> Option<V>
+where
+    K: crate::QueryKey + 'static,
+    V: crate::QueryValue + 'static,
+{
+    let query = query.get_untracked()?;
+    let migrated = query.migrate(unparsed);
+    if migrated.is_none()
+        && query.with_state(|state| matches!(state, QueryState::Created | QueryState::Rejected(_)))
+    {
+        logging::debug_warn!("Discarding server data for query {:?}", query.get_key());
+        query.execute();
+    }
+    migrated
+}
+
+#[track_caller]
 pub(crate) fn register_observer_handle_cleanup<K, V, Fu>(
     fetcher: impl Fn(K) -> Fu + 'static,
-    query: Memo<Query<K, V>>,
+    query: Memo<Option<Query<K, V>>>,
     options: QueryOptions<V>,
 ) -> Signal<QueryState<V>>
 where
@@ -218,12 +394,21 @@ where
     V: crate::QueryValue + 'static,
     Fu: Future<Output = V> + 'static,
 {
-    let state_signal = RwSignal::new(query.get_untracked().get_state());
+    let state_signal = RwSignal::new(query.with_untracked(current_state));
+    #[cfg(any(feature = "csr", feature = "hydrate"))]
+    let visibility_target = options.fetch_on_visible.clone();
     let observer = Rc::new(QueryObserver::with_fetcher(
+        use_query_client().cache,
         fetcher,
         options,
         query.get_untracked(),
     ));
+
+    #[cfg(any(feature = "csr", feature = "hydrate"))]
+    if let Some(target) = visibility_target {
+        let observer = observer.clone();
+        crate::visibility::on_visible(target, move || observer.resume());
+    }
     let listener = Rc::new(Cell::new(None::<ListenerKey>));
 
     create_isomorphic_effect({
@@ -240,8 +425,8 @@ where
 
             // Update
             let query = query.get();
-            state_signal.set(query.get_state());
-            observer.update_query(Some(query));
+            state_signal.set(current_state(&query));
+            observer.update_query(query);
         }
     });
 
@@ -256,3 +441,53 @@ where
 
     state_signal.into()
 }
+
+// An idle query without a key has no state yet.
+fn current_state<K, V>(query: &Option<Query<K, V>>) -> QueryState<V>
+where
+    K: crate::QueryKey + 'static,
+    V: crate::QueryValue + 'static,
+{
+    query
+        .as_ref()
+        .map_or(QueryState::Created, |query| query.get_state())
+}
+
+#[cfg(all(test, not(any(feature = "csr", feature = "hydrate"))))]
+mod tests {
+    use super::*;
+    use crate::{provide_query_client, query::execute_query};
+    use futures::executor::block_on;
+
+    #[test]
+    fn rejected_first_load_resolves() {
+        let _ = create_runtime();
+
+        provide_query_client();
+        let client = use_query_client();
+        let query = client.cache.get_or_create_query::<u32, String>(0);
+        let _observer = QueryObserver::no_fetcher(
+            client.cache.clone(),
+            QueryOptions::default().set_validator(|value: &String| {
+                if value.is_empty() {
+                    Err("empty".to_string())
+                } else {
+                    Ok(())
+                }
+            }),
+            Some(query.clone()),
+        );
+        // Pending until the first load.
+        assert!(resolved_data(&query).is_none());
+
+        block_on(execute_query(query.clone(), |_| async { String::new() }));
+        assert_eq!(QueryState::Rejected("empty".to_string()), query.get_state());
+        assert!(resolved_data(&query).is_some_and(|resolved| resolved.data.is_none()));
+
+        block_on(execute_query(query.clone(), |_| async { "a".to_string() }));
+        assert_eq!(
+            Some("a".to_string()),
+            resolved_data(&query).and_then(|resolved| resolved.data)
+        );
+    }
+}
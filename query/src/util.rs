@@ -1,12 +1,121 @@
-use std::time::Duration;
+use std::{
+    cell::{Ref, RefCell, RefMut},
+    time::Duration,
+};
 
 use crate::instant::Instant;
 
+// Timestamps in the future, e.g. from a server whose clock is ahead, count as now. Otherwise the data would stay fresh until the clocks meet.
 pub(crate) fn time_until_stale(updated_at: Instant, stale_time: Duration) -> Duration {
-    let updated_at = updated_at.0.as_millis() as i64;
-    let now = Instant::now().0.as_millis() as i64;
-    let stale_time = stale_time.as_millis() as i64;
-    let result = (updated_at + stale_time) - now;
-    let ensure_non_negative = result.max(0);
-    Duration::from_millis(ensure_non_negative as u64)
+    let now = Instant::now();
+    let updated_at = updated_at.min(now);
+    updated_at
+        .0
+        .saturating_add(stale_time)
+        .saturating_sub(now.0)
+}
+
+#[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+pub(crate) fn time_until(deadline: Instant) -> Duration {
+    deadline.0.saturating_sub(Instant::now().0)
+}
+
+pub(crate) async fn sleep(duration: Duration) {
+    use cfg_if::cfg_if;
+    cfg_if! {
+        if #[cfg(any(feature = "hydrate", feature = "csr"))] {
+            gloo_timers::future::sleep(duration).await;
+        } else if #[cfg(feature = "ssr")] {
+            tokio::time::sleep(duration).await;
+        } else {
+            let _ = duration;
+            leptos::logging::debug_warn!("You are missing a Cargo feature for leptos_query. Please enable one of 'ssr', 'hydrate', or 'csr'.");
+        }
+    }
+}
+
+// Longest wait for the browser to become idle, before running anyway.
+#[cfg(any(feature = "hydrate", feature = "csr"))]
+const IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Runs `f` once the browser is idle, using `requestIdleCallback`.
+/// Falls back to a timer where `requestIdleCallback` isn't supported (e.g. Safari).
+#[cfg(any(feature = "hydrate", feature = "csr"))]
+pub(crate) fn on_idle(f: impl FnOnce() + 'static) {
+    use js_sys::wasm_bindgen::{closure::Closure, JsCast};
+
+    let window = leptos::window();
+    let supported = js_sys::Reflect::has(&window, &"requestIdleCallback".into()).unwrap_or(false);
+    if !supported {
+        leptos::set_timeout(f, IDLE_TIMEOUT);
+        return;
+    }
+
+    let callback = Closure::once_into_js(f);
+    let mut options = web_sys::IdleRequestOptions::new();
+    options.timeout(IDLE_TIMEOUT.as_millis() as u32);
+    if let Err(e) = window.request_idle_callback_with_options(callback.unchecked_ref(), &options) {
+        leptos::logging::debug_warn!("Failed to request idle callback: {e:?}");
+    }
+}
+
+/// The single failure mode when accessing internal query state.
+///
+/// State is kept in `RefCell`s, and is never borrowed while user code (listeners, fetchers, cache observers) runs.
+/// A failed borrow therefore means the state was accessed re-entrantly from within one of the library's own critical sections.
+/// Rather than panicking, the operation is either deferred with [`run_or_defer`] or degrades gracefully.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ReentrantBorrow(pub(crate) &'static str);
+
+impl std::fmt::Display for ReentrantBorrow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Re-entrant access to query state in `{}`. This should not happen. Please file a bug report.",
+            self.0
+        )
+    }
+}
+
+pub(crate) fn try_borrow<'a, T>(
+    cell: &'a RefCell<T>,
+    context: &'static str,
+) -> Result<Ref<'a, T>, ReentrantBorrow> {
+    cell.try_borrow().map_err(|_| ReentrantBorrow(context))
+}
+
+pub(crate) fn try_borrow_mut<'a, T>(
+    cell: &'a RefCell<T>,
+    context: &'static str,
+) -> Result<RefMut<'a, T>, ReentrantBorrow> {
+    cell.try_borrow_mut().map_err(|_| ReentrantBorrow(context))
+}
+
+/// Runs `op`. If it fails with [`ReentrantBorrow`], it is retried once in a microtask, after the current call stack has unwound.
+/// Outside of the browser microtasks run immediately, so the retry may fail again, in which case a warning is logged.
+pub(crate) fn run_or_defer(op: impl Fn() -> Result<(), ReentrantBorrow> + 'static) {
+    if op().is_err() {
+        leptos::queue_microtask(move || {
+            if let Err(e) = op() {
+                leptos::logging::debug_warn!("{e}");
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn future_timestamps_count_as_now() {
+        let stale_time = Duration::from_secs(60);
+        let now = Instant::now();
+
+        let ahead = Instant(now.0 + Duration::from_secs(3600));
+        assert!(time_until_stale(ahead, stale_time) <= stale_time);
+
+        let old = Instant(now.0 - Duration::from_secs(120));
+        assert!(time_until_stale(old, stale_time).is_zero());
+    }
 }
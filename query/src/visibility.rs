@@ -0,0 +1,115 @@
+use std::rc::Rc;
+
+use leptos::{html::AnyElement, html::ElementDescriptor, HtmlElement, NodeRef, Signal};
+
+/// Reports whether the queries used in the current component tree are visible, e.g. from a tab component that hides its panels with `display: none`.
+///
+/// A query only refetches on its [`refetch_interval`](crate::QueryOptions::refetch_interval) while at least one of its observers is visible.
+/// Queries used outside of any `provide_query_visibility` are always visible.
+///
+/// ```
+/// use leptos::*;
+/// use leptos_query::*;
+///
+/// #[component]
+/// fn TabPanel(active: Signal<bool>, children: Children) -> impl IntoView {
+///     provide_query_visibility(active);
+///     view! { <div style:display=move || if active.get() { "block" } else { "none" }>{children()}</div> }
+/// }
+/// ```
+pub fn provide_query_visibility(visible: impl Into<Signal<bool>>) {
+    leptos::provide_context(QueryVisibility(visible.into()));
+}
+
+#[derive(Clone, Copy)]
+struct QueryVisibility(Signal<bool>);
+
+pub(crate) fn use_query_visibility() -> Option<Signal<bool>> {
+    leptos::use_context::<QueryVisibility>().map(|v| v.0)
+}
+
+/// The element a query waits on before its first fetch. See [`QueryOptions::fetch_on_visible`](crate::QueryOptions::fetch_on_visible).
+///
+/// Created from any [`NodeRef`].
+#[derive(Clone)]
+#[cfg_attr(not(any(feature = "csr", feature = "hydrate")), allow(dead_code))]
+pub struct VisibilityTarget(Rc<dyn Fn() -> Option<HtmlElement<AnyElement>>>);
+
+impl std::fmt::Debug for VisibilityTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VisibilityTarget").finish_non_exhaustive()
+    }
+}
+
+impl<T> From<NodeRef<T>> for VisibilityTarget
+where
+    T: ElementDescriptor + Clone + 'static,
+{
+    fn from(node_ref: NodeRef<T>) -> Self {
+        VisibilityTarget(Rc::new(move || node_ref.get().map(|el| el.into_any())))
+    }
+}
+
+/// Calls `on_visible` once the target is scrolled into view, using an `IntersectionObserver`.
+/// The observer is disconnected once the target is visible, or when the reactive scope is cleaned up.
+#[cfg(any(feature = "csr", feature = "hydrate"))]
+pub(crate) fn on_visible(target: VisibilityTarget, on_visible: impl Fn() + 'static) {
+    use js_sys::wasm_bindgen::{closure::Closure, JsCast};
+    use std::cell::RefCell;
+    use web_sys::{IntersectionObserver, IntersectionObserverEntry};
+
+    type Callback = Closure<dyn FnMut(js_sys::Array, IntersectionObserver)>;
+
+    let on_visible = Rc::new(on_visible);
+    // The closure must outlive the observer.
+    let current = Rc::new(RefCell::new(None::<(IntersectionObserver, Callback)>));
+
+    let disconnect = {
+        let current = current.clone();
+        move || {
+            if let Some((observer, _)) = current.take() {
+                observer.disconnect();
+            }
+        }
+    };
+
+    leptos::create_effect({
+        let disconnect = disconnect.clone();
+        move |_| {
+            // The target may be replaced, or not be mounted yet.
+            disconnect();
+            let Some(element) = (target.0)() else {
+                return;
+            };
+
+            let callback: Callback = Closure::new({
+                let on_visible = on_visible.clone();
+                move |entries: js_sys::Array, observer: IntersectionObserver| {
+                    let visible = entries.iter().any(|entry| {
+                        entry
+                            .unchecked_into::<IntersectionObserverEntry>()
+                            .is_intersecting()
+                    });
+                    if visible {
+                        observer.disconnect();
+                        on_visible();
+                    }
+                }
+            });
+
+            match IntersectionObserver::new(callback.as_ref().unchecked_ref()) {
+                Ok(observer) => {
+                    observer.observe(&element);
+                    *current.borrow_mut() = Some((observer, callback));
+                }
+                // Without an observer, don't hold the query back.
+                Err(e) => {
+                    leptos::logging::debug_warn!("Failed to create IntersectionObserver: {e:?}");
+                    on_visible();
+                }
+            }
+        }
+    });
+
+    leptos::on_cleanup(disconnect);
+}